@@ -0,0 +1,245 @@
+//! Node.js bindings for `fast-bernoulli`, built with napi-rs.
+//!
+//! ```js
+//! const { FastBernoulli } = require("fast-bernoulli");
+//!
+//! const bernoulli = new FastBernoulli(0.01);
+//! if (bernoulli.trial()) {
+//!   // Record the sample...
+//! }
+//!
+//! // Save the state, and pick up where we left off later.
+//! const state = bernoulli.exportState();
+//! const restored = FastBernoulli.importState(state);
+//!
+//! // Encode a configuration in the wire format, to push to agents.
+//! const bytes = encodeConfig({ probability: 0.01, mode: "uniform" });
+//! const config = decodeConfig(bytes);
+//! ```
+
+#![deny(missing_debug_implementations)]
+
+use fast_bernoulli::{FastBernoulliState, Rate, SamplingMode};
+use napi::bindgen_prelude::{BigInt, Buffer};
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+use std::time::Duration;
+
+/// A snapshot of a sampler's state, as a plain JavaScript object.
+#[napi(object)]
+#[derive(Debug, Clone, Copy)]
+pub struct State {
+    /// The probability with which events are sampled.
+    pub probability: f64,
+    /// The number of events to skip before the next sample.
+    pub skip_count: u32,
+}
+
+/// Fast Bernoulli sampling: each event has equal probability of being sampled.
+#[napi(js_name = "FastBernoulli")]
+#[derive(Debug)]
+pub struct FastBernoulli {
+    inner: fast_bernoulli::FastBernoulli,
+}
+
+fn check_probability(probability: f64) -> Result<()> {
+    if (0.0..=1.0).contains(&probability) {
+        Ok(())
+    } else {
+        Err(Error::new(
+            Status::InvalidArg,
+            format!(
+                "`probability` must be in the range `0.0 <= probability <= 1.0`, got {probability}"
+            ),
+        ))
+    }
+}
+
+#[napi]
+impl FastBernoulli {
+    /// Construct a new sampler that samples events with the given probability.
+    #[napi(constructor)]
+    pub fn new(probability: f64) -> Result<Self> {
+        check_probability(probability)?;
+        Ok(FastBernoulli {
+            inner: fast_bernoulli::FastBernoulli::new(probability, &mut rand::thread_rng()),
+        })
+    }
+
+    /// Restore a sampler from a state returned by `exportState`.
+    #[napi(factory)]
+    pub fn import_state(state: State) -> Result<Self> {
+        check_probability(state.probability)?;
+        let state = FastBernoulliState::new(state.probability, state.skip_count);
+        Ok(FastBernoulli {
+            inner: fast_bernoulli::FastBernoulli::from_state(state),
+        })
+    }
+
+    /// Perform a Bernoulli trial: returns `true` with the configured
+    /// probability.
+    #[napi]
+    pub fn trial(&mut self) -> bool {
+        self.inner.trial(&mut rand::thread_rng())
+    }
+
+    /// Perform `n` Bernoulli trials at once, returning `true` if any of them
+    /// would have.
+    #[napi]
+    pub fn multi_trial(&mut self, n: u32) -> bool {
+        self.inner.multi_trial(n, &mut rand::thread_rng())
+    }
+
+    /// The probability with which events are sampled.
+    #[napi(getter)]
+    pub fn probability(&self) -> f64 {
+        self.inner.probability()
+    }
+
+    /// The number of events that will be skipped until the next event is
+    /// sampled.
+    #[napi(getter)]
+    pub fn skip_count(&self) -> u32 {
+        self.inner.skip_count()
+    }
+
+    /// Get a snapshot of this sampler's state.
+    #[napi]
+    pub fn export_state(&self) -> State {
+        let state = self.inner.state();
+        State {
+            probability: state.probability,
+            skip_count: state.skip_count,
+        }
+    }
+}
+
+/// A maximum rate of samples: at most `count` per `periodMs` milliseconds.
+#[napi(object)]
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    /// The number of samples allowed per period.
+    pub count: u32,
+    /// The period, in milliseconds.
+    pub period_ms: f64,
+}
+
+/// A sampler configuration, as encoded by `encodeConfig`.
+///
+/// `mode` is one of `"uniform"`, `"per-key-backoff"`, which uses `backoff` and
+/// `minProbability`, or `"sticky"`, which uses `ttlMs`.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct SamplerConfig {
+    /// The probability with which events are sampled.
+    pub probability: f64,
+    /// How keys affect sampling decisions.
+    pub mode: String,
+    /// The factor by which a key's probability is multiplied after each
+    /// sample, in `"per-key-backoff"` mode.
+    pub backoff: Option<f64>,
+    /// The floor for each key's probability, in `"per-key-backoff"` mode.
+    pub min_probability: Option<f64>,
+    /// How long a sampled key stays sampled, in `"sticky"` mode.
+    pub ttl_ms: Option<f64>,
+    /// The maximum rate of samples, if any.
+    pub quota: Option<Quota>,
+    /// The seed for the agent's RNG, if decisions should be reproducible.
+    pub seed: Option<BigInt>,
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::new(Status::InvalidArg, message.into())
+}
+
+fn duration_from_ms(ms: f64, what: &str) -> Result<Duration> {
+    Duration::try_from_secs_f64(ms / 1000.0).map_err(|_| {
+        invalid(format!(
+            "`{what}` must be a non-negative number of milliseconds"
+        ))
+    })
+}
+
+/// Encode a sampler configuration in the versioned binary wire format.
+#[napi]
+pub fn encode_config(config: SamplerConfig) -> Result<Buffer> {
+    let field = |value: Option<f64>, name: &str| {
+        value.ok_or_else(|| invalid(format!("`{name}` is required in `{}` mode", config.mode)))
+    };
+    let mode = match config.mode.as_str() {
+        "uniform" => SamplingMode::Uniform,
+        "per-key-backoff" => SamplingMode::PerKeyBackoff {
+            backoff: field(config.backoff, "backoff")?,
+            min_probability: field(config.min_probability, "minProbability")?,
+        },
+        "sticky" => SamplingMode::Sticky {
+            ttl: duration_from_ms(field(config.ttl_ms, "ttlMs")?, "ttlMs")?,
+        },
+        mode => return Err(invalid(format!("unknown sampling mode `{mode}`"))),
+    };
+    let quota = match config.quota {
+        Some(quota) => {
+            let period = duration_from_ms(quota.period_ms, "periodMs")?;
+            if period.is_zero() {
+                return Err(invalid("`periodMs` must be non-zero"));
+            }
+            Some(Rate::new(quota.count, period))
+        }
+        None => None,
+    };
+    let seed = match &config.seed {
+        Some(seed) => match seed.get_u64() {
+            (false, seed, true) => Some(seed),
+            _ => return Err(invalid("`seed` must fit in a `u64`")),
+        },
+        None => None,
+    };
+    let bytes = fast_bernoulli::SamplerConfig {
+        probability: config.probability,
+        mode,
+        quota,
+        seed,
+    }
+    .encode();
+    // Decoding validates every field, so that agents never receive a
+    // configuration they would reject.
+    fast_bernoulli::SamplerConfig::decode(&bytes).map_err(|e| invalid(e.to_string()))?;
+    Ok(bytes.into())
+}
+
+/// Decode a sampler configuration encoded in the binary wire format.
+#[napi]
+pub fn decode_config(bytes: Buffer) -> Result<SamplerConfig> {
+    let config =
+        fast_bernoulli::SamplerConfig::decode(&bytes).map_err(|e| invalid(e.to_string()))?;
+    let mut decoded = SamplerConfig {
+        probability: config.probability,
+        mode: String::new(),
+        backoff: None,
+        min_probability: None,
+        ttl_ms: None,
+        quota: config.quota.map(|quota| Quota {
+            count: quota.count(),
+            period_ms: quota.period().as_secs_f64() * 1000.0,
+        }),
+        seed: config.seed.map(BigInt::from),
+    };
+    decoded.mode = match config.mode {
+        SamplingMode::Uniform => "uniform",
+        SamplingMode::PerKeyBackoff {
+            backoff,
+            min_probability,
+        } => {
+            decoded.backoff = Some(backoff);
+            decoded.min_probability = Some(min_probability);
+            "per-key-backoff"
+        }
+        SamplingMode::Sticky { ttl } => {
+            decoded.ttl_ms = Some(ttl.as_secs_f64() * 1000.0);
+            "sticky"
+        }
+        mode => return Err(invalid(format!("sampling mode {mode:?} is not supported"))),
+    }
+    .to_string();
+    Ok(decoded)
+}
@@ -0,0 +1,148 @@
+//! WebAssembly component guest bindings for `fast-bernoulli`, implementing the
+//! `fitzgen:fast-bernoulli/sampler` interface defined in `wit/`.
+
+#![deny(missing_debug_implementations)]
+
+use exports::fitzgen::fast_bernoulli::config::{
+    self, Backoff, Quota, SamplerConfig as WitSamplerConfig, SamplingMode as WitSamplingMode,
+};
+use exports::fitzgen::fast_bernoulli::sampler::{Bernoulli, Guest, GuestBernoulli, State};
+use fast_bernoulli::{FastBernoulli, FastBernoulliState, Rate, SamplerConfig, SamplingMode};
+use std::cell::RefCell;
+use std::time::Duration;
+
+wit_bindgen::generate!({
+    path: "../../wit",
+    world: "fast-bernoulli",
+});
+
+struct Component;
+
+impl Guest for Component {
+    type Bernoulli = BernoulliResource;
+}
+
+/// The host-visible `bernoulli` resource.
+#[derive(Debug)]
+struct BernoulliResource {
+    bernoulli: RefCell<FastBernoulli>,
+}
+
+fn check_probability(probability: f64) -> Result<(), String> {
+    if (0.0..=1.0).contains(&probability) {
+        Ok(())
+    } else {
+        Err(format!(
+            "`probability` must be in the range `0.0 <= probability <= 1.0`, got {probability}"
+        ))
+    }
+}
+
+impl GuestBernoulli for BernoulliResource {
+    fn create(probability: f64) -> Result<Bernoulli, String> {
+        check_probability(probability)?;
+        let bernoulli = FastBernoulli::new(probability, &mut rand::thread_rng());
+        Ok(Bernoulli::new(BernoulliResource {
+            bernoulli: RefCell::new(bernoulli),
+        }))
+    }
+
+    fn restore(state: State) -> Result<Bernoulli, String> {
+        check_probability(state.probability)?;
+        let state = FastBernoulliState::new(state.probability, state.skip_count);
+        Ok(Bernoulli::new(BernoulliResource {
+            bernoulli: RefCell::new(FastBernoulli::from_state(state)),
+        }))
+    }
+
+    fn trial(&self) -> bool {
+        self.bernoulli.borrow_mut().trial(&mut rand::thread_rng())
+    }
+
+    fn multi_trial(&self, n: u32) -> bool {
+        self.bernoulli
+            .borrow_mut()
+            .multi_trial(n, &mut rand::thread_rng())
+    }
+
+    fn probability(&self) -> f64 {
+        self.bernoulli.borrow().probability()
+    }
+
+    fn skip_count(&self) -> u32 {
+        self.bernoulli.borrow().skip_count()
+    }
+
+    fn save(&self) -> State {
+        let state = self.bernoulli.borrow().state();
+        State {
+            probability: state.probability,
+            skip_count: state.skip_count,
+        }
+    }
+}
+
+impl config::Guest for Component {
+    fn encode(config: WitSamplerConfig) -> Result<Vec<u8>, String> {
+        let quota = match config.quota {
+            Some(Quota {
+                period_nanos: 0, ..
+            }) => return Err("quota period must be non-zero".to_string()),
+            Some(quota) => Some(Rate::new(
+                quota.count,
+                Duration::from_nanos(quota.period_nanos),
+            )),
+            None => None,
+        };
+        let mode = match config.mode {
+            WitSamplingMode::Uniform => SamplingMode::Uniform,
+            WitSamplingMode::PerKeyBackoff(backoff) => SamplingMode::PerKeyBackoff {
+                backoff: backoff.factor,
+                min_probability: backoff.min_probability,
+            },
+            WitSamplingMode::Sticky(ttl) => SamplingMode::Sticky {
+                ttl: Duration::from_nanos(ttl),
+            },
+        };
+        let bytes = SamplerConfig {
+            probability: config.probability,
+            mode,
+            quota,
+            seed: config.seed,
+        }
+        .encode();
+        // Decoding validates every field, so that agents never receive a
+        // configuration they would reject.
+        SamplerConfig::decode(&bytes).map_err(|e| e.to_string())?;
+        Ok(bytes)
+    }
+
+    fn decode(bytes: Vec<u8>) -> Result<WitSamplerConfig, String> {
+        let config = SamplerConfig::decode(&bytes).map_err(|e| e.to_string())?;
+        let mode = match config.mode {
+            SamplingMode::PerKeyBackoff {
+                backoff,
+                min_probability,
+            } => WitSamplingMode::PerKeyBackoff(Backoff {
+                factor: backoff,
+                min_probability,
+            }),
+            SamplingMode::Sticky { ttl } => {
+                WitSamplingMode::Sticky(u64::try_from(ttl.as_nanos()).unwrap_or(u64::MAX))
+            }
+            SamplingMode::Uniform => WitSamplingMode::Uniform,
+            mode => return Err(format!("sampling mode {mode:?} is not supported")),
+        };
+        Ok(WitSamplerConfig {
+            probability: config.probability,
+            mode,
+            quota: config.quota.map(|quota| Quota {
+                count: quota.count(),
+                period_nanos: u64::try_from(quota.period().as_nanos()).unwrap_or(u64::MAX),
+            }),
+            seed: config.seed,
+        })
+    }
+}
+
+export!(Component);
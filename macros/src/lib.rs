@@ -0,0 +1,176 @@
+//! Procedural macros for [`fast-bernoulli`](https://docs.rs/fast-bernoulli).
+//!
+//! Don't depend on this crate directly; enable `fast-bernoulli`'s `macros`
+//! feature and use the re-exports from there instead.
+
+#![deny(missing_docs)]
+#![forbid(unsafe_code)]
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Expr, ExprLit, ItemFn, Lit, ReturnType, Token,
+};
+
+/// Run a function's body only on a sampled fraction of calls.
+///
+/// See `fast_bernoulli::sampled` for documentation.
+#[proc_macro_attribute]
+pub fn sampled(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let SampledArgs { probability } = parse_macro_input!(attr as SampledArgs);
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = parse_macro_input!(item as ItemFn);
+
+    if let ReturnType::Type(_, ty) = &sig.output {
+        let is_unit = matches!(&**ty, syn::Type::Tuple(t) if t.elems.is_empty());
+        if !is_unit {
+            return syn::Error::new_spanned(
+                ty,
+                "`#[sampled]` functions must return `()`, since their bodies may not run",
+            )
+            .to_compile_error()
+            .into();
+        }
+    }
+
+    quote! {
+        #(#attrs)*
+        #vis #sig {
+            {
+                ::std::thread_local! {
+                    static SAMPLER: ::fast_bernoulli::__private::CallsiteSampler =
+                        ::fast_bernoulli::__private::CallsiteSampler::new(#probability);
+                }
+                if !SAMPLER.with(::fast_bernoulli::__private::CallsiteSampler::trial) {
+                    return;
+                }
+            }
+            #block
+        }
+    }
+    .into()
+}
+
+/// Derive `SampledKind` for an enum, from per-variant `#[sample(p = ...)]`
+/// attributes.
+///
+/// See `fast_bernoulli::SampledKind` for documentation.
+#[proc_macro_derive(SampledKind, attributes(sample))]
+pub fn derive_sampled_kind(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match sampled_kind(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn sampled_kind(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`SampledKind` can only be derived for enums",
+        ));
+    };
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`SampledKind` can't be derived for an enum without variants",
+        ));
+    }
+
+    // An attribute on the enum itself is the default for its variants.
+    let default = sample_attribute(&input.attrs)?;
+    let mut probabilities = Vec::new();
+    let mut arms = Vec::new();
+    for (index, variant) in data.variants.iter().enumerate() {
+        let probability = match sample_attribute(&variant.attrs)?.or(default) {
+            Some(probability) => probability,
+            None => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "expected `#[sample(p = <probability>)]` on this variant, or on the enum",
+                ))
+            }
+        };
+        let name = &variant.ident;
+        probabilities.push(probability);
+        arms.push(quote! { Self::#name { .. } => #index, });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::fast_bernoulli::SampledKind for #name #ty_generics #where_clause {
+            const PROBABILITIES: &'static [f64] = &[#(#probabilities),*];
+
+            #[inline]
+            fn kind(&self) -> usize {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+/// Parse the probability from a `#[sample(p = <probability>)]` attribute
+/// among `attrs`, if there is one.
+fn sample_attribute(attrs: &[Attribute]) -> syn::Result<Option<f64>> {
+    let mut probability = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("sample")) {
+        if probability.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "duplicate `#[sample]` attribute",
+            ));
+        }
+        let SampledArgs { probability: p } = attr.parse_args()?;
+        probability = Some(p);
+    }
+    Ok(probability)
+}
+
+/// The arguments to `#[sampled]` and `#[sample]`: `p = <probability>`.
+struct SampledArgs {
+    probability: f64,
+}
+
+impl Parse for SampledArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: syn::Ident = input.parse()?;
+        if name != "p" {
+            return Err(syn::Error::new_spanned(
+                name,
+                "expected `p = <probability>`",
+            ));
+        }
+        input.parse::<Token![=]>()?;
+        let value: Expr = input.parse()?;
+        let probability = match &value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Float(f), ..
+            }) => f.base10_parse::<f64>()?,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    value,
+                    "the probability must be a float literal, such as `0.01`",
+                ))
+            }
+        };
+        if !(0.0..=1.0).contains(&probability) {
+            return Err(syn::Error::new_spanned(
+                value,
+                "the probability must be in the range `0.0 <= p <= 1.0`",
+            ));
+        }
+        if !input.is_empty() {
+            return Err(input.error("unexpected arguments after the probability"));
+        }
+        Ok(SampledArgs { probability })
+    }
+}
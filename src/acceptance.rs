@@ -0,0 +1,164 @@
+use rand::Rng;
+
+/// Metropolis acceptance tests for simulated annealing, under a geometric
+/// cooling schedule.
+///
+/// A move that changes the energy by `delta` is accepted with probability
+/// `min(1.0, exp(-delta / temperature))`. As with a
+/// [`FastBernoulli`][crate::FastBernoulli], the common cases don't touch the
+/// random number generator: downhill and sideways moves are always accepted,
+/// and moves so far uphill that their probability underflows to zero are
+/// always rejected. Only the moves in between cost a random number.
+///
+/// With [`with_cooling`][Acceptance::with_cooling], the temperature is
+/// multiplied by a cooling factor after every given number of trials.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::Acceptance;
+/// use rand::Rng;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Minimize `(x - 3)^2`, cooling by 1% every 100 moves.
+/// let energy = |x: f64| (x - 3.0) * (x - 3.0);
+/// let mut acceptance = Acceptance::new(10.0).with_cooling(0.99, 100);
+///
+/// let mut x = 0.0;
+/// for _ in 0..100_000 {
+///     let candidate = x + rng.gen_range(-1.0..1.0);
+///     if acceptance.accept(energy(candidate) - energy(x), &mut rng) {
+///         x = candidate;
+///     }
+/// }
+///
+/// assert!((x - 3.0).abs() < 0.5);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Acceptance {
+    temperature: f64,
+    cooling: f64,
+    steps_per_temperature: u64,
+    steps: u64,
+    trials: u64,
+    accepted: u64,
+}
+
+impl Acceptance {
+    /// Construct a new `Acceptance` helper at the given temperature, with no
+    /// cooling.
+    ///
+    /// # Panics
+    ///
+    /// The temperature must be non-negative and finite, and this method will
+    /// panic if that is not the case.
+    pub fn new(temperature: f64) -> Self {
+        assert!(
+            temperature >= 0.0 && temperature.is_finite(),
+            "`temperature` must be non-negative and finite"
+        );
+        Acceptance {
+            temperature,
+            cooling: 1.0,
+            steps_per_temperature: u64::MAX,
+            steps: 0,
+            trials: 0,
+            accepted: 0,
+        }
+    }
+
+    /// Multiply the temperature by `factor` after every `steps` trials.
+    ///
+    /// # Panics
+    ///
+    /// The factor must be within the range `0.0 <= factor <= 1.0`, and `steps`
+    /// must be non-zero, and this method will panic if that is not the case.
+    pub fn with_cooling(mut self, factor: f64, steps: u64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&factor),
+            "`factor` must be in the range `0.0 <= factor <= 1.0`"
+        );
+        assert!(steps > 0, "`steps` must be non-zero");
+        self.cooling = factor;
+        self.steps_per_temperature = steps;
+        self
+    }
+
+    /// Decide whether to accept a move that changes the energy by `delta`,
+    /// and advance the cooling schedule.
+    pub fn accept<R>(&mut self, delta: f64, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let probability = self.probability(delta);
+        let accepted = if probability >= 1.0 {
+            true
+        } else if probability > 0.0 {
+            rng.gen::<f64>() < probability
+        } else {
+            false
+        };
+
+        self.trials += 1;
+        self.accepted += u64::from(accepted);
+        self.steps += 1;
+        if self.steps == self.steps_per_temperature {
+            self.steps = 0;
+            self.temperature *= self.cooling;
+        }
+        accepted
+    }
+
+    /// Get the probability with which a move that changes the energy by
+    /// `delta` would be accepted at the current temperature.
+    pub fn probability(&self, delta: f64) -> f64 {
+        if delta <= 0.0 {
+            1.0
+        } else {
+            // At zero temperature, this is `exp(-inf) = 0`.
+            (-delta / self.temperature).exp()
+        }
+    }
+
+    /// Get the current temperature.
+    #[inline]
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Get the fraction of trials so far that were accepted.
+    ///
+    /// This is NaN before any trials.
+    #[inline]
+    pub fn acceptance_rate(&self) -> f64 {
+        self.accepted as f64 / self.trials as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_with_the_metropolis_probability() {
+        let mut rng = rand::thread_rng();
+        let mut acceptance = Acceptance::new(2.0).with_cooling(0.5, 100_000);
+
+        let trials = 100_000;
+        // An uphill move of `1.0` at temperature `2.0`.
+        let p = (-0.5_f64).exp();
+        let expected = p * f64::from(trials);
+        let tolerance = 5.0 * (expected * (1.0 - p)).sqrt();
+        let mut accepted = 0;
+        for _ in 0..trials {
+            accepted += u32::from(acceptance.accept(1.0, &mut rng));
+        }
+        assert!((f64::from(accepted) - expected).abs() <= tolerance);
+
+        // The schedule has cooled the temperature once.
+        assert_eq!(acceptance.temperature(), 1.0);
+        assert!(acceptance.accept(-1.0, &mut rng));
+        assert!(!Acceptance::new(0.0).accept(1e-300, &mut rng));
+    }
+}
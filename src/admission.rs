@@ -0,0 +1,153 @@
+use crate::{CountMinSketch, FastBernoulli};
+use rand::Rng;
+
+/// A probabilistic cache admission policy, in the style of TinyLFU.
+///
+/// Admitting every missed key into a cache lets one-hit wonders evict entries
+/// that are actually reused. An `AdmissionPolicy` admits each miss with a
+/// configured probability `p` instead, so a key has to be missed several times,
+/// on average, before it gets in.
+///
+/// With a frequency sketch, see
+/// [`with_frequency_sketch`][AdmissionPolicy::with_frequency_sketch], the
+/// probability is adjusted by how often the key has been accessed: a key with
+/// estimated frequency `f` is admitted with probability `1 - (1 - p)^f`, as if
+/// each access had been a separate trial. This costs a single
+/// [`multi_trial`][FastBernoulli::multi_trial], no matter the frequency.
+///
+/// Keys are identified by their hashes, which the cache has usually computed
+/// already.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{AdmissionPolicy, CountMinSketch};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut policy = AdmissionPolicy::new(0.1, &mut rng)
+///     .with_frequency_sketch(CountMinSketch::new(4096, 4));
+///
+/// # let key_hash = 0x1234_u64;
+/// // On a cache hit, record the access.
+/// policy.record(key_hash);
+///
+/// // On a miss, decide whether to admit the key.
+/// if policy.admit(key_hash, &mut rng) {
+///     // Insert the entry...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdmissionPolicy {
+    bernoulli: FastBernoulli,
+    sketch: Option<CountMinSketch>,
+}
+
+impl AdmissionPolicy {
+    /// Construct a new `AdmissionPolicy` that admits missed keys with the given
+    /// probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        AdmissionPolicy {
+            bernoulli: FastBernoulli::new(probability, rng),
+            sketch: None,
+        }
+    }
+
+    /// Adjust admission probabilities by key frequencies, estimated by
+    /// `sketch`.
+    #[inline]
+    pub fn with_frequency_sketch(mut self, sketch: CountMinSketch) -> Self {
+        self.sketch = Some(sketch);
+        self
+    }
+
+    /// Record an access to the key with the given hash, such as a cache hit.
+    ///
+    /// This does nothing without a frequency sketch.
+    #[inline]
+    pub fn record(&mut self, key_hash: u64) {
+        if let Some(sketch) = &mut self.sketch {
+            sketch.increment(&key_hash);
+        }
+    }
+
+    /// Record a miss on the key with the given hash, and decide whether to
+    /// admit it into the cache.
+    pub fn admit<R>(&mut self, key_hash: u64, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let frequency = match &mut self.sketch {
+            Some(sketch) => sketch.increment(&key_hash),
+            None => 1,
+        };
+        self.bernoulli.multi_trial(frequency, rng)
+    }
+
+    /// Get the probability with which the key with the given hash would be
+    /// admitted if it were missed now.
+    pub fn probability(&self, key_hash: u64) -> f64 {
+        let p = self.bernoulli.probability();
+        match &self.sketch {
+            Some(sketch) => {
+                let frequency = sketch.estimate(&key_hash).saturating_add(1);
+                -(f64::from(frequency) * (-p).ln_1p()).exp_m1()
+            }
+            None => p,
+        }
+    }
+
+    /// Halve every frequency estimate, so that recent accesses count for more
+    /// than old ones.
+    ///
+    /// This does nothing without a frequency sketch.
+    #[inline]
+    pub fn age(&mut self) {
+        if let Some(sketch) = &mut self.sketch {
+            sketch.halve();
+        }
+    }
+
+    /// Get the frequency sketch, if any.
+    #[inline]
+    pub fn sketch(&self) -> Option<&CountMinSketch> {
+        self.sketch.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frequent_keys_are_admitted_more_often() {
+        let mut rng = rand::thread_rng();
+        let popular_policy = |rng: &mut rand::rngs::ThreadRng| {
+            let mut policy =
+                AdmissionPolicy::new(0.01, rng).with_frequency_sketch(CountMinSketch::new(64, 2));
+            for _ in 0..99 {
+                policy.record(2);
+            }
+            policy
+        };
+
+        let policy = popular_policy(&mut rng);
+        assert!((policy.probability(1) - 0.01).abs() < 1e-12);
+        let popular = policy.probability(2);
+        assert!((popular - (1.0 - 0.99_f64.powi(100))).abs() < 1e-12);
+
+        let trials = 10_000;
+        let admitted = (0..trials)
+            .filter(|_| popular_policy(&mut rng).admit(2, &mut rng))
+            .count() as f64;
+        let expected = popular * f64::from(trials);
+        assert!((admitted - expected).abs() <= 5.0 * (expected * (1.0 - popular)).sqrt());
+    }
+}
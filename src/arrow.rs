@@ -0,0 +1,87 @@
+//! Integration with [Apache Arrow](https://arrow.apache.org/).
+//!
+//! Requires the `arrow` feature.
+//!
+//! Query engines built on Arrow, such as DataFusion and Polars, select rows
+//! of a batch with a Boolean mask. The helpers here build that mask for
+//! Bernoulli row sampling from skip counts, so generating it costs one RNG
+//! call per selected row, rather than one per row, on top of zeroing the mask.
+
+use crate::FastBernoulli;
+use arrow_array::{BooleanArray, RecordBatch};
+use arrow_buffer::BooleanBufferBuilder;
+use rand::Rng;
+
+/// Build a selection mask for the next `rows` rows, with each row selected
+/// with `bernoulli`'s probability.
+///
+/// This is exactly equivalent to calling `bernoulli.trial` once per row, and
+/// leaves `bernoulli` in the same state, so a sampler can be carried across
+/// the batches of a stream. The mask has no nulls.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{arrow::selection, FastBernoulli};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut bernoulli = FastBernoulli::new(0.01, &mut rng);
+///
+/// let mask = selection(&mut bernoulli, 8192, &mut rng);
+/// assert_eq!(mask.len(), 8192);
+/// // Pass `mask` to `arrow::compute::filter_record_batch`...
+/// # let _ = mask.true_count();
+/// ```
+pub fn selection<R>(bernoulli: &mut FastBernoulli, rows: usize, rng: &mut R) -> BooleanArray
+where
+    R: Rng + ?Sized,
+{
+    let mut mask = BooleanBufferBuilder::new(rows);
+    mask.append_n(rows, false);
+    for position in bernoulli.sampled_positions(rows, rng) {
+        mask.set_bit(position, true);
+    }
+    BooleanArray::new(mask.finish(), None)
+}
+
+/// Build a selection mask for every row of `batch`.
+///
+/// This is [`selection`] with `batch.num_rows()` rows.
+#[inline]
+pub fn batch_selection<R>(
+    bernoulli: &mut FastBernoulli,
+    batch: &RecordBatch,
+    rng: &mut R,
+) -> BooleanArray
+where
+    R: Rng + ?Sized,
+{
+    selection(bernoulli, batch.num_rows(), rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Array;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn selection_matches_per_row_trials() {
+        let mut bernoulli = FastBernoulli::new(0.05, &mut StdRng::seed_from_u64(3));
+        let mut per_row = FastBernoulli::new(0.05, &mut StdRng::seed_from_u64(3));
+
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut per_row_rng = rng.clone();
+
+        for rows in [0, 1, 1000, 77] {
+            let mask = selection(&mut bernoulli, rows, &mut rng);
+            assert_eq!(mask.null_count(), 0);
+            let expected: Vec<Option<bool>> = (0..rows)
+                .map(|_| Some(per_row.trial(&mut per_row_rng)))
+                .collect();
+            assert_eq!(mask, BooleanArray::from(expected));
+            assert_eq!(bernoulli.skip_count(), per_row.skip_count());
+        }
+    }
+}
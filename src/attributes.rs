@@ -0,0 +1,96 @@
+//! Conventional sample-rate attributes for exporting sampled items.
+//!
+//! Tracing and metrics backends rescale sampled items by their sampling
+//! probability, but each expects to find it under its own, conventional
+//! attribute. The helpers here produce those attributes from a
+//! [`SampleDecision`]'s weight, so that exporters attach the right metadata.
+//!
+//! All of them describe the probability as `1 / weight`, rather than the
+//! decision's raw probability, so that they stay consistent with decisions
+//! whose weights were adjusted after the fact, and forced samples are
+//! described as sampled with certainty.
+//!
+//! # Example
+//!
+//! ```
+//! use fast_bernoulli::{attributes, SampleDecision};
+//!
+//! let decision = SampleDecision::new(0.25);
+//!
+//! assert_eq!(
+//!     attributes::otel(&decision),
+//!     [("sampling.probability", 0.25), ("sampling.adjusted_count", 4.0)],
+//! );
+//! assert_eq!(attributes::datadog(&decision), [("_sample_rate", 0.25)]);
+//! assert_eq!(attributes::otel_threshold(&decision).as_deref(), Some("c"));
+//! ```
+
+use crate::SampleDecision;
+
+/// The OpenTelemetry span attribute for the probability an item was sampled
+/// with.
+pub const OTEL_SAMPLING_PROBABILITY: &str = "sampling.probability";
+
+/// The OpenTelemetry span attribute for the number of items a sampled item
+/// represents.
+pub const OTEL_ADJUSTED_COUNT: &str = "sampling.adjusted_count";
+
+/// The Datadog span metric for the probability a span was sampled with.
+pub const DATADOG_SAMPLE_RATE: &str = "_sample_rate";
+
+/// Get the OpenTelemetry probability and adjusted-count attributes for a
+/// sampled item.
+pub fn otel(decision: &SampleDecision) -> [(&'static str, f64); 2] {
+    [
+        (OTEL_SAMPLING_PROBABILITY, 1.0 / decision.weight),
+        (OTEL_ADJUSTED_COUNT, decision.weight),
+    ]
+}
+
+/// Get the Datadog sample-rate metric for a sampled span.
+pub fn datadog(decision: &SampleDecision) -> [(&'static str, f64); 1] {
+    [(DATADOG_SAMPLE_RATE, 1.0 / decision.weight)]
+}
+
+/// Get the OpenTelemetry rejection threshold for a sampled item, the `th`
+/// value of the `ot` entry in W3C `tracestate`, or `None` if the item's
+/// weight is infinite.
+///
+/// The threshold is `(1 - probability) * 2^56`, written as up to 14
+/// hexadecimal digits with trailing zeros removed, and `"0"` for a probability
+/// of `1.0`.
+pub fn otel_threshold(decision: &SampleDecision) -> Option<String> {
+    let probability = 1.0 / decision.weight;
+    if !(probability > 0.0 && probability <= 1.0) {
+        return None;
+    }
+    const MAX: u64 = 1 << 56;
+    // Scaling by a power of two is exact, so round `probability * 2^56`
+    // rather than `1 - probability`, which would lose its low bits. Never
+    // reach `2^56`, which would reject everything.
+    let threshold = (MAX - (probability * MAX as f64).round() as u64).min(MAX - 1);
+    if threshold == 0 {
+        return Some("0".into());
+    }
+    let hex = format!("{:014x}", threshold);
+    Some(hex.trim_end_matches('0').into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_follow_the_spec_examples() {
+        let threshold = |p| otel_threshold(&SampleDecision::new(p));
+        assert_eq!(threshold(1.0).as_deref(), Some("0"));
+        assert_eq!(threshold(0.5).as_deref(), Some("8"));
+        assert_eq!(threshold(0.25).as_deref(), Some("c"));
+        assert_eq!(threshold(0.01).as_deref(), Some("fd70a3d70a3d71"));
+        assert_eq!(threshold(0.0), None);
+        assert_eq!(
+            otel_threshold(&SampleDecision::new_forced()).as_deref(),
+            Some("0")
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::fmt;
+
+/// A sampler that backs off while the consumer of its samples is congested.
+///
+/// Before each trial, a user-supplied closure reports whether the downstream
+/// consumer (a channel, a queue, an exporter) is saturated. While it is, the
+/// sampler switches to a lower probability, or stops sampling entirely with a
+/// congested probability of zero; once the congestion clears, it resumes its
+/// normal probability.
+///
+/// Every event is counted in [`BackpressureStats`], separately for normal and
+/// congested periods, and sampled events come with the weight in effect when
+/// they were sampled. Summing weights, or combining the statistics, keeps
+/// estimates honest about the periods when sampling was reduced.
+///
+/// The congestion closure is called on every trial, so it should be cheap,
+/// such as comparing a queue's length against a threshold.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::BackpressureSampler;
+/// use std::sync::mpsc;
+///
+/// let mut rng = rand::thread_rng();
+/// let (sender, receiver) = mpsc::sync_channel::<u32>(1024);
+/// # let pending = std::cell::Cell::new(0);
+///
+/// // Sample 1% of events normally, and none while the exporter is backed up.
+/// let mut sampler = BackpressureSampler::new(0.01, 0.0, || pending.get() > 900, &mut rng);
+///
+/// for event in 0..10_000 {
+///     if let Some(_weight) = sampler.trial(&mut rng) {
+///         # pending.set(pending.get() + 1);
+///         let _ = sender.try_send(event);
+///     }
+/// }
+///
+/// let stats = sampler.stats();
+/// assert_eq!(stats.events + stats.congested_events, 10_000);
+/// # drop(receiver);
+/// ```
+pub struct BackpressureSampler<C> {
+    normal: FastBernoulli,
+    congested: FastBernoulli,
+    is_congested: C,
+    currently_congested: bool,
+    stats: BackpressureStats,
+}
+
+/// Statistics about a [`BackpressureSampler`]'s normal and congested periods.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[non_exhaustive]
+pub struct BackpressureStats {
+    /// Events seen while the consumer was not congested.
+    pub events: u64,
+    /// Events sampled while the consumer was not congested.
+    pub samples: u64,
+    /// Events seen while the consumer was congested.
+    pub congested_events: u64,
+    /// Events sampled while the consumer was congested.
+    pub congested_samples: u64,
+    /// The number of distinct periods of congestion that have begun.
+    pub congestion_periods: u64,
+}
+
+impl<C> fmt::Debug for BackpressureSampler<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackpressureSampler")
+            .field("normal", &self.normal)
+            .field("congested", &self.congested)
+            .field("currently_congested", &self.currently_congested)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C> BackpressureSampler<C>
+where
+    C: FnMut() -> bool,
+{
+    /// Construct a new `BackpressureSampler` that samples with `probability`
+    /// normally and with `congested_probability` while `is_congested` returns
+    /// `true`.
+    ///
+    /// # Panics
+    ///
+    /// Both probabilities must be within the range `0.0 <= probability <= 1.0`
+    /// and this method will panic if that is not the case.
+    pub fn new<R>(
+        probability: f64,
+        congested_probability: f64,
+        is_congested: C,
+        rng: &mut R,
+    ) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        BackpressureSampler {
+            normal: FastBernoulli::new(probability, rng),
+            congested: FastBernoulli::new(congested_probability, rng),
+            is_congested,
+            currently_congested: false,
+            stats: BackpressureStats::default(),
+        }
+    }
+
+    /// Check for congestion, and perform a trial at the probability for the
+    /// current state.
+    ///
+    /// Returns the sampled event's weight, which is the reciprocal of the
+    /// probability it was sampled with, or `None` if it was not sampled.
+    pub fn trial<R>(&mut self, rng: &mut R) -> Option<f64>
+    where
+        R: Rng + ?Sized,
+    {
+        let congested = (self.is_congested)();
+        if congested && !self.currently_congested {
+            self.stats.congestion_periods += 1;
+        }
+        self.currently_congested = congested;
+
+        let (bernoulli, events, samples) = if congested {
+            (
+                &mut self.congested,
+                &mut self.stats.congested_events,
+                &mut self.stats.congested_samples,
+            )
+        } else {
+            (
+                &mut self.normal,
+                &mut self.stats.events,
+                &mut self.stats.samples,
+            )
+        };
+
+        *events += 1;
+        if bernoulli.trial(rng) {
+            *samples += 1;
+            Some(1.0 / bernoulli.effective_probability())
+        } else {
+            None
+        }
+    }
+
+    /// Was the consumer congested as of the last trial?
+    #[inline]
+    pub fn is_congested(&self) -> bool {
+        self.currently_congested
+    }
+
+    /// Get the probability that the next trial will use, assuming the
+    /// congestion state doesn't change.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        if self.currently_congested {
+            self.congested.probability()
+        } else {
+            self.normal.probability()
+        }
+    }
+
+    /// Get statistics about normal and congested periods so far.
+    #[inline]
+    pub fn stats(&self) -> BackpressureStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn counts_every_event_while_disabled() {
+        let mut rng = rand::thread_rng();
+        let congested = Cell::new(false);
+        let mut sampler = BackpressureSampler::new(1.0, 0.0, || congested.get(), &mut rng);
+
+        for round in 0..3 {
+            congested.set(round % 2 == 1);
+            for _ in 0..100 {
+                let weight = sampler.trial(&mut rng);
+                assert_eq!(weight.is_some(), !congested.get());
+            }
+            assert_eq!(sampler.is_congested(), congested.get());
+        }
+
+        let stats = sampler.stats();
+        assert_eq!(stats.events, 200);
+        assert_eq!(stats.samples, 200);
+        assert_eq!(stats.congested_events, 100);
+        assert_eq!(stats.congested_samples, 0);
+        assert_eq!(stats.congestion_periods, 1);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::panic::Location;
+
+/// Throttling for expensive diagnostics, such as capturing a backtrace, at
+/// error sites.
+///
+/// Capturing a backtrace every time an error occurs is too expensive, but
+/// never capturing one leaves you blind. `BacktraceThrottler` captures for a
+/// sampled subset of occurrences and counts the rest, per site.
+///
+/// When a capture should happen, [`trial`][BacktraceThrottler::trial] returns
+/// its weight: the number of occurrences at that site since the previous
+/// capture, including this one. Summing the weights of all captures at a
+/// site therefore gives its exact number of occurrences, up to the
+/// occurrences suppressed since its last capture, which are reported by
+/// [`suppressed`][BacktraceThrottler::suppressed].
+///
+/// Sites are identified by any hashable key, defaulting to the source location
+/// of the call.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::BacktraceThrottler;
+/// use std::backtrace::Backtrace;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut throttler = BacktraceThrottler::new(0.01, &mut rng);
+///
+/// # fn report(_: &Backtrace, _: u64) {}
+/// // At an error site...
+/// if let Some(weight) = throttler.trial_here(&mut rng) {
+///     let backtrace = Backtrace::force_capture();
+///     // This backtrace stands in for `weight` occurrences at this site.
+///     report(&backtrace, weight);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BacktraceThrottler<K = &'static Location<'static>> {
+    bernoulli: FastBernoulli,
+    // Occurrences at each site since its last capture.
+    suppressed: HashMap<K, u64>,
+}
+
+impl<K> BacktraceThrottler<K>
+where
+    K: Hash + Eq,
+{
+    /// Construct a new `BacktraceThrottler` that captures occurrences with the
+    /// given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        BacktraceThrottler {
+            bernoulli: FastBernoulli::new(probability, rng),
+            suppressed: HashMap::new(),
+        }
+    }
+
+    /// Perform a trial for an occurrence at `site`.
+    ///
+    /// Returns the capture's weight if a capture should happen, or `None` if
+    /// the occurrence should only be counted.
+    pub fn trial<R>(&mut self, site: K, rng: &mut R) -> Option<u64>
+    where
+        R: Rng + ?Sized,
+    {
+        let suppressed = self.suppressed.entry(site).or_insert(0);
+        if self.bernoulli.trial(rng) {
+            Some(std::mem::take(suppressed) + 1)
+        } else {
+            *suppressed += 1;
+            None
+        }
+    }
+
+    /// Get the number of occurrences at `site` that have been suppressed since
+    /// its last capture.
+    pub fn suppressed(&self, site: &K) -> u64 {
+        self.suppressed.get(site).copied().unwrap_or(0)
+    }
+
+    /// Get the probability with which occurrences are captured.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+}
+
+impl BacktraceThrottler<&'static Location<'static>> {
+    /// Perform a trial for an occurrence at the caller's source location.
+    ///
+    /// See [`trial`][BacktraceThrottler::trial].
+    #[track_caller]
+    pub fn trial_here<R>(&mut self, rng: &mut R) -> Option<u64>
+    where
+        R: Rng + ?Sized,
+    {
+        self.trial(Location::caller(), rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_account_for_every_occurrence() {
+        let mut rng = rand::thread_rng();
+        let mut throttler = BacktraceThrottler::new(0.1, &mut rng);
+
+        let mut total = 0;
+        for _ in 0..10_000 {
+            if let Some(weight) = throttler.trial("site", &mut rng) {
+                total += weight;
+            }
+        }
+
+        assert_eq!(total + throttler.suppressed(&"site"), 10_000);
+    }
+}
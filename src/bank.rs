@@ -0,0 +1,208 @@
+use crate::skip_count_from_uniform;
+use rand::{Rng, RngCore};
+
+/// Many independent Bernoulli samplers, one per entity, stored as a structure
+/// of arrays.
+///
+/// A `Vec<FastBernoulli>` interleaves each entity's probability with its skip
+/// count, but the common case of a trial only touches the skip count. A
+/// `BernoulliBank` keeps all the skip counts contiguous, so a batch of trials
+/// over many entities streams through a quarter of the memory, and only reads
+/// an entity's probability when it is sampled.
+///
+/// Entities are identified by their index in the bank, in the order they were
+/// added.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::BernoulliBank;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // One sampler per tracked connection.
+/// let mut bank = BernoulliBank::new();
+/// let connections: Vec<usize> = (0..100_000).map(|_| bank.push(0.01, &mut rng)).collect();
+///
+/// // Each tick, every connection with activity gets a trial.
+/// let mut active = connections.clone();
+/// let sampled = bank.trial_many(&mut active, &mut rng);
+/// for connection in &active[..sampled] {
+///     // Record a sample of this connection's activity...
+///     # let _ = connection;
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BernoulliBank {
+    skip_counts: Vec<u32>,
+    probabilities: Vec<f64>,
+}
+
+impl BernoulliBank {
+    /// Construct a new, empty `BernoulliBank`.
+    #[inline]
+    pub fn new() -> Self {
+        BernoulliBank::default()
+    }
+
+    /// Construct a new, empty `BernoulliBank` with room for `capacity` entities.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        BernoulliBank {
+            skip_counts: Vec::with_capacity(capacity),
+            probabilities: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Add a sampler for a new entity with the given probability, returning
+    /// the entity's index.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn push<R>(&mut self, probability: f64, rng: &mut R) -> usize
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        let mut rng = rng;
+        self.skip_counts
+            .push(draw_skip_count(probability, &mut rng));
+        self.probabilities.push(probability);
+        self.skip_counts.len() - 1
+    }
+
+    /// Perform a trial for the given entity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `entity` is out of bounds.
+    #[inline]
+    pub fn trial<R>(&mut self, entity: usize, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let skip_count = &mut self.skip_counts[entity];
+        if *skip_count > 0 {
+            *skip_count -= 1;
+            return false;
+        }
+
+        let probability = self.probabilities[entity];
+        let mut rng = rng;
+        *skip_count = draw_skip_count(probability, &mut rng);
+        probability != 0.0
+    }
+
+    /// Perform a trial for each of the given entities, and reorder `entities`
+    /// so that the sampled ones come first, returning how many were sampled.
+    ///
+    /// The sampled entities keep their relative order, but the unsampled ones
+    /// may not. This doesn't allocate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any entity is out of bounds.
+    pub fn trial_many<R>(&mut self, entities: &mut [usize], rng: &mut R) -> usize
+    where
+        R: Rng + ?Sized,
+    {
+        let mut sampled = 0;
+        for i in 0..entities.len() {
+            if self.trial(entities[i], rng) {
+                entities.swap(sampled, i);
+                sampled += 1;
+            }
+        }
+        sampled
+    }
+
+    /// Get the given entity's probability.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `entity` is out of bounds.
+    #[inline]
+    pub fn probability(&self, entity: usize) -> f64 {
+        self.probabilities[entity]
+    }
+
+    /// Set the given entity's probability, drawing it a new skip count.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `entity` is out of bounds, or if the probability is not within
+    /// the range `0.0 <= probability <= 1.0`.
+    pub fn set_probability<R>(&mut self, entity: usize, probability: f64, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        let mut rng = rng;
+        self.skip_counts[entity] = draw_skip_count(probability, &mut rng);
+        self.probabilities[entity] = probability;
+    }
+
+    /// Get the given entity's skip count.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `entity` is out of bounds.
+    #[inline]
+    pub fn skip_count(&self, entity: usize) -> u32 {
+        self.skip_counts[entity]
+    }
+
+    /// Get the number of entities.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.skip_counts.len()
+    }
+
+    /// Does this bank have no entities?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.skip_counts.is_empty()
+    }
+}
+
+/// Draw a skip count for `probability`, as `FastBernoulli` does.
+fn draw_skip_count(probability: f64, rng: &mut dyn RngCore) -> u32 {
+    if probability == 0.0 {
+        u32::MAX
+    } else if probability == 1.0 {
+        0
+    } else {
+        skip_count_from_uniform(probability, rng.gen())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trial_many_partitions_sampled_entities() {
+        let mut rng = rand::thread_rng();
+        let mut bank = BernoulliBank::new();
+        for p in [1.0, 0.0, 0.5, 1.0, 0.0] {
+            bank.push(p, &mut rng);
+        }
+
+        let mut entities = [0, 1, 3, 4];
+        assert_eq!(bank.trial_many(&mut entities, &mut rng), 2);
+        assert_eq!(entities, [0, 3, 1, 4]);
+
+        let n = 10_000;
+        let sampled = (0..n).filter(|_| bank.trial(2, &mut rng)).count() as f64;
+        let expected = 0.5 * f64::from(n);
+        assert!((sampled - expected).abs() <= 5.0 * (expected * 0.5).sqrt());
+    }
+}
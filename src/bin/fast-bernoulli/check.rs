@@ -0,0 +1,247 @@
+use clap::Args;
+use fast_bernoulli::FastBernoulli;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::error::Error;
+use std::io::{self, Write};
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    /// The probability with which to sample each trial.
+    #[arg(short, long)]
+    probability: f64,
+
+    /// The seed for the trials' RNG.
+    #[arg(short, long, default_value_t = 0)]
+    seed: u64,
+
+    /// The number of trials to run.
+    #[arg(short = 'n', long, default_value_t = 1_000_000)]
+    trials: u64,
+}
+
+/// Skip counts are histogrammed in power-of-two buckets: bucket `0` holds
+/// zero, and bucket `k` holds `2^(k - 1)..2^k`.
+const BUCKETS: usize = 33;
+
+pub fn run(args: &CheckArgs) -> Result<(), Box<dyn Error>> {
+    let p = args.probability;
+    if !(0.0..=1.0).contains(&p) {
+        return Err("the probability must be in the range `0.0 <= p <= 1.0`".into());
+    }
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let mut bernoulli = FastBernoulli::new(p, &mut rng);
+
+    // Walk from sample to sample, rather than trial by trial. The skip count
+    // still running at the end of the trials is left out of the histogram,
+    // since it was cut short.
+    let mut samples = 0_u64;
+    let mut histogram = [0_u64; BUCKETS];
+    let mut position = 0_u64;
+    for gap in bernoulli.gaps(&mut rng) {
+        position = position.saturating_add(gap);
+        if position >= args.trials {
+            break;
+        }
+        samples += 1;
+        histogram[bucket(gap)] += 1;
+        position += 1;
+    }
+
+    let mut out = io::stdout().lock();
+    let n = args.trials as f64;
+    writeln!(out, "trials:        {}", args.trials)?;
+    writeln!(out, "samples:       {samples}")?;
+    writeln!(out, "observed rate: {:.6}", samples as f64 / n)?;
+    let sd = (n * p * (1.0 - p)).sqrt();
+    if sd > 0.0 {
+        let z = (samples as f64 - n * p) / sd;
+        writeln!(out, "expected rate: {p:.6} (z = {z:.2})")?;
+    } else {
+        writeln!(out, "expected rate: {p:.6}")?;
+    }
+    if samples == 0 {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "skip counts:")?;
+    writeln!(
+        out,
+        "  {:>23}  {:>12}  {:>14}",
+        "range", "observed", "expected"
+    )?;
+    let last = histogram.iter().rposition(|&count| count > 0).unwrap_or(0);
+    let expected: Vec<f64> = (0..BUCKETS)
+        .map(|k| samples as f64 * bucket_probability(p, k))
+        .collect();
+    for k in 0..=last {
+        let (low, high) = bucket_range(k);
+        writeln!(
+            out,
+            "  {:>23}  {:>12}  {:>14.1}",
+            format!("{low}..={high}"),
+            histogram[k],
+            expected[k]
+        )?;
+    }
+
+    if p < 1.0 {
+        let (statistic, degrees_of_freedom) = chi_squared(&histogram, &expected);
+        writeln!(out)?;
+        writeln!(out, "goodness of fit to the geometric distribution:")?;
+        if degrees_of_freedom == 0 {
+            writeln!(out, "  too few samples")?;
+        } else {
+            writeln!(
+                out,
+                "  chi-squared = {statistic:.2} on {degrees_of_freedom} degrees of freedom, p-value = {:.4}",
+                chi_squared_p_value(statistic, degrees_of_freedom)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn bucket(gap: u64) -> usize {
+    (u64::BITS - gap.leading_zeros()) as usize
+}
+
+fn bucket_range(k: usize) -> (u64, u64) {
+    match k {
+        0 => (0, 0),
+        _ => (1 << (k - 1), (1 << k) - 1),
+    }
+}
+
+/// The probability that a skip count lands in bucket `k`: for a geometric
+/// distribution counting failures before a success, `(1 - p)^low - (1 -
+/// p)^(high + 1)`.
+fn bucket_probability(p: f64, k: usize) -> f64 {
+    let (low, high) = bucket_range(k);
+    let survival = |n: u64| match n {
+        0 => 1.0,
+        _ => ((-p).ln_1p() * n as f64).exp(),
+    };
+    let upper = if k == BUCKETS - 1 {
+        0.0
+    } else {
+        survival(high + 1)
+    };
+    survival(low) - upper
+}
+
+/// Pearson's chi-squared statistic, after merging buckets, from the tail
+/// down, until each expects at least five counts. Returns the statistic and
+/// its degrees of freedom.
+fn chi_squared(observed: &[u64], expected: &[f64]) -> (f64, usize) {
+    let mut cells = Vec::new();
+    let (mut o, mut e) = (0.0, 0.0);
+    for k in (0..observed.len()).rev() {
+        o += observed[k] as f64;
+        e += expected[k];
+        if e >= 5.0 {
+            cells.push((o, e));
+            (o, e) = (0.0, 0.0);
+        }
+    }
+    if let Some(first) = cells.last_mut() {
+        first.0 += o;
+        first.1 += e;
+    }
+    let statistic = cells.iter().map(|(o, e)| (o - e) * (o - e) / e).sum();
+    (statistic, cells.len().saturating_sub(1))
+}
+
+/// The approximate probability that a chi-squared variable with the given
+/// degrees of freedom exceeds `statistic`, by the Wilson–Hilferty
+/// transformation to a standard normal.
+fn chi_squared_p_value(statistic: f64, degrees_of_freedom: usize) -> f64 {
+    let k = degrees_of_freedom as f64;
+    let variance = 2.0 / (9.0 * k);
+    let z = ((statistic / k).cbrt() - (1.0 - variance)) / variance.sqrt();
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+/// The complementary error function, to within about `1.2e-7`, from
+/// Numerical Recipes' Chebyshev fit.
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x.abs());
+    let poly = -x * x - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let y = t * poly.exp();
+    if x >= 0.0 {
+        y
+    } else {
+        2.0 - y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_probabilities_are_geometric() {
+        for p in [1e-9, 0.001, 0.3, 0.5, 0.999] {
+            let total: f64 = (0..BUCKETS).map(|k| bucket_probability(p, k)).sum();
+            assert!((total - 1.0).abs() < 1e-12, "p = {p}: {total}");
+        }
+
+        // Bucket `0` is a skip count of zero, bucket `1` of one, and bucket
+        // `2` of two or three.
+        let p = 0.5;
+        assert!((bucket_probability(p, 0) - 0.5).abs() < 1e-15);
+        assert!((bucket_probability(p, 1) - 0.25).abs() < 1e-15);
+        assert!((bucket_probability(p, 2) - (0.125 + 0.0625)).abs() < 1e-15);
+
+        // The last bucket holds the whole tail, from `2^31` up.
+        let tail = (-2_147_483_648.0 * 1e-12_f64).exp();
+        assert!((bucket_probability(1e-12, BUCKETS - 1) - tail).abs() < 1e-9);
+    }
+
+    #[test]
+    fn chi_squared_merges_sparse_buckets() {
+        // A perfect fit has a statistic of zero.
+        let expected = [50.0, 25.0, 12.5, 6.25, 3.125, 3.125];
+        let observed = [50, 25, 12, 6, 3, 3];
+        let (statistic, degrees_of_freedom) = chi_squared(&observed, &expected);
+        assert!(statistic < 0.1);
+        // The last two buckets expect fewer than five counts each, so they
+        // are merged, leaving five cells.
+        assert_eq!(degrees_of_freedom, 4);
+
+        // Leftover expected counts too small for a cell of their own are
+        // merged into the first cell.
+        let (statistic, degrees_of_freedom) = chi_squared(&[2, 10], &[2.0, 10.0]);
+        assert_eq!((statistic, degrees_of_freedom), (0.0, 0));
+
+        let (statistic, _) = chi_squared(&[60, 40], &[50.0, 50.0]);
+        assert!((statistic - 4.0).abs() < 1e-12);
+        assert!((chi_squared_p_value(statistic, 1) - 0.0455).abs() < 0.005);
+    }
+
+    #[test]
+    fn erfc_matches_known_values() {
+        for (x, expected) in [
+            (0.0, 1.0),
+            (0.5, 0.479_500_122_186_953_5),
+            (1.0, 0.157_299_207_050_285_1),
+            (2.0, 0.004_677_734_981_047_266),
+            (-1.0, 1.842_700_792_949_715),
+        ] {
+            assert!(
+                (erfc(x) - expected).abs() < 1.2e-7,
+                "erfc({x}) = {}",
+                erfc(x)
+            );
+        }
+    }
+}
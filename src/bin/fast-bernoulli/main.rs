@@ -0,0 +1,43 @@
+//! The `fast-bernoulli` command-line tool.
+//!
+//! Requires the `cli` feature.
+
+use clap::{Parser, Subcommand};
+use std::process::ExitCode;
+
+mod check;
+mod sample;
+
+/// Tools for validating and using Bernoulli sampling.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run trials and report the observed rate, the skip-count histogram, and
+    /// how well the skip counts fit the expected geometric distribution.
+    Check(check::CheckArgs),
+
+    /// Sample JSON lines by a key field, keeping or dropping all the lines
+    /// with the same key together, such as whole traces or users.
+    Sample(sample::SampleArgs),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Check(args) => check::run(&args),
+        Command::Sample(args) => sample::run(&args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use clap::Args;
+use fast_bernoulli::ClusterSampler;
+use serde_json::Value;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Args)]
+pub struct SampleArgs {
+    /// The probability with which to sample each key.
+    #[arg(short, long)]
+    probability: f64,
+
+    /// The field holding each line's key. Either a top-level field name, or a
+    /// JSON pointer, such as `/span/trace_id`.
+    #[arg(short, long)]
+    key: String,
+
+    /// The salt for hashing keys. Runs with the same salt and probability
+    /// select the same keys.
+    #[arg(short, long, default_value_t = 0)]
+    salt: u64,
+
+    /// Keep lines that have no key, instead of dropping them.
+    #[arg(long)]
+    keep_missing: bool,
+
+    /// Only sample this share of the keys, out of `--nodes`, so that separate
+    /// runs over the same input together select each key exactly once.
+    #[arg(long, default_value_t = 0, requires = "nodes")]
+    node: u32,
+
+    /// The number of shares to split the sampled keys into.
+    #[arg(long, default_value_t = 1)]
+    nodes: u32,
+
+    /// The JSON lines files to read, or standard input if none are given.
+    files: Vec<PathBuf>,
+}
+
+pub fn run(args: &SampleArgs) -> Result<(), Box<dyn Error>> {
+    if !(0.0..=1.0).contains(&args.probability) {
+        return Err("the probability must be in the range `0.0 <= p <= 1.0`".into());
+    }
+    if args.node >= args.nodes {
+        return Err("`--node` must be less than `--nodes`".into());
+    }
+    let sampler = ClusterSampler::new(args.salt, args.probability, args.node, args.nodes);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if args.files.is_empty() {
+        sample(args, &sampler, "<stdin>", io::stdin().lock(), &mut out)?;
+    } else {
+        for path in &args.files {
+            let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            let name = path.display().to_string();
+            sample(args, &sampler, &name, BufReader::new(file), &mut out)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn sample(
+    args: &SampleArgs,
+    sampler: &ClusterSampler,
+    name: &str,
+    input: impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value =
+            serde_json::from_str(&line).map_err(|e| format!("{name}:{}: {e}", i + 1))?;
+        let key = if args.key.starts_with('/') {
+            value.pointer(&args.key)
+        } else {
+            value.get(&args.key)
+        };
+        let keep = match key {
+            None | Some(Value::Null) => args.keep_missing,
+            // Hash strings' contents, so that `"abc"` and `abc` in other
+            // tools agree, and everything else by its JSON text.
+            Some(Value::String(key)) => sampler.trial(key.as_str()),
+            Some(key) => sampler.trial(key.to_string().as_str()),
+        };
+        if keep {
+            writeln!(out, "{line}")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: SampleArgs,
+    }
+
+    fn sample_lines(args: &[&str], input: &str) -> Vec<String> {
+        let args = Cli::parse_from(["sample"].iter().chain(args)).args;
+        let sampler = ClusterSampler::new(args.salt, args.probability, args.node, args.nodes);
+        let mut out = Vec::new();
+        sample(&args, &sampler, "<test>", input.as_bytes(), &mut out).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    }
+
+    fn input() -> String {
+        (0..1000)
+            .map(|i| {
+                format!(
+                    "{{\"span\": {{\"trace_id\": \"t{}\"}}, \"id\": {i}}}\n",
+                    i % 100
+                )
+            })
+            .chain(["{\"span\": {}}\n".to_owned(), "\n".to_owned()])
+            .collect()
+    }
+
+    #[test]
+    fn lines_are_sampled_by_json_pointer_keys() {
+        let input = input();
+        let kept = sample_lines(&["-p", "0.5", "-k", "/span/trace_id", "-s", "7"], &input);
+
+        // Lines are kept or dropped with their keys, which are hashed by
+        // their contents.
+        let sampler = ClusterSampler::new(7, 0.5, 0, 1);
+        let expected: Vec<&str> = input
+            .lines()
+            .take(1000)
+            .enumerate()
+            .filter(|(i, _)| sampler.trial(format!("t{}", i % 100).as_str()))
+            .map(|(_, line)| line)
+            .collect();
+        assert_eq!(kept, expected);
+        assert!(!kept.is_empty() && kept.len() < 1000);
+
+        // A top-level field name isn't a pointer, so no line has the key.
+        assert!(sample_lines(&["-p", "1.0", "-k", "span/trace_id"], &input).is_empty());
+        let missing = sample_lines(
+            &["-p", "0.0", "-k", "/span/trace_id", "--keep-missing"],
+            &input,
+        );
+        assert_eq!(missing, ["{\"span\": {}}"]);
+    }
+
+    #[test]
+    fn nodes_split_the_sample() {
+        let input = input();
+        let all = sample_lines(&["-p", "0.5", "-k", "/span/trace_id", "-s", "7"], &input);
+
+        let mut shards: Vec<String> = (0..3)
+            .flat_map(|node| {
+                let node = node.to_string();
+                sample_lines(
+                    &[
+                        "-p",
+                        "0.5",
+                        "-k",
+                        "/span/trace_id",
+                        "-s",
+                        "7",
+                        "--node",
+                        &node,
+                        "--nodes",
+                        "3",
+                    ],
+                    &input,
+                )
+            })
+            .collect();
+        shards.sort();
+        let mut all = all;
+        all.sort();
+        assert_eq!(shards, all);
+    }
+}
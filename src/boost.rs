@@ -0,0 +1,242 @@
+use crate::thinning::ThinnedBernoulli;
+use crate::time::Instant;
+use crate::{ClampStats, ProbabilityBounds, SampleDecision};
+use rand::Rng;
+use std::time::Duration;
+
+/// A sampler whose probability can be temporarily boosted, for example when an
+/// anomaly detector fires, and then decays back to its baseline.
+///
+/// [`boost`][BoostedSampler::boost] multiplies the baseline probability by a
+/// factor, which then decays linearly back to `1.0` over the boost's duration.
+///
+/// Boosted trials are as cheap as a plain
+/// [`FastBernoulli`][crate::FastBernoulli]'s, even though the probability
+/// changes continuously. Each sampled event's [`SampleDecision`] records the
+/// probability in effect when it was sampled.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::BoostedSampler;
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sampler = BoostedSampler::new(0.001, &mut rng);
+///
+/// // The anomaly detector fired: sample 100 times as often for now, decaying
+/// // back to normal over the next five minutes.
+/// let now = Instant::now();
+/// sampler.boost(100.0, Duration::from_secs(5 * 60), now, &mut rng);
+/// assert_eq!(sampler.probability(now), 0.1);
+///
+/// if let Some(decision) = sampler.trial(now, &mut rng) {
+///     // Record the sample together with `decision`...
+///     assert_eq!(decision.probability, 0.1);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BoostedSampler {
+    baseline: f64,
+    candidates: ThinnedBernoulli,
+    boost: Option<Boost>,
+    bounds: ProbabilityBounds,
+    clamps: ClampStats,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Boost {
+    factor: f64,
+    start: Instant,
+    duration: Duration,
+}
+
+impl BoostedSampler {
+    /// Construct a new `BoostedSampler` that samples events with the given
+    /// baseline probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(baseline: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        BoostedSampler {
+            baseline,
+            candidates: ThinnedBernoulli::new(baseline, rng),
+            boost: None,
+            bounds: ProbabilityBounds::UNBOUNDED,
+            clamps: ClampStats::default(),
+        }
+    }
+
+    /// Keep the probabilities in effect, boosted or not, within `bounds`.
+    pub fn with_bounds(mut self, bounds: ProbabilityBounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    /// Boost the sampling probability by `factor`, starting at `now` and
+    /// decaying linearly back to the baseline over `duration`.
+    ///
+    /// The boosted probability is capped at `1.0`. A new boost replaces any
+    /// boost already in progress.
+    ///
+    /// # Panics
+    ///
+    /// The factor must be at least `1.0` and finite, and this method will panic
+    /// if that is not the case.
+    pub fn boost<R>(&mut self, factor: f64, duration: Duration, now: Instant, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            factor >= 1.0 && factor.is_finite(),
+            "`factor` must be at least `1.0` and finite"
+        );
+        trace_sampler!(
+            info,
+            from = self.probability(now),
+            to = (self.baseline * factor).min(1.0),
+            ?duration,
+            "boosted probability"
+        );
+        self.boost = Some(Boost {
+            factor,
+            start: now,
+            duration,
+        });
+        self.rebound(now, rng);
+    }
+
+    /// Perform a trial for an event that occurred at `now`.
+    ///
+    /// Returns the decision, recording the probability in effect at `now`, if
+    /// the event should be sampled, or `None` if it should not.
+    pub fn trial<R>(&mut self, now: Instant, rng: &mut R) -> Option<SampleDecision>
+    where
+        R: Rng + ?Sized,
+    {
+        let probability = self
+            .bounds
+            .apply(self.unbounded_probability(now), &mut self.clamps);
+        if self.boost.is_some() && !self.is_boosted(now) {
+            self.rebound(now, rng);
+        }
+
+        if !self.candidates.trial(probability, rng) {
+            return None;
+        }
+
+        // The candidates' skip counts may have been clamped, so scale by the
+        // rate at which they were actually drawn.
+        let bound = self.candidates.bound();
+        let effective = self.candidates.effective_bound();
+        if effective != bound {
+            return Some(SampleDecision::new(probability * effective / bound));
+        }
+        Some(SampleDecision::new(probability))
+    }
+
+    /// Get the probability with which an event occurring at `now` would be
+    /// sampled.
+    pub fn probability(&self, now: Instant) -> f64 {
+        self.bounds.clamp(self.unbounded_probability(now))
+    }
+
+    /// Get the rate at which an event occurring at `now` would actually be
+    /// sampled; see
+    /// [`FastBernoulli::effective_probability`][crate::FastBernoulli::effective_probability].
+    pub fn effective_probability(&self, now: Instant) -> f64 {
+        crate::effective::effective_probability(self.probability(now))
+    }
+
+    /// Get the counts of decisions whose probability was clamped by the bounds.
+    #[inline]
+    pub fn clamp_stats(&self) -> ClampStats {
+        self.clamps
+    }
+
+    fn unbounded_probability(&self, now: Instant) -> f64 {
+        match self.boost {
+            Some(boost) => {
+                let elapsed = now.saturating_duration_since(boost.start);
+                if elapsed >= boost.duration {
+                    return self.baseline;
+                }
+                let remaining = 1.0 - elapsed.as_secs_f64() / boost.duration.as_secs_f64();
+                (self.baseline * (1.0 + (boost.factor - 1.0) * remaining)).min(1.0)
+            }
+            None => self.baseline,
+        }
+    }
+
+    /// Get the baseline probability, to which boosts decay.
+    #[inline]
+    pub fn baseline(&self) -> f64 {
+        self.baseline
+    }
+
+    /// Is a boost in progress at `now`?
+    pub fn is_boosted(&self, now: Instant) -> bool {
+        self.boost
+            .is_some_and(|boost| now.saturating_duration_since(boost.start) < boost.duration)
+    }
+
+    /// Draw candidates at the current probability, which bounds the probability
+    /// at any later time, and drop the boost once it has decayed.
+    fn rebound<R>(&mut self, now: Instant, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.is_boosted(now) {
+            self.boost = None;
+        }
+        self.candidates.rebound(self.probability(now), rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_at_the_decaying_probability() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = BoostedSampler::new(0.01, &mut rng);
+
+        let start = Instant::now();
+        let duration = Duration::from_secs(100);
+        sampler.boost(10.0, duration, start, &mut rng);
+
+        let halfway = start + duration / 2;
+        assert!((sampler.probability(halfway) - 0.055).abs() < 1e-12);
+        assert!(!sampler.is_boosted(start + duration));
+        assert_eq!(sampler.probability(start + duration), 0.01);
+
+        // One event per millisecond, for twice the boost's duration.
+        let mut expected = 0.0;
+        let mut variance = 0.0;
+        let mut sampled = 0;
+        for millis in 0..200_000 {
+            let now = start + Duration::from_millis(millis);
+            let p = sampler.probability(now);
+            expected += p;
+            variance += p * (1.0 - p);
+            if let Some(decision) = sampler.trial(now, &mut rng) {
+                assert_eq!(decision.probability, p);
+                sampled += 1;
+            }
+        }
+
+        let tolerance = 5.0 * variance.sqrt();
+        assert!(
+            (sampled as f64 - expected).abs() <= tolerance,
+            "expected ~{} samples, found {}",
+            expected,
+            sampled,
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Bootstrap resampling, for confidence intervals on statistics of samples.
+//!
+//! The bootstrap estimates a statistic's sampling distribution by recomputing
+//! it over many resamples of the data, each drawn from the data with
+//! replacement, and reads confidence intervals off the percentiles of the
+//! recomputed values. Combined with the crate's samplers, this gives
+//! confidence intervals for statistics, such as medians and percentiles, that
+//! have no closed-form standard error.
+//!
+//! For data that fits in memory, [`percentile_interval`] does the whole
+//! computation, and [`resample_indices`] generates the resamples it uses. For
+//! streams that can't be stored, the Poisson bootstrap draws a weight for each
+//! item in each replicate from [`poisson_weight`] as the item goes by, and
+//! each replicate accumulates its statistic with those weights.
+
+use rand::Rng;
+
+/// Iterate over the indices of one resample of `len` items: `len` indices
+/// drawn uniformly from `0..len`, with replacement.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::bootstrap;
+///
+/// let mut rng = rand::thread_rng();
+/// let data = [3.0, 1.0, 4.0, 1.0, 5.0];
+///
+/// let resample: Vec<f64> = bootstrap::resample_indices(data.len(), &mut rng)
+///     .map(|i| data[i])
+///     .collect();
+/// assert_eq!(resample.len(), data.len());
+/// ```
+pub fn resample_indices<R>(len: usize, rng: &mut R) -> impl Iterator<Item = usize> + '_
+where
+    R: Rng + ?Sized,
+{
+    (0..len).map(move |_| rng.gen_range(0..len))
+}
+
+/// Draw the weight of one item in one replicate of a Poisson bootstrap: the
+/// number of times it appears in the replicate, from a Poisson distribution
+/// with mean `1`.
+///
+/// As the number of items grows, this approximates resampling with
+/// replacement, without needing to know the number of items ahead of time.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::bootstrap;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Ten replicates of a streamed sum.
+/// let mut sums = [0.0; 10];
+/// for value in 0..1000 {
+///     for sum in &mut sums {
+///         *sum += f64::from(bootstrap::poisson_weight(&mut rng)) * f64::from(value);
+///     }
+/// }
+/// ```
+pub fn poisson_weight<R>(rng: &mut R) -> u32
+where
+    R: Rng + ?Sized,
+{
+    // Inversion: walk up the CDF until it passes a uniform variate.
+    let u: f64 = rng.gen();
+    let mut k = 0;
+    let mut p = (-1.0_f64).exp();
+    let mut cdf = p;
+    while u > cdf && p > 0.0 {
+        k += 1;
+        p /= f64::from(k);
+        cdf += p;
+    }
+    k
+}
+
+/// Compute a bootstrap percentile confidence interval for `statistic` over
+/// `data`, from `resamples` resamples.
+///
+/// Returns the `(1 - confidence) / 2` and `(1 + confidence) / 2` percentiles
+/// of the statistic's values over the resamples.
+///
+/// # Panics
+///
+/// Panics if `data` is empty, if `resamples` is zero, or if the confidence is
+/// not within the range `0.0 < confidence < 1.0`.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{bootstrap, FastBernoulli};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut bernoulli = FastBernoulli::new(0.01, &mut rng);
+///
+/// // A sample of request latencies.
+/// let sample: Vec<f64> = (0..100_000)
+///     .filter(|_| bernoulli.trial(&mut rng))
+///     .map(f64::from)
+///     .collect();
+///
+/// let median = |values: &[f64]| {
+///     let mut values = values.to_vec();
+///     values.sort_by(f64::total_cmp);
+///     values[values.len() / 2]
+/// };
+/// let (low, high) = bootstrap::percentile_interval(&sample, median, 200, 0.95, &mut rng);
+/// assert!(low <= high);
+/// ```
+pub fn percentile_interval<T, F, R>(
+    data: &[T],
+    mut statistic: F,
+    resamples: usize,
+    confidence: f64,
+    rng: &mut R,
+) -> (f64, f64)
+where
+    T: Clone,
+    F: FnMut(&[T]) -> f64,
+    R: Rng + ?Sized,
+{
+    assert!(!data.is_empty(), "`data` must not be empty");
+    assert!(resamples > 0, "`resamples` must be greater than zero");
+    assert!(
+        0.0 < confidence && confidence < 1.0,
+        "`confidence` must be in the range `0.0 < confidence < 1.0`"
+    );
+
+    let mut resample = Vec::with_capacity(data.len());
+    let mut values: Vec<f64> = (0..resamples)
+        .map(|_| {
+            resample.clear();
+            resample.extend(resample_indices(data.len(), rng).map(|i| data[i].clone()));
+            statistic(&resample)
+        })
+        .collect();
+    values.sort_by(f64::total_cmp);
+
+    let percentile = |q: f64| values[((resamples - 1) as f64 * q).round() as usize];
+    (
+        percentile((1.0 - confidence) / 2.0),
+        percentile((1.0 + confidence) / 2.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_covers_the_mean() {
+        let mut rng = rand::thread_rng();
+
+        let n = 10_000;
+        let weights: f64 = (0..n).map(|_| f64::from(poisson_weight(&mut rng))).sum();
+        let expected = f64::from(n);
+        assert!((weights - expected).abs() <= 5.0 * expected.sqrt());
+
+        let data: Vec<f64> = (0..1000).map(f64::from).collect();
+        let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+        let (low, high) = percentile_interval(&data, mean, 500, 0.99, &mut rng);
+        assert!(low < 499.5 && 499.5 < high);
+        // The standard error of the mean is about 9.1.
+        assert!(high - low < 100.0);
+
+        let constant = percentile_interval(&[7.0], mean, 10, 0.5, &mut rng);
+        assert_eq!(constant, (7.0, 7.0));
+    }
+}
@@ -0,0 +1,142 @@
+/// Hard bounds on the probabilities an adaptive sampler may choose.
+///
+/// Adaptive, load-driven, and scheduled samplers choose their own
+/// probabilities, which an operator may need to keep within limits: never
+/// sampling so little that rare problems go unseen, and never so much that
+/// the sampling pipeline is overwhelmed. Samplers configured with bounds,
+/// using their `with_bounds` methods, clamp every probability they choose
+/// into `floor..=ceiling`, and count each clamp in their [`ClampStats`].
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{LoadShedder, ProbabilityBounds};
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Never sample below 0.0001% or above 5%, whatever the load.
+/// let bounds = ProbabilityBounds::new(0.000_001, 0.05);
+/// let idle = || 0.0;
+/// let curve = |load: f64| 1.0 - load;
+/// let mut shedder =
+///     LoadShedder::new(idle, curve, Duration::from_secs(1), &mut rng).with_bounds(bounds);
+///
+/// shedder.trial(Instant::now(), &mut rng);
+/// assert_eq!(shedder.probability(), 0.05);
+/// assert_eq!(shedder.clamp_stats().lowered_to_ceiling, 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbabilityBounds {
+    floor: f64,
+    ceiling: f64,
+}
+
+/// Counts of the probabilities a sampler chose that were clamped by its
+/// [`ProbabilityBounds`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[non_exhaustive]
+pub struct ClampStats {
+    /// The number of probabilities that were raised to the floor.
+    pub raised_to_floor: u64,
+    /// The number of probabilities that were lowered to the ceiling.
+    pub lowered_to_ceiling: u64,
+}
+
+impl ProbabilityBounds {
+    /// Bounds that allow every probability, from `0.0` to `1.0`.
+    pub const UNBOUNDED: ProbabilityBounds = ProbabilityBounds {
+        floor: 0.0,
+        ceiling: 1.0,
+    };
+
+    /// Construct new bounds allowing probabilities from `floor` to `ceiling`,
+    /// inclusive.
+    ///
+    /// # Panics
+    ///
+    /// The bounds must satisfy `0.0 <= floor <= ceiling <= 1.0` and this method
+    /// will panic if that is not the case.
+    pub fn new(floor: f64, ceiling: f64) -> Self {
+        assert!(
+            0.0 <= floor && floor <= ceiling && ceiling <= 1.0,
+            "bounds must satisfy `0.0 <= floor <= ceiling <= 1.0`"
+        );
+        ProbabilityBounds { floor, ceiling }
+    }
+
+    /// Get the lowest allowed probability.
+    #[inline]
+    pub fn floor(&self) -> f64 {
+        self.floor
+    }
+
+    /// Get the highest allowed probability.
+    #[inline]
+    pub fn ceiling(&self) -> f64 {
+        self.ceiling
+    }
+
+    /// Clamp `probability` into these bounds.
+    #[inline]
+    pub fn clamp(&self, probability: f64) -> f64 {
+        probability.clamp(self.floor, self.ceiling)
+    }
+
+    /// Clamp `probability` into these bounds, counting the clamp, if any, in
+    /// `stats`.
+    pub(crate) fn apply(&self, probability: f64, stats: &mut ClampStats) -> f64 {
+        if probability < self.floor {
+            stats.raised_to_floor += 1;
+            trace_sampler!(
+                debug,
+                probability,
+                floor = self.floor,
+                "raised probability to floor"
+            );
+            self.floor
+        } else if probability > self.ceiling {
+            stats.lowered_to_ceiling += 1;
+            trace_sampler!(
+                debug,
+                probability,
+                ceiling = self.ceiling,
+                "lowered probability to ceiling"
+            );
+            self.ceiling
+        } else {
+            probability
+        }
+    }
+}
+
+impl Default for ProbabilityBounds {
+    fn default() -> Self {
+        ProbabilityBounds::UNBOUNDED
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_clamps() {
+        let bounds = ProbabilityBounds::new(0.01, 0.05);
+        let mut stats = ClampStats::default();
+        assert_eq!(bounds.apply(0.001, &mut stats), 0.01);
+        assert_eq!(bounds.apply(0.02, &mut stats), 0.02);
+        assert_eq!(bounds.apply(0.5, &mut stats), 0.05);
+        assert_eq!(
+            stats,
+            ClampStats {
+                raised_to_floor: 1,
+                lowered_to_ceiling: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use crate::{FastBernoulli, IntegerBernoulli, InvalidProbability, WideBernoulli};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::error::Error;
+use std::fmt;
+
+/// A builder for [`FastBernoulli`]s, returned by [`FastBernoulli::builder`].
+///
+/// This is the recommended way to construct a `FastBernoulli` with anything
+/// beyond a probability and an RNG: the probability can be given as a float or
+/// as a ratio, the initial skip count can be drawn from a seed, so that the
+/// first decisions are reproducible, and its draw can be deferred until the
+/// first trial with [`lazy`][SamplerBuilder::lazy].
+///
+/// The same options can also build the other kinds of Bernoulli sampler:
+/// [`integer`][SamplerBuilder::integer] selects float-free skip counts, built
+/// into an [`IntegerBernoulli`], and [`wide`][SamplerBuilder::wide] selects
+/// 64-bit skip counts, built into a [`WideBernoulli`].
+///
+/// Options are validated when building, rather than as they are set, so that
+/// configurations from untrusted sources fail with a [`BuildError`] instead of
+/// panicking.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::FastBernoulli;
+///
+/// # fn main() -> Result<(), fast_bernoulli::BuildError> {
+/// let mut rng = rand::thread_rng();
+///
+/// let mut bernoulli = FastBernoulli::builder().ratio(1, 1000).seed(42).build()?;
+/// assert_eq!(bernoulli.probability(), 0.001);
+///
+/// if bernoulli.trial(&mut rng) {
+///     // Record the sample...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SamplerBuilder {
+    probability: Option<Probability>,
+    seed: Option<u64>,
+}
+
+/// A builder for [`LazyBernoulli`]s, returned by [`SamplerBuilder::lazy`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LazySamplerBuilder {
+    builder: SamplerBuilder,
+}
+
+/// A builder for [`IntegerBernoulli`]s, returned by
+/// [`SamplerBuilder::integer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntegerSamplerBuilder {
+    builder: SamplerBuilder,
+}
+
+/// A builder for [`WideBernoulli`]s, returned by [`SamplerBuilder::wide`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WideSamplerBuilder {
+    builder: SamplerBuilder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Probability {
+    Float(f64),
+    Ratio(u64, u64),
+}
+
+/// An error building a sampler from a [`SamplerBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum BuildError {
+    /// Neither a probability nor a ratio was given.
+    MissingProbability,
+    /// The probability, or the ratio's value, is invalid.
+    InvalidProbability(InvalidProbability),
+    /// The ratio's denominator is zero.
+    ZeroDenominator,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingProbability => write!(f, "no sampling probability was given"),
+            BuildError::InvalidProbability(e) => e.fmt(f),
+            BuildError::ZeroDenominator => write!(f, "sampling ratio has a zero denominator"),
+        }
+    }
+}
+
+impl Error for BuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BuildError::InvalidProbability(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<InvalidProbability> for BuildError {
+    fn from(e: InvalidProbability) -> Self {
+        BuildError::InvalidProbability(e)
+    }
+}
+
+impl FastBernoulli {
+    /// Get a [`SamplerBuilder`] for constructing a `FastBernoulli`.
+    #[inline]
+    pub fn builder() -> SamplerBuilder {
+        SamplerBuilder::default()
+    }
+}
+
+impl SamplerBuilder {
+    /// Sample events with the given probability.
+    #[inline]
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = Some(Probability::Float(probability));
+        self
+    }
+
+    /// Sample `numerator` in `denominator` events.
+    #[inline]
+    pub fn ratio(mut self, numerator: u64, denominator: u64) -> Self {
+        self.probability = Some(Probability::Ratio(numerator, denominator));
+        self
+    }
+
+    /// Draw the initial skip count from an RNG seeded with `seed`, rather than
+    /// from the [`default_rng`][crate::default_rng], so that the first sampled event is the same
+    /// every time.
+    ///
+    /// Later skip counts are drawn from the RNGs passed to each trial.
+    ///
+    /// Seeded skip counts use [`StdRng`], whose algorithm may change between
+    /// major versions of `rand`, so they are only reproducible with the same
+    /// version.
+    #[inline]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Defer drawing the initial skip count until the first trial, building a
+    /// [`LazyBernoulli`] instead of a `FastBernoulli`.
+    ///
+    /// This makes building free of any randomness, for samplers that are
+    /// constructed eagerly but may never be used.
+    #[inline]
+    pub fn lazy(self) -> LazySamplerBuilder {
+        LazySamplerBuilder { builder: self }
+    }
+
+    /// Compute skip counts with integer arithmetic only, building an
+    /// [`IntegerBernoulli`] instead of a `FastBernoulli`.
+    ///
+    /// Ratios are used exactly, rather than converted to a float first, and
+    /// skip counts are the same on every platform.
+    #[inline]
+    pub fn integer(self) -> IntegerSamplerBuilder {
+        IntegerSamplerBuilder { builder: self }
+    }
+
+    /// Use 64-bit skip counts, building a [`WideBernoulli`] instead of a
+    /// `FastBernoulli`.
+    ///
+    /// This keeps probabilities below about one in a billion from being skewed
+    /// by `FastBernoulli`'s 32-bit skip counts.
+    #[inline]
+    pub fn wide(self) -> WideSamplerBuilder {
+        WideSamplerBuilder { builder: self }
+    }
+
+    /// Build the `FastBernoulli`.
+    pub fn build(self) -> Result<FastBernoulli, BuildError> {
+        let mut bernoulli = self.uninitialized()?;
+        bernoulli.reset_initial_skip_count(self.seed, &mut crate::default_rng());
+        Ok(bernoulli)
+    }
+
+    /// Validate the probability, returning it as given.
+    fn validated(&self) -> Result<Probability, BuildError> {
+        let probability = self.probability.ok_or(BuildError::MissingProbability)?;
+        match probability {
+            Probability::Float(p) => InvalidProbability::check(p)?,
+            Probability::Ratio(_, 0) => return Err(BuildError::ZeroDenominator),
+            Probability::Ratio(n, d) if n > d => {
+                return Err(InvalidProbability::OutOfRange(n as f64 / d as f64).into())
+            }
+            Probability::Ratio(..) => {}
+        }
+        Ok(probability)
+    }
+
+    /// Validate the options, returning the probability as a float.
+    fn float_probability(&self) -> Result<f64, BuildError> {
+        Ok(match self.validated()? {
+            Probability::Float(p) => p,
+            Probability::Ratio(n, d) => n as f64 / d as f64,
+        })
+    }
+
+    /// Draw from an RNG seeded with the seed, if any, or the default RNG.
+    fn with_initial_rng<T>(&self, f: impl FnOnce(&mut dyn rand::RngCore) -> T) -> T {
+        match self.seed {
+            Some(seed) => f(&mut StdRng::seed_from_u64(seed)),
+            None => f(&mut crate::default_rng()),
+        }
+    }
+
+    /// Validate the options, returning a `FastBernoulli` whose skip count has
+    /// yet to be drawn.
+    fn uninitialized(&self) -> Result<FastBernoulli, BuildError> {
+        let probability = self.float_probability()?;
+        Ok(FastBernoulli {
+            probability,
+            denominator: 0,
+            skip_count: 0,
+            lineage: crate::lineage::Lineage::new(),
+        })
+    }
+}
+
+impl LazySamplerBuilder {
+    /// Build the `LazyBernoulli`.
+    pub fn build(self) -> Result<LazyBernoulli, BuildError> {
+        Ok(LazyBernoulli {
+            bernoulli: self.builder.uninitialized()?,
+            seed: self.builder.seed,
+            initialized: false,
+        })
+    }
+}
+
+impl IntegerSamplerBuilder {
+    /// Build the `IntegerBernoulli`.
+    pub fn build(self) -> Result<IntegerBernoulli, BuildError> {
+        let probability = self.builder.validated()?;
+        Ok(self.builder.with_initial_rng(|rng| match probability {
+            Probability::Float(p) => IntegerBernoulli::new(p, rng),
+            Probability::Ratio(n, d) => IntegerBernoulli::from_ratio(n, d, rng),
+        }))
+    }
+}
+
+impl WideSamplerBuilder {
+    /// Build the `WideBernoulli`.
+    pub fn build(self) -> Result<WideBernoulli, BuildError> {
+        let probability = self.builder.float_probability()?;
+        Ok(self
+            .builder
+            .with_initial_rng(|rng| WideBernoulli::new(probability, rng)))
+    }
+}
+
+impl FastBernoulli {
+    fn reset_initial_skip_count<R>(&mut self, seed: Option<u64>, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        match seed {
+            Some(seed) => self.reset_skip_count(&mut StdRng::seed_from_u64(seed)),
+            None => self.reset_skip_count(rng),
+        }
+    }
+}
+
+/// A [`FastBernoulli`] that draws its initial skip count on its first trial,
+/// built with [`SamplerBuilder::lazy`].
+///
+/// After the first trial, this behaves exactly like a `FastBernoulli`, at the
+/// cost of one extra, well-predicted branch per trial.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::FastBernoulli;
+///
+/// # fn main() -> Result<(), fast_bernoulli::BuildError> {
+/// let mut rng = rand::thread_rng();
+///
+/// // Building doesn't touch any RNG...
+/// let mut bernoulli = FastBernoulli::builder().ratio(1, 1000).seed(42).lazy().build()?;
+/// assert!(!bernoulli.is_initialized());
+///
+/// // ...until the first trial.
+/// bernoulli.trial(&mut rng);
+/// assert!(bernoulli.is_initialized());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LazyBernoulli {
+    bernoulli: FastBernoulli,
+    seed: Option<u64>,
+    initialized: bool,
+}
+
+impl LazyBernoulli {
+    /// Perform a Bernoulli trial, as with [`FastBernoulli::trial`].
+    #[inline]
+    pub fn trial<R>(&mut self, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.get(rng).trial(rng)
+    }
+
+    /// Perform `n` Bernoulli trials at once, as with
+    /// [`FastBernoulli::multi_trial`].
+    #[inline]
+    pub fn multi_trial<R>(&mut self, n: u32, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.get(rng).multi_trial(n, rng)
+    }
+
+    /// Get the underlying `FastBernoulli`, drawing its initial skip count if it
+    /// hasn't been drawn yet.
+    #[inline]
+    pub fn get<R>(&mut self, rng: &mut R) -> &mut FastBernoulli
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.initialized {
+            self.bernoulli.reset_initial_skip_count(self.seed, rng);
+            self.initialized = true;
+        }
+        &mut self.bernoulli
+    }
+
+    /// Get the probability with which events are sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Has the initial skip count been drawn yet?
+    #[inline]
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_validated_samplers() {
+        let builder = FastBernoulli::builder();
+        assert_eq!(builder.build().unwrap_err(), BuildError::MissingProbability);
+        assert_eq!(
+            builder.probability(1.5).build().unwrap_err(),
+            BuildError::InvalidProbability(InvalidProbability::OutOfRange(1.5))
+        );
+        assert_eq!(
+            builder.probability(f64::NAN).wide().build().unwrap_err(),
+            BuildError::InvalidProbability(InvalidProbability::NaN)
+        );
+        assert_eq!(
+            builder.ratio(3, 2).integer().build().unwrap_err(),
+            BuildError::InvalidProbability(InvalidProbability::OutOfRange(1.5))
+        );
+        assert_eq!(
+            builder.ratio(1, 0).lazy().build().unwrap_err(),
+            BuildError::ZeroDenominator
+        );
+
+        // Seeded samplers start with the same skip count, lazily or not.
+        let eager = builder.ratio(1, 1000).seed(42).build().unwrap();
+        let mut lazy = builder.ratio(1, 1000).seed(42).lazy().build().unwrap();
+        assert_eq!(eager.probability(), 0.001);
+        assert!(!lazy.is_initialized());
+        let mut rng = rand::thread_rng();
+        assert_eq!(lazy.get(&mut rng).skip_count(), eager.skip_count());
+    }
+
+    #[test]
+    fn builds_integer_and_wide_samplers() {
+        let builder = FastBernoulli::builder().ratio(1, 3).seed(7);
+        let integer = builder.integer().build().unwrap();
+        assert_eq!(
+            builder.integer().build().unwrap().skip_count(),
+            integer.skip_count()
+        );
+        let mut rng = rand::thread_rng();
+        assert_eq!(
+            integer.skip_count(),
+            IntegerBernoulli::from_ratio(1, 3, &mut StdRng::seed_from_u64(7)).skip_count()
+        );
+
+        let wide = builder.probability(1e-12).wide().build().unwrap();
+        assert_eq!(wide.probability(), 1e-12);
+        assert_eq!(
+            wide.skip_count(),
+            WideBernoulli::new(1e-12, &mut StdRng::seed_from_u64(7)).skip_count()
+        );
+        let mut always = builder.ratio(1, 1).wide().build().unwrap();
+        assert!(always.trial(&mut rng));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::fmt;
+
+/// Extension methods for sampling iterators.
+pub trait SampleExt: Iterator + Sized {
+    /// Sample this iterator's items with `multi_trial_weighted`, using `size`
+    /// to get each item's size, and yield the sampled items together with
+    /// their inclusion weights.
+    ///
+    /// This is the size-weighted analogue of sampling each item with `trial`:
+    /// larger items are proportionally more likely to be sampled, as if each
+    /// of their units were offered separately; see
+    /// [`FastBernoulli::multi_trial`]. Each sampled item's weight is the
+    /// inverse of its probability of being sampled, `1 / (1 - (1 - p)^size)`,
+    /// with `p` the [effective][FastBernoulli::effective_probability]
+    /// probability, so that weighted sums over the sampled items are unbiased
+    /// estimates of sums over all of them.
+    ///
+    /// Sizes of any magnitude are sampled exactly, including those above
+    /// `u32::MAX`; see [`FastBernoulli::multi_trial_weighted`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::{FastBernoulli, SampleExt};
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// // Sample about one in every thousand bytes.
+    /// let mut bernoulli = FastBernoulli::new(0.001, &mut rng);
+    ///
+    /// let payloads = vec![vec![0_u8; 100]; 10_000];
+    /// let estimated_bytes: f64 = payloads
+    ///     .iter()
+    ///     .sample_by_size(&mut bernoulli, &mut rng, |payload| payload.len())
+    ///     .map(|(payload, weight)| payload.len() as f64 * weight)
+    ///     .sum();
+    /// # let _ = estimated_bytes;
+    /// ```
+    fn sample_by_size<'a, F, R>(
+        self,
+        bernoulli: &'a mut FastBernoulli,
+        rng: &'a mut R,
+        size: F,
+    ) -> SampledBySize<'a, Self, F, R>
+    where
+        F: FnMut(&Self::Item) -> usize,
+        R: Rng + ?Sized,
+    {
+        SampledBySize {
+            items: self,
+            bernoulli,
+            rng,
+            size,
+        }
+    }
+}
+
+impl<I> SampleExt for I where I: Iterator {}
+
+/// An iterator over size-weighted sampled items, and their inclusion weights,
+/// returned by [`SampleExt::sample_by_size`].
+pub struct SampledBySize<'a, I, F, R: ?Sized> {
+    items: I,
+    bernoulli: &'a mut FastBernoulli,
+    rng: &'a mut R,
+    size: F,
+}
+
+impl<I, F, R: ?Sized> fmt::Debug for SampledBySize<'_, I, F, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SampledBySize")
+            .field("bernoulli", &self.bernoulli)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<I, F, R> Iterator for SampledBySize<'_, I, F, R>
+where
+    I: Iterator,
+    F: FnMut(&I::Item) -> usize,
+    R: Rng + ?Sized,
+{
+    type Item = (I::Item, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for item in self.items.by_ref() {
+            // The weight is computed for the same size as the trial, so it
+            // is the trial's inclusion probability even for huge sizes.
+            let size = (self.size)(&item) as f64;
+            if self.bernoulli.multi_trial_weighted(size, self.rng) {
+                let probability = self.bernoulli.multi_trial_weighted_probability(size);
+                return Some((item, 1.0 / probability));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.items.size_hint().1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::estimate::multi_trial_probability;
+
+    #[test]
+    fn weighted_sums_are_unbiased() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = FastBernoulli::new(0.01, &mut rng);
+
+        let sizes: Vec<usize> = (0..20_000).map(|i| 1 + i % 50).collect();
+        let total: usize = sizes.iter().sum();
+
+        let mut estimate = 0.0;
+        let mut variance = 0.0;
+        for (&size, weight) in sizes
+            .iter()
+            .sample_by_size(&mut bernoulli, &mut rng, |&&size| size)
+        {
+            // Each item's weight is the inverse of its inclusion probability.
+            let p = 1.0 / weight;
+            assert!((p - (1.0 - 0.99_f64.powi(size as i32))).abs() < 1e-12);
+            estimate += size as f64 * weight;
+            variance += (1.0 - p) * (size as f64 * weight).powi(2);
+        }
+        assert!((estimate - total as f64).abs() <= 5.0 * variance.sqrt());
+
+        let mut all = FastBernoulli::new(1.0, &mut rng);
+        let empty: Vec<(&str, f64)> = ["", "a"]
+            .into_iter()
+            .sample_by_size(&mut all, &mut rng, |s| s.len())
+            .collect();
+        assert_eq!(empty, [("a", 1.0)]);
+    }
+
+    #[test]
+    fn huge_sizes_are_weighted_as_sampled() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = FastBernoulli::new(1e-10, &mut rng);
+
+        // A size of `u32::MAX` would be sampled with a probability of about
+        // 0.35, but these are sampled with a probability of about 0.63, which
+        // is what their weights must reflect.
+        let size = 10_000_000_000_usize;
+        let sampled: Vec<f64> = std::iter::repeat_n(size, 10_000)
+            .sample_by_size(&mut bernoulli, &mut rng, |&size| size)
+            .map(|(_, weight)| weight)
+            .collect();
+        let expected = multi_trial_probability(1e-10, size as f64);
+        assert!((expected - 0.632).abs() < 0.001);
+        assert!(sampled.iter().all(|&weight| weight == 1.0 / expected));
+        let n = 10_000.0;
+        let rate = sampled.len() as f64 / n;
+        assert!((rate - expected).abs() <= 5.0 * (expected * (1.0 - expected) / n).sqrt());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{default_rng, CellBernoulli};
+
+/// A per-callsite, per-thread sampler, for the code generated by `#[sampled]`
+/// and `debug_assert_sampled!`.
+///
+/// Not part of the public API.
+#[derive(Debug)]
+pub struct CallsiteSampler {
+    bernoulli: CellBernoulli,
+}
+
+impl CallsiteSampler {
+    /// Construct a new `CallsiteSampler` that samples calls with the given
+    /// probability.
+    pub fn new(probability: f64) -> Self {
+        CallsiteSampler {
+            bernoulli: CellBernoulli::new(probability, &mut default_rng()),
+        }
+    }
+
+    /// Perform a trial for one call.
+    #[inline]
+    pub fn trial(&self) -> bool {
+        self.bernoulli.trial(&mut default_rng())
+    }
+}
+
+/// Assert that a boolean expression is `true`, on a sampled fraction of
+/// executions, in debug builds.
+///
+/// `debug_assert_sampled!(p, ...)` is like `debug_assert!(...)`, except that
+/// each callsite has its own sampler, one per thread, and the assertion is only
+/// evaluated when that sampler samples the execution, with probability `p`.
+/// Like `debug_assert!`, it is compiled out of release builds. This keeps
+/// heavyweight invariant checks enabled in debug and staging builds without
+/// paying for them on every execution.
+///
+/// The probability must be a constant expression, since it initializes the
+/// callsite's sampler, and may not refer to local variables.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::debug_assert_sampled;
+///
+/// # fn is_sorted(v: &[u32]) -> bool { v.windows(2).all(|w| w[0] <= w[1]) }
+/// let mut v = vec![3, 1, 2];
+/// v.sort();
+///
+/// // Check the whole vector on 1% of executions.
+/// debug_assert_sampled!(0.01, is_sorted(&v), "not sorted: {:?}", v);
+/// ```
+#[macro_export]
+macro_rules! debug_assert_sampled {
+    ($probability:expr, $($arg:tt)+) => {
+        if ::core::cfg!(debug_assertions) {
+            ::std::thread_local! {
+                static SAMPLER: $crate::__private::CallsiteSampler =
+                    $crate::__private::CallsiteSampler::new($probability);
+            }
+            if SAMPLER.with($crate::__private::CallsiteSampler::trial) {
+                ::core::assert!($($arg)+);
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn samples_calls() {
+        let always = CallsiteSampler::new(1.0);
+        let never = CallsiteSampler::new(0.0);
+        assert!((0..100).all(|_| always.trial()));
+        assert!((0..100).all(|_| !never.trial()));
+
+        let sometimes = CallsiteSampler::new(0.5);
+        let n = 10_000;
+        let sampled = (0..n).filter(|_| sometimes.trial()).count() as f64;
+        let expected = 0.5 * f64::from(n);
+        assert!((sampled - expected).abs() <= 5.0 * (expected * 0.5).sqrt());
+    }
+
+    #[test]
+    fn unsampled_assertions_are_not_evaluated() {
+        let evaluated = Cell::new(0);
+        for _ in 0..100 {
+            crate::debug_assert_sampled!(0.0, {
+                evaluated.set(evaluated.get() + 1);
+                true
+            });
+        }
+        assert_eq!(evaluated.get(), 0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "sampled assertion failed")]
+    fn sampled_assertions_are_evaluated() {
+        crate::debug_assert_sampled!(1.0, 1 + 1 == 3, "sampled assertion failed");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{FastBernoulli, SampleDecision};
+use rand::Rng;
+
+/// A sampler that can be told to capture a burst of upcoming events, for
+/// example when an operator asks for on-demand debugging data.
+///
+/// [`capture_next(n)`][CaptureSampler::capture_next] forces the next `n`
+/// events to be sampled regardless of probability, after which normal
+/// geometric sampling resumes.
+///
+/// Every event still goes through the underlying [`FastBernoulli`], and
+/// events that it would have sampled anyway aren't counted as forced. Only
+/// events that were sampled *because of* a capture are flagged as
+/// [`forced`][SampleDecision::forced] in their decisions, so the unforced
+/// samples remain a proper Bernoulli sample that estimates can be built from.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::CaptureSampler;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sampler = CaptureSampler::new(0.001, &mut rng);
+///
+/// // An operator asked to see the next ten events.
+/// sampler.capture_next(10);
+///
+/// for _ in 0..10 {
+///     let decision = sampler.trial(&mut rng).unwrap();
+///     // Record the sample, together with `decision`...
+///     # let _ = decision;
+/// }
+/// assert_eq!(sampler.remaining_captures(), 0);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureSampler {
+    bernoulli: FastBernoulli,
+    captures: u64,
+}
+
+impl CaptureSampler {
+    /// Construct a new `CaptureSampler` that samples events with the given
+    /// probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        CaptureSampler {
+            bernoulli: FastBernoulli::new(probability, rng),
+            captures: 0,
+        }
+    }
+
+    /// Force the next `n` events to be sampled.
+    ///
+    /// This replaces any capture already in progress, so `capture_next(0)`
+    /// cancels one.
+    #[inline]
+    pub fn capture_next(&mut self, n: u64) {
+        self.captures = n;
+    }
+
+    /// Perform a trial for an event.
+    ///
+    /// Returns the decision if the event should be sampled, or `None` if it
+    /// should not.
+    pub fn trial<R>(&mut self, rng: &mut R) -> Option<SampleDecision>
+    where
+        R: Rng + ?Sized,
+    {
+        let sampled = self.bernoulli.trial(rng);
+        if self.captures == 0 {
+            return sampled.then(|| SampleDecision::for_bernoulli(&self.bernoulli));
+        }
+
+        self.captures -= 1;
+        Some(if sampled {
+            SampleDecision::for_bernoulli(&self.bernoulli)
+        } else {
+            SampleDecision::new_forced()
+        })
+    }
+
+    /// Get the number of upcoming events that will be forced to be sampled.
+    #[inline]
+    pub fn remaining_captures(&self) -> u64 {
+        self.captures
+    }
+
+    /// Get the probability with which events are sampled when they aren't
+    /// being captured.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_bursts() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = CaptureSampler::new(0.0, &mut rng);
+        assert!(sampler.trial(&mut rng).is_none());
+
+        sampler.capture_next(3);
+        for _ in 0..3 {
+            assert!(sampler.trial(&mut rng).unwrap().forced);
+        }
+        assert!(sampler.trial(&mut rng).is_none());
+
+        // Events that would have been sampled anyway aren't forced.
+        let mut sampler = CaptureSampler::new(1.0, &mut rng);
+        sampler.capture_next(3);
+        assert!(!sampler.trial(&mut rng).unwrap().forced);
+        assert_eq!(sampler.remaining_captures(), 2);
+    }
+}
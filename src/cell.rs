@@ -0,0 +1,135 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::cell::Cell;
+
+/// A [`FastBernoulli`] that performs trials through a shared reference, for
+/// use inside `Fn` closures and other places that only have `&self`.
+///
+/// `CellBernoulli` keeps its state in a [`Cell`], so it can't panic like a
+/// `RefCell` can, and has no locking overhead like a `Mutex` does, but it is
+/// not `Sync`: each thread needs its own.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::CellBernoulli;
+///
+/// let mut rng = rand::thread_rng();
+/// let bernoulli = CellBernoulli::new(0.1, &mut rng);
+///
+/// // `for_each_event` only accepts `Fn` closures.
+/// fn for_each_event(f: impl Fn(u32)) {
+///     (0..100).for_each(f);
+/// }
+///
+/// for_each_event(|event| {
+///     if bernoulli.trial(&mut rand::thread_rng()) {
+///         // Record a sample of `event`...
+///         # let _ = event;
+///     }
+/// });
+/// ```
+#[derive(Debug)]
+pub struct CellBernoulli {
+    bernoulli: Cell<FastBernoulli>,
+}
+
+impl CellBernoulli {
+    /// Construct a new `CellBernoulli` that samples events with the given
+    /// probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        CellBernoulli::from(FastBernoulli::new(probability, rng))
+    }
+
+    /// Perform a Bernoulli trial, as with [`FastBernoulli::trial`].
+    #[inline]
+    pub fn trial<R>(&self, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let mut bernoulli = self.bernoulli.get();
+        let sampled = bernoulli.trial(rng);
+        self.bernoulli.set(bernoulli);
+        sampled
+    }
+
+    /// Perform `n` Bernoulli trials at once, as with
+    /// [`FastBernoulli::multi_trial`].
+    #[inline]
+    pub fn multi_trial<R>(&self, n: u32, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let mut bernoulli = self.bernoulli.get();
+        let sampled = bernoulli.multi_trial(n, rng);
+        self.bernoulli.set(bernoulli);
+        sampled
+    }
+
+    /// Get the probability with which events are sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.get().probability()
+    }
+
+    /// How many events will be skipped until the next event is sampled?
+    #[inline]
+    pub fn skip_count(&self) -> u32 {
+        self.bernoulli.get().skip_count()
+    }
+
+    /// Get the underlying `FastBernoulli`.
+    #[inline]
+    pub fn into_inner(self) -> FastBernoulli {
+        self.bernoulli.into_inner()
+    }
+}
+
+impl From<FastBernoulli> for CellBernoulli {
+    #[inline]
+    fn from(bernoulli: FastBernoulli) -> Self {
+        CellBernoulli {
+            bernoulli: Cell::new(bernoulli),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trials_through_shared_references() {
+        let mut rng = rand::thread_rng();
+        let bernoulli = CellBernoulli::new(0.25, &mut rng);
+        let skip_count = bernoulli.skip_count();
+
+        let trial = || bernoulli.trial(&mut rand::thread_rng());
+        for _ in 0..skip_count {
+            assert!(!trial());
+        }
+        assert!(trial());
+        assert_eq!(bernoulli.into_inner().probability(), 0.25);
+    }
+
+    #[test]
+    fn round_trips_are_not_copies() {
+        // Each trial copies the sampler out of the cell and back, replacing
+        // the original, so only one instance ever draws skip counts.
+        let mut rng = rand::thread_rng();
+        let bernoulli = CellBernoulli::new(0.5, &mut rng);
+        for _ in 0..1000 {
+            bernoulli.trial(&mut rng);
+            bernoulli.multi_trial(3, &mut rng);
+        }
+        assert_eq!(crate::lineage::duplicates_on_this_thread(), 0);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::error::Error;
+use std::fmt;
+
+/// An invalid sampling probability, rejected by
+/// [`FastBernoulli::new_checked`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum InvalidProbability {
+    /// The probability is NaN.
+    NaN,
+    /// The probability is positive or negative infinity.
+    Infinite,
+    /// The probability is finite, but not within the range
+    /// `0.0 <= probability <= 1.0`.
+    OutOfRange(f64),
+}
+
+impl fmt::Display for InvalidProbability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidProbability::NaN => write!(f, "sampling probability is NaN"),
+            InvalidProbability::Infinite => write!(f, "sampling probability is infinite"),
+            InvalidProbability::OutOfRange(probability) => write!(
+                f,
+                "sampling probability {} is out of the range `0.0..=1.0`",
+                probability
+            ),
+        }
+    }
+}
+
+impl Error for InvalidProbability {}
+
+impl InvalidProbability {
+    /// Check that `probability` is valid.
+    pub(crate) fn check(probability: f64) -> Result<(), InvalidProbability> {
+        if probability.is_nan() {
+            Err(InvalidProbability::NaN)
+        } else if probability.is_infinite() {
+            Err(InvalidProbability::Infinite)
+        } else if !(0.0..=1.0).contains(&probability) {
+            Err(InvalidProbability::OutOfRange(probability))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl FastBernoulli {
+    /// Construct a new `FastBernoulli` instance that samples events with the
+    /// given probability, or return an error if the probability is invalid.
+    ///
+    /// This is like [`new`][FastBernoulli::new], but for probabilities that
+    /// come from outside the program, such as from configuration files, where
+    /// an invalid probability should be reported rather than panic.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::{FastBernoulli, InvalidProbability};
+    ///
+    /// let mut rng = rand::thread_rng();
+    ///
+    /// # let config = "0.01";
+    /// let probability: f64 = config.parse().unwrap();
+    /// let bernoulli = FastBernoulli::new_checked(probability, &mut rng)?;
+    /// # let _ = bernoulli;
+    ///
+    /// assert_eq!(
+    ///     FastBernoulli::new_checked(1.5, &mut rng).unwrap_err(),
+    ///     InvalidProbability::OutOfRange(1.5),
+    /// );
+    /// # Ok::<(), InvalidProbability>(())
+    /// ```
+    pub fn new_checked<R>(probability: f64, rng: &mut R) -> Result<Self, InvalidProbability>
+    where
+        R: Rng + ?Sized,
+    {
+        InvalidProbability::check(probability)?;
+        Ok(FastBernoulli::new(probability, rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_probabilities() {
+        let mut rng = rand::thread_rng();
+        let check = |p| FastBernoulli::new_checked(p, &mut rand::thread_rng()).map(|_| ());
+        assert_eq!(check(f64::NAN), Err(InvalidProbability::NaN));
+        assert_eq!(check(f64::INFINITY), Err(InvalidProbability::Infinite));
+        assert_eq!(check(f64::NEG_INFINITY), Err(InvalidProbability::Infinite));
+        assert_eq!(check(-0.1), Err(InvalidProbability::OutOfRange(-0.1)));
+        assert_eq!(
+            check(1.0 + f64::EPSILON),
+            Err(InvalidProbability::OutOfRange(1.0 + f64::EPSILON))
+        );
+
+        for p in [0.0, 0.5, 1.0] {
+            let bernoulli = FastBernoulli::new_checked(p, &mut rng).unwrap();
+            assert_eq!(bernoulli.probability(), p);
+        }
+    }
+}
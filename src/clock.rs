@@ -0,0 +1,203 @@
+use crate::time::Instant;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+/// A source of the current time, for time-based samplers.
+///
+/// Time-based samplers in this crate, such as
+/// [`StickySampler`][crate::StickySampler], take the current time as an
+/// argument rather than reading it themselves, just like they take an RNG.
+/// That makes the time source pluggable: on hot paths where
+/// [`Instant::now`] is too expensive to call for every event, pass the time
+/// from a cheaper `Clock`, such as a [`CoarseClock`], instead.
+///
+/// On `wasm32-unknown-unknown`, where the standard library's `Instant::now`
+/// panics, enable the `web-time` feature: time-based samplers then take
+/// `web_time::Instant`s, which read `performance.now()` in the browser and are
+/// the standard library's `Instant` everywhere else, and [`StdClock`] reads
+/// them.
+pub trait Clock {
+    /// Get the current time.
+    fn now(&self) -> Instant;
+}
+
+/// The standard library's clock: [`Instant::now`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdClock;
+
+impl Clock for StdClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that caches the current time, updating it only periodically.
+///
+/// Reading a `CoarseClock` is a single relaxed atomic load, instead of a call
+/// to [`Instant::now`] (which can be a system call on some platforms). In
+/// exchange, it only advances when [`tick`][CoarseClock::tick] is called,
+/// which can be done by a background thread with
+/// [`with_ticker`][CoarseClock::with_ticker], or manually, e.g. once per
+/// event-loop iteration.
+///
+/// Clones share the same cached time.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{Clock, CoarseClock, StickySampler};
+/// use std::time::Duration;
+///
+/// let mut rng = rand::thread_rng();
+/// let clock = CoarseClock::with_ticker(Duration::from_millis(1));
+/// let mut sampler = StickySampler::new(0.01, Duration::from_secs(60), &mut rng);
+///
+/// // Time-based samplers now cost an atomic load per event, not a syscall.
+/// sampler.trial(&"session", clock.now(), &mut rng);
+/// ```
+#[derive(Clone)]
+pub struct CoarseClock {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    base: Instant,
+    // Nanoseconds since `base` as of the last tick.
+    elapsed: AtomicU64,
+}
+
+impl Inner {
+    fn tick(&self) {
+        let elapsed = self.base.elapsed().as_nanos();
+        self.elapsed.fetch_max(
+            u64::try_from(elapsed).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+impl fmt::Debug for CoarseClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoarseClock")
+            .field("now", &self.now())
+            .finish()
+    }
+}
+
+impl CoarseClock {
+    /// Construct a new `CoarseClock` that only advances when
+    /// [`tick`][CoarseClock::tick] is called.
+    pub fn new() -> Self {
+        CoarseClock {
+            inner: Arc::new(Inner {
+                base: Instant::now(),
+                elapsed: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Construct a new `CoarseClock`, and spawn a background thread that ticks
+    /// it every `interval`.
+    ///
+    /// The thread exits once every clone of the returned clock has been
+    /// dropped.
+    pub fn with_ticker(interval: Duration) -> Self {
+        let clock = CoarseClock::new();
+        let weak: Weak<Inner> = Arc::downgrade(&clock.inner);
+        thread::Builder::new()
+            .name("fast-bernoulli-coarse-clock".into())
+            .spawn(move || {
+                while let Some(inner) = weak.upgrade() {
+                    inner.tick();
+                    drop(inner);
+                    thread::sleep(interval);
+                }
+            })
+            .expect("failed to spawn the coarse clock's ticker thread");
+        clock
+    }
+
+    /// Update the cached time to the current time.
+    #[inline]
+    pub fn tick(&self) {
+        self.inner.tick();
+    }
+}
+
+impl Default for CoarseClock {
+    fn default() -> Self {
+        CoarseClock::new()
+    }
+}
+
+impl Clock for CoarseClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        let elapsed = self.inner.elapsed.load(Ordering::Relaxed);
+        self.inner.base + Duration::from_nanos(elapsed)
+    }
+}
+
+/// A clock backed by the [`quanta`] crate's TSC-based clock.
+///
+/// Requires the `quanta` feature.
+///
+/// Reading the time stamp counter is much cheaper than [`Instant::now`] on
+/// most platforms, while remaining precise. Readings are converted to
+/// [`Instant`]s relative to a reference point taken at construction.
+#[cfg(feature = "quanta")]
+#[derive(Debug, Clone)]
+pub struct QuantaClock {
+    clock: quanta::Clock,
+    base: Instant,
+    quanta_base: quanta::Instant,
+}
+
+#[cfg(feature = "quanta")]
+impl QuantaClock {
+    /// Construct a new `QuantaClock`.
+    pub fn new() -> Self {
+        let clock = quanta::Clock::new();
+        let quanta_base = clock.now();
+        QuantaClock {
+            clock,
+            base: Instant::now(),
+            quanta_base,
+        }
+    }
+}
+
+#[cfg(feature = "quanta")]
+impl Default for QuantaClock {
+    fn default() -> Self {
+        QuantaClock::new()
+    }
+}
+
+#[cfg(feature = "quanta")]
+impl Clock for QuantaClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        self.base + self.clock.now().duration_since(self.quanta_base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coarse_clock_only_advances_on_tick() {
+        let clock = CoarseClock::new();
+        let before = clock.now();
+        thread::sleep(Duration::from_millis(2));
+        assert_eq!(clock.now(), before);
+
+        clock.clone().tick();
+        assert!(clock.now() >= before + Duration::from_millis(2));
+    }
+}
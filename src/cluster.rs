@@ -0,0 +1,131 @@
+use crate::hash::hash_with_salt;
+use std::hash::Hash;
+
+/// Key-based sampling partitioned across a fleet of nodes, so that together
+/// they take exactly one global sample.
+///
+/// When `N` collectors that all see the same events each sample them
+/// independently with probability `p`, the same event is often sampled more
+/// than once, and the fleet as a whole samples `1 - (1 - p)^N` of events
+/// rather than `p`. A `ClusterSampler` instead hashes each event's key into a
+/// 64-bit space, takes the lowest `p` of that space as the global sample, and
+/// gives each node a disjoint `1 / N` slice of it. Each node samples only the
+/// events whose keys land in its slice, so every event in the global sample
+/// is sampled by exactly one node, and the union across the fleet is exactly
+/// a `p`-sample, without any coordination at decision time.
+///
+/// All nodes must be configured with the same salt, probability, and number
+/// of nodes, and each with its own node index.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::ClusterSampler;
+///
+/// // Four collectors together keep 1% of traces.
+/// let nodes: Vec<ClusterSampler> = (0..4)
+///     .map(|node| ClusterSampler::new(0x5eed, 0.01, node, 4))
+///     .collect();
+///
+/// for trace_id in 0..100_000_u64 {
+///     let keepers = nodes.iter().filter(|node| node.trial(&trace_id)).count();
+///     assert!(keepers <= 1);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClusterSampler {
+    salt: u64,
+    probability: f64,
+    node: u32,
+    nodes: u32,
+    // This node's slice of the 64-bit hash space, `low..high`. Kept as `u128`
+    // so that a probability of exactly `1.0` covers `u64::MAX`.
+    low: u128,
+    high: u128,
+}
+
+impl ClusterSampler {
+    /// Construct a new `ClusterSampler` for node `node` of `nodes`, which
+    /// together sample keys with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0`,
+    /// and `node` must be less than `nodes`. This method will panic if that is
+    /// not the case.
+    pub fn new(salt: u64, probability: f64, node: u32, nodes: u32) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        assert!(node < nodes, "`node` must be less than `nodes`");
+
+        let total = (probability * TWO_POW_64) as u128;
+        ClusterSampler {
+            salt,
+            probability,
+            node,
+            nodes,
+            low: total * u128::from(node) / u128::from(nodes),
+            high: total * (u128::from(node) + 1) / u128::from(nodes),
+        }
+    }
+
+    /// Should this node sample the event with the given key?
+    #[inline]
+    pub fn trial<K>(&self, key: &K) -> bool
+    where
+        K: Hash + ?Sized,
+    {
+        let h = u128::from(hash_with_salt(self.salt, key));
+        self.low <= h && h < self.high
+    }
+
+    /// Get the probability with which the whole fleet samples keys.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// Get the probability with which this node samples the keys it sees:
+    /// its share, `1 / nodes`, of the fleet's probability.
+    #[inline]
+    pub fn node_probability(&self) -> f64 {
+        self.probability / f64::from(self.nodes)
+    }
+
+    /// Get this node's index.
+    #[inline]
+    pub fn node(&self) -> u32 {
+        self.node
+    }
+
+    /// Get the number of nodes in the fleet.
+    #[inline]
+    pub fn nodes(&self) -> u32 {
+        self.nodes
+    }
+}
+
+const TWO_POW_64: f64 = 18_446_744_073_709_551_616.0;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_is_the_global_sample() {
+        let global = ClusterSampler::new(7, 0.3, 0, 1);
+        let nodes: Vec<ClusterSampler> =
+            (0..5).map(|i| ClusterSampler::new(7, 0.3, i, 5)).collect();
+
+        for key in 0..10_000_u32 {
+            let keepers = nodes.iter().filter(|node| node.trial(&key)).count();
+            assert_eq!(keepers, usize::from(global.trial(&key)));
+        }
+
+        let everything: Vec<ClusterSampler> =
+            (0..3).map(|i| ClusterSampler::new(7, 1.0, i, 3)).collect();
+        assert!((0..1000_u32).all(|key| everything.iter().filter(|n| n.trial(&key)).count() == 1));
+    }
+}
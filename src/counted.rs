@@ -0,0 +1,113 @@
+use crate::hash::mix64;
+use std::ops::Range;
+
+/// A stateless sampler, whose decision for each event is a pure function of a
+/// seed and the event's index.
+///
+/// [`FastBernoulli`][crate::FastBernoulli] is stateful: deciding event `i`
+/// means first deciding every event before it. A `CountedSampler` instead
+/// hashes the seed and the index with the SplitMix64 generator, and samples
+/// the event if the hash falls below a threshold. Decisions can be made in any
+/// order, for any index, on any thread or machine, and always agree, so work
+/// can be partitioned or retried freely without changing which events are
+/// sampled.
+///
+/// Each decision costs a hash, rather than a decrement, so prefer
+/// `FastBernoulli` when events are processed in order anyway.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::CountedSampler;
+///
+/// let sampler = CountedSampler::new(0.01, 0x5eed);
+///
+/// // Decide in any order...
+/// let late = sampler.sample(1_000_000);
+/// let early = sampler.sample(7);
+///
+/// // ...and always get the same answers.
+/// assert_eq!(sampler.sample(1_000_000), late);
+/// assert_eq!(sampler.sample(7), early);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CountedSampler {
+    probability: f64,
+    seed: u64,
+    // Sample an event if its hash is below this. Kept as `u128` so that a
+    // probability of exactly `1.0` covers every possible hash.
+    threshold: u128,
+}
+
+/// SplitMix64's increment, the golden ratio in 0.64 fixed point.
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl CountedSampler {
+    /// Construct a new `CountedSampler` that samples events with the given
+    /// probability, deciding them from `seed`.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new(probability: f64, seed: u64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        CountedSampler {
+            probability,
+            seed,
+            threshold: (probability * 18_446_744_073_709_551_616.0) as u128,
+        }
+    }
+
+    /// Decide whether to sample the event with the given index.
+    #[inline]
+    pub fn sample(&self, index: u64) -> bool {
+        // The `index`th output of a SplitMix64 generator seeded with `seed`.
+        let hash = mix64(
+            self.seed
+                .wrapping_add(index.wrapping_add(1).wrapping_mul(GAMMA)),
+        );
+        u128::from(hash) < self.threshold
+    }
+
+    /// Get an iterator over the sampled indices in `range`.
+    pub fn sampled_indices(&self, range: Range<u64>) -> impl Iterator<Item = u64> + '_ {
+        range.filter(move |&index| self.sample(index))
+    }
+
+    /// Get the probability with which events are sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// Get the seed.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decisions_are_order_independent() {
+        let sampler = CountedSampler::new(0.1, 42);
+        let forwards: Vec<u64> = sampler.sampled_indices(0..100_000).collect();
+        let mut backwards: Vec<u64> = (0..100_000).rev().filter(|&i| sampler.sample(i)).collect();
+        backwards.reverse();
+        assert_eq!(forwards, backwards);
+
+        let expected = 10_000.0;
+        let tolerance = 5.0 * (expected * 0.9_f64).sqrt();
+        assert!((forwards.len() as f64 - expected).abs() <= tolerance);
+
+        assert!((0..1000).all(|i| CountedSampler::new(1.0, 42).sample(i)));
+        assert!((0..1000).all(|i| !CountedSampler::new(0.0, 42).sample(i)));
+    }
+}
@@ -0,0 +1,139 @@
+use rand::RngCore;
+
+/// An RNG adapter that counts how much randomness it has supplied, for
+/// reproducibility audits.
+///
+/// Deterministic-replay systems need two runs to consume randomness
+/// identically: if one run draws a single extra word, every later decision
+/// diverges. Wrapping the RNG passed to samplers in a `CountingRng` and
+/// comparing [`usage`][CountingRng::usage] snapshots between runs, after each
+/// step, pinpoints the first step where they diverged.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{CountingRng, FastBernoulli};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = CountingRng::new(StdRng::seed_from_u64(42));
+/// let mut bernoulli = FastBernoulli::new(0.1, &mut rng);
+///
+/// for _ in 0..1000 {
+///     bernoulli.trial(&mut rng);
+/// }
+///
+/// // Each skip count is drawn from a single `u64`.
+/// let usage = rng.usage();
+/// assert_eq!(usage.words, 2 * usage.draws);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CountingRng<R> {
+    rng: R,
+    usage: RngUsage,
+}
+
+/// A snapshot of how much randomness a [`CountingRng`] has supplied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[non_exhaustive]
+pub struct RngUsage {
+    /// The number of calls to `next_u32`, `next_u64`, `fill_bytes`, and
+    /// `try_fill_bytes`.
+    pub draws: u64,
+
+    /// The number of 32-bit words supplied: one per `next_u32`, two per
+    /// `next_u64`, and one per started four bytes filled.
+    pub words: u64,
+}
+
+impl<R> CountingRng<R>
+where
+    R: RngCore,
+{
+    /// Construct a new `CountingRng` wrapping `rng`, with nothing consumed yet.
+    #[inline]
+    pub fn new(rng: R) -> Self {
+        CountingRng {
+            rng,
+            usage: RngUsage::default(),
+        }
+    }
+
+    /// Get a snapshot of how much randomness has been consumed.
+    #[inline]
+    pub fn usage(&self) -> RngUsage {
+        self.usage
+    }
+
+    /// Reset the counts to zero.
+    #[inline]
+    pub fn reset_usage(&mut self) {
+        self.usage = RngUsage::default();
+    }
+
+    /// Get the wrapped RNG.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.rng
+    }
+
+    #[inline]
+    fn count(&mut self, words: u64) {
+        self.usage.draws += 1;
+        self.usage.words += words;
+    }
+}
+
+impl<R> RngCore for CountingRng<R>
+where
+    R: RngCore,
+{
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.count(1);
+        self.rng.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.count(2);
+        self.rng.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.count(dest.len().div_ceil(4) as u64);
+        self.rng.fill_bytes(dest)
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.count(dest.len().div_ceil(4) as u64);
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastBernoulli;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn counts_one_draw_per_skip_count() {
+        let mut rng = CountingRng::new(StdRng::seed_from_u64(7));
+        let mut bernoulli = FastBernoulli::new(0.25, &mut rng);
+        let sampled = (0..10_000).filter(|_| bernoulli.trial(&mut rng)).count() as u64;
+        assert_eq!(rng.usage().draws, 1 + sampled);
+
+        rng.reset_usage();
+        let mut buf = [0; 5];
+        rng.fill_bytes(&mut buf);
+        assert_eq!(rng.usage(), RngUsage { draws: 1, words: 2 });
+    }
+}
@@ -0,0 +1,142 @@
+use crate::FastBernoulli;
+use csv::{ByteRecord, Reader, StringRecord};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::io;
+
+/// An iterator over a sampled subset of a CSV reader's records.
+///
+/// Each record is included independently with the given probability. Rather
+/// than deciding record by record, the iterator skips ahead over the records
+/// between samples, reading each into a reused [`ByteRecord`] so that skipped
+/// records are neither allocated nor validated as UTF-8. The header row, if
+/// the reader has one, is never sampled.
+///
+/// Seeded samples are reproducible: the same seed over the same file selects
+/// the same records. They use [`StdRng`], whose algorithm may change between
+/// major versions of `rand`, so they are only reproducible with the same
+/// version.
+///
+/// Requires the `csv` feature.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::SampledCsvRecords;
+///
+/// let data = "city,population\nBoston,650000\nDenver,715000\nFresno,545000\n";
+/// let mut reader = csv::Reader::from_reader(data.as_bytes());
+///
+/// for record in SampledCsvRecords::with_seed(&mut reader, 0.5, 42) {
+///     let record = record?;
+///     assert_eq!(record.len(), 2);
+/// }
+/// # Ok::<(), csv::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SampledCsvRecords<'r, R> {
+    reader: &'r mut Reader<R>,
+    bernoulli: FastBernoulli,
+    rng: StdRng,
+    skipped: ByteRecord,
+}
+
+impl<'r, R> SampledCsvRecords<'r, R>
+where
+    R: io::Read,
+{
+    /// Sample `reader`'s records with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new(reader: &'r mut Reader<R>, probability: f64) -> Self {
+        let rng = StdRng::from_rng(crate::default_rng()).expect("the default RNG never fails");
+        Self::with_rng(reader, probability, rng)
+    }
+
+    /// Sample `reader`'s records with the given probability, reproducibly,
+    /// seeding the sample with `seed`.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn with_seed(reader: &'r mut Reader<R>, probability: f64, seed: u64) -> Self {
+        Self::with_rng(reader, probability, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(reader: &'r mut Reader<R>, probability: f64, mut rng: StdRng) -> Self {
+        SampledCsvRecords {
+            reader,
+            bernoulli: FastBernoulli::new(probability, &mut rng),
+            rng,
+            skipped: ByteRecord::new(),
+        }
+    }
+
+    /// Get the probability with which each record is included.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+}
+
+impl<R> Iterator for SampledCsvRecords<'_, R>
+where
+    R: io::Read,
+{
+    type Item = csv::Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bernoulli.probability() == 0.0 {
+            return None;
+        }
+        for _ in 0..self.bernoulli.skip_count() {
+            match self.reader.read_byte_record(&mut self.skipped) {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.bernoulli.reset_skip_count(&mut self.rng);
+
+        let mut record = StringRecord::new();
+        match self.reader.read_record(&mut record) {
+            Ok(true) => Some(Ok(record)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_samples_are_reproducible() {
+        let mut data = String::from("n\n");
+        for i in 0..10_000 {
+            data.push_str(&format!("{i}\n"));
+        }
+        let sample = |seed| -> Vec<u32> {
+            let mut reader = Reader::from_reader(data.as_bytes());
+            SampledCsvRecords::with_seed(&mut reader, 0.1, seed)
+                .map(|record| record.unwrap()[0].parse().unwrap())
+                .collect()
+        };
+
+        let first = sample(7);
+        assert_eq!(first, sample(7));
+        assert!(first.windows(2).all(|w| w[0] < w[1]));
+
+        let expected = 1000.0;
+        let tolerance = 5.0 * (expected * 0.9_f64).sqrt();
+        assert!((first.len() as f64 - expected).abs() <= tolerance);
+
+        let mut reader = Reader::from_reader(data.as_bytes());
+        assert_eq!(SampledCsvRecords::new(&mut reader, 1.0).count(), 10_000);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// Probabilistic decimation of a stream of sensor or audio samples.
+///
+/// Strict periodic decimation, keeping every `n`th sample, aliases any signal
+/// component near a multiple of the kept rate. A `Decimator` instead passes
+/// each sample through independently with probability `p`, which reduces
+/// bandwidth by the same factor on average without a fixed period to alias
+/// against.
+///
+/// Samples that cover varying amounts of time, such as readings taken at
+/// irregular intervals, can be weighted by their duration with
+/// [`pass_weighted`][Decimator::pass_weighted], so that each unit of time has
+/// an equal chance of being represented.
+///
+/// Random decimation occasionally drops long runs of samples. To bound how
+/// stale a logger's data can get, [`with_max_gap`][Decimator::with_max_gap]
+/// forces a sample through whenever passing on it would drop more than the
+/// given number of consecutive units. Forced samples bias the pass rate
+/// upwards, and are counted separately by [`forced`][Decimator::forced].
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::Decimator;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Keep a tenth of the readings, and never go more than 50 without one.
+/// let mut decimator = Decimator::new(0.1, &mut rng).with_max_gap(50);
+///
+/// let mut gap = 0;
+/// for reading in 0..10_000 {
+///     if decimator.pass(&mut rng) {
+///         // Log `reading`...
+///         # let _ = reading;
+///         gap = 0;
+///     } else {
+///         gap += 1;
+///     }
+///     assert!(gap <= 50);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Decimator {
+    bernoulli: FastBernoulli,
+    max_gap: Option<u32>,
+    gap: u32,
+    forced: u64,
+}
+
+impl Decimator {
+    /// Construct a new `Decimator` that passes samples through with the given
+    /// probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        Decimator {
+            bernoulli: FastBernoulli::new(probability, rng),
+            max_gap: None,
+            gap: 0,
+            forced: 0,
+        }
+    }
+
+    /// Never drop more than `max_gap` consecutive units: force a sample
+    /// through instead.
+    #[inline]
+    pub fn with_max_gap(mut self, max_gap: u32) -> Self {
+        self.max_gap = Some(max_gap);
+        self
+    }
+
+    /// Should this sample be passed through?
+    #[inline]
+    pub fn pass<R>(&mut self, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let sampled = self.bernoulli.trial(rng);
+        self.decide(sampled, 1)
+    }
+
+    /// Should this sample, covering `duration` units of time, be passed
+    /// through?
+    ///
+    /// The sample is passed through if any of its units would have been, so
+    /// that longer samples are proportionally more likely to be kept. See
+    /// [`FastBernoulli::multi_trial`].
+    #[inline]
+    pub fn pass_weighted<R>(&mut self, duration: u32, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let sampled = self.bernoulli.multi_trial(duration, rng);
+        self.decide(sampled, duration)
+    }
+
+    fn decide(&mut self, sampled: bool, duration: u32) -> bool {
+        if sampled {
+            self.gap = 0;
+            return true;
+        }
+
+        let gap = self.gap.saturating_add(duration);
+        match self.max_gap {
+            Some(max_gap) if gap > max_gap => {
+                self.gap = 0;
+                self.forced += 1;
+                true
+            }
+            _ => {
+                self.gap = gap;
+                false
+            }
+        }
+    }
+
+    /// Get the probability with which samples are passed through.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Get the maximum number of consecutive units that will be dropped, if
+    /// bounded.
+    #[inline]
+    pub fn max_gap(&self) -> Option<u32> {
+        self.max_gap
+    }
+
+    /// Get the number of samples forced through by the maximum gap.
+    #[inline]
+    pub fn forced(&self) -> u64 {
+        self.forced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_gap_forces_passes() {
+        let mut rng = rand::thread_rng();
+
+        let mut never = Decimator::new(0.0, &mut rng).with_max_gap(3);
+        let passes: Vec<bool> = (0..8).map(|_| never.pass(&mut rng)).collect();
+        assert_eq!(
+            passes,
+            [false, false, false, true, false, false, false, true]
+        );
+        assert_eq!(never.forced(), 2);
+
+        let mut weighted = Decimator::new(0.0, &mut rng).with_max_gap(10);
+        assert!(!weighted.pass_weighted(6, &mut rng));
+        assert!(weighted.pass_weighted(6, &mut rng));
+
+        let n = 10_000;
+        let mut unbounded = Decimator::new(0.2, &mut rng);
+        let passed = (0..n).filter(|_| unbounded.pass(&mut rng)).count() as f64;
+        let expected = 0.2 * f64::from(n);
+        assert!((passed - expected).abs() <= 5.0 * (expected * 0.8).sqrt());
+        assert_eq!(unbounded.forced(), 0);
+    }
+}
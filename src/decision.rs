@@ -0,0 +1,152 @@
+use crate::estimate::multi_trial_probability;
+use crate::time::SystemTime;
+use crate::FastBernoulli;
+use std::borrow::Cow;
+
+/// Provenance for a sampling decision, to be carried along with the sampled
+/// event.
+///
+/// Sampled records often outlive the configuration that sampled them: they sit
+/// in queues, get batched, and are analyzed long after the sampling rate has
+/// been changed. Attaching a `SampleDecision` to each record captures the
+/// probability and weight in effect when the decision was made, so that the
+/// record can always be reweighted correctly later.
+///
+/// With the `serde` feature enabled, `SampleDecision` implements `Serialize`
+/// and `Deserialize`.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{FastBernoulli, SampleDecision};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut bernoulli = FastBernoulli::new(0.01, &mut rng);
+///
+/// if bernoulli.trial(&mut rng) {
+///     let decision = SampleDecision::for_bernoulli(&bernoulli).with_stage("head");
+///     assert_eq!(decision.weight, 100.0);
+///     // Send `decision` along with the sampled event...
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct SampleDecision {
+    /// The probability with which the event was sampled.
+    pub probability: f64,
+
+    /// The number of events this sample stands in for. For a single Bernoulli
+    /// trial, this is `1.0 / probability`.
+    pub weight: f64,
+
+    /// The name of the sampling stage that made the decision, if any.
+    pub stage: Option<Cow<'static, str>>,
+
+    /// When the decision was made.
+    pub timestamp: SystemTime,
+
+    /// Whether the event was forced to be sampled, for example by
+    /// [`CaptureSampler::capture_next`][crate::CaptureSampler::capture_next],
+    /// rather than sampled by chance. Forced samples aren't part of the
+    /// probability sample, and should be left out of estimates.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub forced: bool,
+}
+
+impl SampleDecision {
+    /// Construct a new `SampleDecision` for an event sampled by a single
+    /// Bernoulli trial with the given probability, timestamped now.
+    #[inline]
+    pub fn new(probability: f64) -> Self {
+        SampleDecision::at(probability, SystemTime::now())
+    }
+
+    /// Construct a new `SampleDecision` for an event sampled by a single
+    /// Bernoulli trial with the given probability at the given time.
+    #[inline]
+    pub fn at(probability: f64, timestamp: SystemTime) -> Self {
+        SampleDecision {
+            probability,
+            weight: 1.0 / probability,
+            stage: None,
+            timestamp,
+            forced: false,
+        }
+    }
+
+    /// Construct a new `SampleDecision` for an event sampled by a trial of
+    /// `bernoulli`, timestamped now.
+    ///
+    /// This records the sampler's
+    /// [`effective_probability`][FastBernoulli::effective_probability], which
+    /// is the event's true inclusion probability even when the configured one
+    /// is too small to be realized exactly.
+    #[inline]
+    pub fn for_bernoulli(bernoulli: &FastBernoulli) -> Self {
+        SampleDecision::new(bernoulli.effective_probability())
+    }
+
+    /// Construct a new `SampleDecision` for an event that was forced to be
+    /// sampled, timestamped now.
+    ///
+    /// The event was sampled with certainty, so its probability and weight
+    /// are both `1.0`.
+    #[inline]
+    pub fn new_forced() -> Self {
+        SampleDecision {
+            forced: true,
+            ..SampleDecision::new(1.0)
+        }
+    }
+
+    /// Construct a new `SampleDecision` for an event of size `n` that was
+    /// sampled by [`multi_trial`][crate::FastBernoulli::multi_trial] with the
+    /// given per-unit probability, timestamped now.
+    ///
+    /// Such an event is sampled with probability `1 - (1 - probability)^n`,
+    /// and that is the probability recorded in the decision.
+    pub fn for_multi_trial(probability: f64, n: u32) -> Self {
+        SampleDecision::new(multi_trial_probability(probability, f64::from(n)))
+    }
+
+    /// Set the name of the sampling stage that made this decision.
+    #[inline]
+    pub fn with_stage(mut self, stage: impl Into<Cow<'static, str>>) -> Self {
+        self.stage = Some(stage.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn weight_is_the_reciprocal_of_probability() {
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        let decision = SampleDecision::at(0.25, timestamp).with_stage("tail");
+        assert_eq!(decision.probability, 0.25);
+        assert_eq!(decision.weight, 4.0);
+        assert_eq!(decision.stage.as_deref(), Some("tail"));
+        assert_eq!(decision.timestamp, timestamp);
+        assert!(!decision.forced);
+
+        let forced = SampleDecision::new_forced();
+        assert!(forced.forced);
+        assert_eq!((forced.probability, forced.weight), (1.0, 1.0));
+    }
+
+    #[test]
+    fn multi_trial_decisions_record_the_inclusion_probability() {
+        // Two trials at 50% sample with probability `1 - 0.5^2`.
+        let decision = SampleDecision::for_multi_trial(0.5, 2);
+        assert!((decision.probability - 0.75).abs() < 1e-12);
+        assert!((decision.weight - 4.0 / 3.0).abs() < 1e-12);
+
+        // One trial is just a trial.
+        let decision = SampleDecision::for_multi_trial(1e-12, 1);
+        assert!((decision.probability - 1e-12).abs() < 1e-24);
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(all(feature = "madsim", madsim))]
+use madsim::rand::GlobalRng;
+#[cfg(feature = "deterministic")]
+use rand::rngs::StdRng;
+#[cfg(not(feature = "deterministic"))]
+use rand::rngs::ThreadRng;
+use rand::RngCore;
+use std::fmt;
+#[cfg(feature = "deterministic")]
+use std::{cell::RefCell, rc::Rc};
+
+/// The seed used by [`default_rng`] with the `deterministic` feature enabled,
+/// unless the `FAST_BERNOULLI_SEED` environment variable overrides it.
+#[cfg(feature = "deterministic")]
+pub const DEFAULT_SEED: u64 = 0x5eed_fa57_b3e5_0000;
+
+/// Get a handle to this thread's default RNG.
+///
+/// Normally, this is [`rand::thread_rng`]. With the `deterministic` feature
+/// enabled, it is instead a [`StdRng`][rand::rngs::StdRng] seeded from
+/// [`DEFAULT_SEED`], or from the `FAST_BERNOULLI_SEED` environment variable if
+/// it is set to an integer, mixed with the name of the thread, if any, so
+/// that every run of the process makes the same decisions.
+///
+/// This is the RNG that samplers use wherever they don't take one as an
+/// argument: [`SamplerBuilder::build`][crate::SamplerBuilder::build],
+/// [`TableSample`][crate::TableSample]s that aren't repeatable, and the
+/// per-callsite samplers of `#[sampled]` and
+/// [`debug_assert_sampled!`][crate::debug_assert_sampled]. Passing it to every
+/// other sampler too makes a whole system of samplers reproducible, for
+/// integration and snapshot tests, just by enabling the feature, without
+/// threading seeds through every constructor.
+///
+/// Threads are seeded by name, because the order they start in is not
+/// reproducible; unnamed threads all start from the same seed. Test harnesses
+/// usually name each test's thread after the test.
+///
+/// With the `madsim` feature enabled, in a build for [madsim]'s deterministic
+/// simulator, it is instead madsim's global RNG whenever it is called inside a
+/// simulation, taking precedence over the `deterministic` feature. Sampling
+/// decisions are then made from the simulation's seed, in the simulation's own
+/// order, so a simulation test that includes sampling replays exactly, and
+/// madsim's determinism check covers the samplers' draws too. Outside of a
+/// simulation, and in builds without `--cfg madsim`, the feature has no
+/// effect.
+///
+/// Only the randomness comes from madsim. Samplers' own state, such as their
+/// skip counts, isn't registered with the simulator; it is reproduced by
+/// replaying the simulation from its seed, and can be saved and restored by
+/// hand with [`FastBernoulli::state`][crate::FastBernoulli::state].
+///
+/// [madsim]: https://docs.rs/madsim
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::FastBernoulli;
+///
+/// let mut rng = fast_bernoulli::default_rng();
+/// let mut bernoulli = FastBernoulli::new(0.1, &mut rng);
+///
+/// if bernoulli.trial(&mut rng) {
+///     // Record the sample...
+/// }
+/// ```
+#[inline]
+pub fn default_rng() -> DefaultRng {
+    DefaultRng {
+        #[cfg(not(feature = "deterministic"))]
+        rng: rand::thread_rng(),
+        #[cfg(feature = "deterministic")]
+        rng: SEEDED.with(Rc::clone),
+        #[cfg(all(feature = "madsim", madsim))]
+        simulated: madsim::runtime::Handle::try_current()
+            .is_ok()
+            .then(madsim::rand::thread_rng),
+    }
+}
+
+/// A handle to this thread's default RNG, returned by [`default_rng`].
+#[derive(Clone)]
+pub struct DefaultRng {
+    #[cfg(not(feature = "deterministic"))]
+    rng: ThreadRng,
+    #[cfg(feature = "deterministic")]
+    rng: Rc<RefCell<StdRng>>,
+    // The simulation's RNG, which replaces `rng` inside a madsim simulation.
+    #[cfg(all(feature = "madsim", madsim))]
+    simulated: Option<GlobalRng>,
+}
+
+impl fmt::Debug for DefaultRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefaultRng").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "deterministic")]
+thread_local! {
+    static SEEDED: Rc<RefCell<StdRng>> = {
+        use rand::SeedableRng;
+        let seed = std::env::var("FAST_BERNOULLI_SEED")
+            .ok()
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or(DEFAULT_SEED);
+        let seed = match std::thread::current().name() {
+            Some(name) => crate::hash::hash_with_salt(seed, name),
+            None => seed,
+        };
+        Rc::new(RefCell::new(StdRng::seed_from_u64(seed)))
+    };
+}
+
+impl DefaultRng {
+    #[inline]
+    fn with<T>(&mut self, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        #[cfg(all(feature = "madsim", madsim))]
+        if let Some(rng) = &mut self.simulated {
+            return f(rng);
+        }
+        #[cfg(not(feature = "deterministic"))]
+        {
+            f(&mut self.rng)
+        }
+        #[cfg(feature = "deterministic")]
+        {
+            f(&mut *self.rng.borrow_mut())
+        }
+    }
+}
+
+impl RngCore for DefaultRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.with(|rng| rng.next_u32())
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.with(|rng| rng.next_u64())
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.with(|rng| rng.fill_bytes(dest))
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.with(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+#[cfg(all(test, feature = "deterministic"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threads_with_the_same_name_draw_the_same_sequence() {
+        let draw = |name: &str| {
+            std::thread::Builder::new()
+                .name(name.into())
+                .spawn(|| default_rng().next_u64())
+                .unwrap()
+                .join()
+                .unwrap()
+        };
+        assert_eq!(draw("worker"), draw("worker"));
+        assert_ne!(draw("worker"), draw("other worker"));
+    }
+}
+
+#[cfg(all(test, feature = "madsim", madsim))]
+mod madsim_tests {
+    use super::*;
+
+    #[test]
+    fn simulations_with_the_same_seed_draw_the_same_sequence() {
+        let draw = |seed| {
+            madsim::runtime::Runtime::with_seed_and_config(seed, Default::default())
+                .block_on(async { default_rng().next_u64() })
+        };
+        assert_eq!(draw(1), draw(1));
+        assert_ne!(draw(1), draw(2));
+
+        // Outside of a simulation, the usual default RNG is used.
+        default_rng().next_u64();
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// An estimator of the number of distinct keys in a stream, from a Bernoulli
+/// sample of it.
+///
+/// Counting distinct keys from a sample is harder than counting events: a key
+/// that occurs a thousand times is almost surely sampled, while a key that
+/// occurs once is probably missed, and the sample alone can't tell how many
+/// such keys were missed. Simply dividing the number of distinct sampled keys
+/// by the sampling probability can be wildly wrong in either direction.
+///
+/// This uses the Guaranteed-Error Estimator (GEE) of Charikar, Chaudhuri,
+/// Motwani, and Narasayya, which is provably within a factor of about
+/// `1 / sqrt(p)` of the truth for any stream. Keys seen more than once in the
+/// sample count once each, and keys seen exactly once, which may stand for
+/// anywhere from one to `1 / p` distinct keys in the full stream, count
+/// `1 / sqrt(p)` each. [`bounds`][SampledDistinctCount::bounds] gives the
+/// range between those extremes.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{FastBernoulli, SampledDistinctCount};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut bernoulli = FastBernoulli::new(0.1, &mut rng);
+/// let mut distinct = SampledDistinctCount::new(bernoulli.probability());
+///
+/// // 100 users, each making 1,000 requests.
+/// for request in 0..100_000_u32 {
+///     if bernoulli.trial(&mut rng) {
+///         distinct.add(request % 100);
+///     }
+/// }
+///
+/// // Every user is almost surely in the sample more than once.
+/// let (low, high) = distinct.bounds();
+/// assert!(low <= 100.0 && 100.0 <= high);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SampledDistinctCount<K> {
+    probability: f64,
+    counts: HashMap<K, u64>,
+    singletons: u64,
+}
+
+impl<K> SampledDistinctCount<K>
+where
+    K: Hash + Eq,
+{
+    /// Construct a new, empty estimator for a sample taken with the given
+    /// probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 < probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new(probability: f64) -> Self {
+        assert!(
+            0.0 < probability && probability <= 1.0,
+            "`probability` must be in the range `0.0 < probability <= 1.0`"
+        );
+        SampledDistinctCount {
+            probability,
+            counts: HashMap::new(),
+            singletons: 0,
+        }
+    }
+
+    /// Add a sampled occurrence of `key`.
+    pub fn add(&mut self, key: K) {
+        let count = self.counts.entry(key).or_insert(0);
+        *count += 1;
+        match *count {
+            1 => self.singletons += 1,
+            2 => self.singletons -= 1,
+            _ => {}
+        }
+    }
+
+    /// Get the number of distinct keys in the sample.
+    #[inline]
+    pub fn sampled_distinct(&self) -> u64 {
+        self.counts.len() as u64
+    }
+
+    /// Get the number of keys that occur exactly once in the sample.
+    #[inline]
+    pub fn singletons(&self) -> u64 {
+        self.singletons
+    }
+
+    /// Estimate the number of distinct keys in the full stream.
+    pub fn estimate(&self) -> f64 {
+        let repeated = (self.sampled_distinct() - self.singletons) as f64;
+        repeated + self.singletons as f64 / self.probability.sqrt()
+    }
+
+    /// Get the range of plausible distinct counts for the full stream.
+    ///
+    /// The lower end is the number of distinct keys in the sample, which the
+    /// full stream has at least. The upper end assumes that every key seen
+    /// only once in the sample stands for `1 / p` distinct keys in the full
+    /// stream, as it would if every key occurred only once.
+    pub fn bounds(&self) -> (f64, f64) {
+        let low = self.sampled_distinct() as f64;
+        let high = low + self.singletons as f64 * (1.0 / self.probability - 1.0);
+        (low, high)
+    }
+
+    /// Get the probability with which the sample was taken.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_singletons() {
+        let mut distinct = SampledDistinctCount::new(0.25);
+        for key in [1, 2, 2, 3, 3, 3] {
+            distinct.add(key);
+        }
+        assert_eq!(distinct.sampled_distinct(), 3);
+        assert_eq!(distinct.singletons(), 1);
+
+        // Two repeated keys, and one singleton standing for `sqrt(4)` keys.
+        assert_eq!(distinct.estimate(), 4.0);
+        assert_eq!(distinct.bounds(), (3.0, 6.0));
+    }
+}
@@ -0,0 +1,127 @@
+//! Interoperability with the [`rand_distr`] crate.
+//!
+//! Requires the `rand_distr` feature.
+//!
+//! A `FastBernoulli`'s decisions follow a Bernoulli distribution, and its skip
+//! counts follow a geometric distribution: the number of failed trials before
+//! each success. This module converts between the two views, so that code built
+//! on `rand_distr` can use a `FastBernoulli`'s distributions directly.
+
+use crate::FastBernoulli;
+use rand::Rng;
+use rand_distr::{Bernoulli, Distribution, Geometric};
+
+/// The distribution of a [`FastBernoulli`]'s skip counts.
+///
+/// Samples are drawn exactly as `FastBernoulli` draws its own skip counts,
+/// including clamping to `u32::MAX`, which is what makes this different from
+/// [`rand_distr::Geometric`] for very small probabilities.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::distr::SkipCounts;
+/// use rand_distr::Distribution;
+///
+/// let mut rng = rand::thread_rng();
+/// let skip_counts = SkipCounts::new(0.01);
+///
+/// let gaps: Vec<u32> = skip_counts.sample_iter(&mut rng).take(100).collect();
+/// # let _ = gaps;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkipCounts {
+    probability: f64,
+}
+
+impl SkipCounts {
+    /// Construct the skip count distribution for the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new(probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        SkipCounts { probability }
+    }
+}
+
+impl Distribution<u32> for SkipCounts {
+    #[inline]
+    fn sample<R>(&self, rng: &mut R) -> u32
+    where
+        R: Rng + ?Sized,
+    {
+        // A fresh instance's skip count is a fresh draw.
+        FastBernoulli::new(self.probability, rng).skip_count()
+    }
+}
+
+/// Independent Bernoulli trials with the same probability.
+///
+/// Since `Distribution::sample` takes `&self`, this can't count down a skip
+/// count, and generates a random number for every sample, like
+/// [`rand_distr::Bernoulli`]. Call [`FastBernoulli::trial`] instead wherever
+/// a mutable reference is available.
+impl Distribution<bool> for FastBernoulli {
+    #[inline]
+    fn sample<R>(&self, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        rng.gen_bool(self.probability())
+    }
+}
+
+impl From<&FastBernoulli> for Bernoulli {
+    fn from(bernoulli: &FastBernoulli) -> Self {
+        Bernoulli::new(bernoulli.probability()).expect("probability is always valid")
+    }
+}
+
+impl From<&FastBernoulli> for Geometric {
+    fn from(bernoulli: &FastBernoulli) -> Self {
+        Geometric::new(bernoulli.probability()).expect("probability is always valid")
+    }
+}
+
+impl From<&FastBernoulli> for SkipCounts {
+    fn from(bernoulli: &FastBernoulli) -> Self {
+        SkipCounts::new(bernoulli.probability())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_counts_match_geometric() {
+        let mut rng = rand::thread_rng();
+        let bernoulli = FastBernoulli::new(0.1, &mut rng);
+        let skip_counts = SkipCounts::from(&bernoulli);
+        let geometric = Geometric::from(&bernoulli);
+
+        let n = 100_000;
+        let mean = |samples: &mut dyn Iterator<Item = f64>| samples.sum::<f64>() / n as f64;
+        let ours = mean(&mut (0..n).map(|_| f64::from(skip_counts.sample(&mut rng))));
+        let theirs = mean(&mut (0..n).map(|_| geometric.sample(&mut rng) as f64));
+
+        // The mean is 9 and the standard deviation is ~9.5, so the standard
+        // error of each mean is ~0.03.
+        assert!(
+            (ours - bernoulli.expected_gap()).abs() < 0.2,
+            "mean {}",
+            ours
+        );
+        assert!(
+            (theirs - bernoulli.expected_gap()).abs() < 0.2,
+            "mean {}",
+            theirs
+        );
+    }
+}
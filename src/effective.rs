@@ -0,0 +1,78 @@
+use crate::FastBernoulli;
+
+impl FastBernoulli {
+    /// Get the probability with which events are actually sampled, which can
+    /// differ from the configured [`probability`][FastBernoulli::probability].
+    ///
+    /// Skip counts are clamped to `u32::MAX`, so no more than `2^32 - 1`
+    /// consecutive events are ever skipped. For probabilities much below
+    /// `2^-32`, this samples noticeably more events than configured: at a
+    /// probability of `1e-12`, the realized rate is close to `2^-32`, over two
+    /// hundred times higher. For everything above about `1e-8`, the two are
+    /// equal to within floating-point precision.
+    ///
+    /// Reweighting sampled events by `1.0 / effective_probability()` keeps
+    /// estimates unbiased even when the skip count clamps.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    ///
+    /// let bernoulli = FastBernoulli::new(0.01, &mut rng);
+    /// assert_eq!(bernoulli.effective_probability(), 0.01);
+    ///
+    /// let rare = FastBernoulli::new(1e-12, &mut rng);
+    /// assert!(rare.effective_probability() > 2.0f64.powi(-32));
+    /// ```
+    #[inline]
+    pub fn effective_probability(&self) -> f64 {
+        effective_probability(self.probability())
+    }
+}
+
+/// The rate at which a `FastBernoulli` with the given probability samples
+/// events, given that its skip counts are clamped to `u32::MAX`.
+///
+/// With `q = 1 - p`, the unclamped skip count `G` has `P(G >= k) = q^k`, so the
+/// mean clamped skip count is `sum(q^k for k in 1..=u32::MAX)`, and one event
+/// is sampled per `1 + E[min(G, u32::MAX)]` events. That simplifies to
+/// `p / (1 - q^(2^32))`.
+pub(crate) fn effective_probability(probability: f64) -> f64 {
+    if probability == 0.0 || probability == 1.0 {
+        return probability;
+    }
+    let never_clamped = -((u32::MAX as f64 + 1.0) * (-probability).ln_1p()).exp_m1();
+    probability / never_clamped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_clamped_skip_counts() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(effective_probability(0.0), 0.0);
+        assert_eq!(effective_probability(1.0), 1.0);
+        assert_eq!(effective_probability(1e-6), 1e-6);
+
+        // At this probability, about two thirds of skip counts are clamped.
+        let probability = 1e-10;
+        let mut bernoulli = FastBernoulli::new(probability, &mut rng);
+        let n = 10_000;
+        let total: u64 = bernoulli.gaps(&mut rng).take(n).map(|gap| gap + 1).sum();
+        let observed = total as f64 / n as f64;
+
+        let expected = 1.0 / bernoulli.effective_probability();
+        assert!((1.0 / probability - expected) > 1e9);
+        // The clamped gap's standard deviation is less than `u32::MAX`.
+        let tolerance = 5.0 * u32::MAX as f64 / (n as f64).sqrt();
+        assert!(
+            (observed - expected).abs() <= tolerance,
+            "observed mean gap {observed}, expected {expected}"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::thinning::ThinnedBernoulli;
+use rand::Rng;
+
+/// Epsilon-greedy explore-versus-exploit decisions for multi-armed bandits.
+///
+/// Each step explores with probability `ε`, picking an arm uniformly at
+/// random, and otherwise exploits the caller's current best arm. `ε` can decay
+/// geometrically from step to step, down to a floor, with
+/// [`with_decay`][EpsilonGreedy::with_decay].
+///
+/// Exploit steps are as cheap as a plain [`FastBernoulli`][crate::FastBernoulli]
+/// trial that skips, even while `ε` decays.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::EpsilonGreedy;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut rewards = [0.0; 4];
+/// let mut pulls = [0; 4];
+///
+/// // Start fully exploring, and decay towards exploring 1% of the time.
+/// let mut policy = EpsilonGreedy::new(4, 1.0, &mut rng).with_decay(0.999, 0.01);
+///
+/// for _ in 0..10_000 {
+///     let best = (0..4)
+///         .max_by(|&a, &b| {
+///             let mean = |arm: usize| rewards[arm] / f64::from(pulls[arm]).max(1.0);
+///             mean(a).total_cmp(&mean(b))
+///         })
+///         .unwrap();
+///     let arm = policy.explore(&mut rng).unwrap_or(best);
+///
+///     pulls[arm] += 1;
+///     rewards[arm] += arm as f64;
+/// }
+///
+/// assert!(pulls[3] > 5_000);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct EpsilonGreedy {
+    arms: usize,
+    epsilon: f64,
+    decay: f64,
+    min_epsilon: f64,
+    candidates: ThinnedBernoulli,
+}
+
+impl EpsilonGreedy {
+    /// Construct a new `EpsilonGreedy` policy over `arms` arms that explores
+    /// with probability `epsilon`.
+    ///
+    /// # Panics
+    ///
+    /// There must be at least one arm, and `epsilon` must be within the range
+    /// `0.0 <= epsilon <= 1.0`, and this method will panic if that is not the
+    /// case.
+    pub fn new<R>(arms: usize, epsilon: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(arms > 0, "there must be at least one arm");
+        assert!(
+            (0.0..=1.0).contains(&epsilon),
+            "`epsilon` must be in the range `0.0 <= epsilon <= 1.0`"
+        );
+        EpsilonGreedy {
+            arms,
+            epsilon,
+            decay: 1.0,
+            min_epsilon: epsilon,
+            candidates: ThinnedBernoulli::new(epsilon, rng),
+        }
+    }
+
+    /// Multiply `ε` by `decay` after every step, until it reaches
+    /// `min_epsilon`.
+    ///
+    /// # Panics
+    ///
+    /// The decay must be within the range `0.0 <= decay <= 1.0` and
+    /// `min_epsilon` must be within the range `0.0 <= min_epsilon <= epsilon`,
+    /// and this method will panic if that is not the case.
+    pub fn with_decay(mut self, decay: f64, min_epsilon: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&decay),
+            "`decay` must be in the range `0.0 <= decay <= 1.0`"
+        );
+        assert!(
+            (0.0..=self.epsilon).contains(&min_epsilon),
+            "`min_epsilon` must be in the range `0.0 <= min_epsilon <= epsilon`"
+        );
+        self.decay = decay;
+        self.min_epsilon = min_epsilon;
+        self
+    }
+
+    /// Decide this step's action.
+    ///
+    /// Returns the arm to explore, chosen uniformly at random, or `None` if
+    /// this step should exploit the best arm known so far.
+    pub fn explore<R>(&mut self, rng: &mut R) -> Option<usize>
+    where
+        R: Rng + ?Sized,
+    {
+        let epsilon = self.epsilon;
+        if self.epsilon > self.min_epsilon {
+            self.epsilon = (self.epsilon * self.decay).max(self.min_epsilon);
+        }
+
+        if !self.candidates.trial(epsilon, rng) {
+            return None;
+        }
+        Some(rng.gen_range(0..self.arms))
+    }
+
+    /// Get the probability with which the next step explores.
+    #[inline]
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Get the number of arms.
+    #[inline]
+    pub fn arms(&self) -> usize {
+        self.arms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explores_at_the_decaying_epsilon() {
+        let mut rng = rand::thread_rng();
+        let mut policy = EpsilonGreedy::new(3, 0.5, &mut rng).with_decay(0.9999, 0.05);
+
+        let mut expected = 0.0;
+        let mut variance = 0.0;
+        let mut explored = [0_u32; 3];
+        for _ in 0..100_000 {
+            let epsilon = policy.epsilon();
+            expected += epsilon;
+            variance += epsilon * (1.0 - epsilon);
+            if let Some(arm) = policy.explore(&mut rng) {
+                explored[arm] += 1;
+            }
+        }
+        assert_eq!(policy.epsilon(), 0.05);
+
+        let total: u32 = explored.iter().sum();
+        let tolerance = 5.0 * variance.sqrt();
+        assert!(
+            (f64::from(total) - expected).abs() <= tolerance,
+            "expected ~{} explorations, found {}",
+            expected,
+            total,
+        );
+        assert!(explored.iter().all(|&n| n > total / 4));
+    }
+}
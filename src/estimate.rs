@@ -0,0 +1,226 @@
+use rand::Rng;
+
+/// Get the probability that an event of size `n` is sampled by
+/// `multi_trial(n)` with per-unit probability `p`: `1 - (1 - p)^n`.
+///
+/// Computed without cancellation, so that it stays accurate for tiny `p`.
+#[inline]
+pub(crate) fn multi_trial_probability(p: f64, n: f64) -> f64 {
+    if p == 1.0 {
+        return if n > 0.0 { 1.0 } else { 0.0 };
+    }
+    -((-p).ln_1p() * n).exp_m1()
+}
+
+/// Round a non-negative weight to an integer count, up or down at random with
+/// probability proportional to its fractional part, so that the expected
+/// count equals the weight exactly.
+///
+/// This lets integer-count sinks, such as histograms, record fractional
+/// weights like `1 / 0.03` without rounding bias.
+#[cfg_attr(not(feature = "hdrhistogram"), allow(dead_code))]
+pub(crate) fn stochastic_round<R>(weight: f64, rng: &mut R) -> u64
+where
+    R: Rng + ?Sized,
+{
+    let floor = weight.floor();
+    let up = rng.gen::<f64>() < weight - floor;
+    floor as u64 + u64::from(up)
+}
+
+/// An accumulator for Horvitz–Thompson estimates of population totals from a
+/// sample.
+///
+/// Under Bernoulli (or, more generally, Poisson) sampling, where each item is
+/// independently included with some known probability, dividing each sampled
+/// item's value by its inclusion probability and summing gives an unbiased
+/// estimate of the total over the whole population. This accumulator does
+/// that, and also tracks the estimate's variance so that it can report a
+/// standard error and confidence interval.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{FastBernoulli, HorvitzThompson};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut bernoulli = FastBernoulli::new(0.1, &mut rng);
+/// let mut estimate = HorvitzThompson::new();
+///
+/// let mut true_total = 0.0;
+/// for bytes in 1..=10_000 {
+///     true_total += bytes as f64;
+///     if bernoulli.trial(&mut rng) {
+///         estimate.add(bernoulli.probability(), bytes as f64);
+///     }
+/// }
+///
+/// // About 99.7% of the time, this interval contains `true_total`, and with
+/// // this seed, it does.
+/// let (low, high) = estimate.confidence_interval(3.0);
+/// assert!(low <= true_total && true_total <= high);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HorvitzThompson {
+    total: f64,
+    variance: f64,
+    samples: u64,
+}
+
+impl HorvitzThompson {
+    /// Construct a new, empty `HorvitzThompson` accumulator.
+    #[inline]
+    pub fn new() -> Self {
+        HorvitzThompson::default()
+    }
+
+    /// Add a sampled item with the given value, which was included in the
+    /// sample with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 < probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    #[inline]
+    pub fn add(&mut self, probability: f64, value: f64) {
+        assert!(
+            0.0 < probability && probability <= 1.0,
+            "`probability` must be in the range `0.0 < probability <= 1.0`"
+        );
+        let weighted = value / probability;
+        self.total += weighted;
+        // The unbiased variance estimator for Poisson sampling.
+        self.variance += (1.0 - probability) * weighted * weighted;
+        self.samples += 1;
+    }
+
+    /// Merge another accumulator's samples into this one.
+    ///
+    /// Both accumulators must have been fed samples from disjoint parts of the
+    /// population.
+    #[inline]
+    pub fn merge(&mut self, other: &HorvitzThompson) {
+        self.total += other.total;
+        self.variance += other.variance;
+        self.samples += other.samples;
+    }
+
+    /// Get the estimated population total.
+    #[inline]
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+
+    /// Get the estimated variance of the estimated total.
+    #[inline]
+    pub fn variance(&self) -> f64 {
+        self.variance
+    }
+
+    /// Get the estimated standard error of the estimated total.
+    #[inline]
+    pub fn standard_error(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    /// Get an approximate confidence interval for the population total,
+    /// spanning `z` standard errors on either side of the estimate.
+    ///
+    /// For example, `z = 1.96` gives an approximately 95% confidence interval.
+    #[inline]
+    pub fn confidence_interval(&self, z: f64) -> (f64, f64) {
+        let margin = z * self.standard_error();
+        (self.total - margin, self.total + margin)
+    }
+
+    /// Get the number of sampled items added.
+    #[inline]
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_trial_probability_edge_cases() {
+        assert_eq!(multi_trial_probability(0.0, 100.0), 0.0);
+        assert_eq!(multi_trial_probability(1.0, 1.0), 1.0);
+        assert_eq!(multi_trial_probability(1.0, 0.0), 0.0);
+        assert_eq!(multi_trial_probability(0.5, 0.0), 0.0);
+        assert!((multi_trial_probability(0.5, 3.0) - 0.875).abs() < 1e-12);
+
+        // No cancellation for tiny probabilities.
+        let p = 1e-15;
+        assert!((multi_trial_probability(p, 2.0) / (2.0 * p) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stochastic_rounding_is_unbiased() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(stochastic_round(3.0, &mut rng), 3);
+
+        let n = 100_000;
+        let total: u64 = (0..n).map(|_| stochastic_round(2.25, &mut rng)).sum();
+        assert!((0..100).all(|_| (2..=3).contains(&stochastic_round(2.25, &mut rng))));
+
+        // Each rounding adds a Bernoulli(0.25) to 2.
+        let expected = 2.25 * f64::from(n);
+        let tolerance = 5.0 * (f64::from(n) * 0.25 * 0.75).sqrt();
+        assert!((total as f64 - expected).abs() <= tolerance);
+    }
+
+    #[test]
+    fn accumulates_weighted_totals_and_variances() {
+        let mut estimate = HorvitzThompson::new();
+        estimate.add(1.0, 5.0);
+        assert_eq!(estimate.total(), 5.0);
+        assert_eq!(estimate.variance(), 0.0);
+
+        // Weighted up to 8, with variance `(1 - 0.25) * 8^2`.
+        estimate.add(0.25, 2.0);
+        assert_eq!(estimate.total(), 13.0);
+        assert_eq!(estimate.variance(), 48.0);
+        assert_eq!(estimate.samples(), 2);
+        assert_eq!(
+            estimate.confidence_interval(2.0),
+            (13.0 - 2.0 * 48f64.sqrt(), 13.0 + 2.0 * 48f64.sqrt())
+        );
+
+        let mut other = HorvitzThompson::new();
+        other.add(0.5, 1.0);
+        estimate.merge(&other);
+        assert_eq!(estimate.total(), 15.0);
+        assert_eq!(estimate.variance(), 50.0);
+        assert_eq!(estimate.samples(), 3);
+    }
+
+    #[test]
+    fn estimates_are_unbiased() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = crate::FastBernoulli::new(0.2, &mut rng);
+        let mut estimate = HorvitzThompson::new();
+
+        let n = 50_000;
+        for _ in 0..n {
+            if bernoulli.trial(&mut rng) {
+                estimate.add(0.2, 1.0);
+            }
+        }
+
+        // The estimated total of `n` ones is `n`, within 5 true standard
+        // errors: `sqrt(n * (1 - p) / p)`.
+        let tolerance = 5.0 * (f64::from(n) * 0.8 / 0.2).sqrt();
+        assert!((estimate.total() - f64::from(n)).abs() <= tolerance);
+    }
+
+    #[test]
+    #[should_panic(expected = "`probability` must be in the range `0.0 < probability <= 1.0`")]
+    fn rejects_zero_probability() {
+        HorvitzThompson::new().add(0.0, 1.0);
+    }
+}
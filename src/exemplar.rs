@@ -0,0 +1,199 @@
+use crate::rate::TokenBucket;
+use crate::time::Instant;
+use crate::{FastBernoulli, Rate};
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Selection of exemplars for metric series, for metrics library authors.
+///
+/// Exemplars, such as the trace IDs that Prometheus and OpenMetrics attach to
+/// histogram buckets and counters, should come from a small random subset of
+/// observations: enough to link each series to representative traces, but
+/// few enough that recording them costs nothing noticeable. Each series gets
+/// its own Bernoulli sampler and its own rate cap, so that a hot series can't
+/// crowd out a quiet one, and a burst of traffic can't turn into a burst of
+/// exemplar updates.
+///
+/// Exemplars go stale, and a series whose only exemplar is from an hour ago
+/// isn't much help. With [`with_max_age`][ExemplarSampler::with_max_age], a
+/// series whose exemplar is older than the maximum age takes the next
+/// observation as its new exemplar, whether or not it wins its Bernoulli
+/// trial, as long as the rate cap allows. So quiet series still refresh their
+/// exemplars, while busy ones are sampled as usual.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{ExemplarSampler, RateExt};
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Consider 1% of observations, update each series' exemplar at most once
+/// // every ten seconds, and never let one get older than a minute.
+/// let mut exemplars = ExemplarSampler::new(0.01, 1.per(Duration::from_secs(10)))
+///     .with_max_age(Duration::from_secs(60));
+///
+/// let now = Instant::now();
+/// if exemplars.offer(&"http_request_duration_seconds_bucket{le=\"0.1\"}", now, &mut rng) {
+///     // Attach this observation's trace ID as the series' exemplar...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExemplarSampler<S> {
+    probability: f64,
+    cap: Rate,
+    max_age: Option<Duration>,
+    series: HashMap<S, SeriesState>,
+}
+
+#[derive(Debug, Clone)]
+struct SeriesState {
+    bernoulli: FastBernoulli,
+    cap: TokenBucket,
+    last: Option<Instant>,
+}
+
+impl<S> ExemplarSampler<S>
+where
+    S: Hash + Eq + Clone,
+{
+    /// Construct a new `ExemplarSampler` that considers observations with the
+    /// given probability, and selects at most `cap` exemplars per series.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new(probability: f64, cap: Rate) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        ExemplarSampler {
+            probability,
+            cap,
+            max_age: None,
+            series: HashMap::new(),
+        }
+    }
+
+    /// Select the next observation of any series whose exemplar is older than
+    /// `max_age`, or that has none, regardless of its Bernoulli trial.
+    #[inline]
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Offer an observation of `series`, made at `now`, returning whether it
+    /// should become the series' exemplar.
+    pub fn offer<R>(&mut self, series: &S, now: Instant, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let (probability, cap) = (self.probability, self.cap);
+        let state = match self.series.get_mut(series) {
+            Some(state) => state,
+            None => self
+                .series
+                .entry(series.clone())
+                .or_insert_with(|| SeriesState {
+                    bernoulli: FastBernoulli::new(probability, rng),
+                    cap: TokenBucket::new(cap),
+                    last: None,
+                }),
+        };
+
+        let stale = self.max_age.is_some_and(|max_age| {
+            state
+                .last
+                .is_none_or(|last| now.saturating_duration_since(last) > max_age)
+        });
+        // Always perform the trial, so that stale series don't disturb their
+        // skip counts.
+        let sampled = state.bernoulli.trial(rng);
+        if !(sampled || stale) || !state.cap.take(now) {
+            return false;
+        }
+        state.last = Some(now);
+        true
+    }
+
+    /// Get when `series` last selected an exemplar, if it has.
+    #[inline]
+    pub fn last_selected(&self, series: &S) -> Option<Instant> {
+        self.series.get(series).and_then(|state| state.last)
+    }
+
+    /// Forget `series`, for example when it is unregistered.
+    #[inline]
+    pub fn remove(&mut self, series: &S) {
+        self.series.remove(series);
+    }
+
+    /// Get the number of series tracked.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.series.len()
+    }
+
+    /// Are no series tracked?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+
+    /// Get the probability with which observations are considered.
+    ///
+    /// This is the configured probability, adjusted for clamped skip counts;
+    /// see [`FastBernoulli::effective_probability`].
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        crate::effective::effective_probability(self.probability)
+    }
+
+    /// Get the per-series cap on selected exemplars.
+    #[inline]
+    pub fn cap(&self) -> Rate {
+        self.cap
+    }
+
+    /// Get the maximum age of an exemplar, if any.
+    #[inline]
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RateExt;
+
+    #[test]
+    fn stale_series_refresh_within_the_cap() {
+        let mut rng = rand::thread_rng();
+        let mut exemplars = ExemplarSampler::new(0.0, 1.per(Duration::from_secs(10)))
+            .with_max_age(Duration::from_secs(30));
+
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // A series without an exemplar takes the first observation.
+        assert!(exemplars.offer(&"a", at(0), &mut rng));
+        assert!(!exemplars.offer(&"a", at(20), &mut rng));
+        assert!(exemplars.offer(&"a", at(31), &mut rng));
+        assert_eq!(exemplars.last_selected(&"a"), Some(at(31)));
+
+        // Series are capped independently.
+        let mut busy = ExemplarSampler::new(1.0, 2.per_second());
+        assert!(busy.offer(&"a", at(0), &mut rng));
+        assert!(busy.offer(&"a", at(0), &mut rng));
+        assert!(!busy.offer(&"a", at(0), &mut rng));
+        assert!(busy.offer(&"b", at(0), &mut rng));
+        assert_eq!(busy.len(), 2);
+    }
+}
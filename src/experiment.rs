@@ -0,0 +1,147 @@
+use crate::hash::hash_with_salt;
+use std::hash::Hash;
+
+/// Deterministic assignment of units (users, sessions, devices, ...) to
+/// experiment variants.
+///
+/// Each variant is configured with the proportion of units it should receive;
+/// whatever proportion is left over is the holdout. A unit's variant is a pure
+/// function of the experiment's salt and the unit's ID, so the same unit lands
+/// in the same variant every time it is bucketed, in every process, without
+/// storing any assignments.
+///
+/// Using a fresh salt for each experiment keeps assignments in different
+/// experiments independent of one another.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::ExperimentBucketer;
+///
+/// // Control and treatment each get 10% of users; the other 80% are held out.
+/// let bucketer = ExperimentBucketer::new(0x5eed, &[0.1, 0.1]);
+///
+/// match bucketer.bucket("user-1234") {
+///     Some(0) => { /* control */ }
+///     Some(1) => { /* treatment */ }
+///     None => { /* holdout */ }
+///     Some(_) => unreachable!(),
+/// }
+///
+/// // Assignment is sticky.
+/// assert_eq!(bucketer.bucket("user-1234"), bucketer.bucket("user-1234"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExperimentBucketer {
+    salt: u64,
+    proportions: Vec<f64>,
+    // Exclusive upper bounds of each variant's slice of the 64-bit hash
+    // space. Kept as `u128` so that a cumulative proportion of exactly `1.0`
+    // covers every possible hash, including `u64::MAX`.
+    thresholds: Vec<u128>,
+}
+
+impl ExperimentBucketer {
+    /// Construct a new `ExperimentBucketer` with the given salt and per-variant
+    /// proportions.
+    ///
+    /// Variant `i` receives `proportions[i]` of all units. The holdout receives
+    /// the remaining `1.0 - proportions.iter().sum()`.
+    ///
+    /// # Panics
+    ///
+    /// Every proportion must be within the range `0.0 <= proportion <= 1.0`,
+    /// and the proportions must not sum to more than `1.0`. This method will
+    /// panic if that is not the case.
+    pub fn new(salt: u64, proportions: &[f64]) -> Self {
+        let mut cumulative = 0.0;
+        let mut thresholds = Vec::with_capacity(proportions.len());
+        for &p in proportions {
+            assert!(
+                (0.0..=1.0).contains(&p),
+                "every proportion must be in the range `0.0 <= proportion <= 1.0`"
+            );
+            cumulative += p;
+            // Allow a little slack for proportions like `[0.1; 10]` whose
+            // floating-point sum is a hair over one.
+            assert!(
+                cumulative <= 1.0 + 1e-9,
+                "proportions must not sum to more than `1.0`"
+            );
+            thresholds.push((cumulative.min(1.0) * TWO_POW_64) as u128);
+        }
+
+        ExperimentBucketer {
+            salt,
+            proportions: proportions.to_vec(),
+            thresholds,
+        }
+    }
+
+    /// Get the variant that the given unit is assigned to, or `None` if the
+    /// unit is in the holdout.
+    pub fn bucket<K>(&self, unit_id: &K) -> Option<usize>
+    where
+        K: Hash + ?Sized,
+    {
+        let h = u128::from(hash_with_salt(self.salt, unit_id));
+        self.thresholds.iter().position(|&t| h < t)
+    }
+
+    /// Get the number of configured variants, not counting the holdout.
+    #[inline]
+    pub fn num_variants(&self) -> usize {
+        self.proportions.len()
+    }
+
+    /// Get the proportion of units assigned to the given variant, or `None` if
+    /// there is no such variant.
+    #[inline]
+    pub fn proportion(&self, variant: usize) -> Option<f64> {
+        self.proportions.get(variant).copied()
+    }
+
+    /// Get the proportion of units that are not assigned to any variant.
+    pub fn holdout(&self) -> f64 {
+        (1.0 - self.proportions.iter().sum::<f64>()).max(0.0)
+    }
+}
+
+const TWO_POW_64: f64 = 18_446_744_073_709_551_616.0;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proportions_are_respected() {
+        let bucketer = ExperimentBucketer::new(42, &[0.2, 0.3]);
+        let units = 100_000;
+        let mut counts = [0; 3];
+        for id in 0..units {
+            match bucketer.bucket(&(id as u64)) {
+                Some(v) => counts[v] += 1,
+                None => counts[2] += 1,
+            }
+        }
+
+        for (count, expected) in counts.iter().zip([0.2, 0.3, 0.5]) {
+            let expected = expected * units as f64;
+            assert!(
+                (*count as f64 - expected).abs() < expected * 0.05,
+                "expected ~{} units, found {}",
+                expected,
+                count,
+            );
+        }
+    }
+
+    #[test]
+    fn full_coverage_has_no_holdout() {
+        let bucketer = ExperimentBucketer::new(0, &[0.5, 0.5]);
+        assert_eq!(bucketer.holdout(), 0.0);
+        for id in 0..1000_u64 {
+            assert!(bucketer.bucket(&id).is_some());
+        }
+    }
+}
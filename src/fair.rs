@@ -0,0 +1,240 @@
+use crate::time::Instant;
+use crate::{ClampStats, FastBernoulli, ProbabilityBounds};
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Fair per-key sampling: every key gets about the same number of samples per
+/// window, however many events it has.
+///
+/// Each key's probability is set so that its expected number of samples per
+/// window is the configured target: a key that had `n` events in the previous
+/// window is sampled with probability `min(1.0, target / n)` in this one. A
+/// noisy key therefore can't crowd quieter keys out of the sample budget.
+///
+/// Keys that are new, or that grow within a window, are sampled at their
+/// previous rate until their count doubles past the count their probability
+/// was based on, and then at a correspondingly lower probability. So a key's
+/// probability only changes a handful of times per window, and trials between
+/// changes are as cheap as a plain [`FastBernoulli`]'s.
+///
+/// Each sampled event comes with its weight, the reciprocal of its effective
+/// probability, for reweighting; [`probability`][FairSampler::probability]
+/// exposes the probability a key's next event would be sampled with.
+///
+/// Keys that had no events in the previous window are forgotten when a new
+/// window begins.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::FairSampler;
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Aim for 10 samples per tenant per minute.
+/// let mut sampler = FairSampler::new(10.0, Duration::from_secs(60));
+///
+/// let now = Instant::now();
+/// for i in 0..10_000 {
+///     let tenant = if i % 100 == 0 { "quiet" } else { "noisy" };
+///     if let Some(weight) = sampler.trial(&tenant, now, &mut rng) {
+///         // Record the sample together with its weight...
+///         # let _ = weight;
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FairSampler<K> {
+    target: f64,
+    window: Duration,
+    window_start: Option<Instant>,
+    keys: HashMap<K, KeyState>,
+    bounds: ProbabilityBounds,
+    clamps: ClampStats,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct KeyState {
+    previous: u64,
+    current: u64,
+    basis: u64,
+    bernoulli: FastBernoulli,
+}
+
+impl<K> FairSampler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Construct a new `FairSampler` that aims for `target` samples per key per
+    /// `window`.
+    ///
+    /// # Panics
+    ///
+    /// The target must be positive and finite, and the window must be non-zero,
+    /// and this method will panic if that is not the case.
+    pub fn new(target: f64, window: Duration) -> Self {
+        assert!(
+            target > 0.0 && target.is_finite(),
+            "`target` must be positive and finite"
+        );
+        assert!(!window.is_zero(), "`window` must be non-zero");
+        FairSampler {
+            target,
+            window,
+            window_start: None,
+            keys: HashMap::new(),
+            bounds: ProbabilityBounds::UNBOUNDED,
+            clamps: ClampStats::default(),
+        }
+    }
+
+    /// Keep the probabilities chosen for each key within `bounds`.
+    ///
+    /// Keys' probabilities are clamped as they are next chosen, so set the
+    /// bounds before offering any events.
+    pub fn with_bounds(mut self, bounds: ProbabilityBounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    /// Perform a trial for an event of `key` that occurred at `now`.
+    ///
+    /// Returns the event's weight if it should be sampled, or `None` if it
+    /// should not.
+    pub fn trial<R>(&mut self, key: &K, now: Instant, rng: &mut R) -> Option<f64>
+    where
+        R: Rng + ?Sized,
+    {
+        self.roll_windows(now, rng);
+
+        let target = self.target;
+        let bounds = self.bounds;
+        let clamps = &mut self.clamps;
+        let state = match self.keys.get_mut(key) {
+            Some(state) => state,
+            None => self.keys.entry(key.clone()).or_insert_with(|| KeyState {
+                previous: 0,
+                current: 0,
+                basis: 0,
+                bernoulli: FastBernoulli::new(
+                    bounds.apply(probability_for(target, 0), clamps),
+                    rng,
+                ),
+            }),
+        };
+
+        state.current += 1;
+        if state.current as f64 > 2.0 * target.max(state.basis as f64) {
+            state.basis = state.current;
+            let probability = bounds.apply(probability_for(target, state.basis), clamps);
+            state.bernoulli = FastBernoulli::new(probability, rng);
+        }
+
+        if state.bernoulli.trial(rng) {
+            Some(1.0 / state.bernoulli.effective_probability())
+        } else {
+            None
+        }
+    }
+
+    /// Get the probability with which `key`'s next event would be sampled, if
+    /// it occurred in the current window.
+    pub fn probability(&self, key: &K) -> f64 {
+        self.keys
+            .get(key)
+            .map_or(self.bounds.ceiling(), |state| state.bernoulli.probability())
+    }
+
+    /// Get the counts of chosen probabilities that were clamped by the bounds.
+    #[inline]
+    pub fn clamp_stats(&self) -> ClampStats {
+        self.clamps
+    }
+
+    /// Get the number of keys being tracked.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Are no keys being tracked?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    fn roll_windows<R>(&mut self, now: Instant, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        let start = match self.window_start {
+            None => {
+                self.window_start = Some(now);
+                return;
+            }
+            Some(start) => start,
+        };
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed < self.window {
+            return;
+        }
+
+        let windows = elapsed.as_nanos() / self.window.as_nanos();
+        let advance = u32::try_from(windows).map_or(elapsed, |w| self.window * w);
+        self.window_start = Some(start + advance);
+
+        let target = self.target;
+        let bounds = self.bounds;
+        let clamps = &mut self.clamps;
+        self.keys.retain(|_, state| {
+            state.previous = if windows == 1 { state.current } else { 0 };
+            state.current = 0;
+            if state.previous == 0 {
+                return false;
+            }
+            state.basis = state.previous;
+            let probability = bounds.apply(probability_for(target, state.basis), clamps);
+            state.bernoulli = FastBernoulli::new(probability, rng);
+            true
+        });
+    }
+}
+
+#[inline]
+fn probability_for(target: f64, count: u64) -> f64 {
+    (target / count as f64).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balances_samples_across_keys() {
+        let mut rng = rand::thread_rng();
+        let window = Duration::from_secs(60);
+        let mut sampler = FairSampler::new(50.0, window);
+
+        let start = Instant::now();
+        let mut run_window = |now: Instant, rng: &mut rand::rngs::ThreadRng| {
+            let (mut hot, mut cold) = (0, 0);
+            for i in 0..100_000 {
+                let key = if i % 100 == 0 { "cold" } else { "hot" };
+                if sampler.trial(&key, now, rng).is_some() {
+                    *(if key == "hot" { &mut hot } else { &mut cold }) += 1;
+                }
+            }
+            (hot, cold)
+        };
+
+        run_window(start, &mut rng);
+        let (hot, cold) = run_window(start + window, &mut rng);
+
+        // Both keys expect 50 samples; allow five standard deviations.
+        assert!((15..=85).contains(&hot), "hot key got {} samples", hot);
+        assert!((15..=85).contains(&cold), "cold key got {} samples", cold);
+    }
+}
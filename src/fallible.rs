@@ -0,0 +1,126 @@
+use crate::FastBernoulli;
+use rand::RngCore;
+
+impl FastBernoulli {
+    /// Like [`trial`][FastBernoulli::trial], but for random number generators
+    /// that can fail, such as hardware entropy sources.
+    ///
+    /// Uses `rng.try_fill_bytes` to draw randomness, and returns its error
+    /// rather than panicking. Most trials don't draw randomness at all, and so
+    /// can't fail. On error, `self` is left unchanged, so the next trial
+    /// retries the draw.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::rngs::OsRng;
+    /// let mut bernoulli = FastBernoulli::new(0.1, &mut rng);
+    ///
+    /// match bernoulli.try_trial(&mut rng) {
+    ///     Ok(true) => { /* Record a sample of this event. */ }
+    ///     Ok(false) => {}
+    ///     Err(e) => eprintln!("entropy source failed: {}", e),
+    /// }
+    /// ```
+    #[inline]
+    pub fn try_trial<R>(&mut self, rng: &mut R) -> Result<bool, rand::Error>
+    where
+        R: RngCore + ?Sized,
+    {
+        if self.skip_count > 0 {
+            self.skip_count -= 1;
+            return Ok(false);
+        }
+
+        self.try_reset_skip_count(rng)?;
+        Ok(self.probability != 0.0)
+    }
+
+    /// Like [`multi_trial`][FastBernoulli::multi_trial], but for random
+    /// number generators that can fail.
+    ///
+    /// See [`try_trial`][FastBernoulli::try_trial] for details.
+    #[inline]
+    pub fn try_multi_trial<R>(&mut self, n: u32, rng: &mut R) -> Result<bool, rand::Error>
+    where
+        R: RngCore + ?Sized,
+    {
+        if n <= self.skip_count {
+            self.skip_count -= n;
+            return Ok(false);
+        }
+
+        self.try_reset_skip_count(rng)?;
+        Ok(self.probability != 0.0)
+    }
+
+    #[inline]
+    fn try_reset_skip_count<R>(&mut self, rng: &mut R) -> Result<(), rand::Error>
+    where
+        R: RngCore + ?Sized,
+    {
+        // As in `reset_skip_count`, compile the slow path only once.
+        let mut rng = rng;
+        self.try_reset_skip_count_dyn(&mut rng)
+    }
+
+    fn try_reset_skip_count_dyn(&mut self, rng: &mut dyn RngCore) -> Result<(), rand::Error> {
+        if self.probability == 0.0 || self.probability == 1.0 {
+            // The edge cases don't draw any randomness.
+            self.reset_skip_count(rng);
+            return Ok(());
+        }
+
+        let mut bytes = [0; 8];
+        rng.try_fill_bytes(&mut bytes)?;
+        self.set_skip_count_from_bits(u64::from_le_bytes(bytes));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FailingRng;
+
+    impl RngCore for FailingRng {
+        fn next_u32(&mut self) -> u32 {
+            unreachable!("FailingRng only supports try_fill_bytes")
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            unreachable!("FailingRng only supports try_fill_bytes")
+        }
+
+        fn fill_bytes(&mut self, _dest: &mut [u8]) {
+            unreachable!("FailingRng only supports try_fill_bytes")
+        }
+
+        fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand::Error> {
+            Err(rand::Error::new("entropy exhausted"))
+        }
+    }
+
+    #[test]
+    fn propagates_rng_errors() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = FastBernoulli::new(0.5, &mut rng);
+        bernoulli.skip_count = 1;
+
+        // Skipping doesn't draw randomness, so it can't fail.
+        assert!(!bernoulli.try_trial(&mut FailingRng).unwrap());
+        assert!(bernoulli.try_trial(&mut FailingRng).is_err());
+        assert!(bernoulli.try_multi_trial(10, &mut FailingRng).is_err());
+        assert_eq!(bernoulli.skip_count(), 0);
+
+        // The next trial retries, and samples.
+        assert!(bernoulli.try_trial(&mut rng).unwrap());
+
+        // Neither does always or never sampling.
+        let mut always = FastBernoulli::new(1.0, &mut rng);
+        assert!(always.try_multi_trial(10, &mut FailingRng).unwrap());
+    }
+}
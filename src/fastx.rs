@@ -0,0 +1,268 @@
+//! Subsampling of FASTA and FASTQ sequencing reads.
+//!
+//! Requires the `fastx` feature.
+//!
+//! FASTQ records span four lines, and FASTA records a header line followed by
+//! any number of sequence lines, so sampling lines, as a generic line sampler
+//! would, corrupts the files. The functions here sample whole records,
+//! copying each sampled record to the output verbatim.
+//!
+//! Paired-end reads are stored as two files whose records correspond one to
+//! one, and the mates of a pair have to be kept or dropped together.
+//! [`sample_paired`] makes one decision per pair, and fails if the files have
+//! different numbers of records.
+
+use crate::FastBernoulli;
+use rand::Rng;
+use std::io::{self, BufRead, Write};
+
+/// A sequencing read file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Format {
+    /// FASTA: a `>` header line, followed by sequence lines.
+    Fasta,
+    /// FASTQ: an `@` header line, a sequence line, a `+` separator line, and a
+    /// quality line.
+    Fastq,
+}
+
+/// Statistics about a subsampling run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[non_exhaustive]
+pub struct FastxStats {
+    /// Records, or pairs of records, read.
+    pub records: u64,
+    /// Records, or pairs of records, sampled and written.
+    pub sampled: u64,
+}
+
+/// Copy a sampled subset of `input`'s records to `output`.
+///
+/// Each record is sampled by one trial of `bernoulli`.
+///
+/// # Errors
+///
+/// Returns any I/O error, and an error of kind
+/// [`InvalidData`][io::ErrorKind::InvalidData] if `input` is not well-formed.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::fastx::{self, Format};
+/// use fast_bernoulli::FastBernoulli;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut bernoulli = FastBernoulli::new(0.5, &mut rng);
+///
+/// let reads = b"@read1\nACGT\n+\nIIII\n@read2\nTTGA\n+\nIIII\n";
+/// let mut sampled = Vec::new();
+/// let stats = fastx::sample(Format::Fastq, &reads[..], &mut sampled, &mut bernoulli, &mut rng)?;
+///
+/// assert_eq!(stats.records, 2);
+/// assert_eq!(sampled.iter().filter(|&&b| b == b'\n').count() as u64, 4 * stats.sampled);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn sample<I, O, R>(
+    format: Format,
+    mut input: I,
+    mut output: O,
+    bernoulli: &mut FastBernoulli,
+    rng: &mut R,
+) -> io::Result<FastxStats>
+where
+    I: BufRead,
+    O: Write,
+    R: Rng + ?Sized,
+{
+    let mut stats = FastxStats::default();
+    let mut record = Vec::new();
+    while read_record(format, &mut input, &mut record)? {
+        stats.records += 1;
+        if bernoulli.trial(rng) {
+            stats.sampled += 1;
+            output.write_all(&record)?;
+        }
+    }
+    output.flush()?;
+    Ok(stats)
+}
+
+/// Copy a sampled subset of the pairs of records in two paired-end files to
+/// two outputs, keeping or dropping the mates of each pair together.
+///
+/// Each pair is sampled by one trial of `bernoulli`.
+///
+/// # Errors
+///
+/// Returns any I/O error, and an error of kind
+/// [`InvalidData`][io::ErrorKind::InvalidData] if either input is not
+/// well-formed, or if one input has more records than the other.
+pub fn sample_paired<I1, I2, O1, O2, R>(
+    format: Format,
+    (mut input1, mut input2): (I1, I2),
+    (mut output1, mut output2): (O1, O2),
+    bernoulli: &mut FastBernoulli,
+    rng: &mut R,
+) -> io::Result<FastxStats>
+where
+    I1: BufRead,
+    I2: BufRead,
+    O1: Write,
+    O2: Write,
+    R: Rng + ?Sized,
+{
+    let mut stats = FastxStats::default();
+    let mut record1 = Vec::new();
+    let mut record2 = Vec::new();
+    loop {
+        let more1 = read_record(format, &mut input1, &mut record1)?;
+        let more2 = read_record(format, &mut input2, &mut record2)?;
+        match (more1, more2) {
+            (false, false) => break,
+            (true, true) => {}
+            _ => {
+                return Err(invalid_data(
+                    "paired inputs have different numbers of records",
+                ))
+            }
+        }
+
+        stats.records += 1;
+        if bernoulli.trial(rng) {
+            stats.sampled += 1;
+            output1.write_all(&record1)?;
+            output2.write_all(&record2)?;
+        }
+    }
+    output1.flush()?;
+    output2.flush()?;
+    Ok(stats)
+}
+
+/// Read the next whole record, including its line terminators, into `record`,
+/// returning `false` at the end of the input.
+fn read_record<I>(format: Format, input: &mut I, record: &mut Vec<u8>) -> io::Result<bool>
+where
+    I: BufRead,
+{
+    record.clear();
+    match format {
+        Format::Fastq => {
+            if input.read_until(b'\n', record)? == 0 {
+                return Ok(false);
+            }
+            if record[0] != b'@' {
+                return Err(invalid_data("FASTQ record doesn't start with `@`"));
+            }
+            let mut lines = 1;
+            let mut separator = 0;
+            while lines < 4 {
+                if lines == 2 {
+                    separator = record.len();
+                }
+                if input.read_until(b'\n', record)? == 0 {
+                    return Err(invalid_data("truncated FASTQ record"));
+                }
+                lines += 1;
+            }
+            if record[separator] != b'+' {
+                return Err(invalid_data(
+                    "FASTQ record's third line doesn't start with `+`",
+                ));
+            }
+            ensure_newline(record);
+        }
+        Format::Fasta => {
+            if input.read_until(b'\n', record)? == 0 {
+                return Ok(false);
+            }
+            if record[0] != b'>' {
+                return Err(invalid_data("FASTA record doesn't start with `>`"));
+            }
+            loop {
+                ensure_newline(record);
+                match input.fill_buf()?.first() {
+                    None | Some(b'>') => break,
+                    Some(_) => input.read_until(b'\n', record)?,
+                };
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Terminate a final line without a newline, so that records can be
+/// concatenated.
+fn ensure_newline(record: &mut Vec<u8>) {
+    if record.last() != Some(&b'\n') {
+        record.push(b'\n');
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_whole_records_and_pairs() {
+        let mut rng = rand::thread_rng();
+        let mut all = FastBernoulli::new(1.0, &mut rng);
+
+        let fasta = b">a\nACGT\nACGT\n>b\n\n>c\nTT";
+        let mut out = Vec::new();
+        let stats = sample(Format::Fasta, &fasta[..], &mut out, &mut all, &mut rng).unwrap();
+        assert_eq!((stats.records, stats.sampled), (3, 3));
+        assert_eq!(out, b">a\nACGT\nACGT\n>b\n\n>c\nTT\n");
+
+        let mut half = FastBernoulli::new(0.5, &mut rng);
+        let mut r1 = String::new();
+        let mut r2 = String::new();
+        let n = 10_000;
+        for i in 0..n {
+            r1.push_str(&format!("@{i}/1\nACGT\n+\nIIII\n"));
+            r2.push_str(&format!("@{i}/2\nTGCA\n+\nIIII\n"));
+        }
+        let (mut o1, mut o2) = (Vec::new(), Vec::new());
+        let stats = sample_paired(
+            Format::Fastq,
+            (r1.as_bytes(), r2.as_bytes()),
+            (&mut o1, &mut o2),
+            &mut half,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(stats.records, n);
+        let mates = |o: &[u8], mate: &str| -> Vec<String> {
+            String::from_utf8(o.to_vec())
+                .unwrap()
+                .lines()
+                .step_by(4)
+                .map(|header| header.strip_suffix(mate).unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(mates(&o1, "/1"), mates(&o2, "/2"));
+        assert_eq!(mates(&o1, "/1").len() as u64, stats.sampled);
+
+        let expected = 0.5 * n as f64;
+        assert!((stats.sampled as f64 - expected).abs() <= 5.0 * (expected * 0.5).sqrt());
+
+        let err = sample_paired(
+            Format::Fastq,
+            (r1.as_bytes(), &b"@x\nA\n+\nI\n"[..]),
+            (io::sink(), io::sink()),
+            &mut half,
+            &mut rng,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
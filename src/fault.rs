@@ -0,0 +1,267 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::time::Duration;
+
+/// A kind of fault that a [`FaultInjector`] can inject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FaultKind {
+    /// Fail the operation with an error.
+    Error,
+    /// Delay the operation.
+    Latency,
+    /// Silently drop the operation.
+    Drop,
+}
+
+/// The faults to inject into one operation, returned by
+/// [`FaultInjector::inject`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Faults {
+    /// Fail the operation with an error.
+    pub error: bool,
+    /// Delay the operation by this long.
+    pub latency: Option<Duration>,
+    /// Silently drop the operation.
+    pub drop: bool,
+}
+
+impl Faults {
+    /// Are there no faults to inject?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        !self.error && self.latency.is_none() && !self.drop
+    }
+}
+
+/// A chaos-engineering fault injector, that decides which faults to inject
+/// into each operation.
+///
+/// Each kind of fault is injected independently, with its own probability, so
+/// an operation can be both delayed and then failed, and each kind's rate is
+/// exactly its configured probability. Probabilities can be adjusted at
+/// runtime, and [`disable`][FaultInjector::disable] is a kill switch that stops
+/// all injection immediately, without forgetting the configuration.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::FaultInjector;
+/// use std::time::Duration;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// let mut faults = FaultInjector::new()
+///     .with_error(0.01, &mut rng)
+///     .with_latency(0.05, Duration::from_millis(200), &mut rng);
+///
+/// for _ in 0..1_000 {
+///     let injected = faults.inject(&mut rng);
+///     if let Some(latency) = injected.latency {
+///         // Sleep for `latency`...
+///         # let _ = latency;
+///     }
+///     if injected.error {
+///         // Fail the request...
+///         continue;
+///     }
+///     // Handle the request...
+/// }
+///
+/// // Something went wrong; stop injecting faults.
+/// faults.disable();
+/// assert!(faults.inject(&mut rng).is_empty());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct FaultInjector {
+    error: FastBernoulli,
+    latency: FastBernoulli,
+    latency_duration: Duration,
+    drop: FastBernoulli,
+    enabled: bool,
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        FaultInjector::new()
+    }
+}
+
+impl FaultInjector {
+    /// Construct a new, enabled `FaultInjector` that injects no faults.
+    pub fn new() -> Self {
+        // Samplers with probability zero never draw from their RNG.
+        let never = FastBernoulli::new(0.0, &mut crate::default_rng());
+        FaultInjector {
+            error: never,
+            latency: never,
+            latency_duration: Duration::ZERO,
+            drop: never,
+            enabled: true,
+        }
+    }
+
+    /// Fail operations with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn with_error<R>(mut self, probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        self.set_probability(FaultKind::Error, probability, rng);
+        self
+    }
+
+    /// Delay operations by `duration` with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn with_latency<R>(mut self, probability: f64, duration: Duration, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        self.set_probability(FaultKind::Latency, probability, rng);
+        self.latency_duration = duration;
+        self
+    }
+
+    /// Drop operations with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn with_drop<R>(mut self, probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        self.set_probability(FaultKind::Drop, probability, rng);
+        self
+    }
+
+    /// Inject faults of the given kind with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn set_probability<R>(&mut self, kind: FaultKind, probability: f64, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        trace_sampler!(
+            info,
+            kind = ?kind,
+            from = self.probability(kind),
+            to = probability,
+            "fault probability changed"
+        );
+        *self.sampler_mut(kind) = FastBernoulli::new(probability, rng);
+    }
+
+    /// Set how long injected latency faults delay operations.
+    #[inline]
+    pub fn set_latency(&mut self, duration: Duration) {
+        self.latency_duration = duration;
+    }
+
+    /// Decide which faults to inject into an operation.
+    ///
+    /// While disabled, this injects no faults, and doesn't perform any trials.
+    pub fn inject<R>(&mut self, rng: &mut R) -> Faults
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.enabled {
+            return Faults::default();
+        }
+        Faults {
+            error: self.error.trial(rng),
+            latency: self.latency.trial(rng).then_some(self.latency_duration),
+            drop: self.drop.trial(rng),
+        }
+    }
+
+    /// Stop injecting faults, until [`enable`][FaultInjector::enable] is
+    /// called.
+    #[inline]
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    /// Resume injecting faults.
+    #[inline]
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// Is fault injection enabled?
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Get the probability with which faults of the given kind are injected.
+    #[inline]
+    pub fn probability(&self, kind: FaultKind) -> f64 {
+        match kind {
+            FaultKind::Error => self.error.probability(),
+            FaultKind::Latency => self.latency.probability(),
+            FaultKind::Drop => self.drop.probability(),
+        }
+    }
+
+    /// Get how long injected latency faults delay operations.
+    #[inline]
+    pub fn latency(&self) -> Duration {
+        self.latency_duration
+    }
+
+    fn sampler_mut(&mut self, kind: FaultKind) -> &mut FastBernoulli {
+        match kind {
+            FaultKind::Error => &mut self.error,
+            FaultKind::Latency => &mut self.latency,
+            FaultKind::Drop => &mut self.drop,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faults_are_injected_independently() {
+        let mut rng = rand::thread_rng();
+        let mut faults = FaultInjector::new().with_drop(1.0, &mut rng).with_latency(
+            0.25,
+            Duration::from_millis(5),
+            &mut rng,
+        );
+
+        let n = 10_000;
+        let mut delayed = 0;
+        for _ in 0..n {
+            let injected = faults.inject(&mut rng);
+            assert!(injected.drop && !injected.error);
+            if let Some(latency) = injected.latency {
+                assert_eq!(latency, Duration::from_millis(5));
+                delayed += 1;
+            }
+        }
+        let expected = 0.25 * f64::from(n);
+        assert!((f64::from(delayed) - expected).abs() <= 5.0 * (expected * 0.75).sqrt());
+
+        faults.disable();
+        assert!(faults.inject(&mut rng).is_empty());
+        faults.enable();
+        faults.set_probability(FaultKind::Drop, 0.0, &mut rng);
+        assert!(!faults.inject(&mut rng).drop);
+    }
+}
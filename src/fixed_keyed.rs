@@ -0,0 +1,323 @@
+use crate::FastBernoulli;
+use heapless::LinearMap;
+use rand::Rng;
+use std::fmt;
+
+/// What a [`FixedKeyedSampler`] does with a new key when it is already
+/// tracking as many keys as it can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eviction {
+    /// Don't track the new key. Events for untracked keys are sampled at the
+    /// default probability, from a single skip count that they all share.
+    Refuse,
+    /// Stop tracking the least recently used key, and track the new key in its
+    /// place.
+    LeastRecentlyUsed,
+}
+
+/// Per-key Bernoulli sampling, in a fixed amount of memory and without
+/// allocating.
+///
+/// Like a map from keys to [`FastBernoulli`]s, each key, such as a sensor ID or
+/// a message type, gets its own skip count, so that a flood of events for one
+/// key doesn't affect which events are sampled for the others, and a key can
+/// be given its own probability. The keys are held in a `heapless` map with
+/// room for `N` of them, inline in the sampler, so the sampler never
+/// allocates. [`new`][FixedKeyedSampler::new] is a `const fn`, so a sampler
+/// can also be initialized in a `static`, behind a lock. Keys are compared
+/// linearly, which is fast for the small `N` of, say, per-sensor or
+/// per-message-type sampling.
+///
+/// This doesn't make the crate usable without `std`, which it always requires.
+///
+/// When all `N` slots are taken, new keys are handled according to the
+/// sampler's [`Eviction`] policy. An evicted key's probability is forgotten,
+/// and it is sampled at the default probability if it is seen again.
+///
+/// Requires the `heapless` feature.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{Eviction, FixedKeyedSampler};
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Sample 1% of each sensor's readings, tracking up to eight sensors.
+/// let mut sampler: FixedKeyedSampler<u8, 8> =
+///     FixedKeyedSampler::new(0.01, Eviction::LeastRecentlyUsed);
+///
+/// // Keep every reading from the sensor being debugged.
+/// sampler.set_probability(3, 1.0, &mut rng).unwrap();
+///
+/// let sensor = 3;
+/// assert!(sampler.trial(sensor, &mut rng));
+/// ```
+pub struct FixedKeyedSampler<K, const N: usize> {
+    keys: LinearMap<K, Slot, N>,
+    default_probability: f64,
+    eviction: Eviction,
+    // Shared by untracked keys, with the `Refuse` policy, and drawn when the
+    // first one is seen.
+    overflow: Option<FastBernoulli>,
+    clock: u64,
+    stats: FixedKeyedStats,
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    bernoulli: FastBernoulli,
+    last_used: u64,
+}
+
+/// Statistics about a [`FixedKeyedSampler`]'s use of its capacity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[non_exhaustive]
+pub struct FixedKeyedStats {
+    /// The number of keys evicted to make room for new ones.
+    pub evictions: u64,
+    /// The number of trials for keys that were refused a slot, and shared the
+    /// overflow skip count instead.
+    pub overflowed: u64,
+}
+
+impl<K, const N: usize> fmt::Debug for FixedKeyedSampler<K, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedKeyedSampler")
+            .field("default_probability", &self.default_probability)
+            .field("eviction", &self.eviction)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, const N: usize> Clone for FixedKeyedSampler<K, N>
+where
+    K: Eq + Clone,
+{
+    fn clone(&self) -> Self {
+        FixedKeyedSampler {
+            keys: self.keys.clone(),
+            default_probability: self.default_probability,
+            eviction: self.eviction,
+            overflow: self.overflow,
+            clock: self.clock,
+            stats: self.stats,
+        }
+    }
+}
+
+impl<K, const N: usize> FixedKeyedSampler<K, N>
+where
+    K: Eq,
+{
+    /// Construct a new `FixedKeyedSampler` that samples each key's events with
+    /// `default_probability`, and handles new keys according to `eviction`
+    /// once it is full.
+    ///
+    /// No skip counts are drawn until keys are seen, so this needs no RNG.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::{Eviction, FixedKeyedSampler};
+    /// use std::sync::Mutex;
+    ///
+    /// static SAMPLER: Mutex<FixedKeyedSampler<u16, 4>> =
+    ///     Mutex::new(FixedKeyedSampler::new(0.1, Eviction::Refuse));
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let sampled = SAMPLER.lock().unwrap().trial(0x2a, &mut rng);
+    /// # let _ = sampled;
+    /// ```
+    pub const fn new(default_probability: f64, eviction: Eviction) -> Self {
+        assert!(
+            0.0 <= default_probability && default_probability <= 1.0,
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        FixedKeyedSampler {
+            keys: LinearMap::new(),
+            default_probability,
+            eviction,
+            overflow: None,
+            clock: 0,
+            stats: FixedKeyedStats {
+                evictions: 0,
+                overflowed: 0,
+            },
+        }
+    }
+
+    /// Perform a trial for an event for `key`.
+    pub fn trial<R>(&mut self, key: K, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.clock += 1;
+        if let Some(slot) = self.keys.get_mut(&key) {
+            slot.last_used = self.clock;
+            return slot.bernoulli.trial(rng);
+        }
+
+        if !self.make_room() {
+            self.stats.overflowed += 1;
+            let default_probability = self.default_probability;
+            return self
+                .overflow
+                .get_or_insert_with(|| FastBernoulli::new(default_probability, rng))
+                .trial(rng);
+        }
+        let mut bernoulli = FastBernoulli::new(self.default_probability, rng);
+        let sampled = bernoulli.trial(rng);
+        self.insert(key, bernoulli);
+        sampled
+    }
+
+    /// Sample `key`'s events with `probability`, instead of the default
+    /// probability, starting to track it if it isn't tracked already.
+    ///
+    /// Returns the key as an error if the sampler is full and its eviction
+    /// policy is [`Refuse`][Eviction::Refuse].
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn set_probability<R>(&mut self, key: K, probability: f64, rng: &mut R) -> Result<(), K>
+    where
+        R: Rng + ?Sized,
+    {
+        self.clock += 1;
+        if let Some(slot) = self.keys.get_mut(&key) {
+            slot.last_used = self.clock;
+            slot.bernoulli.set_probability(probability, rng);
+            return Ok(());
+        }
+
+        let bernoulli = FastBernoulli::new(probability, rng);
+        if !self.make_room() {
+            return Err(key);
+        }
+        self.insert(key, bernoulli);
+        Ok(())
+    }
+
+    /// Get the probability with which `key`'s events are sampled.
+    pub fn probability(&self, key: &K) -> f64 {
+        self.keys.get(key).map_or(self.default_probability, |slot| {
+            slot.bernoulli.probability()
+        })
+    }
+
+    /// Stop tracking `key`, returning whether it was tracked.
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.keys.remove(key).is_some()
+    }
+
+    /// Get the number of keys being tracked.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Is no key being tracked?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Get the number of keys this sampler can track: `N`.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Get the policy for new keys once the sampler is full.
+    #[inline]
+    pub fn eviction(&self) -> Eviction {
+        self.eviction
+    }
+
+    /// Get the statistics for the keys seen so far.
+    #[inline]
+    pub fn stats(&self) -> FixedKeyedStats {
+        self.stats
+    }
+
+    /// Make room for a new key, if there isn't any, as the eviction policy
+    /// allows. Returns whether there is room now.
+    fn make_room(&mut self) -> bool {
+        if !self.keys.is_full() {
+            return true;
+        }
+        match self.eviction {
+            Eviction::Refuse => false,
+            Eviction::LeastRecentlyUsed => {
+                let Some(oldest) = self.keys.values().map(|slot| slot.last_used).min() else {
+                    // With no capacity at all, there is nothing to evict.
+                    return false;
+                };
+                // Every use gets its own tick, so this removes exactly one key.
+                self.keys.retain(|_, slot| slot.last_used != oldest);
+                self.stats.evictions += 1;
+                trace_sampler!(debug, "evicted least recently used key");
+                true
+            }
+        }
+    }
+
+    fn insert(&mut self, key: K, bernoulli: FastBernoulli) {
+        let slot = Slot {
+            bernoulli,
+            last_used: self.clock,
+        };
+        let inserted = self.keys.insert(key, slot);
+        debug_assert!(matches!(inserted, Ok(None)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_according_to_policy() {
+        let mut rng = rand::thread_rng();
+
+        let mut lru: FixedKeyedSampler<u8, 2> =
+            FixedKeyedSampler::new(0.0, Eviction::LeastRecentlyUsed);
+        lru.set_probability(1, 1.0, &mut rng).unwrap();
+        lru.set_probability(2, 1.0, &mut rng).unwrap();
+        assert!(lru.trial(1, &mut rng));
+        // Key 2 is the least recently used, so it makes room for key 3.
+        assert!(!lru.trial(3, &mut rng));
+        assert_eq!(lru.probability(&2), 0.0);
+        assert!(lru.trial(1, &mut rng));
+        assert_eq!(lru.len(), 2);
+        assert_eq!(lru.stats().evictions, 1);
+
+        let mut refuse: FixedKeyedSampler<u8, 1> = FixedKeyedSampler::new(1.0, Eviction::Refuse);
+        assert!(refuse.trial(1, &mut rng));
+        assert!(refuse.trial(2, &mut rng));
+        assert_eq!(refuse.set_probability(2, 0.0, &mut rng), Err(2));
+        assert_eq!(refuse.stats().overflowed, 1);
+        assert!(refuse.remove(&1));
+        assert_eq!(refuse.set_probability(2, 0.0, &mut rng), Ok(()));
+        assert!(!refuse.trial(2, &mut rng));
+    }
+
+    #[test]
+    #[should_panic(expected = "`probability` must be in the range")]
+    fn new_rejects_invalid_probabilities() {
+        let _: FixedKeyedSampler<u8, 1> = FixedKeyedSampler::new(f64::NAN, Eviction::Refuse);
+    }
+}
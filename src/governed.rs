@@ -0,0 +1,173 @@
+use crate::FastBernoulli;
+use governor::clock::Clock;
+use governor::middleware::RateLimitingMiddleware;
+use governor::state::{DirectStateStore, NotKeyed};
+use governor::RateLimiter;
+use rand::Rng;
+use std::fmt;
+use std::ops::Deref;
+
+/// A sampler whose samples are additionally gated by a [`governor`] rate
+/// limiter.
+///
+/// Each event is first sampled with a fixed probability, and each sampled
+/// event then has to pass the limiter's quota, so the limiter only spends its
+/// budget on events that were sampled. Samples that the quota rejects are
+/// counted in [`GovernedStats`], so that estimates can account for them.
+///
+/// The limiter can be owned, or shared through any pointer to it, such as an
+/// `Arc` or a reference, so that existing quotas can be reused.
+///
+/// Requires the `governor` feature.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::GovernedSampler;
+/// use governor::{Quota, RateLimiter};
+/// use std::num::NonZeroU32;
+/// use std::sync::Arc;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Sample 10% of requests, but no more than 50 per second in total.
+/// let quota = Quota::per_second(NonZeroU32::new(50).unwrap());
+/// let limiter = Arc::new(RateLimiter::direct(quota));
+/// let mut sampler = GovernedSampler::new(0.1, limiter, &mut rng);
+///
+/// for _ in 0..10_000 {
+///     if sampler.trial(&mut rng) {
+///         // Record a sample of this request...
+///     }
+/// }
+///
+/// let stats = sampler.stats();
+/// assert!(stats.samples <= 50);
+/// assert_eq!(stats.events, 10_000);
+/// ```
+pub struct GovernedSampler<L> {
+    bernoulli: FastBernoulli,
+    limiter: L,
+    stats: GovernedStats,
+}
+
+/// Statistics about a [`GovernedSampler`]'s decisions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[non_exhaustive]
+pub struct GovernedStats {
+    /// Events seen.
+    pub events: u64,
+    /// Events sampled and allowed by the quota.
+    pub samples: u64,
+    /// Events sampled, but rejected by the quota.
+    pub rate_limited: u64,
+}
+
+impl<L> fmt::Debug for GovernedSampler<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GovernedSampler")
+            .field("bernoulli", &self.bernoulli)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<L> GovernedSampler<L> {
+    /// Construct a new `GovernedSampler` that samples events with the given
+    /// probability, and then gates samples with `limiter`.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, limiter: L, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        GovernedSampler {
+            bernoulli: FastBernoulli::new(probability, rng),
+            limiter,
+            stats: GovernedStats::default(),
+        }
+    }
+
+    /// Get statistics about the decisions made so far.
+    #[inline]
+    pub fn stats(&self) -> GovernedStats {
+        self.stats
+    }
+
+    /// Get the probability with which events are sampled, before the quota.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Get the rate limiter.
+    #[inline]
+    pub fn limiter(&self) -> &L {
+        &self.limiter
+    }
+}
+
+impl<L, S, C, MW> GovernedSampler<L>
+where
+    L: Deref<Target = RateLimiter<NotKeyed, S, C, MW>>,
+    S: DirectStateStore,
+    C: Clock,
+    MW: RateLimitingMiddleware<C::Instant>,
+{
+    /// Perform a trial, and if it succeeds, check the quota.
+    ///
+    /// Returns `true` if the event was sampled and allowed by the quota.
+    pub fn trial<R>(&mut self, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.stats.events += 1;
+        if !self.bernoulli.trial(rng) {
+            return false;
+        }
+        if self.limiter.check().is_ok() {
+            self.stats.samples += 1;
+            true
+        } else {
+            self.stats.rate_limited += 1;
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use governor::clock::FakeRelativeClock;
+    use governor::Quota;
+    use std::num::NonZeroU32;
+    use std::time::Duration;
+
+    #[test]
+    fn quota_rejections_are_counted() {
+        let mut rng = rand::thread_rng();
+        let clock = FakeRelativeClock::default();
+        let quota = Quota::per_second(NonZeroU32::new(10).unwrap());
+        let limiter = RateLimiter::direct_with_clock(quota, clock.clone());
+        let mut sampler = GovernedSampler::new(1.0, &limiter, &mut rng);
+
+        assert_eq!((0..100).filter(|_| sampler.trial(&mut rng)).count(), 10);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!((0..100).filter(|_| sampler.trial(&mut rng)).count(), 10);
+        assert_eq!(
+            sampler.stats(),
+            GovernedStats {
+                events: 200,
+                samples: 20,
+                rate_limited: 180,
+            }
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! Generating Bernoulli decision masks on the GPU, with [`wgpu`].
+//!
+//! Requires the `wgpu` feature.
+//!
+//! ML-scale data pipelines decide whether to keep each of hundreds of
+//! millions of rows at a time. [`GpuMaskGenerator`] computes those decisions
+//! in a compute shader, as a bitmask with one bit per event.
+//!
+//! Each event's decision is a pure function of the key and the event's index:
+//! event `i` is sampled when the first word of the [`PhiloxRng`] block for
+//! counter `i` and the key is less than `probability * 2^32`. So the GPU's
+//! masks are bit-for-bit identical to those computed on the CPU by
+//! [`mask_on_cpu`], on every device, and any range of events can be recomputed
+//! independently of the rest.
+//!
+//! That threshold is an integer, so probabilities are rounded to the nearest
+//! multiple of `2^-32`, except that non-zero probabilities below `2^-32` are
+//! rounded up to it, rather than down to zero. Sample at such tiny rates on
+//! the CPU, with a [`FastBernoulli`][crate::FastBernoulli], if the difference
+//! matters.
+//!
+//! [`PhiloxRng`]: crate::PhiloxRng
+
+use crate::philox::philox4x32_10;
+use std::error::Error;
+use std::fmt;
+use wgpu::util::DeviceExt;
+
+/// The largest number of events a single mask can cover with the default
+/// device limits: a 128 MiB storage buffer of bits.
+pub const MAX_EVENTS: u64 = 128 * 1024 * 1024 * 8;
+
+const WORKGROUP_SIZE: u32 = 64;
+const MAX_WORKGROUPS_PER_DIMENSION: u32 = 65_535;
+
+/// An error generating a mask on the GPU.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GpuError {
+    /// No suitable GPU adapter was found.
+    Adapter(wgpu::RequestAdapterError),
+    /// The GPU device could not be opened.
+    Device(wgpu::RequestDeviceError),
+    /// Waiting for the GPU failed.
+    Poll(wgpu::PollError),
+    /// Reading back the mask failed.
+    Map(wgpu::BufferAsyncError),
+    /// More than [`MAX_EVENTS`] events were requested.
+    TooManyEvents,
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuError::Adapter(e) => write!(f, "no suitable GPU adapter: {e}"),
+            GpuError::Device(e) => write!(f, "failed to open the GPU device: {e}"),
+            GpuError::Poll(e) => write!(f, "failed to wait for the GPU: {e}"),
+            GpuError::Map(e) => write!(f, "failed to read back the mask: {e}"),
+            GpuError::TooManyEvents => write!(f, "more than `MAX_EVENTS` events requested"),
+        }
+    }
+}
+
+impl Error for GpuError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GpuError::Adapter(e) => Some(e),
+            GpuError::Device(e) => Some(e),
+            GpuError::Poll(e) => Some(e),
+            GpuError::Map(e) => Some(e),
+            GpuError::TooManyEvents => None,
+        }
+    }
+}
+
+/// Generates Bernoulli decision masks in a GPU compute shader.
+///
+/// Masks are returned as `u32` words, with event `i` in bit `i % 32` of word
+/// `i / 32`, and any bits past the last event clear.
+///
+/// # Example
+///
+/// ```no_run
+/// use fast_bernoulli::gpu::{mask_on_cpu, GpuMaskGenerator};
+///
+/// # fn main() -> Result<(), fast_bernoulli::gpu::GpuError> {
+/// let generator = GpuMaskGenerator::new()?;
+///
+/// // Keep 1% of a hundred million rows.
+/// let mask = generator.generate(0x5eed, 0.01, 100_000_000)?;
+/// let kept: u32 = mask.iter().map(|word| word.count_ones()).sum();
+/// # let _ = kept;
+///
+/// // The CPU agrees with the GPU on every decision.
+/// assert_eq!(mask_on_cpu(0x5eed, 0.01, 1_000), generator.generate(0x5eed, 0.01, 1_000)?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GpuMaskGenerator {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl GpuMaskGenerator {
+    /// Open the default GPU and construct a new `GpuMaskGenerator` on it.
+    ///
+    /// This blocks until the device is open.
+    pub fn new() -> Result<Self, GpuError> {
+        pollster::block_on(async {
+            let instance =
+                wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions::default())
+                .await
+                .map_err(GpuError::Adapter)?;
+            let (device, queue) = adapter
+                .request_device(&wgpu::DeviceDescriptor::default())
+                .await
+                .map_err(GpuError::Device)?;
+            Ok(Self::from_device(device, queue))
+        })
+    }
+
+    /// Construct a new `GpuMaskGenerator` on an already-open device.
+    pub fn from_device(device: wgpu::Device, queue: wgpu::Queue) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("fast_bernoulli::gpu"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("fast_bernoulli::gpu"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        GpuMaskGenerator {
+            device,
+            queue,
+            pipeline,
+        }
+    }
+
+    /// Generate the decision mask for events `0..events`, sampled with the
+    /// given probability, for the given key.
+    ///
+    /// This blocks until the mask has been read back from the GPU.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn generate(&self, key: u64, probability: f64, events: u64) -> Result<Vec<u32>, GpuError> {
+        let threshold = threshold(probability);
+        if events > MAX_EVENTS {
+            return Err(GpuError::TooManyEvents);
+        }
+        let words = events.div_ceil(32) as u32;
+        if words == 0 {
+            return Ok(Vec::new());
+        }
+        let size = u64::from(words) * 4;
+
+        let params: Vec<u8> = [
+            key as u32,
+            (key >> 32) as u32,
+            // A threshold of `2^32` samples everything, and doesn't fit in a
+            // `u32`, so pass it as a flag.
+            threshold as u32,
+            u32::from(threshold > u64::from(u32::MAX)),
+            events as u32,
+            (events >> 32) as u32,
+            words,
+            0,
+        ]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+        let params = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("fast_bernoulli::gpu params"),
+                contents: &params,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let mask = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("fast_bernoulli::gpu mask"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("fast_bernoulli::gpu staging"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("fast_bernoulli::gpu"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: mask.as_entire_binding(),
+                },
+            ],
+        });
+
+        // Spread the workgroups over two dimensions, since each is limited.
+        let workgroups = words.div_ceil(WORKGROUP_SIZE);
+        let x = workgroups.min(MAX_WORKGROUPS_PER_DIMENSION);
+        let y = workgroups.div_ceil(x);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(x, y, 1);
+        }
+        encoder.copy_buffer_to_buffer(&mask, 0, &staging, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        staging
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(GpuError::Poll)?;
+        receiver
+            .recv()
+            .expect("`poll` runs the mapping callback")
+            .map_err(GpuError::Map)?;
+
+        let view = staging
+            .slice(..)
+            .get_mapped_range()
+            .expect("the staging buffer was just mapped");
+        let mask = view
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        drop(view);
+        staging.unmap();
+        Ok(mask)
+    }
+}
+
+/// Generate the decision mask for events `0..events`, sampled with the given
+/// probability, for the given key, on the CPU.
+///
+/// This computes exactly the same mask as [`GpuMaskGenerator::generate`], one
+/// Philox block per event.
+///
+/// # Panics
+///
+/// The probability must be within the range `0.0 <= probability <= 1.0` and
+/// this function will panic if that is not the case.
+pub fn mask_on_cpu(key: u64, probability: f64, events: u64) -> Vec<u32> {
+    let threshold = threshold(probability);
+    let key = [key as u32, (key >> 32) as u32];
+    (0..events.div_ceil(32))
+        .map(|word| {
+            let mut bits = 0;
+            for bit in 0..32 {
+                let event = word * 32 + bit;
+                if event >= events {
+                    break;
+                }
+                let block = philox4x32_10([event as u32, (event >> 32) as u32, 0, 0], key);
+                if u64::from(block[0]) < threshold {
+                    bits |= 1 << bit;
+                }
+            }
+            bits
+        })
+        .collect()
+}
+
+/// Get the threshold below which a uniform `u32` is sampled: `p * 2^32`,
+/// rounded, but never rounded down to zero unless `p` is zero.
+fn threshold(probability: f64) -> u64 {
+    assert!(
+        (0.0..=1.0).contains(&probability),
+        "`probability` must be in the range `0.0 <= probability <= 1.0`"
+    );
+    let threshold = (probability * 4_294_967_296.0).round() as u64;
+    if probability > 0.0 {
+        threshold.max(1)
+    } else {
+        threshold
+    }
+}
+
+const SHADER: &str = r#"
+struct Params {
+    key: vec2<u32>,
+    threshold: u32,
+    always: u32,
+    events: vec2<u32>,
+    words: u32,
+    _padding: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> mask: array<u32>;
+
+// The high and low halves of the 64-bit product of `a` and `b`.
+fn mulhilo(a: u32, b: u32) -> vec2<u32> {
+    let a_lo = a & 0xffffu;
+    let a_hi = a >> 16u;
+    let b_lo = b & 0xffffu;
+    let b_hi = b >> 16u;
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let cross = (lo_lo >> 16u) + (hi_lo & 0xffffu) + lo_hi;
+    let hi = a_hi * b_hi + (hi_lo >> 16u) + (cross >> 16u);
+    return vec2<u32>(hi, a * b);
+}
+
+// The first word of the Philox4x32-10 block for `counter` and `key`.
+fn philox(counter_in: vec4<u32>, key_in: vec2<u32>) -> u32 {
+    var counter = counter_in;
+    var key = key_in;
+    for (var round = 0u; round < 10u; round++) {
+        if (round > 0u) {
+            key = key + vec2<u32>(0x9e3779b9u, 0xbb67ae85u);
+        }
+        let p0 = mulhilo(0xd2511f53u, counter.x);
+        let p1 = mulhilo(0xcd9e8d57u, counter.z);
+        counter = vec4<u32>(p1.x ^ counter.y ^ key.x, p1.y, p0.x ^ counter.w ^ key.y, p0.y);
+    }
+    return counter.x;
+}
+
+@compute @workgroup_size(64)
+fn main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+) {
+    let word = id.x + id.y * groups.x * 64u;
+    if (word >= params.words) {
+        return;
+    }
+
+    var bits = 0u;
+    for (var bit = 0u; bit < 32u; bit++) {
+        let lo = (word << 5u) | bit;
+        let hi = word >> 27u;
+        if (hi > params.events.y || (hi == params.events.y && lo >= params.events.x)) {
+            break;
+        }
+        let x = philox(vec4<u32>(lo, hi, 0u, 0u), params.key);
+        if (params.always != 0u || x < params.threshold) {
+            bits |= 1u << bit;
+        }
+    }
+    mask[word] = bits;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_conversion() {
+        assert_eq!(threshold(0.0), 0);
+        assert_eq!(threshold(1.0), 1 << 32);
+        assert_eq!(threshold(0.5), 1 << 31);
+        assert_eq!(threshold(0.25 + 0.6 / 4_294_967_296.0), (1 << 30) + 1);
+
+        // Tiny probabilities are rounded up, not down to zero.
+        assert_eq!(threshold(1.0 / 4_294_967_296.0), 1);
+        assert_eq!(threshold(0.4 / 4_294_967_296.0), 1);
+        assert_eq!(threshold(f64::MIN_POSITIVE), 1);
+    }
+
+    #[test]
+    fn gpu_matches_cpu() {
+        let events = 100_000;
+        let mask = mask_on_cpu(42, 0.3, events);
+        assert_eq!(mask.len(), 3125);
+        let sampled = f64::from(mask.iter().map(|word| word.count_ones()).sum::<u32>());
+        let expected = 0.3 * events as f64;
+        assert!((sampled - expected).abs() <= 5.0 * (expected * 0.7).sqrt());
+
+        assert_eq!(mask_on_cpu(42, 1.0, 40), [u32::MAX, 0xff]);
+        assert_eq!(mask_on_cpu(42, 0.0, 40), [0, 0]);
+
+        // Not every machine that runs the tests has a GPU.
+        let Ok(generator) = GpuMaskGenerator::new() else {
+            return;
+        };
+        for (probability, events) in [(0.3, events), (1.0, 40), (0.5, 0)] {
+            assert_eq!(
+                generator.generate(42, probability, events).unwrap(),
+                mask_on_cpu(42, probability, events)
+            );
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::borrow::Cow;
+use std::fmt;
+
+/// A sampler that hands out [`SampleGuard`]s for sampled events, and records
+/// each guard's sample when it is dropped.
+///
+/// This makes "do the expensive work only when sampled" read naturally: the
+/// work happens inside `if let Some(guard) = sampler.sample(&mut rng)`, attaches
+/// its results to the guard as metadata, and the sample is recorded, along
+/// with its weight, when the guard goes out of scope.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{GuardedSampler, SampleRecord};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut records = Vec::new();
+/// let recorder = |record: SampleRecord| records.push(record);
+/// let mut sampler = GuardedSampler::new(1.0, recorder, &mut rng);
+///
+/// if let Some(mut guard) = sampler.sample(&mut rng) {
+///     // Only capture a backtrace for sampled events.
+///     guard.attach("backtrace", std::backtrace::Backtrace::capture());
+/// }
+///
+/// assert_eq!(records.len(), 1);
+/// assert_eq!(records[0].metadata[0].0, "backtrace");
+/// ```
+pub struct GuardedSampler<F> {
+    bernoulli: FastBernoulli,
+    recorder: F,
+}
+
+/// A sampled event's inclusion weight and metadata, passed to a
+/// [`GuardedSampler`]'s recorder when the event's [`SampleGuard`] is dropped.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SampleRecord {
+    /// The number of events this sample stands in for: the reciprocal of the
+    /// probability with which it was sampled.
+    pub weight: f64,
+
+    /// The metadata attached to the guard, in the order it was attached.
+    pub metadata: Vec<(Cow<'static, str>, String)>,
+}
+
+/// A guard for a sampled event, returned by [`GuardedSampler::sample`].
+///
+/// When dropped, the guard passes its [`SampleRecord`] to the sampler's
+/// recorder, unless it was [`cancel`][SampleGuard::cancel]led.
+pub struct SampleGuard<'a, F>
+where
+    F: FnMut(SampleRecord),
+{
+    record: Option<SampleRecord>,
+    recorder: &'a mut F,
+}
+
+impl<F> fmt::Debug for GuardedSampler<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuardedSampler")
+            .field("bernoulli", &self.bernoulli)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> fmt::Debug for SampleGuard<'_, F>
+where
+    F: FnMut(SampleRecord),
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SampleGuard")
+            .field("record", &self.record)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> GuardedSampler<F>
+where
+    F: FnMut(SampleRecord),
+{
+    /// Construct a new `GuardedSampler` that samples events with the given
+    /// probability, and passes each sample's record to `recorder`.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, recorder: F, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        GuardedSampler {
+            bernoulli: FastBernoulli::new(probability, rng),
+            recorder,
+        }
+    }
+
+    /// Perform a trial for an event.
+    ///
+    /// Returns a guard for the event if it should be sampled, or `None` if it
+    /// should not.
+    pub fn sample<R>(&mut self, rng: &mut R) -> Option<SampleGuard<'_, F>>
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.bernoulli.trial(rng) {
+            return None;
+        }
+        Some(SampleGuard {
+            record: Some(SampleRecord {
+                weight: 1.0 / self.bernoulli.effective_probability(),
+                metadata: Vec::new(),
+            }),
+            recorder: &mut self.recorder,
+        })
+    }
+
+    /// Get the probability with which events are sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+}
+
+impl<F> SampleGuard<'_, F>
+where
+    F: FnMut(SampleRecord),
+{
+    /// Get the sampled event's weight.
+    #[inline]
+    pub fn weight(&self) -> f64 {
+        self.record().weight
+    }
+
+    /// Attach a piece of metadata to the sample.
+    pub fn attach(&mut self, key: impl Into<Cow<'static, str>>, value: impl fmt::Display) {
+        if let Some(record) = &mut self.record {
+            record.metadata.push((key.into(), value.to_string()));
+        }
+    }
+
+    /// Get the metadata attached so far.
+    #[inline]
+    pub fn metadata(&self) -> &[(Cow<'static, str>, String)] {
+        &self.record().metadata
+    }
+
+    /// Drop the guard without recording its sample.
+    #[inline]
+    pub fn cancel(mut self) {
+        self.record = None;
+    }
+
+    #[inline]
+    fn record(&self) -> &SampleRecord {
+        // Only `cancel` and `drop` take the record, and both consume the guard.
+        self.record
+            .as_ref()
+            .expect("guard's record was already taken")
+    }
+}
+
+impl<F> Drop for SampleGuard<'_, F>
+where
+    F: FnMut(SampleRecord),
+{
+    fn drop(&mut self) {
+        if let Some(record) = self.record.take() {
+            (self.recorder)(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_on_drop_unless_cancelled() {
+        let mut rng = rand::thread_rng();
+        let mut records = Vec::new();
+        let mut sampler = GuardedSampler::new(1.0, |record| records.push(record), &mut rng);
+
+        let mut guard = sampler.sample(&mut rng).unwrap();
+        guard.attach("size", 42);
+        assert_eq!(guard.weight(), 1.0);
+        drop(guard);
+
+        sampler.sample(&mut rng).unwrap().cancel();
+
+        assert_eq!(
+            records,
+            vec![SampleRecord {
+                weight: 1.0,
+                metadata: vec![("size".into(), "42".to_string())],
+            }]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Integration with the [`embedded_hal`] crate.
+//!
+//! Requires the `embedded-hal` feature.
+//!
+//! Microcontrollers usually have a hardware random number generator, exposed
+//! through `embedded-hal`'s [`Read`] trait. [`HalRng`] adapts one into a
+//! [`RngCore`], so that firmware can drive samplers from it directly.
+//!
+//! This crate still requires `std`, so this is for embedded targets with `std`
+//! support, such as ESP-IDF.
+
+use embedded_hal::blocking::rng::Read;
+use rand::RngCore;
+use std::error::Error;
+use std::fmt;
+
+/// An adapter from an `embedded-hal` hardware RNG to a [`RngCore`].
+///
+/// The infallible `RngCore` methods panic if the hardware fails; use
+/// [`FastBernoulli::try_trial`][crate::FastBernoulli::try_trial] to handle
+/// failures instead.
+///
+/// # Example
+///
+/// ```
+/// use embedded_hal::blocking::rng::Read;
+/// use fast_bernoulli::hal::HalRng;
+/// use fast_bernoulli::FastBernoulli;
+///
+/// # struct Trng;
+/// # impl Read for Trng {
+/// #     type Error = ();
+/// #     fn read(&mut self, buffer: &mut [u8]) -> Result<(), ()> {
+/// #         buffer.fill(0x5a);
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let trng = Trng;
+/// let mut rng = HalRng::new(trng);
+/// let mut bernoulli = FastBernoulli::new(0.01, &mut rng);
+///
+/// if bernoulli.try_trial(&mut rng).unwrap_or(false) {
+///     // Log this sensor reading...
+/// }
+/// ```
+#[derive(Debug)]
+pub struct HalRng<T> {
+    rng: T,
+}
+
+impl<T> HalRng<T>
+where
+    T: Read,
+{
+    /// Construct a new `HalRng` drawing from the given hardware RNG.
+    #[inline]
+    pub fn new(rng: T) -> Self {
+        HalRng { rng }
+    }
+
+    /// Get the wrapped hardware RNG.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.rng
+    }
+}
+
+/// An error reading from a hardware RNG, carrying its `Debug` description.
+#[derive(Debug)]
+struct HalRngError(String);
+
+impl fmt::Display for HalRngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hardware RNG failed: {}", self.0)
+    }
+}
+
+impl Error for HalRngError {}
+
+impl<T> RngCore for HalRng<T>
+where
+    T: Read,
+    T::Error: fmt::Debug,
+{
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(e) = self.try_fill_bytes(dest) {
+            panic!("{}", e);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng
+            .read(dest)
+            .map_err(|e| rand::Error::new(HalRngError(format!("{:?}", e))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastBernoulli;
+
+    struct BrokenTrng;
+
+    impl Read for BrokenTrng {
+        type Error = &'static str;
+
+        fn read(&mut self, _buffer: &mut [u8]) -> Result<(), Self::Error> {
+            Err("seed error")
+        }
+    }
+
+    #[test]
+    fn hardware_failures_are_errors() {
+        let mut rng = HalRng::new(BrokenTrng);
+        let mut bernoulli = FastBernoulli::new(1.0, &mut rng);
+        assert_eq!(bernoulli.try_trial(&mut rng).ok(), Some(true));
+
+        let mut bits = [0; 8];
+        let error = rng.try_fill_bytes(&mut bits).unwrap_err();
+        assert_eq!(error.to_string(), "hardware RNG failed: \"seed error\"");
+    }
+}
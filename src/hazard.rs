@@ -0,0 +1,130 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// A sampler that fires at a constant rate per unit of observed time, rather
+/// than per event.
+///
+/// A continuously running process, such as a worker thread or a long-running
+/// request, can be sampled per second of runtime: the caller reports elapsed
+/// durations with [`observe`][HazardSampler::observe], and each observation of
+/// length `dt` is sampled with probability `1 - e^(-λ dt)`, where `λ` is the
+/// rate per second. Over time, the sampler fires `λ` times per second on
+/// average, however the time is split into observations.
+///
+/// Like `FastBernoulli`'s skip counts, this draws the time until it next fires
+/// up front, so observations only touch the RNG when the sampler fires.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::HazardSampler;
+/// use std::time::Duration;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Take a stack sample about twice a minute of busy time.
+/// let mut sampler = HazardSampler::per_second(1.0 / 30.0, &mut rng);
+///
+/// let busy = Duration::from_millis(250);
+/// if sampler.observe(busy, &mut rng) {
+///     // Capture a stack sample...
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HazardSampler {
+    rate: f64,
+    // Seconds of observed time until the sampler next fires.
+    remaining: f64,
+}
+
+impl HazardSampler {
+    /// Construct a new `HazardSampler` that fires `rate` times per second of
+    /// observed time, on average.
+    ///
+    /// # Panics
+    ///
+    /// The rate must be non-negative and finite, and this method will panic if
+    /// that is not the case.
+    pub fn per_second<R>(rate: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            rate >= 0.0 && rate.is_finite(),
+            "`rate` must be non-negative and finite"
+        );
+        let mut sampler = HazardSampler {
+            rate,
+            remaining: 0.0,
+        };
+        sampler.reset_remaining(rng);
+        sampler
+    }
+
+    /// Observe `dt` of elapsed time, returning whether the sampler fired
+    /// during it.
+    #[inline]
+    pub fn observe<R>(&mut self, dt: Duration, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let dt = dt.as_secs_f64();
+        if dt < self.remaining {
+            self.remaining -= dt;
+            return false;
+        }
+        self.reset_remaining(rng);
+        true
+    }
+
+    /// Get the probability that an observation of length `dt` fires the
+    /// sampler: `1 - e^(-λ dt)`.
+    #[inline]
+    pub fn probability(&self, dt: Duration) -> f64 {
+        -(-self.rate * dt.as_secs_f64()).exp_m1()
+    }
+
+    /// Get the rate at which the sampler fires, per second of observed time.
+    #[inline]
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    fn reset_remaining<R>(&mut self, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        if self.rate == 0.0 {
+            self.remaining = f64::INFINITY;
+            return;
+        }
+        // Exponentially distributed, by inversion. `1 - x` is in `(0, 1]`, so
+        // the logarithm is finite.
+        let x: f64 = rng.gen();
+        self.remaining = -(1.0 - x).ln() / self.rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fires_at_the_configured_rate() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = HazardSampler::per_second(2.0, &mut rng);
+
+        // 5000 seconds, in 10ms observations.
+        let n = 500_000;
+        let dt = Duration::from_millis(10);
+        let fired = (0..n).filter(|_| sampler.observe(dt, &mut rng)).count() as f64;
+
+        let q = sampler.probability(dt);
+        let expected = q * f64::from(n);
+        assert!((sampler.probability(dt) - 0.0198013).abs() < 1e-6);
+        assert!((fired - expected).abs() <= 5.0 * (expected * (1.0 - q)).sqrt());
+
+        let mut never = HazardSampler::per_second(0.0, &mut rng);
+        assert!(!never.observe(Duration::MAX, &mut rng));
+    }
+}
@@ -0,0 +1,200 @@
+//! Integration with the [`hdrhistogram`] crate.
+//!
+//! Requires the `hdrhistogram` feature.
+//!
+//! HDR histograms store integer counts, but a sampled observation stands in
+//! for `1 / p` observations, which is usually fractional. The helpers here
+//! round weights stochastically, up or down with probability proportional to
+//! the fractional part, so that recorded counts are unbiased.
+
+use crate::estimate::stochastic_round;
+use crate::FastBernoulli;
+use hdrhistogram::errors::RecordError;
+use hdrhistogram::Histogram;
+use rand::Rng;
+
+/// Record `value` into `histogram` as a sample taken with the given
+/// probability, adding its inclusion weight rather than a count of one.
+///
+/// # Panics
+///
+/// The probability must be within the range `0.0 < probability <= 1.0` and
+/// this function will panic if that is not the case.
+pub fn record_weighted<R>(
+    histogram: &mut Histogram<u64>,
+    value: u64,
+    probability: f64,
+    rng: &mut R,
+) -> Result<(), RecordError>
+where
+    R: Rng + ?Sized,
+{
+    assert!(
+        0.0 < probability && probability <= 1.0,
+        "`probability` must be in the range `0.0 < probability <= 1.0`"
+    );
+    let count = stochastic_round(1.0 / probability, rng);
+    if count == 0 {
+        return Ok(());
+    }
+    histogram.record_n(value, count)
+}
+
+/// Add every count in `source`, scaled by `weight`, into `target`.
+///
+/// Use this to merge a histogram of raw, unscaled sampled observations into a
+/// histogram of corrected counts: pass `1 / p` as the weight, where `p` is the
+/// probability with which `source` was sampled. Histograms recorded at
+/// different sampling rates can be merged into one this way.
+///
+/// # Panics
+///
+/// The weight must be non-negative and finite, and this function will panic
+/// if that is not the case.
+pub fn add_scaled<R>(
+    target: &mut Histogram<u64>,
+    source: &Histogram<u64>,
+    weight: f64,
+    rng: &mut R,
+) -> Result<(), RecordError>
+where
+    R: Rng + ?Sized,
+{
+    assert!(
+        weight >= 0.0 && weight.is_finite(),
+        "`weight` must be non-negative and finite"
+    );
+    for v in source.iter_recorded() {
+        let count = stochastic_round(v.count_at_value() as f64 * weight, rng);
+        if count > 0 {
+            target.record_n(v.value_iterated_to(), count)?;
+        }
+    }
+    Ok(())
+}
+
+/// An HDR histogram that records only a Bernoulli-sampled subset of
+/// observations, with counts corrected to estimate every observation offered.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::hdr::SampledHdrHistogram;
+/// use hdrhistogram::Histogram;
+///
+/// let mut rng = rand::thread_rng();
+/// let histogram = Histogram::<u64>::new(3).unwrap();
+/// let mut latencies = SampledHdrHistogram::new(histogram, 0.01, &mut rng);
+///
+/// for micros in 0..100_000 {
+///     latencies.record(micros % 1000, &mut rng).unwrap();
+/// }
+///
+/// // Roughly 100,000 observations, from ~1,000 recorded samples.
+/// let estimated = latencies.histogram().len();
+/// # let _ = estimated;
+/// ```
+#[derive(Debug, Clone)]
+pub struct SampledHdrHistogram {
+    bernoulli: FastBernoulli,
+    histogram: Histogram<u64>,
+}
+
+impl SampledHdrHistogram {
+    /// Wrap `histogram`, recording observations into it with the given
+    /// probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(histogram: Histogram<u64>, probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        SampledHdrHistogram {
+            bernoulli: FastBernoulli::new(probability, rng),
+            histogram,
+        }
+    }
+
+    /// Offer an observation, recording it with its inclusion weight if a
+    /// Bernoulli trial says to.
+    ///
+    /// Returns whether the observation was recorded.
+    pub fn record<R>(&mut self, value: u64, rng: &mut R) -> Result<bool, RecordError>
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.bernoulli.trial(rng) {
+            return Ok(false);
+        }
+        record_weighted(
+            &mut self.histogram,
+            value,
+            self.bernoulli.effective_probability(),
+            rng,
+        )?;
+        Ok(true)
+    }
+
+    /// Get the probability with which observations are recorded.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Get the histogram of corrected counts.
+    #[inline]
+    pub fn histogram(&self) -> &Histogram<u64> {
+        &self.histogram
+    }
+
+    /// Unwrap this `SampledHdrHistogram`, returning the histogram of corrected
+    /// counts.
+    #[inline]
+    pub fn into_inner(self) -> Histogram<u64> {
+        self.histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_inclusion_weights() {
+        let mut rng = rand::thread_rng();
+        let mut histogram = Histogram::<u64>::new(3).unwrap();
+        record_weighted(&mut histogram, 10, 1.0, &mut rng).unwrap();
+        record_weighted(&mut histogram, 20, 0.25, &mut rng).unwrap();
+        assert_eq!(histogram.count_at(10), 1);
+        assert_eq!(histogram.count_at(20), 4);
+
+        let mut merged = Histogram::<u64>::new(3).unwrap();
+        add_scaled(&mut merged, &histogram, 2.0, &mut rng).unwrap();
+        add_scaled(&mut merged, &histogram, 0.0, &mut rng).unwrap();
+        assert_eq!(merged.count_at(10), 2);
+        assert_eq!(merged.count_at(20), 8);
+        assert_eq!(merged.len(), 10);
+    }
+
+    #[test]
+    fn sampled_histogram_estimates_every_observation() {
+        let mut rng = rand::thread_rng();
+        let histogram = Histogram::<u64>::new(3).unwrap();
+        let p = 0.05;
+        let mut sampled = SampledHdrHistogram::new(histogram, p, &mut rng);
+
+        let n = 100_000;
+        let recorded = (0..n)
+            .filter(|&value| sampled.record(value % 100, &mut rng).unwrap())
+            .count() as f64;
+        let estimated = sampled.histogram().len() as f64;
+
+        // Each recorded observation is counted 20 times.
+        assert_eq!(estimated, 20.0 * recorded);
+        let tolerance = 5.0 * (n as f64 * (1.0 - p) / p).sqrt();
+        assert!((estimated - n as f64).abs() <= tolerance);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// A fixed-bucket histogram that records only a sampled subset of
+/// observations, and scales counts to estimate the full distribution.
+///
+/// Each sampled observation adds its inclusion weight, `1.0 / probability`,
+/// using the [effective][FastBernoulli::effective_probability] probability,
+/// to its bucket, so bucket counts, totals, and percentiles estimate those of
+/// every observation offered, not just the sampled ones. This centralizes the
+/// reweighting that is easy to get subtly wrong by hand.
+///
+/// Buckets are defined by their inclusive upper bounds. Observations greater
+/// than the last bound go into a final overflow bucket.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::SampledHistogram;
+///
+/// let mut rng = rand::thread_rng();
+/// let bounds = [1.0, 5.0, 10.0, 50.0, 100.0, 500.0];
+/// let mut latencies = SampledHistogram::new(&bounds, 0.1, &mut rng);
+///
+/// for i in 0..10_000 {
+///     let latency_ms = (i % 100) as f64;
+///     latencies.record(latency_ms, &mut rng);
+/// }
+///
+/// // Roughly 10,000 observations, even though only ~1,000 were recorded.
+/// let total = latencies.total();
+/// let p99 = latencies.percentile(0.99);
+/// # let _ = (total, p99);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SampledHistogram {
+    bernoulli: FastBernoulli,
+    bounds: Vec<f64>,
+    // One more count than bounds, for the overflow bucket.
+    counts: Vec<f64>,
+    samples: u64,
+    min: f64,
+    max: f64,
+}
+
+impl SampledHistogram {
+    /// Construct a new, empty `SampledHistogram` with buckets bounded by
+    /// `bounds`, that records observations with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The bounds must be strictly increasing and not NaN, and the probability
+    /// must be within the range `0.0 <= probability <= 1.0`. This method will
+    /// panic if that is not the case.
+    pub fn new<R>(bounds: &[f64], probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            bounds.windows(2).all(|w| w[0] < w[1]) && bounds.iter().all(|b| !b.is_nan()),
+            "`bounds` must be strictly increasing"
+        );
+        SampledHistogram {
+            bernoulli: FastBernoulli::new(probability, rng),
+            bounds: bounds.to_vec(),
+            counts: vec![0.0; bounds.len() + 1],
+            samples: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Offer an observation, recording it if a Bernoulli trial says to.
+    ///
+    /// Returns whether the observation was recorded.
+    pub fn record<R>(&mut self, value: f64, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.bernoulli.trial(rng) {
+            return false;
+        }
+
+        let bucket = self.bounds.partition_point(|&b| b < value);
+        self.counts[bucket] += 1.0 / self.bernoulli.effective_probability();
+        self.samples += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        true
+    }
+
+    /// Get the estimated number of observations in each bucket, along with each
+    /// bucket's upper bound.
+    ///
+    /// The overflow bucket's upper bound is infinity.
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.bounds
+            .iter()
+            .copied()
+            .chain(Some(f64::INFINITY))
+            .zip(self.counts.iter().copied())
+    }
+
+    /// Get the estimated total number of observations offered.
+    pub fn total(&self) -> f64 {
+        self.counts.iter().sum()
+    }
+
+    /// Get the number of observations actually recorded.
+    #[inline]
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Estimate the `q`th quantile of all observations offered.
+    ///
+    /// The estimate interpolates linearly within the bucket containing the
+    /// quantile. The lowest and overflow buckets are bounded by the smallest
+    /// and largest recorded observations. Returns `None` if nothing has been
+    /// recorded.
+    ///
+    /// # Panics
+    ///
+    /// The quantile must be within the range `0.0 <= q <= 1.0` and this method
+    /// will panic if that is not the case.
+    pub fn percentile(&self, q: f64) -> Option<f64> {
+        assert!(
+            (0.0..=1.0).contains(&q),
+            "`q` must be in the range `0.0 <= q <= 1.0`"
+        );
+        if self.samples == 0 {
+            return None;
+        }
+
+        let target = q * self.total();
+        let mut cumulative = 0.0;
+        for (i, &count) in self.counts.iter().enumerate() {
+            if count == 0.0 {
+                continue;
+            }
+            if cumulative + count >= target {
+                let lower = if i == 0 {
+                    self.min
+                } else {
+                    self.bounds[i - 1].max(self.min)
+                };
+                let upper = self
+                    .bounds
+                    .get(i)
+                    .copied()
+                    .unwrap_or(self.max)
+                    .min(self.max);
+                let fraction = (target - cumulative) / count;
+                return Some(lower + (upper - lower) * fraction);
+            }
+            cumulative += count;
+        }
+        Some(self.max)
+    }
+
+    /// Reset every bucket to zero.
+    pub fn clear(&mut self) {
+        self.counts.iter_mut().for_each(|c| *c = 0.0);
+        self.samples = 0;
+        self.min = f64::INFINITY;
+        self.max = f64::NEG_INFINITY;
+    }
+
+    /// Get the probability with which observations are recorded.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_are_scaled() {
+        let mut rng = rand::thread_rng();
+        let mut histogram = SampledHistogram::new(&[10.0, 20.0], 0.1, &mut rng);
+
+        for i in 0..30_000 {
+            histogram.record((i % 30) as f64 + 0.5, &mut rng);
+        }
+
+        for (bound, count) in histogram.buckets() {
+            assert!(
+                (count - 10_000.0).abs() < 1_500.0,
+                "bucket <= {} has ~{} observations, expected ~10000",
+                bound,
+                count,
+            );
+        }
+        let median = histogram.percentile(0.5).unwrap();
+        assert!((10.0..=20.0).contains(&median), "median was {}", median);
+    }
+
+    #[test]
+    fn rare_observations_are_weighted_by_the_effective_probability() {
+        let mut rng = rand::thread_rng();
+        let mut histogram = SampledHistogram::new(&[10.0], 1e-12, &mut rng);
+
+        // Start from a skip count of zero, so that the next observation is
+        // recorded.
+        histogram.bernoulli = FastBernoulli::from_state(crate::FastBernoulliState::new(1e-12, 0));
+        assert!(histogram.record(5.0, &mut rng));
+        let effective = histogram.bernoulli.effective_probability();
+        assert!(1.0 / effective < 1e10);
+        assert_eq!(histogram.total(), 1.0 / effective);
+    }
+}
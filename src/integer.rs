@@ -0,0 +1,321 @@
+// Integer-only skip counts, for targets without a floating-point unit.
+//
+// The skip count formula is `floor(log(X) / log(1-P))`, and the base of the
+// logarithms doesn't matter, so we use base two, which is cheap to compute
+// with integers: the integer part of `log2(v)` is the index of `v`'s highest
+// set bit, and the fractional part can be produced one bit at a time by
+// repeatedly squaring the normalized mantissa (if the square is at least two,
+// the next bit is one, and we halve it).
+//
+// Writing `D = -log2(1-P)`, the skip count is `floor(-log2(X) / D)`. At
+// construction we precompute `1/D` as a fixed-point number, so that each new
+// skip count costs one fixed-point `log2` and one multiplication.
+//
+// All fixed-point numbers here have 32 fractional bits ("Q32"), except inside
+// `inverse_from_ratio`, which needs 64 to keep tiny probabilities accurate.
+
+use rand::Rng;
+
+/// Bernoulli sampling that generates skip counts without any floating-point
+/// operations.
+///
+/// This is a drop-in alternative to [`FastBernoulli`][crate::FastBernoulli]
+/// for targets where floating-point math is emulated in software, such as
+/// soft-float microcontrollers, where a single `ln` can take hundreds of
+/// cycles. Skip counts follow the same geometric distribution, computed with
+/// fixed-point base-two logarithms instead.
+///
+/// Construct it with [`from_ratio`][IntegerBernoulli::from_ratio] to avoid
+/// floating point entirely, or with [`new`][IntegerBernoulli::new], which uses
+/// floating point once at construction time but never afterwards.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::IntegerBernoulli;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Sample one in a thousand events, without ever touching the FPU.
+/// let mut bernoulli = IntegerBernoulli::from_ratio(1, 1000, &mut rng);
+///
+/// if bernoulli.trial(&mut rng) {
+///     // Record the sample...
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IntegerBernoulli {
+    // `1 / -log2(1 - P)` in Q32, or one of the sentinels below.
+    inverse: u128,
+    skip_count: u32,
+}
+
+// Sentinel `inverse` values for the edge cases.
+const NEVER: u128 = 0;
+const ALWAYS: u128 = u128::MAX;
+
+impl IntegerBernoulli {
+    /// Construct a new `IntegerBernoulli` instance that samples events with
+    /// the given probability.
+    ///
+    /// This performs a few floating-point operations to convert the
+    /// probability to fixed point; use
+    /// [`from_ratio`][IntegerBernoulli::from_ratio] to avoid them.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+
+        let inverse = if probability == 0.0 {
+            NEVER
+        } else if probability == 1.0 {
+            ALWAYS
+        } else {
+            let inverse = -std::f64::consts::LN_2 / (-probability).ln_1p();
+            ((inverse * 4_294_967_296.0) as u128).clamp(1, ALWAYS - 1)
+        };
+        Self::with_inverse(inverse, rng)
+    }
+
+    /// Construct a new `IntegerBernoulli` instance that samples events with
+    /// probability `numerator / denominator`, using only integer arithmetic.
+    ///
+    /// # Panics
+    ///
+    /// The denominator must be non-zero and not less than the numerator, and
+    /// this method will panic if that is not the case.
+    pub fn from_ratio<R>(numerator: u64, denominator: u64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(denominator != 0, "`denominator` must be non-zero");
+        assert!(
+            numerator <= denominator,
+            "`numerator` must not be greater than `denominator`"
+        );
+
+        let inverse = if numerator == 0 {
+            NEVER
+        } else if numerator == denominator {
+            ALWAYS
+        } else {
+            inverse_from_ratio(numerator, denominator)
+        };
+        Self::with_inverse(inverse, rng)
+    }
+
+    fn with_inverse<R>(inverse: u128, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let mut bernoulli = IntegerBernoulli {
+            inverse,
+            skip_count: 0,
+        };
+        bernoulli.reset_skip_count(rng);
+        bernoulli
+    }
+
+    fn reset_skip_count<R>(&mut self, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        self.skip_count = match self.inverse {
+            NEVER => u32::MAX,
+            ALWAYS => 0,
+            inverse => {
+                let skip_count = skip_count_from_bits(inverse, rng.next_u64());
+                u32::try_from(skip_count).unwrap_or(u32::MAX)
+            }
+        };
+    }
+
+    /// Perform a Bernoulli trial: returns `true` with the configured
+    /// probability.
+    ///
+    /// See [`FastBernoulli::trial`][crate::FastBernoulli::trial].
+    #[inline]
+    pub fn trial<R>(&mut self, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        if self.skip_count > 0 {
+            self.skip_count -= 1;
+            return false;
+        }
+
+        self.reset_skip_count(rng);
+        self.inverse != NEVER
+    }
+
+    /// Perform `n` Bernoulli trials at once.
+    ///
+    /// See [`FastBernoulli::multi_trial`][crate::FastBernoulli::multi_trial].
+    #[inline]
+    pub fn multi_trial<R>(&mut self, n: u32, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        if n <= self.skip_count {
+            self.skip_count -= n;
+            return false;
+        }
+
+        self.reset_skip_count(rng);
+        self.inverse != NEVER
+    }
+
+    /// How many events will be skipped until the next event is sampled?
+    ///
+    /// See [`FastBernoulli::skip_count`][crate::FastBernoulli::skip_count].
+    #[inline]
+    pub fn skip_count(&self) -> u32 {
+        self.skip_count
+    }
+}
+
+/// Compute a skip count for a probability of one in `n`, for `n >= 2`, from 64
+/// uniformly random `bits`, saturating at `u64::MAX`.
+pub(crate) fn one_in_skip_count(n: u64, bits: u64) -> u64 {
+    debug_assert!(n >= 2);
+    skip_count_from_bits(inverse_from_ratio(1, n), bits)
+}
+
+/// Compute a skip count from `inverse` and 64 uniformly random `bits`,
+/// saturating at `u64::MAX`.
+fn skip_count_from_bits(inverse: u128, bits: u64) -> u64 {
+    // `X = v / 2^63` is uniform over `(0, 1]`.
+    let v = (bits >> 1) + 1;
+    let neg_log_x = (63 << 32) - log2_q32(v);
+    // Q32 times Q32 is Q64; the integer part is the skip count.
+    let skip_count = u128::from(neg_log_x).saturating_mul(inverse) >> 64;
+    u64::try_from(skip_count).unwrap_or(u64::MAX)
+}
+
+/// Compute `log2(v)` in Q32, for `v >= 1`.
+fn log2_q32(v: u64) -> u64 {
+    debug_assert!(v >= 1);
+    let int = 63 - v.leading_zeros();
+
+    // Normalize the mantissa into `[1, 2)` as Q31.
+    let mut y = if int >= 31 {
+        v >> (int - 31)
+    } else {
+        v << (31 - int)
+    };
+
+    let mut frac = 0;
+    for bit in (0..32).rev() {
+        // Squaring the mantissa doubles its logarithm, bringing the next
+        // fractional bit into the integer position.
+        y = (y * y) >> 31;
+        if y >= 1 << 32 {
+            y >>= 1;
+            frac |= 1 << bit;
+        }
+    }
+
+    (u64::from(int) << 32) | frac
+}
+
+/// Compute `1 / -log2(1 - n/d)` in Q32, for `0 < n < d`.
+fn inverse_from_ratio(n: u64, d: u64) -> u128 {
+    if n <= d / 2 {
+        // For small probabilities, subtracting two logarithms would cancel
+        // catastrophically, so sum the series `-ln(1 - p) = p + p^2/2 + ...`
+        // in Q64 instead. It converges quickly for `p <= 1/2`.
+        let p = (u128::from(n) << 64) / u128::from(d);
+        let mut power = p;
+        let mut ln = 0;
+        let mut k = 1;
+        while power != 0 {
+            ln += power / k;
+            power = (power * p) >> 64;
+            k += 1;
+        }
+
+        // `log2(x) = ln(x) * log2(e)`, with `log2(e)` in Q63.
+        const LOG2_E_Q63: u128 = 0xb8aa_3b29_5c17_f0bb;
+        let neg_log2 = (ln * LOG2_E_Q63) >> 63;
+        ((1 << 96) / neg_log2).max(1)
+    } else {
+        let neg_log2 = u128::from(log2_q32(d) - log2_q32(d - n));
+        ((1 << 64) / neg_log2).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn log2_is_accurate() {
+        for v in [1_u64, 2, 3, 10, 1000, 123_456_789, u64::MAX] {
+            let expected = (v as f64).log2();
+            let actual = log2_q32(v) as f64 / 4_294_967_296.0;
+            assert!((expected - actual).abs() < 1e-8, "log2({})", v);
+        }
+    }
+
+    #[test]
+    fn inverse_matches_floating_point() {
+        for (n, d) in [(1, 1_000_000_000), (1, 3), (1, 2), (2, 3), (999, 1000)] {
+            let p = n as f64 / d as f64;
+            let expected = -std::f64::consts::LN_2 / (-p).ln_1p();
+            let actual = inverse_from_ratio(n, d) as f64 / 4_294_967_296.0;
+            assert!(
+                ((expected - actual) / expected).abs() < 1e-6,
+                "{}/{}: expected {}, found {}",
+                n,
+                d,
+                expected,
+                actual,
+            );
+        }
+    }
+
+    #[test]
+    fn expected_number_of_samples() {
+        // Seeded, so that this can't fail by chance.
+        let mut rng = StdRng::seed_from_u64(420);
+        let mut bernoulli = IntegerBernoulli::from_ratio(1, 100, &mut rng);
+
+        let events = 100_000;
+        let sampled = (0..events).filter(|_| bernoulli.trial(&mut rng)).count();
+
+        let expected = events as f64 / 100.0;
+        assert!(
+            (sampled as f64 - expected).abs() < expected * 0.05,
+            "expected ~{} samples, found {}",
+            expected,
+            sampled,
+        );
+    }
+
+    #[test]
+    fn multi_trial_is_equivalent_to_n_trials() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = IntegerBernoulli::from_ratio(1, 2, &mut rng);
+        while bernoulli.skip_count() == 0 {
+            bernoulli = IntegerBernoulli::from_ratio(1, 2, &mut rng);
+        }
+
+        // Exactly `skip_count` trials all return `false`, so `multi_trial` of
+        // that many must too, and the very next trial must return `true`.
+        let n = bernoulli.skip_count();
+        assert!(!bernoulli.multi_trial(n, &mut rng));
+        assert!(bernoulli.trial(&mut rng));
+    }
+}
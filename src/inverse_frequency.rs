@@ -0,0 +1,168 @@
+use crate::{ClampStats, CountMinSketch, ProbabilityBounds};
+use rand::Rng;
+use std::hash::Hash;
+
+/// Per-key adaptive sampling with probability inversely proportional to each
+/// key's frequency.
+///
+/// Every event's key is counted in a [`CountMinSketch`], and the event is
+/// sampled with probability `min(1.0, target / count)`, where `count` is the
+/// key's estimated number of occurrences so far (including this one). Rare
+/// keys are therefore sampled at or near 100%, while hot keys are thinned
+/// heavily: each key contributes on the order of `target * ln(count)` samples
+/// rather than `count * p`.
+///
+/// Because the probability differs from event to event, each sampled event
+/// comes with its inclusion weight, `1.0 / probability`. Summing the weights
+/// of sampled events gives an unbiased estimate of the total number of
+/// events.
+///
+/// Since the probability changes on every event, this sampler cannot use skip
+/// counts and generates a random number for each event whose probability is
+/// below one.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{CountMinSketch, InverseFrequencySampler};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sampler = InverseFrequencySampler::new(5.0, CountMinSketch::new(4096, 4));
+///
+/// let mut estimated_total = 0.0;
+/// for i in 0..10_000 {
+///     let event_type = if i % 100 == 0 { "rare" } else { "hot" };
+///     if let Some(weight) = sampler.trial(&event_type, &mut rng) {
+///         // Record the sample together with its weight...
+///         estimated_total += weight;
+///     }
+/// }
+/// # let _ = estimated_total;
+/// ```
+#[derive(Debug, Clone)]
+pub struct InverseFrequencySampler {
+    target: f64,
+    sketch: CountMinSketch,
+    bounds: ProbabilityBounds,
+    clamps: ClampStats,
+}
+
+impl InverseFrequencySampler {
+    /// Construct a new `InverseFrequencySampler` that counts keys with the given
+    /// sketch.
+    ///
+    /// Each key's first `target` occurrences are always sampled; afterwards,
+    /// its probability decays as `target / count`.
+    ///
+    /// # Panics
+    ///
+    /// The target must be positive and finite, and this method will panic if
+    /// that is not the case.
+    pub fn new(target: f64, sketch: CountMinSketch) -> Self {
+        assert!(
+            target > 0.0 && target.is_finite(),
+            "`target` must be positive and finite"
+        );
+        InverseFrequencySampler {
+            target,
+            sketch,
+            bounds: ProbabilityBounds::UNBOUNDED,
+            clamps: ClampStats::default(),
+        }
+    }
+
+    /// Keep the probabilities chosen for each event within `bounds`.
+    ///
+    /// Lowering the ceiling below `1.0` means that even a key's first
+    /// occurrences may go unsampled.
+    pub fn with_bounds(mut self, bounds: ProbabilityBounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    /// Count an event for `key` and perform a trial for it.
+    ///
+    /// Returns the event's inclusion weight if it should be sampled, or `None`
+    /// if it should not.
+    pub fn trial<K, R>(&mut self, key: &K, rng: &mut R) -> Option<f64>
+    where
+        K: Hash + ?Sized,
+        R: Rng + ?Sized,
+    {
+        let count = self.sketch.increment(key);
+        let probability = self.probability_for_count(count);
+        let probability = self.bounds.apply(probability, &mut self.clamps);
+        if probability >= 1.0 || (probability > 0.0 && rng.gen::<f64>() < probability) {
+            Some(1.0 / probability)
+        } else {
+            None
+        }
+    }
+
+    /// Get the probability with which `key`'s next event would be sampled.
+    pub fn probability<K>(&self, key: &K) -> f64
+    where
+        K: Hash + ?Sized,
+    {
+        let count = self.sketch.estimate(key).saturating_add(1);
+        self.bounds.clamp(self.probability_for_count(count))
+    }
+
+    /// Age out old observations, so that probabilities track recent rather
+    /// than all-time frequency.
+    ///
+    /// Call this periodically, e.g. once per reporting interval.
+    pub fn decay(&mut self) {
+        self.sketch.halve();
+    }
+
+    /// Get the underlying frequency sketch.
+    #[inline]
+    pub fn sketch(&self) -> &CountMinSketch {
+        &self.sketch
+    }
+
+    /// Get the counts of chosen probabilities that were clamped by the bounds.
+    #[inline]
+    pub fn clamp_stats(&self) -> ClampStats {
+        self.clamps
+    }
+
+    #[inline]
+    fn probability_for_count(&self, count: u32) -> f64 {
+        (self.target / f64::from(count.max(1))).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_estimate_totals() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = InverseFrequencySampler::new(100.0, CountMinSketch::new(1024, 4));
+
+        let events = 100_000;
+        let mut rare_samples = 0;
+        let mut estimated_total = 0.0;
+        for i in 0..events {
+            let key = if i % 1000 == 0 { "rare" } else { "hot" };
+            if let Some(weight) = sampler.trial(key, &mut rng) {
+                estimated_total += weight;
+                if key == "rare" {
+                    rare_samples += 1;
+                }
+            }
+        }
+
+        // The rare key never exceeds its target, so it is always sampled.
+        assert_eq!(rare_samples, events / 1000);
+        assert!(
+            (estimated_total - events as f64).abs() < events as f64 * 0.25,
+            "estimated {} events, expected ~{}",
+            estimated_total,
+            events,
+        );
+    }
+}
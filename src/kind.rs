@@ -0,0 +1,176 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// An event type whose kinds, such as an enum's variants, are each sampled
+/// with their own probability.
+///
+/// With the `macros` feature enabled, `SampledKind` can be derived for an
+/// enum, declaring each variant's probability next to its definition with a
+/// `#[sample(p = ...)]` attribute. An attribute on the enum itself gives the
+/// probability for variants without one of their own. The probabilities must
+/// be float literals.
+///
+/// ```
+/// # #[cfg(feature = "macros")] {
+/// use fast_bernoulli::SampledKind;
+///
+/// #[derive(SampledKind)]
+/// #[sample(p = 0.01)]
+/// enum Event {
+///     Request { path: String },
+///     #[sample(p = 1.0)]
+///     Error(String),
+///     #[sample(p = 0.0001)]
+///     Heartbeat,
+/// }
+/// # }
+/// ```
+///
+/// A [`KindSampler`] then samples events with their kinds' probabilities.
+///
+/// # Example
+///
+/// Implementing `SampledKind` by hand:
+///
+/// ```
+/// use fast_bernoulli::{KindSampler, SampledKind};
+///
+/// enum Event {
+///     Request,
+///     Error,
+/// }
+///
+/// impl SampledKind for Event {
+///     const PROBABILITIES: &'static [f64] = &[0.01, 1.0];
+///
+///     fn kind(&self) -> usize {
+///         match self {
+///             Event::Request => 0,
+///             Event::Error => 1,
+///         }
+///     }
+/// }
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sampler = KindSampler::<Event>::new(&mut rng);
+///
+/// // Every error is sampled.
+/// assert!(sampler.trial(&Event::Error, &mut rng));
+/// assert_eq!(sampler.probability(&Event::Request), 0.01);
+/// ```
+pub trait SampledKind {
+    /// The probability with which each kind is sampled, indexed by kind.
+    const PROBABILITIES: &'static [f64];
+
+    /// Get this event's kind, an index into
+    /// [`PROBABILITIES`][SampledKind::PROBABILITIES].
+    fn kind(&self) -> usize;
+}
+
+/// A sampler for a [`SampledKind`] event type, with a separate skip count for
+/// each kind.
+///
+/// Each kind is sampled by its own [`FastBernoulli`], so a flood of events of
+/// one kind doesn't affect which events of other kinds are sampled.
+pub struct KindSampler<E> {
+    samplers: Box<[FastBernoulli]>,
+    _events: PhantomData<fn(&E)>,
+}
+
+impl<E> fmt::Debug for KindSampler<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KindSampler")
+            .field("samplers", &self.samplers)
+            .finish()
+    }
+}
+
+impl<E> KindSampler<E>
+where
+    E: SampledKind,
+{
+    /// Construct a new `KindSampler`, with a sampler for each of `E`'s kinds.
+    ///
+    /// # Panics
+    ///
+    /// Every one of `E`'s probabilities must be within the range
+    /// `0.0 <= probability <= 1.0` and this method will panic if that is not
+    /// the case.
+    pub fn new<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        KindSampler {
+            samplers: E::PROBABILITIES
+                .iter()
+                .map(|&probability| FastBernoulli::new(probability, rng))
+                .collect(),
+            _events: PhantomData,
+        }
+    }
+
+    /// Perform a trial for `event`, with its kind's probability.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `event`'s kind is out of bounds for `E::PROBABILITIES`.
+    #[inline]
+    pub fn trial<R>(&mut self, event: &E, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.samplers[event.kind()].trial(rng)
+    }
+
+    /// Get the probability with which events of `event`'s kind are sampled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `event`'s kind is out of bounds for `E::PROBABILITIES`.
+    #[inline]
+    pub fn probability(&self, event: &E) -> f64 {
+        self.samplers[event.kind()].probability()
+    }
+}
+
+#[cfg(all(test, feature = "macros"))]
+mod tests {
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(crate::SampledKind)]
+    #[sample(p = 0.5)]
+    enum Event<T> {
+        Request {
+            path: T,
+        },
+        #[sample(p = 1.0)]
+        Error(T),
+        #[sample(p = 0.0)]
+        Heartbeat,
+    }
+
+    #[test]
+    fn derived_kinds_have_their_own_probabilities() {
+        assert_eq!(Event::<()>::PROBABILITIES, &[0.5, 1.0, 0.0]);
+        assert_eq!(Event::Request { path: () }.kind(), 0);
+        assert_eq!(Event::Error(()).kind(), 1);
+        assert_eq!(Event::<()>::Heartbeat.kind(), 2);
+
+        let mut rng = rand::thread_rng();
+        let mut sampler = KindSampler::new(&mut rng);
+        for _ in 0..100 {
+            assert!(sampler.trial(&Event::Error(()), &mut rng));
+            assert!(!sampler.trial(&Event::Heartbeat, &mut rng));
+        }
+
+        let n = 10_000;
+        let sampled = (0..n)
+            .filter(|_| sampler.trial(&Event::Request { path: () }, &mut rng))
+            .count() as f64;
+        let expected = 0.5 * f64::from(n);
+        assert!((sampled - expected).abs() <= 5.0 * (expected * 0.5).sqrt());
+    }
+}
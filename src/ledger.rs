@@ -0,0 +1,112 @@
+/// Accounting for an event's inclusion probability across a pipeline of
+/// sampling stages.
+///
+/// When several sampling stages are chained, such as a head sampler, a rate
+/// limiter, and a tail sampler, a surviving event's overall inclusion
+/// probability is the product of the probabilities with which each stage
+/// kept it. A `ProbabilityLedger` travels with the event, each stage records
+/// the probability it applied, and at the end the ledger yields the event's
+/// weight. No stage needs to know about any of the others.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::ProbabilityLedger;
+///
+/// let mut ledger = ProbabilityLedger::new();
+///
+/// // The head sampler kept this event with probability 10%...
+/// ledger.record(0.1);
+/// // ...and the tail sampler kept it with probability 50%.
+/// ledger.record(0.5);
+///
+/// assert_eq!(ledger.stages(), 2);
+/// assert!((ledger.probability() - 0.05).abs() < 1e-12);
+/// assert!((ledger.weight() - 20.0).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbabilityLedger {
+    probability: f64,
+    stages: u32,
+}
+
+impl ProbabilityLedger {
+    /// Construct a new, empty `ProbabilityLedger` for an event that has not
+    /// been through any sampling stages yet.
+    #[inline]
+    pub fn new() -> Self {
+        ProbabilityLedger {
+            probability: 1.0,
+            stages: 0,
+        }
+    }
+
+    /// Record that a stage kept the event with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 < probability <= 1.0` and
+    /// this method will panic if that is not the case. A stage that keeps an
+    /// event can't have done so with probability zero.
+    #[inline]
+    pub fn record(&mut self, probability: f64) -> &mut Self {
+        assert!(
+            0.0 < probability && probability <= 1.0,
+            "`probability` must be in the range `0.0 < probability <= 1.0`"
+        );
+        self.probability *= probability;
+        self.stages += 1;
+        self
+    }
+
+    /// Get the event's overall inclusion probability: the product of every
+    /// recorded stage's probability.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// Get the event's weight: the number of events it stands in for, which is
+    /// the reciprocal of its overall inclusion probability.
+    #[inline]
+    pub fn weight(&self) -> f64 {
+        1.0 / self.probability
+    }
+
+    /// Get the number of stages that have recorded a probability.
+    #[inline]
+    pub fn stages(&self) -> u32 {
+        self.stages
+    }
+}
+
+impl Default for ProbabilityLedger {
+    fn default() -> Self {
+        ProbabilityLedger::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_multiply() {
+        let mut ledger = ProbabilityLedger::default();
+        assert_eq!(ledger, ProbabilityLedger::new());
+        assert_eq!(ledger.probability(), 1.0);
+        assert_eq!(ledger.weight(), 1.0);
+        assert_eq!(ledger.stages(), 0);
+
+        ledger.record(0.5).record(0.25).record(1.0);
+        assert_eq!(ledger.probability(), 0.125);
+        assert_eq!(ledger.weight(), 8.0);
+        assert_eq!(ledger.stages(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "`probability` must be in the range `0.0 < probability <= 1.0`")]
+    fn rejects_zero_probability() {
+        ProbabilityLedger::new().record(0.0);
+    }
+}
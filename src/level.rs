@@ -0,0 +1,115 @@
+use rand::Rng;
+
+/// Geometric node heights for skip lists, treaps, and other probabilistic data
+/// structures.
+///
+/// A node's level is `k` or more with probability `p^(k - 1)`, for `p` one
+/// over a power of two, such as `1/2` or `1/4`, capped at a maximum level.
+/// Rather than a trial per level, or any floating-point math, each level is
+/// computed from the trailing zero bits of a single random word: every zero
+/// bit is a coin flip that came up tails.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::LevelGenerator;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // A skip list where each level has a quarter of the nodes of the one below.
+/// let levels = LevelGenerator::one_in(4, 16);
+///
+/// let level = levels.level(&mut rng);
+/// assert!(1 <= level && level <= 16);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelGenerator {
+    shift: u32,
+    max_level: u32,
+}
+
+impl LevelGenerator {
+    /// Construct a new `LevelGenerator` where each level has one in `n` of the
+    /// nodes of the level below, and there are at most `max_level` levels.
+    ///
+    /// # Panics
+    ///
+    /// `n` must be a power of two that is at least `2`, and `max_level` must be
+    /// non-zero, and this method will panic if that is not the case.
+    pub fn one_in(n: u32, max_level: u32) -> Self {
+        assert!(
+            n >= 2 && n.is_power_of_two(),
+            "`n` must be a power of two that is at least `2`"
+        );
+        assert!(max_level > 0, "`max_level` must be non-zero");
+        LevelGenerator {
+            shift: n.trailing_zeros(),
+            max_level,
+        }
+    }
+
+    /// Generate a level, between `1` and the maximum level inclusive.
+    pub fn level<R>(&self, rng: &mut R) -> u32
+    where
+        R: Rng + ?Sized,
+    {
+        // Each level above the first needs `shift` more zero bits. Only draw
+        // another word in the astronomically rare case that every bit of one
+        // is zero and more levels remain.
+        let needed = u64::from(self.max_level - 1) * u64::from(self.shift);
+        let mut zeros = 0_u64;
+        while zeros < needed {
+            let word: u64 = rng.gen();
+            zeros += u64::from(word.trailing_zeros());
+            if word != 0 {
+                break;
+            }
+        }
+        let levels = zeros.min(needed) / u64::from(self.shift);
+        1 + levels as u32
+    }
+
+    /// Get the probability that a node at one level is also at the next.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        1.0 / f64::from(1_u32 << self.shift)
+    }
+
+    /// Get the maximum level.
+    #[inline]
+    pub fn max_level(&self) -> u32 {
+        self.max_level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_are_geometric() {
+        let mut rng = rand::thread_rng();
+        let levels = LevelGenerator::one_in(4, 3);
+
+        let n = 100_000;
+        let mut counts = [0_u32; 4];
+        for _ in 0..n {
+            counts[levels.level(&mut rng) as usize] += 1;
+        }
+        assert_eq!(counts[0], 0);
+
+        // Levels 1 and 2 hold 3/4 and 3/16 of the nodes, and the capped top
+        // level holds the remaining 1/16.
+        for (level, p) in [(1, 0.75), (2, 0.1875), (3, 0.0625)] {
+            let expected = p * f64::from(n);
+            let tolerance = 5.0 * (expected * (1.0 - p)).sqrt();
+            assert!(
+                (f64::from(counts[level]) - expected).abs() <= tolerance,
+                "level {}: expected ~{}, found {}",
+                level,
+                expected,
+                counts[level],
+            );
+        }
+    }
+}
@@ -86,14 +86,226 @@
 // count. Return `false` from `trial` that many times, and then compute a new
 // skip count.
 //
-// For a call to `multi_trial(n)`, if the skip count is greater than `n`, return
+// For a call to `multi_trial(n)`, if the skip count is at least `n`, return
 // `false` and subtract `n` from the skip count. If the skip count is less than
 // `n`, return true and compute a new skip count. Since each trial is
 // independent, it doesn't matter by how much `n` overshoots the skip count; we
 // can actually compute a new skip count at *any* time without affecting the
 // distribution. This is really beautiful.
 
-use rand::Rng;
+// Derived impls name this crate by its external path.
+#[cfg(all(test, feature = "macros"))]
+extern crate self as fast_bernoulli;
+
+/// Emit a `tracing` event about the sampler's own behavior, if the `tracing`
+/// feature is enabled, and do nothing otherwise.
+macro_rules! trace_sampler {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
+mod acceptance;
+mod admission;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod attributes;
+mod backpressure;
+mod backtrace;
+mod bank;
+mod boost;
+pub mod bootstrap;
+mod bounds;
+mod builder;
+mod by_size;
+mod callsite;
+mod capture;
+mod cell;
+mod checked;
+mod clock;
+mod cluster;
+mod counted;
+mod counting;
+#[cfg(feature = "csv")]
+mod csv_sample;
+mod decimate;
+mod decision;
+mod default_rng;
+mod distinct;
+#[cfg(feature = "rand_distr")]
+pub mod distr;
+mod effective;
+mod epsilon_greedy;
+mod estimate;
+mod exemplar;
+mod experiment;
+mod fair;
+mod fallible;
+#[cfg(feature = "fastx")]
+pub mod fastx;
+mod fault;
+#[cfg(feature = "heapless")]
+mod fixed_keyed;
+#[cfg(feature = "governor")]
+mod governed;
+#[cfg(feature = "wgpu")]
+pub mod gpu;
+mod guard;
+#[cfg(feature = "embedded-hal")]
+pub mod hal;
+mod hash;
+mod hazard;
+#[cfg(feature = "hdrhistogram")]
+pub mod hdr;
+mod histogram;
+mod integer;
+mod inverse_frequency;
+mod kind;
+mod ledger;
+mod level;
+mod lineage;
+mod load_shedding;
+mod memoized;
+mod monte_carlo;
+mod one_in;
+mod pause;
+mod philox;
+mod pipeline;
+mod pixels;
+mod poisson;
+mod privacy;
+mod randomized_response;
+mod rate;
+mod report;
+mod representation;
+mod rethin;
+mod rle;
+#[cfg(feature = "serde")]
+mod sampled_de;
+mod sampled_vec;
+mod sampler;
+mod sequential_poisson;
+mod severity;
+mod sflow;
+mod sink;
+mod sketch;
+mod span_latency;
+mod split;
+mod state;
+#[cfg(feature = "cadence")]
+mod statsd;
+mod sticky;
+#[cfg(feature = "sysinfo")]
+mod system_pressure;
+mod table_sample;
+mod tenant;
+mod thinning;
+mod tiered;
+mod time;
+mod token;
+mod unit;
+mod weighted;
+mod wide;
+mod wire;
+
+pub use acceptance::Acceptance;
+pub use admission::AdmissionPolicy;
+pub use backpressure::{BackpressureSampler, BackpressureStats};
+pub use backtrace::BacktraceThrottler;
+pub use bank::BernoulliBank;
+pub use boost::BoostedSampler;
+pub use bounds::{ClampStats, ProbabilityBounds};
+pub use builder::{
+    BuildError, IntegerSamplerBuilder, LazyBernoulli, LazySamplerBuilder, SamplerBuilder,
+    WideSamplerBuilder,
+};
+pub use by_size::{SampleExt, SampledBySize};
+pub use capture::CaptureSampler;
+pub use cell::CellBernoulli;
+pub use checked::InvalidProbability;
+#[cfg(feature = "quanta")]
+pub use clock::QuantaClock;
+pub use clock::{Clock, CoarseClock, StdClock};
+pub use cluster::ClusterSampler;
+pub use counted::CountedSampler;
+pub use counting::{CountingRng, RngUsage};
+#[cfg(feature = "csv")]
+pub use csv_sample::SampledCsvRecords;
+pub use decimate::Decimator;
+pub use decision::SampleDecision;
+#[cfg(feature = "deterministic")]
+pub use default_rng::DEFAULT_SEED;
+pub use default_rng::{default_rng, DefaultRng};
+pub use distinct::SampledDistinctCount;
+pub use epsilon_greedy::EpsilonGreedy;
+pub use estimate::HorvitzThompson;
+pub use exemplar::ExemplarSampler;
+pub use experiment::ExperimentBucketer;
+pub use fair::FairSampler;
+#[cfg(feature = "macros")]
+pub use fast_bernoulli_macros::{sampled, SampledKind};
+pub use fault::{FaultInjector, FaultKind, Faults};
+#[cfg(feature = "heapless")]
+pub use fixed_keyed::{Eviction, FixedKeyedSampler, FixedKeyedStats};
+#[cfg(feature = "governor")]
+pub use governed::{GovernedSampler, GovernedStats};
+pub use guard::{GuardedSampler, SampleGuard, SampleRecord};
+pub use hazard::HazardSampler;
+pub use histogram::SampledHistogram;
+pub use integer::IntegerBernoulli;
+pub use inverse_frequency::InverseFrequencySampler;
+pub use kind::{KindSampler, SampledKind};
+pub use ledger::ProbabilityLedger;
+pub use level::LevelGenerator;
+pub use load_shedding::{LinearLoadCurve, LoadCurve, LoadShedder};
+pub use memoized::MemoizedSampler;
+pub use monte_carlo::{monte_carlo_mean, MonteCarloEstimate};
+pub use pause::PausableSampler;
+pub use philox::PhiloxRng;
+pub use pipeline::{Pipeline, PipelineBuilder};
+pub use poisson::PoissonThinner;
+pub use privacy::{BudgetExceeded, PrivacyBudget, PrivacyCost};
+pub use randomized_response::{RandomizedResponse, RandomizedResponseEstimator};
+pub use rate::{Rate, RateExt};
+pub use report::{Report, ReportThrottler};
+pub use representation::{Inclusion, RepresentationSampler};
+pub use rethin::Rethinner;
+pub use rle::DecisionRun;
+#[cfg(feature = "serde")]
+pub use sampled_de::SampledDeserializer;
+pub use sampled_vec::SampledVec;
+pub use sampler::Sampler;
+pub use sequential_poisson::SequentialPoissonSampler;
+pub use severity::SeveritySampler;
+pub use sflow::{FlowSampleHeader, PacketSampler};
+pub use sink::SamplingSink;
+pub use sketch::CountMinSketch;
+pub use span_latency::SpanLatencySampler;
+#[cfg(feature = "rkyv")]
+pub use state::ArchivedFastBernoulliState;
+pub use state::FastBernoulliState;
+#[cfg(feature = "cadence")]
+pub use statsd::SampledStatsd;
+pub use sticky::StickySampler;
+#[cfg(feature = "sysinfo")]
+pub use system_pressure::{
+    PressureReading, SystemPressure, SystemPressureSampler, SystemPressureStats,
+};
+pub use table_sample::{TableSample, TableSampleRows};
+pub use tenant::{TenantPolicy, TenantSampler, TenantStats};
+pub use tiered::TieredSampler;
+pub use token::decision_token;
+pub use unit::UnitScaledBernoulli;
+pub use wide::WideBernoulli;
+pub use wire::{DecodeError, SamplerConfig, SamplingMode};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::callsite::CallsiteSampler;
+}
+
+use rand::{Rng, RngCore};
 
 /// Fast Bernoulli sampling: each event has equal probability of being sampled.
 ///
@@ -120,20 +332,49 @@ use rand::Rng;
 ///     }
 /// };
 /// ```
+///
+/// For more construction options, such as ratios and seeds, use
+/// [`FastBernoulli::builder`].
+///
+/// `FastBernoulli` is `#[repr(C)]`, and with the `abi_stable` feature enabled,
+/// it implements `abi_stable::StableAbi`; see [`FastBernoulliState`]. Its
+/// layout is the same in debug and release builds, both of which carry a field
+/// for detecting accidental copies, in what would otherwise be padding, and a
+/// field for the denominator of a [one-in-`n`][FastBernoulli::one_in] rate.
+///
+/// # Copies
+///
+/// `FastBernoulli` is `Copy`, but a copy has the same skip count as the
+/// original, so the two sample the same events until their next skip counts
+/// are drawn. Use [`split`][FastBernoulli::split] or
+/// [`reseed`][FastBernoulli::reseed] to make copies on purpose. In debug
+/// builds, a copy that keeps drawing the same skip counts as its original
+/// prints a warning to stderr, once per process.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "abi_stable", derive(abi_stable::StableAbi))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(C)]
 pub struct FastBernoulli {
     probability: f64,
+    // The `n` of a one-in-`n` probability, which skip counts are then drawn
+    // from exactly, or zero.
+    denominator: u64,
     skip_count: u32,
+    lineage: lineage::Lineage,
 }
 
 impl FastBernoulli {
     /// Construct a new `FastBernoulli` instance that samples events with the
     /// given probability.
     ///
+    /// For rates given as "one in `n`" events, especially very low ones, use
+    /// [`one_in`][FastBernoulli::one_in], which takes `n` as an integer.
+    ///
     /// # Panics
     ///
     /// The probability must be within the range `0.0 <= probability <= 1.0` and
-    /// this method will panic if that is not the case.
+    /// this method will panic if that is not the case. Use
+    /// [`new_checked`][FastBernoulli::new_checked] to get an error instead.
     ///
     /// # Example
     ///
@@ -149,22 +390,34 @@ impl FastBernoulli {
         R: Rng + ?Sized,
     {
         assert!(
-            0.0 <= probability && probability <= 1.0,
+            (0.0..=1.0).contains(&probability),
             "`probability` must be in the range `0.0 <= probability <= 1.0`"
         );
 
         let mut bernoulli = FastBernoulli {
             probability,
+            denominator: 0,
             skip_count: 0,
+            lineage: lineage::Lineage::new(),
         };
         bernoulli.reset_skip_count(rng);
         bernoulli
     }
 
+    #[inline]
     fn reset_skip_count<R>(&mut self, rng: &mut R)
     where
         R: Rng + ?Sized,
     {
+        // Funnel every RNG type through one non-generic implementation, so
+        // that the slow path is compiled once, however many RNG types call in.
+        // `&mut R` is itself an `RngCore`, and unlike `R`, it is always sized,
+        // so this works for `R = dyn RngCore` too.
+        let mut rng = rng;
+        self.reset_skip_count_dyn(&mut rng);
+    }
+
+    fn reset_skip_count_dyn(&mut self, rng: &mut dyn RngCore) {
         if self.probability == 0.0 {
             // Edge case: we will never sample any event.
             self.skip_count = u32::MAX;
@@ -172,23 +425,34 @@ impl FastBernoulli {
             // Edge case: we will sample every event.
             self.skip_count = 0;
         } else {
-            // Common case: we need to choose a new skip count using the
-            // formula `floor(log(x) / log(1 - P))`, as explained in the
-            // comment at the top of this file.
-            let x: f64 = rng.gen_range(0.0..1.0);
-            let skip_count = (x.ln() / (1.0 - self.probability).ln()).floor();
-            debug_assert!(skip_count >= 0.0);
-            self.skip_count = if skip_count <= (u32::MAX as f64) {
-                skip_count as u32
-            } else {
-                // Clamp the skip count to `u32::MAX`. This can skew
-                // sampling when we are sampling with a very low
-                // probability, but it is better than any super-robust
-                // alternative we have, such as representing skip counts
-                // with big nums.
-                u32::MAX
-            };
+            // Common case: we need to choose a new skip count from a random
+            // draw. Converting the bits is a plain bit conversion, unlike
+            // `gen_range`, so this never panics.
+            self.set_skip_count_from_bits(rng.next_u64());
         }
+        trace_sampler!(
+            trace,
+            probability = self.probability,
+            skip_count = self.skip_count,
+            "reset skip count"
+        );
+    }
+
+    /// Choose a new skip count from 64 uniformly random `bits`.
+    ///
+    /// A one-in-`n` probability's skip count is drawn from `n` with integer
+    /// arithmetic. Otherwise, the bits become an `f64` in `0.0..1.0`, just as
+    /// `gen::<f64>()` makes one; see [`skip_count_from_uniform`].
+    fn set_skip_count_from_bits(&mut self, bits: u64) {
+        self.lineage.advance();
+
+        self.skip_count = if self.denominator != 0 {
+            let skip_count = integer::one_in_skip_count(self.denominator, bits);
+            u32::try_from(skip_count).unwrap_or(u32::MAX)
+        } else {
+            let x = (bits >> 11) as f64 / (1_u64 << 53) as f64;
+            skip_count_from_uniform(self.probability, x)
+        };
     }
 
     /// Perform a Bernoulli trial: returns `true` with the configured
@@ -200,6 +464,13 @@ impl FastBernoulli {
     /// The lower the configured probability, the less overhead calling this
     /// function has.
     ///
+    /// This never panics, allocates, or formats anything, in debug or release
+    /// builds, as long as `rng` doesn't, so it is safe to call from inside a
+    /// global allocator. The exception is with the `tracing` feature enabled,
+    /// which leaves it up to the `tracing` subscriber. When a debug build
+    /// detects a copied instance, it writes a fixed warning to stderr, without
+    /// formatting or allocating; see [Copies][FastBernoulli#copies].
+    ///
     /// # Example
     ///
     /// ```
@@ -214,6 +485,7 @@ impl FastBernoulli {
     ///     // ...and if it returns true, record a sample of this event.
     /// }
     /// ```
+    #[inline]
     pub fn trial<R>(&mut self, rng: &mut R) -> bool
     where
         R: Rng + ?Sized,
@@ -247,6 +519,9 @@ impl FastBernoulli {
     /// small values for `n`, despite being potentially much more likely to be
     /// sampled.
     ///
+    /// Like `trial`, this never panics, allocates, or formats anything as long
+    /// as `rng` doesn't.
+    ///
     /// # Example
     ///
     /// ```
@@ -270,11 +545,12 @@ impl FastBernoulli {
     /// }
     /// # fn record_malloc_sample(_: u32) {}
     /// ```
+    #[inline]
     pub fn multi_trial<R>(&mut self, n: u32, rng: &mut R) -> bool
     where
         R: Rng + ?Sized,
     {
-        if n < self.skip_count {
+        if n <= self.skip_count {
             self.skip_count -= n;
             return false;
         }
@@ -288,12 +564,93 @@ impl FastBernoulli {
     /// This is a number between `0.0` and `1.0`.
     ///
     /// This is the same value that was passed to `FastBernoulli::new` when
-    /// constructing this instance.
+    /// constructing this instance, or to the most recent call to
+    /// [`set_probability`][FastBernoulli::set_probability].
     #[inline]
     pub fn probability(&self) -> f64 {
         self.probability
     }
 
+    /// Change the probability with which events are sampled, drawing a new
+    /// skip count.
+    ///
+    /// The current skip count was drawn for the old probability, and keeping it
+    /// would sample the next event as if the old probability were still in
+    /// effect. Since each trial is independent, drawing a new one makes every
+    /// later event sampled with exactly the new probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let mut bernoulli = FastBernoulli::new(0.001, &mut rng);
+    ///
+    /// // Sample more while an incident is being investigated.
+    /// bernoulli.set_probability(0.1, &mut rng);
+    /// assert_eq!(bernoulli.probability(), 0.1);
+    /// ```
+    pub fn set_probability<R>(&mut self, probability: f64, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        trace_sampler!(
+            info,
+            from = self.probability,
+            to = probability,
+            "probability changed"
+        );
+        self.probability = probability;
+        self.denominator = 0;
+        self.reset_skip_count(rng);
+    }
+
+    /// Multiply the probability with which events are sampled by `factor`,
+    /// clamping the result to the range `0.0..=1.0`, and draw a new skip
+    /// count.
+    ///
+    /// This is [`set_probability`][FastBernoulli::set_probability] relative to
+    /// the current probability, for backing off and recovering without
+    /// keeping track of each sampler's configured rate. It never panics: a
+    /// negative factor turns sampling off, a factor above `1.0 / probability`
+    /// samples everything, and a factor (or product) of NaN keeps the current
+    /// probability.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let mut bernoulli = FastBernoulli::new(0.1, &mut rng);
+    ///
+    /// // The pipeline is overloaded: halve the sampling rate.
+    /// bernoulli.scale_probability(0.5, &mut rng);
+    /// assert_eq!(bernoulli.probability(), 0.05);
+    /// ```
+    pub fn scale_probability<R>(&mut self, factor: f64, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        let scaled = self.probability * factor;
+        let probability = if scaled.is_nan() {
+            self.probability
+        } else {
+            scaled.clamp(0.0, 1.0)
+        };
+        self.set_probability(probability, rng);
+    }
+
     /// How many events will be skipped until the next event is sampled?
     ///
     /// When `self.probability() == 0.0` this method's return value is
@@ -323,12 +680,325 @@ impl FastBernoulli {
     pub fn skip_count(&self) -> u32 {
         self.skip_count
     }
+
+    /// Iterate over successive skip counts: the number of events skipped
+    /// before each upcoming sampled event.
+    ///
+    /// This lets advanced users drive their own cursors directly from the
+    /// geometric stream. Each item consumed advances `self` as if that many
+    /// events were skipped and one more was sampled, so interleaving `gaps`
+    /// with calls to `trial` stays consistent.
+    ///
+    /// When `self.probability() == 0.0` no event is ever sampled, and the
+    /// iterator is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let mut bernoulli = FastBernoulli::new(0.01, &mut rng);
+    ///
+    /// // Find the indices of the sampled events among the next million.
+    /// let mut index = 0;
+    /// let mut sampled = Vec::new();
+    /// for gap in bernoulli.gaps(&mut rng) {
+    ///     index += gap;
+    ///     if index >= 1_000_000 {
+    ///         break;
+    ///     }
+    ///     sampled.push(index);
+    ///     index += 1;
+    /// }
+    /// ```
+    pub fn gaps<'a, R>(&'a mut self, rng: &'a mut R) -> impl Iterator<Item = u64> + 'a
+    where
+        R: Rng + ?Sized,
+    {
+        std::iter::from_fn(move || {
+            if self.probability == 0.0 {
+                return None;
+            }
+            let gap = u64::from(self.skip_count);
+            self.reset_skip_count(rng);
+            Some(gap)
+        })
+    }
+
+    /// Iterate over the positions, out of `len` consecutive events, that are
+    /// sampled, in `O(samples)` time rather than `O(len)`.
+    ///
+    /// This is exactly equivalent to calling `trial` once per position and
+    /// collecting the positions for which it returned `true`: once the iterator
+    /// is exhausted, `self` is in the same state those `len` trials would have
+    /// left it in. For example, an evolutionary algorithm can find the genes
+    /// to mutate in a genome without a trial per gene.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let mut genome = vec![0_u8; 100_000];
+    ///
+    /// // Mutate each gene with probability 1/1000.
+    /// let mut mutation = FastBernoulli::new(0.001, &mut rng);
+    /// let positions: Vec<usize> = mutation.sampled_positions(genome.len(), &mut rng).collect();
+    /// for &position in &positions {
+    ///     genome[position] ^= 1;
+    /// }
+    /// ```
+    pub fn sampled_positions<'a, R>(
+        &'a mut self,
+        len: usize,
+        rng: &'a mut R,
+    ) -> impl Iterator<Item = usize> + 'a
+    where
+        R: Rng + ?Sized,
+    {
+        let mut position = 0;
+        std::iter::from_fn(move || {
+            while position < len {
+                let remaining = len - position;
+                let skip = self.skip_count as usize;
+                if skip >= remaining {
+                    self.skip_count -= remaining as u32;
+                    position = len;
+                    return None;
+                }
+
+                position += skip;
+                self.skip_count = 0;
+                let sampled = self.trial(rng);
+                position += 1;
+                if sampled {
+                    return Some(position - 1);
+                }
+            }
+            None
+        })
+    }
+
+    /// Get the expected number of events skipped between two samples.
+    ///
+    /// Gaps between samples follow a geometric distribution, whose mean is
+    /// `(1 - p) / p`. This is infinite when `self.probability() == 0.0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let bernoulli = FastBernoulli::new(0.01, &mut rng);
+    ///
+    /// // On average, 99 events are skipped between samples.
+    /// assert!((bernoulli.expected_gap() - 99.0).abs() < 1e-9);
+    /// ```
+    #[inline]
+    pub fn expected_gap(&self) -> f64 {
+        (1.0 - self.probability) / self.probability
+    }
+
+    /// Get the variance of the number of events skipped between two samples.
+    ///
+    /// This is `(1 - p) / p^2`, and is infinite when
+    /// `self.probability() == 0.0`.
+    #[inline]
+    pub fn gap_variance(&self) -> f64 {
+        (1.0 - self.probability) / (self.probability * self.probability)
+    }
+
+    /// Get the `q`th quantile of the number of events skipped between two
+    /// samples.
+    ///
+    /// The result is the smallest gap `g` such that at least fraction `q` of
+    /// all gaps are at most `g`. This answers questions like "99% of the time,
+    /// how many events at most go by between samples?" without simulating.
+    ///
+    /// The result is infinite when no finite gap satisfies the quantile, which
+    /// happens when `self.probability() == 0.0`, or when `q == 1.0` and
+    /// `self.probability() != 1.0`.
+    ///
+    /// # Panics
+    ///
+    /// The quantile must be within the range `0.0 <= q <= 1.0` and this method
+    /// will panic if that is not the case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let bernoulli = FastBernoulli::new(0.01, &mut rng);
+    ///
+    /// // 99% of gaps between samples are at most 458 events.
+    /// assert_eq!(bernoulli.gap_percentile(0.99), 458.0);
+    /// ```
+    pub fn gap_percentile(&self, q: f64) -> f64 {
+        assert!(
+            (0.0..=1.0).contains(&q),
+            "`q` must be in the range `0.0 <= q <= 1.0`"
+        );
+
+        if self.probability == 1.0 {
+            return 0.0;
+        }
+        if self.probability == 0.0 || q == 1.0 {
+            return f64::INFINITY;
+        }
+
+        // `P(gap <= g) = 1 - (1 - p)^(g + 1)`; solve for the smallest `g` that
+        // makes this at least `q`.
+        let g = ((-q).ln_1p() / (-self.probability).ln_1p()).ceil() - 1.0;
+        g.max(0.0)
+    }
+
+    /// Get an upper bound on how many of the next `n` events will be sampled,
+    /// that holds with at least the given confidence.
+    ///
+    /// That is, the probability that more than the returned number of events
+    /// are sampled is at most `1.0 - confidence`. This is useful for
+    /// pre-allocating buffers for samples, or reserving bandwidth to send
+    /// them.
+    ///
+    /// The bound is derived from a Chernoff bound on the binomial
+    /// distribution, so it is conservative: the true probability of exceeding
+    /// it is usually much smaller than `1.0 - confidence`. It is never greater
+    /// than `n`.
+    ///
+    /// # Panics
+    ///
+    /// The confidence must be within the range `0.0 <= confidence <= 1.0` and
+    /// this method will panic if that is not the case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let bernoulli = FastBernoulli::new(0.01, &mut rng);
+    ///
+    /// // We expect 100 samples among 10,000 events, and are 99.9% sure there
+    /// // won't be more than this many.
+    /// let capacity = bernoulli.samples_upper_bound(10_000, 0.999);
+    /// assert!(100 <= capacity && capacity <= 200);
+    /// let samples: Vec<u32> = Vec::with_capacity(capacity as usize);
+    /// # let _ = samples;
+    /// ```
+    pub fn samples_upper_bound(&self, n: u64, confidence: f64) -> u64 {
+        assert!(
+            (0.0..=1.0).contains(&confidence),
+            "`confidence` must be in the range `0.0 <= confidence <= 1.0`"
+        );
+
+        if self.probability == 0.0 {
+            return 0;
+        }
+        if self.probability == 1.0 || confidence == 1.0 {
+            return n;
+        }
+
+        // Chernoff: `P(X >= (1 + d) * mu) <= exp(-d^2 * mu / (2 + d))`. Setting
+        // the right-hand side to `1 - confidence` and solving the quadratic for
+        // `d * mu` gives the bound.
+        let mu = n as f64 * self.probability;
+        let l = -(-confidence).ln_1p();
+        let bound = mu + (l + (l * l + 8.0 * mu * l).sqrt()) / 2.0;
+        if bound >= n as f64 {
+            n
+        } else {
+            bound.ceil() as u64
+        }
+    }
+}
+
+/// Choose a skip count for `probability` from `x`, drawn uniformly from
+/// `0.0..1.0`, using the formula `floor(log(x) / log(1 - P))`, as explained in
+/// the comment at the top of this file.
+///
+/// This is reached from `trial` and `multi_trial`, which must never panic, so
+/// that they can be used inside global allocators and the like. `ln_1p` keeps
+/// `log(1 - P)` from rounding to zero when `P` is tiny, so the quotient is
+/// either a non-negative number or positive infinity.
+pub(crate) fn skip_count_from_uniform(probability: f64, x: f64) -> u32 {
+    let skip_count = (x.ln() / (-probability).ln_1p()).floor();
+    if skip_count <= (u32::MAX as f64) {
+        skip_count as u32
+    } else {
+        // Clamp the skip count to `u32::MAX`. This can skew sampling when we
+        // are sampling with a very low probability, but it is better than any
+        // super-robust alternative we have, such as representing skip counts
+        // with big nums.
+        trace_sampler!(debug, probability, "clamped skip count to u32::MAX");
+        u32::MAX
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn set_probability_redraws_the_skip_count() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = FastBernoulli::new(0.0, &mut rng);
+        assert_eq!(bernoulli.skip_count(), u32::MAX);
+
+        bernoulli.set_probability(1.0, &mut rng);
+        assert_eq!(bernoulli.skip_count(), 0);
+        assert!(bernoulli.trial(&mut rng));
+
+        bernoulli.set_probability(0.0, &mut rng);
+        assert!(!bernoulli.trial(&mut rng));
+
+        bernoulli.set_probability(0.25, &mut rng);
+        bernoulli.scale_probability(2.0, &mut rng);
+        assert_eq!(bernoulli.probability(), 0.5);
+        bernoulli.scale_probability(10.0, &mut rng);
+        assert_eq!(bernoulli.probability(), 1.0);
+        bernoulli.scale_probability(0.0, &mut rng);
+        assert_eq!(bernoulli.probability(), 0.0);
+    }
+
+    #[test]
+    fn scale_probability_clamps_any_factor() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = FastBernoulli::new(0.25, &mut rng);
+
+        bernoulli.scale_probability(-2.0, &mut rng);
+        assert_eq!(bernoulli.probability(), 0.0);
+        assert!(!bernoulli.trial(&mut rng));
+
+        bernoulli.set_probability(0.25, &mut rng);
+        bernoulli.scale_probability(5.0, &mut rng);
+        assert_eq!(bernoulli.probability(), 1.0);
+        assert!(bernoulli.trial(&mut rng));
+
+        bernoulli.set_probability(0.25, &mut rng);
+        bernoulli.scale_probability(f64::NAN, &mut rng);
+        assert_eq!(bernoulli.probability(), 0.25);
+        bernoulli.scale_probability(f64::INFINITY, &mut rng);
+        assert_eq!(bernoulli.probability(), 1.0);
+        bernoulli.scale_probability(f64::NEG_INFINITY, &mut rng);
+        assert_eq!(bernoulli.probability(), 0.0);
+
+        // Zero times infinity is NaN, so this keeps the probability at zero.
+        bernoulli.scale_probability(f64::INFINITY, &mut rng);
+        assert_eq!(bernoulli.probability(), 0.0);
+    }
+
+    #[test]
+    fn layout_is_the_same_in_every_profile() {
+        // `f64`, `u64`, `u32`, and a `u32` lineage in what would be padding.
+        assert_eq!(std::mem::size_of::<FastBernoulli>(), 24);
+        assert_eq!(std::mem::align_of::<FastBernoulli>(), 8);
+    }
+
     #[test]
     fn expected_number_of_samples() {
         let mut rng = rand::thread_rng();
@@ -358,4 +1028,88 @@ mod tests {
             max,
         );
     }
+
+    #[test]
+    fn multi_trial_is_equivalent_to_n_trials() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = FastBernoulli::new(0.5, &mut rng);
+        while bernoulli.skip_count() == 0 {
+            bernoulli = FastBernoulli::new(0.5, &mut rng);
+        }
+
+        // Exactly `skip_count` trials all return `false`, so `multi_trial` of
+        // that many must too, and the very next trial must return `true`.
+        let n = bernoulli.skip_count();
+        assert!(!bernoulli.multi_trial(n, &mut rng));
+        assert!(bernoulli.trial(&mut rng));
+
+        // Zero trials never sample anything.
+        assert!(!FastBernoulli::new(1.0, &mut rng).multi_trial(0, &mut rng));
+    }
+
+    #[test]
+    fn gaps_agree_with_trials() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = FastBernoulli::new(0.1, &mut rng);
+
+        for _ in 0..100 {
+            // Each gap is the skip count that `trial` would have counted down.
+            let skip_count = u64::from(bernoulli.skip_count());
+            assert_eq!(bernoulli.gaps(&mut rng).next(), Some(skip_count));
+        }
+
+        let mut never = FastBernoulli::new(0.0, &mut rng);
+        assert_eq!(never.gaps(&mut rng).next(), None);
+    }
+
+    #[test]
+    fn extreme_probabilities_do_not_panic() {
+        use rand::rngs::mock::StepRng;
+
+        // Draws of exactly `0.0`, and of the largest value below `1.0`.
+        for &bits in &[0, u64::MAX] {
+            let mut rng = StepRng::new(bits, 0);
+            for &probability in &[f64::MIN_POSITIVE, 1e-300, 1e-17, 0.5, 1.0 - f64::EPSILON] {
+                let mut bernoulli = FastBernoulli::new(probability, &mut rng);
+                bernoulli.trial(&mut rng);
+                bernoulli.multi_trial(u32::MAX, &mut rng);
+            }
+        }
+
+        // A tiny probability must not round down to sampling everything.
+        let mut rng = StepRng::new(u64::MAX, 0);
+        let bernoulli = FastBernoulli::new(1e-300, &mut rng);
+        assert_eq!(bernoulli.skip_count(), u32::MAX);
+    }
+
+    #[test]
+    fn sampled_positions_agree_with_trials() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        for &probability in &[0.0_f64, 0.01, 0.5, 1.0] {
+            let mut rng = StdRng::seed_from_u64(probability.to_bits());
+            let mut by_positions = FastBernoulli::new(probability, &mut rng);
+            // A deliberate copy, with the same state but its own identity.
+            let mut by_trials = FastBernoulli::from_state(by_positions.state());
+            let mut trials_rng = rng.clone();
+
+            let positions: Vec<usize> = by_positions.sampled_positions(10_000, &mut rng).collect();
+            let expected: Vec<usize> = (0..10_000)
+                .filter(|_| by_trials.trial(&mut trials_rng))
+                .collect();
+            assert_eq!(positions, expected, "probability = {}", probability);
+            assert_eq!(by_positions.skip_count(), by_trials.skip_count());
+            assert_eq!(crate::lineage::duplicates_on_this_thread(), 0);
+        }
+    }
+
+    #[test]
+    fn trials_accept_dyn_rngs() {
+        let mut rng = rand::thread_rng();
+        let rng: &mut dyn RngCore = &mut rng;
+        let mut bernoulli = FastBernoulli::new(1.0, rng);
+        assert!(bernoulli.trial(rng));
+        assert!(bernoulli.multi_trial(10, rng));
+    }
 }
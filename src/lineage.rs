@@ -0,0 +1,185 @@
+//! Detection of accidentally duplicated samplers.
+//!
+//! `FastBernoulli` is `Copy`, so it is easy to copy one by accident, say by
+//! moving it into two closures, or handing each worker thread a copy of a
+//! shared sampler. Every copy has the same skip count, so the copies sample
+//! the same events until their next skip counts are drawn, and forever if
+//! they are given identically seeded RNGs.
+//!
+//! Each instance carries a `Lineage`: a nonce, unique to the constructed
+//! instance and shared by all of its copies, and a generation, incremented
+//! every time a skip count is drawn. Both are packed into a single `u32`,
+//! which fits in what would otherwise be `FastBernoulli`'s padding, so the
+//! lineage costs no space, and `FastBernoulli`'s layout doesn't depend on the
+//! build profile. Only debug builds check it. They record draws in a small
+//! global table, without allocating or locking. When an instance draws a skip
+//! count from a generation that another instance with the same nonce has
+//! already drawn from, it must be a copy, and we write a warning to stderr.
+//!
+//! The check is lossy: nonces that share a slot evict each other, and
+//! generations wrap, so some duplicates go unnoticed. Nonces wrap too, after
+//! `2^24` constructions, but an unrelated instance only triggers a warning if
+//! it shares both a nonce and a slot with one that is still drawing.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+const SLOTS: usize = 256;
+
+/// The low bits of a lineage hold its generation, and the rest its nonce.
+const GENERATION_BITS: u32 = 8;
+const GENERATION_MASK: u32 = (1 << GENERATION_BITS) - 1;
+
+static LINEAGES: [AtomicU64; SLOTS] = [const { AtomicU64::new(0) }; SLOTS];
+static NEXT_NONCE: AtomicU32 = AtomicU32::new(1);
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(test)]
+thread_local! {
+    static DUPLICATES: std::cell::Cell<u32> = const { std::cell::Cell::new(0) };
+}
+
+/// Get the number of duplicates detected on this thread, for tests that check
+/// that deliberate copies don't look like accidental ones.
+#[cfg(test)]
+pub(crate) fn duplicates_on_this_thread() -> u32 {
+    DUPLICATES.with(std::cell::Cell::get)
+}
+
+const WARNING: &[u8] = b"warning: fast_bernoulli: a copied `FastBernoulli` is drawing the same \
+    skip counts as the instance it was copied from, so both sample the same events; `reseed` or \
+    `split` copies made on purpose (this warning is only printed once, and only in debug \
+    builds)\n";
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "abi_stable", derive(abi_stable::StableAbi))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(C)]
+pub(crate) struct Lineage {
+    nonce_and_generation: u32,
+}
+
+impl Lineage {
+    /// A fresh lineage, for a newly constructed instance.
+    pub(crate) fn new() -> Self {
+        Lineage {
+            nonce_and_generation: NEXT_NONCE.fetch_add(1, Ordering::Relaxed) << GENERATION_BITS,
+        }
+    }
+
+    /// Get the nonce shared by this instance and its copies.
+    pub(crate) fn nonce(&self) -> u32 {
+        self.nonce_and_generation >> GENERATION_BITS
+    }
+
+    fn generation(&self) -> u32 {
+        self.nonce_and_generation & GENERATION_MASK
+    }
+
+    /// Record that this instance is drawing a new skip count, warning if it is
+    /// a duplicate in a debug build. Returns whether it is; release builds
+    /// don't check, and always return `false`.
+    pub(crate) fn advance(&mut self) -> bool {
+        if !cfg!(debug_assertions) {
+            self.next_generation();
+            return false;
+        }
+
+        let duplicate = self.advance_in(&LINEAGES[self.nonce() as usize % SLOTS]);
+        if duplicate {
+            #[cfg(test)]
+            DUPLICATES.with(|duplicates| duplicates.set(duplicates.get() + 1));
+            trace_sampler!(warn, nonce = self.nonce(), "duplicated sampler detected");
+            if !WARNED.swap(true, Ordering::Relaxed) {
+                // Unlike `eprintln!`, this neither formats nor panics if stderr
+                // is closed.
+                let _ = std::io::stderr().write_all(WARNING);
+            }
+        }
+        duplicate
+    }
+
+    fn next_generation(&mut self) {
+        let generation = (self.generation() + 1) & GENERATION_MASK;
+        self.nonce_and_generation = (self.nonce_and_generation & !GENERATION_MASK) | generation;
+    }
+
+    fn advance_in(&mut self, slot: &AtomicU64) -> bool {
+        let seen = slot.load(Ordering::Relaxed);
+        // Another instance with this nonce is ahead of this one, allowing for
+        // the generation wrapping around.
+        let ahead = (seen as u32).wrapping_sub(self.generation()) & GENERATION_MASK;
+        let duplicate =
+            (seen >> 32) as u32 == self.nonce() && 0 < ahead && ahead <= GENERATION_MASK / 2;
+
+        self.next_generation();
+        if !duplicate {
+            slot.store(
+                u64::from(self.nonce()) << 32 | u64::from(self.generation()),
+                Ordering::Relaxed,
+            );
+        }
+        duplicate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_are_detected() {
+        // Use a private slot, so that other tests' samplers can't evict these.
+        let slot = AtomicU64::new(0);
+        let mut original = Lineage::new();
+        assert!(!original.advance_in(&slot));
+
+        let mut copy = original;
+        assert!(!original.advance_in(&slot));
+        assert!(copy.advance_in(&slot));
+
+        // Moves aren't copies.
+        let mut moved = original;
+        assert!(!moved.advance_in(&slot));
+
+        // Nor are unrelated instances.
+        let mut unrelated = Lineage::new();
+        assert!(!unrelated.advance_in(&slot));
+    }
+
+    #[test]
+    fn generations_wrap() {
+        let slot = AtomicU64::new(0);
+        let mut original = Lineage::new();
+        let nonce = original.nonce();
+        for _ in 0..1000 {
+            assert!(!original.advance_in(&slot));
+        }
+        assert_eq!(original.nonce(), nonce);
+
+        let mut copy = original;
+        assert!(!original.advance_in(&slot));
+        assert!(copy.advance_in(&slot));
+    }
+
+    #[test]
+    fn duplicates_are_counted() {
+        // Don't print the warning into the test output.
+        WARNED.store(true, Ordering::Relaxed);
+        // Other tests' samplers can evict these from the global table, so
+        // allow for a few misses.
+        let detected = (0..10)
+            .filter(|_| {
+                let mut original = Lineage::new();
+                original.advance();
+                let mut copy = original;
+                original.advance();
+                copy.advance()
+            })
+            .count() as u32;
+        assert_eq!(duplicates_on_this_thread(), detected);
+        if cfg!(debug_assertions) {
+            assert!(detected > 0);
+        }
+    }
+}
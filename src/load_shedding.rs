@@ -0,0 +1,255 @@
+use crate::time::Instant;
+use crate::{ClampStats, FastBernoulli, ProbabilityBounds};
+use rand::Rng;
+use std::fmt;
+use std::time::Duration;
+
+/// A mapping from a load signal to a sampling probability.
+///
+/// Implemented for any `Fn(f64) -> f64`, and for [`LinearLoadCurve`].
+pub trait LoadCurve {
+    /// Get the sampling probability to use under the given load.
+    ///
+    /// The result is clamped into `0.0..=1.0`.
+    fn probability(&self, load: f64) -> f64;
+}
+
+impl<F> LoadCurve for F
+where
+    F: Fn(f64) -> f64,
+{
+    #[inline]
+    fn probability(&self, load: f64) -> f64 {
+        self(load)
+    }
+}
+
+/// A load curve that interpolates linearly between a probability for low load
+/// and a probability for high load.
+///
+/// At or below `low_load`, the probability is `low_load_probability`; at or
+/// above `high_load`, it is `high_load_probability`; in between, it is
+/// interpolated linearly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearLoadCurve {
+    /// The load at and below which sampling is at its most generous.
+    pub low_load: f64,
+    /// The probability to use at low load.
+    pub low_load_probability: f64,
+    /// The load at and above which sampling is at its most restrictive.
+    pub high_load: f64,
+    /// The probability to use at high load.
+    pub high_load_probability: f64,
+}
+
+impl LoadCurve for LinearLoadCurve {
+    fn probability(&self, load: f64) -> f64 {
+        if load <= self.low_load {
+            self.low_load_probability
+        } else if load >= self.high_load {
+            self.high_load_probability
+        } else {
+            let t = (load - self.low_load) / (self.high_load - self.low_load);
+            self.low_load_probability + t * (self.high_load_probability - self.low_load_probability)
+        }
+    }
+}
+
+/// A sampler whose probability is driven by a load signal, so that sampling
+/// automatically tightens under pressure.
+///
+/// The load is read from a user-supplied closure (queue depth, CPU usage,
+/// remaining error budget, ...) and mapped to a probability by a
+/// [`LoadCurve`]. Reading the load may be expensive, so it is re-evaluated at
+/// most once per configured interval; in between, trials are as cheap as a
+/// plain [`FastBernoulli`]'s.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{LinearLoadCurve, LoadShedder};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+/// let queue_depth = AtomicUsize::new(0);
+///
+/// // Sample 10% of events while the queue is short, tapering to 0.1% as it
+/// // fills up.
+/// let curve = LinearLoadCurve {
+///     low_load: 100.0,
+///     low_load_probability: 0.1,
+///     high_load: 10_000.0,
+///     high_load_probability: 0.001,
+/// };
+/// let mut shedder = LoadShedder::new(
+///     || queue_depth.load(Ordering::Relaxed) as f64,
+///     curve,
+///     Duration::from_millis(100),
+///     &mut rng,
+/// );
+///
+/// if shedder.trial(Instant::now(), &mut rng) {
+///     // Record the sample...
+/// }
+/// ```
+pub struct LoadShedder<L, C> {
+    load: L,
+    curve: C,
+    interval: Duration,
+    next_evaluation: Option<Instant>,
+    bernoulli: FastBernoulli,
+    bounds: ProbabilityBounds,
+    clamps: ClampStats,
+}
+
+impl<L, C> fmt::Debug for LoadShedder<L, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadShedder")
+            .field("interval", &self.interval)
+            .field("next_evaluation", &self.next_evaluation)
+            .field("bernoulli", &self.bernoulli)
+            .field("bounds", &self.bounds)
+            .field("clamps", &self.clamps)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<L, C> LoadShedder<L, C>
+where
+    L: FnMut() -> f64,
+    C: LoadCurve,
+{
+    /// Construct a new `LoadShedder` that reads the load from `load`, maps it
+    /// to a probability with `curve`, and re-evaluates at most once per
+    /// `interval`.
+    ///
+    /// The load is read once immediately, to choose the initial probability,
+    /// and not again until `interval` has elapsed.
+    pub fn new<R>(mut load: L, curve: C, interval: Duration, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let probability = clamp_probability(curve.probability(load()));
+        LoadShedder {
+            load,
+            curve,
+            interval,
+            next_evaluation: Some(Instant::now() + interval),
+            bernoulli: FastBernoulli::new(probability, rng),
+            bounds: ProbabilityBounds::UNBOUNDED,
+            clamps: ClampStats::default(),
+        }
+    }
+
+    /// Keep the probabilities chosen from the load within `bounds`.
+    ///
+    /// The load is re-evaluated on the next trial, so the bounds take effect
+    /// immediately.
+    pub fn with_bounds(mut self, bounds: ProbabilityBounds) -> Self {
+        self.bounds = bounds;
+        self.next_evaluation = None;
+        self
+    }
+
+    /// Perform a trial for an event that occurred at `now`, first re-evaluating
+    /// the load if the interval has elapsed.
+    pub fn trial<R>(&mut self, now: Instant, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        match self.next_evaluation {
+            Some(next) if now < next => {}
+            _ => self.evaluate(now, rng),
+        }
+        self.bernoulli.trial(rng)
+    }
+
+    /// Re-evaluate the load now, regardless of the interval.
+    pub fn evaluate<R>(&mut self, now: Instant, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        self.next_evaluation = Some(now + self.interval);
+        let probability = clamp_probability(self.curve.probability((self.load)()));
+        let probability = self.bounds.apply(probability, &mut self.clamps);
+        if probability != self.bernoulli.probability() {
+            self.bernoulli.set_probability(probability, rng);
+        }
+    }
+
+    /// Get the probability currently in effect.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Get the rate at which events are actually being sampled; see
+    /// [`FastBernoulli::effective_probability`].
+    #[inline]
+    pub fn effective_probability(&self) -> f64 {
+        self.bernoulli.effective_probability()
+    }
+
+    /// Get the counts of chosen probabilities that were clamped by the bounds.
+    #[inline]
+    pub fn clamp_stats(&self) -> ClampStats {
+        self.clamps
+    }
+}
+
+fn clamp_probability(p: f64) -> f64 {
+    if p.is_nan() {
+        0.0
+    } else {
+        p.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn reevaluates_at_most_once_per_interval() {
+        let mut rng = rand::thread_rng();
+        let load = Cell::new(0.0);
+        let reads = Cell::new(0);
+        let interval = Duration::from_secs(1);
+        let mut shedder = LoadShedder::new(
+            || {
+                reads.set(reads.get() + 1);
+                load.get()
+            },
+            |load: f64| 1.0 - load,
+            interval,
+            &mut rng,
+        );
+        assert_eq!(shedder.probability(), 1.0);
+        assert_eq!(reads.get(), 1);
+
+        // The read at construction counts for the first interval.
+        let start = Instant::now();
+        load.set(1.0);
+        for _ in 0..100 {
+            shedder.trial(start, &mut rng);
+        }
+        assert_eq!(shedder.probability(), 1.0);
+        assert_eq!(reads.get(), 1);
+
+        shedder.trial(start + interval, &mut rng);
+        assert_eq!(shedder.probability(), 0.0);
+        assert_eq!(reads.get(), 2);
+
+        load.set(0.5);
+        for _ in 0..100 {
+            shedder.trial(start + interval, &mut rng);
+        }
+        assert_eq!(shedder.probability(), 0.0);
+        assert_eq!(reads.get(), 2);
+
+        shedder.trial(start + interval * 2, &mut rng);
+        assert_eq!(shedder.probability(), 0.5);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::time::Instant;
+use crate::FastBernoulli;
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Per-key decision memoization: the most recent decision for each key is
+/// reused until a time-to-live expires.
+///
+/// Unlike [`StickySampler`][crate::StickySampler], which only remembers
+/// positive decisions, `MemoizedSampler` remembers negative decisions as
+/// well. A burst of events for the same key therefore performs a single
+/// Bernoulli trial, and every event in the burst gets the same answer: the
+/// decision cannot flip mid-burst, and repeats don't consume trials.
+///
+/// Once a key's decision expires, its next event performs a fresh trial and
+/// memoizes the new decision. Expired entries are dropped when their key is
+/// next seen, or in bulk with
+/// [`evict_expired`][MemoizedSampler::evict_expired].
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::MemoizedSampler;
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sampler = MemoizedSampler::new(0.1, Duration::from_secs(1), &mut rng);
+///
+/// let now = Instant::now();
+/// let first = sampler.trial(&"request-path", now, &mut rng);
+///
+/// // Within the next second, the same key always gets the same decision.
+/// for _ in 0..100 {
+///     assert_eq!(sampler.trial(&"request-path", now, &mut rng), first);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoizedSampler<K> {
+    bernoulli: FastBernoulli,
+    ttl: Duration,
+    decisions: HashMap<K, Memoized>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Memoized {
+    decision: bool,
+    expiration: Instant,
+}
+
+impl<K> MemoizedSampler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Construct a new `MemoizedSampler` that samples keys with the given
+    /// probability, and memoizes each decision for `ttl`.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, ttl: Duration, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        MemoizedSampler {
+            bernoulli: FastBernoulli::new(probability, rng),
+            ttl,
+            decisions: HashMap::new(),
+        }
+    }
+
+    /// Perform a trial for an event belonging to `key` that occurred at `now`.
+    ///
+    /// If the key has a decision that has not expired, that decision is
+    /// returned without performing a trial. Otherwise a fresh Bernoulli trial
+    /// is performed and its result is memoized until `now + ttl`.
+    pub fn trial<R>(&mut self, key: &K, now: Instant, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        if let Some(memoized) = self.decisions.get_mut(key) {
+            if now < memoized.expiration {
+                return memoized.decision;
+            }
+            memoized.decision = self.bernoulli.trial(rng);
+            memoized.expiration = now + self.ttl;
+            return memoized.decision;
+        }
+
+        let decision = self.bernoulli.trial(rng);
+        self.decisions.insert(
+            key.clone(),
+            Memoized {
+                decision,
+                expiration: now + self.ttl,
+            },
+        );
+        decision
+    }
+
+    /// Get the memoized decision for `key`, if it has one that has not expired
+    /// by `now`.
+    pub fn decision(&self, key: &K, now: Instant) -> Option<bool> {
+        self.decisions
+            .get(key)
+            .filter(|m| now < m.expiration)
+            .map(|m| m.decision)
+    }
+
+    /// Forget the memoized decision for `key`, so that its next event performs a
+    /// fresh trial.
+    pub fn forget(&mut self, key: &K) {
+        self.decisions.remove(key);
+    }
+
+    /// Drop every memoized decision that has expired by `now`.
+    pub fn evict_expired(&mut self, now: Instant) {
+        self.decisions.retain(|_, m| now < m.expiration);
+    }
+
+    /// Get the number of memoized decisions, including any expired decisions
+    /// that have not yet been evicted.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.decisions.len()
+    }
+
+    /// Are there no memoized decisions?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.decisions.is_empty()
+    }
+
+    /// Get the probability with which keys are sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Get the time-to-live of memoized decisions.
+    #[inline]
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_decisions_are_memoized() {
+        let mut rng = rand::thread_rng();
+        let ttl = Duration::from_secs(10);
+        let mut sampler = MemoizedSampler::new(0.0, ttl, &mut rng);
+        let start = Instant::now();
+
+        assert!(!sampler.trial(&1, start, &mut rng));
+
+        // Even at probability one, the memoized negative decision stands until
+        // it expires.
+        sampler.bernoulli = FastBernoulli::new(1.0, &mut rng);
+        assert!(!sampler.trial(&1, start + Duration::from_secs(9), &mut rng));
+        assert!(sampler.trial(&1, start + ttl, &mut rng));
+        assert_eq!(sampler.decision(&1, start + ttl), Some(true));
+    }
+}
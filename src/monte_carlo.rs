@@ -0,0 +1,116 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// A Monte Carlo estimate of a function's mean over a domain, returned by
+/// [`monte_carlo_mean`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MonteCarloEstimate {
+    /// The estimated mean: the mean of the function's values at the sampled
+    /// points. This is `NaN` if no points were sampled.
+    pub mean: f64,
+    /// The estimated standard error of the mean, including the finite
+    /// population correction. This is `NaN` if fewer than two points were
+    /// sampled.
+    pub standard_error: f64,
+    /// The number of sampled points, at which the function was evaluated.
+    pub samples: u64,
+}
+
+impl MonteCarloEstimate {
+    /// Get an approximate confidence interval for the mean, spanning `z`
+    /// standard errors on either side of the estimate.
+    ///
+    /// For example, `z = 1.96` gives an approximately 95% confidence interval.
+    #[inline]
+    pub fn confidence_interval(&self, z: f64) -> (f64, f64) {
+        let margin = z * self.standard_error;
+        (self.mean - margin, self.mean + margin)
+    }
+}
+
+/// Estimate the mean of `f` over the points `0..points` of a domain, by
+/// evaluating it only at the points sampled by `bernoulli`.
+///
+/// Map each index to a point of the domain inside `f`. Indicator functions,
+/// returning `1.0` or `0.0`, estimate the fraction of the domain where some
+/// condition holds. The sampled points are found from skip counts, so this
+/// runs in `O(samples)` time, plus the cost of evaluating `f` at each.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{monte_carlo_mean, FastBernoulli};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut bernoulli = FastBernoulli::new(0.01, &mut rng);
+///
+/// // Estimate π from the fraction of a 2000x2000 grid inside the unit circle.
+/// let side = 2000;
+/// let inside = |i: usize| {
+///     let x = (i % side) as f64 / side as f64;
+///     let y = (i / side) as f64 / side as f64;
+///     if x * x + y * y <= 1.0 { 1.0 } else { 0.0 }
+/// };
+/// let estimate = monte_carlo_mean(side * side, inside, &mut bernoulli, &mut rng);
+///
+/// let (low, high) = estimate.confidence_interval(5.0);
+/// assert!(low <= std::f64::consts::FRAC_PI_4 && std::f64::consts::FRAC_PI_4 <= high);
+/// ```
+pub fn monte_carlo_mean<F, R>(
+    points: usize,
+    mut f: F,
+    bernoulli: &mut FastBernoulli,
+    rng: &mut R,
+) -> MonteCarloEstimate
+where
+    F: FnMut(usize) -> f64,
+    R: Rng + ?Sized,
+{
+    // Welford's online algorithm, for a numerically stable variance.
+    let mut samples = 0_u64;
+    let mut mean = 0.0;
+    let mut sum_of_squares = 0.0;
+    for point in bernoulli.sampled_positions(points, rng) {
+        let value = f(point);
+        samples += 1;
+        let delta = value - mean;
+        mean += delta / samples as f64;
+        sum_of_squares += delta * (value - mean);
+    }
+
+    let n = samples as f64;
+    let standard_error = if samples < 2 {
+        f64::NAN
+    } else {
+        let variance = sum_of_squares / (n - 1.0);
+        let correction = 1.0 - n / points as f64;
+        (variance / n * correction).sqrt()
+    };
+    MonteCarloEstimate {
+        mean: if samples == 0 { f64::NAN } else { mean },
+        standard_error,
+        samples,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhaustive_sampling_is_exact() {
+        let mut rng = rand::thread_rng();
+
+        let mut all = FastBernoulli::new(1.0, &mut rng);
+        let estimate = monte_carlo_mean(100, |i| i as f64, &mut all, &mut rng);
+        assert_eq!(estimate.samples, 100);
+        assert!((estimate.mean - 49.5).abs() < 1e-9);
+        assert_eq!(estimate.standard_error, 0.0);
+
+        let mut none = FastBernoulli::new(0.0, &mut rng);
+        let estimate = monte_carlo_mean(100, |_| unreachable!(), &mut none, &mut rng);
+        assert_eq!(estimate.samples, 0);
+        assert!(estimate.mean.is_nan());
+    }
+}
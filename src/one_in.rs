@@ -0,0 +1,194 @@
+use crate::{FastBernoulli, WideBernoulli};
+use rand::Rng;
+
+impl FastBernoulli {
+    /// Construct a new `FastBernoulli` instance that samples one in `n` events
+    /// on average.
+    ///
+    /// This is the clearest way to configure low rates, such as one in a
+    /// million, where a literal like `0.000001` is easy to get wrong by a
+    /// factor of ten. The instance stores `n` itself, and draws skip counts
+    /// from it with the integer arithmetic of
+    /// [`IntegerBernoulli::from_ratio`][crate::IntegerBernoulli::from_ratio], so
+    /// the rate is never rounded to an `f64`.
+    /// [`probability`][FastBernoulli::probability] reports the `f64` nearest to
+    /// `1 / n`, and a [state][FastBernoulli::state] snapshot records only that,
+    /// so an instance restored from one draws skip counts from the `f64`, as
+    /// [`new`][FastBernoulli::new] does.
+    ///
+    /// Skip counts are still `u32`s, though, clamped to `u32::MAX`, so for `n`
+    /// above about a billion, events are sampled noticeably more often than
+    /// one in `n`, and for `n` much above `u32::MAX`, about one in `2^32`; see
+    /// [`effective_probability`][FastBernoulli::effective_probability]. For
+    /// such rates, use [`WideBernoulli::one_in`] instead.
+    ///
+    /// # Panics
+    ///
+    /// `n` must be non-zero and this method will panic if that is not the case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let one_in_a_million = FastBernoulli::one_in(1_000_000, &mut rng);
+    /// assert_eq!(one_in_a_million.probability(), 1.0 / 1e6);
+    /// ```
+    pub fn one_in<R>(n: u64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let mut bernoulli = FastBernoulli {
+            probability: one_in_probability(n),
+            denominator: n,
+            skip_count: 0,
+            lineage: crate::lineage::Lineage::new(),
+        };
+        bernoulli.reset_skip_count(rng);
+        bernoulli
+    }
+}
+
+impl WideBernoulli {
+    /// Construct a new `WideBernoulli` instance that samples one in `n` events
+    /// on average.
+    ///
+    /// This is [`FastBernoulli::one_in`] with 64-bit skip counts, which keep
+    /// the rate at one in `n` for any `n` up to about `10^18`. As there, skip
+    /// counts are drawn from `n` itself, without rounding it to an `f64`.
+    ///
+    /// # Panics
+    ///
+    /// `n` must be non-zero and this method will panic if that is not the case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::WideBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let one_in_a_trillion = WideBernoulli::one_in(1_000_000_000_000, &mut rng);
+    /// assert_eq!(one_in_a_trillion.probability(), 1.0 / 1e12);
+    /// ```
+    pub fn one_in<R>(n: u64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        WideBernoulli::with_denominator(one_in_probability(n), n, rng)
+    }
+}
+
+/// The `f64` nearest to `1 / n`, for reporting; skip counts are drawn from `n`.
+fn one_in_probability(n: u64) -> f64 {
+    assert!(n != 0, "`n` must be non-zero");
+    1.0 / n as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The mean of many freshly drawn skip counts.
+    fn mean_skip_count(mut draw: impl FnMut() -> f64, draws: u32) -> f64 {
+        (0..draws).map(|_| draw()).sum::<f64>() / f64::from(draws)
+    }
+
+    #[test]
+    fn samples_one_in_n_on_average() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(FastBernoulli::one_in(1, &mut rng).probability(), 1.0);
+        assert_eq!(
+            FastBernoulli::one_in(1 << 40, &mut rng).probability(),
+            2f64.powi(-40)
+        );
+
+        // With probability `1 / n`, skip counts are geometric with mean `n - 1`.
+        let n = 1_000;
+        let mut bernoulli = FastBernoulli::one_in(n, &mut rng);
+        let draws = 10_000;
+        let mean = mean_skip_count(
+            || {
+                bernoulli.reset_skip_count(&mut rng);
+                f64::from(bernoulli.skip_count())
+            },
+            draws,
+        );
+        let expected = (n - 1) as f64;
+        let std_dev = (expected * n as f64).sqrt();
+        assert!(
+            (mean - expected).abs() <= 5.0 * std_dev / f64::from(draws).sqrt(),
+            "mean skip count {mean}, expected ~{expected}"
+        );
+    }
+
+    #[test]
+    fn huge_n_samples_at_the_effective_rate() {
+        let mut rng = rand::thread_rng();
+        let n = 1 << 40;
+        let draws = 10_000;
+        let tolerance = |std_dev: f64| 5.0 * std_dev / f64::from(draws).sqrt();
+
+        // Clamped skip counts sample far more often than one in `n`.
+        let mut bernoulli = FastBernoulli::one_in(n, &mut rng);
+        let effective = bernoulli.effective_probability();
+        assert!(effective > 100.0 / n as f64);
+        let mean = mean_skip_count(
+            || {
+                bernoulli.reset_skip_count(&mut rng);
+                f64::from(bernoulli.skip_count())
+            },
+            draws,
+        );
+        let expected = 1.0 / effective - 1.0;
+        assert!(
+            (mean - expected).abs() <= tolerance(u32::MAX as f64),
+            "mean skip count {mean}, expected ~{expected}"
+        );
+
+        // 64-bit skip counts don't.
+        let mut wide = WideBernoulli::one_in(n, &mut rng);
+        let mean = mean_skip_count(
+            || {
+                wide.reset_skip_count(&mut rng);
+                wide.skip_count() as f64
+            },
+            draws,
+        );
+        let expected = (n - 1) as f64;
+        assert!(
+            (mean - expected).abs() <= tolerance(n as f64),
+            "mean skip count {mean}, expected ~{expected}"
+        );
+    }
+
+    #[test]
+    fn draws_skip_counts_from_the_denominator() {
+        use crate::IntegerBernoulli;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        for n in [2, 3, 10, 1_000_000_007] {
+            for seed in 0..20 {
+                let rng = || StdRng::seed_from_u64(seed);
+                let exact = IntegerBernoulli::from_ratio(1, n, &mut rng()).skip_count();
+                let bernoulli = FastBernoulli::one_in(n, &mut rng());
+                assert_eq!(bernoulli.skip_count(), exact, "one in {n}");
+                let wide = WideBernoulli::one_in(n, &mut rng());
+                assert_eq!(wide.skip_count(), u64::from(exact), "one in {n}");
+            }
+        }
+
+        // A new probability is drawn from as a float again.
+        let mut bernoulli = FastBernoulli::one_in(3, &mut rand::thread_rng());
+        bernoulli.set_probability(0.5, &mut StdRng::seed_from_u64(1));
+        let expected = FastBernoulli::new(0.5, &mut StdRng::seed_from_u64(1));
+        assert_eq!(bernoulli.skip_count(), expected.skip_count());
+    }
+
+    #[test]
+    #[should_panic(expected = "`n` must be non-zero")]
+    fn rejects_zero() {
+        FastBernoulli::one_in(0, &mut rand::thread_rng());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// A sampler that can be paused, while still keeping count of the events that
+/// go unsampled, so that estimates can account for the gap in coverage.
+///
+/// While paused, [`trial`][PausableSampler::trial] never samples, but still
+/// counts each event. Callers that skip trials altogether while paused can
+/// report events in bulk with
+/// [`record_unsampled`][PausableSampler::record_unsampled] instead.
+///
+/// Knowing how many events happened during pauses, downstream estimates can
+/// scale up by the fraction of events that were eligible for sampling,
+/// [`coverage`][PausableSampler::coverage], instead of silently treating the
+/// pauses as zero traffic. [`weight`][PausableSampler::weight] includes that
+/// correction.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::PausableSampler;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sampler = PausableSampler::new(0.01, &mut rng);
+///
+/// for _ in 0..1_000 {
+///     sampler.trial(&mut rng);
+/// }
+///
+/// // Stop sampling during a deploy, but keep counting requests.
+/// sampler.pause();
+/// sampler.record_unsampled(1_000);
+/// sampler.resume();
+///
+/// assert_eq!(sampler.events(), 2_000);
+/// assert_eq!(sampler.coverage(), 0.5);
+/// assert!((sampler.weight() - 200.0).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PausableSampler {
+    bernoulli: FastBernoulli,
+    paused: bool,
+    events: u64,
+    unsampled: u64,
+}
+
+impl PausableSampler {
+    /// Construct a new, unpaused `PausableSampler` that samples events with
+    /// the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        PausableSampler {
+            bernoulli: FastBernoulli::new(probability, rng),
+            paused: false,
+            events: 0,
+            unsampled: 0,
+        }
+    }
+
+    /// Pause sampling.
+    #[inline]
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resume sampling.
+    #[inline]
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Is sampling paused?
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Perform a trial for an event.
+    ///
+    /// Always returns `false` while paused.
+    pub fn trial<R>(&mut self, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        if self.paused {
+            self.record_unsampled(1);
+            return false;
+        }
+        self.events += 1;
+        self.bernoulli.trial(rng)
+    }
+
+    /// Record `n` events that were not eligible for sampling, such as events
+    /// that occurred while paused without a call to `trial`.
+    #[inline]
+    pub fn record_unsampled(&mut self, n: u64) {
+        self.events += n;
+        self.unsampled += n;
+    }
+
+    /// Get the total number of events, whether or not they were eligible for
+    /// sampling.
+    #[inline]
+    pub fn events(&self) -> u64 {
+        self.events
+    }
+
+    /// Get the number of events that were not eligible for sampling.
+    #[inline]
+    pub fn unsampled_events(&self) -> u64 {
+        self.unsampled
+    }
+
+    /// Get the fraction of events that were eligible for sampling.
+    ///
+    /// This is `1.0` before any events have occurred.
+    pub fn coverage(&self) -> f64 {
+        if self.events == 0 {
+            return 1.0;
+        }
+        (self.events - self.unsampled) as f64 / self.events as f64
+    }
+
+    /// Get the number of events each sample stands in for, accounting for
+    /// both the sampling probability and the coverage.
+    ///
+    /// This assumes that the events that were not eligible for sampling
+    /// resemble those that were.
+    #[inline]
+    pub fn weight(&self) -> f64 {
+        1.0 / (self.bernoulli.effective_probability() * self.coverage())
+    }
+
+    /// Get the probability with which eligible events are sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_events_are_counted_but_not_sampled() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = PausableSampler::new(1.0, &mut rng);
+        assert!(sampler.trial(&mut rng));
+
+        sampler.pause();
+        assert!(!sampler.trial(&mut rng));
+        sampler.record_unsampled(2);
+        sampler.resume();
+        assert!(sampler.trial(&mut rng));
+
+        assert_eq!(sampler.events(), 5);
+        assert_eq!(sampler.unsampled_events(), 3);
+        assert_eq!(sampler.coverage(), 0.4);
+        assert_eq!(sampler.weight(), 2.5);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::rate::TokenBucket;
+use crate::time::Instant;
+use crate::{FastBernoulli, Rate, ReportThrottler, Sampler};
+use rand::{Rng, RngCore};
+use std::fmt;
+use std::hash::Hash;
+
+/// A sampler composed of several stages, built with a [`PipelineBuilder`].
+///
+/// An event is sampled only if every stage agrees to sample it. Stages are
+/// consulted in the order they were added, and an event rejected by one stage
+/// is not offered to later ones: a rate limit after a probability stage only
+/// spends its budget on events the probability stage let through, and a
+/// per-key backoff only counts the occurrences it actually saw.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{Pipeline, RateExt, Sampler};
+/// use std::time::Instant;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Sample 1% of errors, back off exponentially on each fingerprint, and
+/// // never send more than 100 reports per second in total.
+/// let mut sampler = Pipeline::builder()
+///     .probability(0.01)
+///     .per_key_backoff()
+///     .rate_limit(100.per_second())
+///     .build(&mut rng);
+///
+/// # fn send_report(_: &str) {}
+/// let fingerprint = "TypeError at app.js:42";
+/// if sampler.sample(&fingerprint, Instant::now(), &mut rng) {
+///     send_report(fingerprint);
+/// }
+/// ```
+pub struct Pipeline<K> {
+    stages: Vec<Stage<K>>,
+}
+
+enum Stage<K> {
+    Bernoulli(FastBernoulli),
+    Backoff(ReportThrottler<K>),
+    RateLimit(TokenBucket),
+    Custom(Box<dyn Sampler<K>>),
+}
+
+impl<K> fmt::Debug for Stage<K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Bernoulli(b) => f.debug_tuple("Bernoulli").field(b).finish(),
+            Stage::Backoff(t) => f.debug_tuple("Backoff").field(t).finish(),
+            Stage::RateLimit(r) => f.debug_tuple("RateLimit").field(r).finish(),
+            Stage::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl<K> fmt::Debug for Pipeline<K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("stages", &self.stages)
+            .finish()
+    }
+}
+
+impl<K> Pipeline<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Start building a new `Pipeline`.
+    #[inline]
+    pub fn builder() -> PipelineBuilder<K> {
+        PipelineBuilder { stages: Vec::new() }
+    }
+
+    /// Get the number of stages in this pipeline.
+    #[inline]
+    pub fn num_stages(&self) -> usize {
+        self.stages.len()
+    }
+}
+
+impl<K> Sampler<K> for Pipeline<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn sample(&mut self, key: &K, now: Instant, rng: &mut dyn RngCore) -> bool {
+        self.stages.iter_mut().all(|stage| match stage {
+            Stage::Bernoulli(b) => b.trial(rng),
+            Stage::Backoff(t) => t.trial(key, rng).is_some(),
+            Stage::RateLimit(r) => r.take(now),
+            Stage::Custom(s) => s.sample(key, now, rng),
+        })
+    }
+}
+
+/// A builder for [`Pipeline`]s.
+///
+/// Each method appends a stage; see [`Pipeline`] for how stages combine.
+pub struct PipelineBuilder<K> {
+    stages: Vec<StageConfig<K>>,
+}
+
+enum StageConfig<K> {
+    Probability(f64),
+    Backoff { backoff: f64, min_probability: f64 },
+    RateLimit(Rate),
+    Custom(Box<dyn Sampler<K>>),
+}
+
+impl<K> fmt::Debug for PipelineBuilder<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipelineBuilder")
+            .field("stages", &self.stages.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K> PipelineBuilder<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Add a stage that samples events with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        self.stages.push(StageConfig::Probability(probability));
+        self
+    }
+
+    /// Add a stage that backs off exponentially on each key, as a
+    /// [`ReportThrottler`] does: the first occurrence of a key is always
+    /// sampled, and each sample halves the probability of sampling that key
+    /// again, down to `0.001`.
+    pub fn per_key_backoff(self) -> Self {
+        self.per_key_backoff_with(0.5, 0.001)
+    }
+
+    /// Like [`per_key_backoff`][PipelineBuilder::per_key_backoff], but with the
+    /// given backoff factor and probability floor.
+    ///
+    /// # Panics
+    ///
+    /// Both `backoff` and `min_probability` must be within the range
+    /// `0.0 <= x <= 1.0` and this method will panic if that is not the case.
+    pub fn per_key_backoff_with(mut self, backoff: f64, min_probability: f64) -> Self {
+        // Validate eagerly, rather than when building.
+        drop(ReportThrottler::<K>::new(backoff, min_probability));
+        self.stages.push(StageConfig::Backoff {
+            backoff,
+            min_probability,
+        });
+        self
+    }
+
+    /// Add a stage that samples at most `rate` events, with bursts of up to one
+    /// period's worth.
+    pub fn rate_limit(mut self, rate: Rate) -> Self {
+        self.stages.push(StageConfig::RateLimit(rate));
+        self
+    }
+
+    /// Add a custom stage.
+    pub fn stage<S>(mut self, sampler: S) -> Self
+    where
+        S: Sampler<K> + 'static,
+    {
+        self.stages.push(StageConfig::Custom(Box::new(sampler)));
+        self
+    }
+
+    /// Build the pipeline.
+    pub fn build<R>(self, rng: &mut R) -> Pipeline<K>
+    where
+        R: Rng + ?Sized,
+    {
+        let stages = self
+            .stages
+            .into_iter()
+            .map(|config| match config {
+                StageConfig::Probability(p) => Stage::Bernoulli(FastBernoulli::new(p, rng)),
+                StageConfig::Backoff {
+                    backoff,
+                    min_probability,
+                } => Stage::Backoff(ReportThrottler::new(backoff, min_probability)),
+                StageConfig::RateLimit(rate) => Stage::RateLimit(TokenBucket::new(rate)),
+                StageConfig::Custom(sampler) => Stage::Custom(sampler),
+            })
+            .collect();
+        Pipeline { stages }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RateExt;
+    use std::time::Duration;
+
+    #[test]
+    fn rate_limit_caps_samples_per_period() {
+        let mut rng = rand::thread_rng();
+        let mut pipeline = Pipeline::<()>::builder()
+            .probability(1.0)
+            .rate_limit(10.per_second())
+            .build(&mut rng);
+
+        let start = Instant::now();
+        let sampled = (0..100)
+            .filter(|_| pipeline.sample(&(), start, &mut rng))
+            .count();
+        assert_eq!(sampled, 10);
+
+        let later = start + Duration::from_millis(500);
+        let sampled = (0..100)
+            .filter(|_| pipeline.sample(&(), later, &mut rng))
+            .count();
+        assert_eq!(sampled, 5);
+    }
+
+    #[test]
+    fn stages_short_circuit() {
+        let mut rng = rand::thread_rng();
+        let mut pipeline = Pipeline::builder()
+            .probability(0.0)
+            .per_key_backoff()
+            .build(&mut rng);
+
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(!pipeline.sample(&"key", now, &mut rng));
+        }
+
+        // The backoff stage never saw the key, so its first occurrence after
+        // the probability stage is always sampled.
+        let mut pipeline = Pipeline::builder()
+            .probability(1.0)
+            .per_key_backoff_with(0.0, 0.0)
+            .build(&mut rng);
+        assert!(pipeline.sample(&"key", now, &mut rng));
+        assert!(!pipeline.sample(&"key", now, &mut rng));
+    }
+}
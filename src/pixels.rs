@@ -0,0 +1,66 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+impl FastBernoulli {
+    /// Iterate over the sampled `(x, y)` coordinates of a `width` by `height`
+    /// grid, in scanline order, in `O(samples)` time rather than
+    /// `O(width * height)`.
+    ///
+    /// Each pixel is sampled independently with this instance's probability.
+    /// This is [`sampled_positions`][FastBernoulli::sampled_positions] over the
+    /// grid's pixels in row-major order, so once the iterator is exhausted,
+    /// `self` is in the same state a trial per pixel would have left it in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid has more than `usize::MAX` pixels.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    ///
+    /// // Compare a tenth of a percent of two 4K frames' pixels.
+    /// let (width, height) = (3840, 2160);
+    /// let mut probe = FastBernoulli::new(0.001, &mut rng);
+    /// for (x, y) in probe.sampled_pixels(width, height, &mut rng) {
+    ///     assert!(x < width && y < height);
+    ///     // Compare the pixels at `(x, y)`...
+    /// }
+    /// ```
+    pub fn sampled_pixels<'a, R>(
+        &'a mut self,
+        width: usize,
+        height: usize,
+        rng: &'a mut R,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a
+    where
+        R: Rng + ?Sized,
+    {
+        let pixels = width
+            .checked_mul(height)
+            .expect("`width * height` must not overflow `usize`");
+        self.sampled_positions(pixels, rng)
+            .map(move |position| (position % width, position / width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_are_in_scanline_order() {
+        let mut rng = rand::thread_rng();
+
+        let mut all = FastBernoulli::new(1.0, &mut rng);
+        let pixels: Vec<(usize, usize)> = all.sampled_pixels(3, 2, &mut rng).collect();
+        assert_eq!(pixels, [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+
+        let mut none = FastBernoulli::new(0.0, &mut rng);
+        assert_eq!(none.sampled_pixels(1000, 1000, &mut rng).count(), 0);
+        assert_eq!(all.sampled_pixels(0, 1000, &mut rng).count(), 0);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::fmt;
+use std::time::Duration;
+
+/// A generator of Poisson arrival times, thinned by Bernoulli trials.
+///
+/// This simulates a stream of events arriving as a Poisson process, and the
+/// subset of them that a Bernoulli sampler would keep, which is useful for
+/// simulating sampled telemetry streams and for load-test generators. Thinning
+/// a Poisson process of rate `λ` with probability `p` gives another Poisson
+/// process, of rate `λ * p`.
+///
+/// The process is either homogeneous, with a constant rate, or inhomogeneous,
+/// with a rate that varies over time according to a user-supplied intensity
+/// function. Inhomogeneous arrivals are generated by the Lewis-Shedler method:
+/// candidates are generated at a maximum rate, and each is kept with
+/// probability `intensity(t) / max_rate`.
+///
+/// Times are measured from the start of the process.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::PoissonThinner;
+/// use std::time::Duration;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // 1,000 events per second, of which 1% are sampled.
+/// let mut process = PoissonThinner::homogeneous(1_000.0, 0.01, &mut rng);
+/// let sampled: Vec<Duration> = process
+///     .arrivals_until(Duration::from_secs(60), &mut rng)
+///     .collect();
+///
+/// // Roughly 600 samples, out of roughly 60,000 events.
+/// assert_eq!(process.retained_count(), sampled.len() as u64);
+/// ```
+pub struct PoissonThinner<F = fn(Duration) -> f64> {
+    max_rate: f64,
+    intensity: Option<F>,
+    bernoulli: FastBernoulli,
+    // Seconds since the start of the process.
+    time: f64,
+    pending: Option<f64>,
+    arrivals: u64,
+    retained: u64,
+}
+
+impl<F> fmt::Debug for PoissonThinner<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoissonThinner")
+            .field("max_rate", &self.max_rate)
+            .field("bernoulli", &self.bernoulli)
+            .field("time", &self.time)
+            .field("arrivals", &self.arrivals)
+            .field("retained", &self.retained)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PoissonThinner {
+    /// Construct a new generator for a homogeneous Poisson process with `rate`
+    /// arrivals per second, of which each is retained with the given
+    /// probability.
+    ///
+    /// # Panics
+    ///
+    /// The rate must be non-negative and finite, and the probability must be
+    /// within the range `0.0 <= probability <= 1.0`, and this method will panic
+    /// if that is not the case.
+    pub fn homogeneous<R>(rate: f64, probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        PoissonThinner::with_intensity(rate, None, probability, rng)
+    }
+}
+
+impl<F> PoissonThinner<F>
+where
+    F: FnMut(Duration) -> f64,
+{
+    /// Construct a new generator for an inhomogeneous Poisson process whose
+    /// rate, in arrivals per second, at time `t` is `intensity(t)`, of which
+    /// each arrival is retained with the given probability.
+    ///
+    /// The intensity must never exceed `max_rate`; it is clamped to it if it
+    /// does.
+    ///
+    /// # Panics
+    ///
+    /// The maximum rate must be non-negative and finite, and the probability
+    /// must be within the range `0.0 <= probability <= 1.0`, and this method
+    /// will panic if that is not the case.
+    pub fn inhomogeneous<R>(max_rate: f64, intensity: F, probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        PoissonThinner::with_intensity(max_rate, Some(intensity), probability, rng)
+    }
+
+    fn with_intensity<R>(max_rate: f64, intensity: Option<F>, probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            max_rate >= 0.0 && max_rate.is_finite(),
+            "`rate` must be non-negative and finite"
+        );
+        PoissonThinner {
+            max_rate,
+            intensity,
+            bernoulli: FastBernoulli::new(probability, rng),
+            time: 0.0,
+            pending: None,
+            arrivals: 0,
+            retained: 0,
+        }
+    }
+
+    /// Get the time of the next retained arrival, or `None` if there is none
+    /// before `horizon`.
+    ///
+    /// Returning `None` doesn't end the process: a later call with a later
+    /// horizon picks up where this one left off.
+    pub fn next_arrival<R>(&mut self, horizon: Duration, rng: &mut R) -> Option<Duration>
+    where
+        R: Rng + ?Sized,
+    {
+        if self.max_rate == 0.0 {
+            return None;
+        }
+        let horizon = horizon.as_secs_f64();
+        loop {
+            let candidate = match self.pending.take() {
+                Some(candidate) => candidate,
+                None => {
+                    // Exponentially distributed gaps between candidates.
+                    let u = 1.0 - rng.gen::<f64>();
+                    self.time -= u.ln() / self.max_rate;
+                    self.time
+                }
+            };
+            if candidate > horizon {
+                self.pending = Some(candidate);
+                return None;
+            }
+
+            let t = Duration::from_secs_f64(candidate);
+            if let Some(intensity) = &mut self.intensity {
+                let accept = intensity(t) / self.max_rate;
+                if !(accept >= 1.0 || rng.gen::<f64>() < accept) {
+                    continue;
+                }
+            }
+
+            self.arrivals += 1;
+            if self.bernoulli.trial(rng) {
+                self.retained += 1;
+                return Some(t);
+            }
+        }
+    }
+
+    /// Iterate over the times of retained arrivals before `horizon`.
+    pub fn arrivals_until<'a, R>(
+        &'a mut self,
+        horizon: Duration,
+        rng: &'a mut R,
+    ) -> impl Iterator<Item = Duration> + 'a
+    where
+        R: Rng + ?Sized,
+    {
+        std::iter::from_fn(move || self.next_arrival(horizon, rng))
+    }
+
+    /// Get the number of arrivals generated so far, before thinning.
+    #[inline]
+    pub fn arrival_count(&self) -> u64 {
+        self.arrivals
+    }
+
+    /// Get the number of arrivals retained so far.
+    #[inline]
+    pub fn retained_count(&self) -> u64 {
+        self.retained
+    }
+
+    /// Get the probability with which arrivals are retained.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thinned_rate() {
+        let mut rng = rand::thread_rng();
+
+        // Rate 1,000/s for the first half of the period, and zero afterwards.
+        let mut process = PoissonThinner::inhomogeneous(
+            1_000.0,
+            |t: Duration| {
+                if t < Duration::from_secs(50) {
+                    1_000.0
+                } else {
+                    0.0
+                }
+            },
+            0.1,
+            &mut rng,
+        );
+        let retained: Vec<_> = process
+            .arrivals_until(Duration::from_secs(100), &mut rng)
+            .collect();
+
+        assert!(retained.iter().all(|&t| t < Duration::from_secs(50)));
+        assert!(retained.windows(2).all(|w| w[0] <= w[1]));
+
+        // 50,000 expected arrivals, and 5,000 expected retained; allow five
+        // standard deviations.
+        let arrivals = process.arrival_count() as f64;
+        assert!((arrivals - 50_000.0).abs() < 5.0 * 50_000_f64.sqrt());
+        let retained = retained.len() as f64;
+        assert!((retained - 5_000.0).abs() < 5.0 * 5_000_f64.sqrt());
+    }
+}
@@ -0,0 +1,206 @@
+use std::error::Error;
+use std::fmt;
+
+/// The privacy cost of one differentially private collection, as an
+/// `(ε, δ)` pair.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct PrivacyCost {
+    /// The collection's `ε`.
+    pub epsilon: f64,
+    /// The collection's `δ`.
+    pub delta: f64,
+}
+
+impl PrivacyCost {
+    /// Construct a new `PrivacyCost`.
+    #[inline]
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        PrivacyCost { epsilon, delta }
+    }
+
+    /// Get the cost of running an `(ε, δ)`-differentially private mechanism on
+    /// a Bernoulli sample of the data, taken with the given probability.
+    ///
+    /// Subsampling amplifies privacy: an individual who probably isn't in the
+    /// sample probably isn't affected by the mechanism. The amplified cost is
+    /// `ε' = ln(1 + p(e^ε - 1))` and `δ' = pδ`.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn subsampled(self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        PrivacyCost {
+            epsilon: (probability * self.epsilon.exp_m1()).ln_1p(),
+            delta: probability * self.delta,
+        }
+    }
+}
+
+/// A privacy budget, tracking the cumulative `(ε, δ)` spent across sampled
+/// differentially private collections.
+///
+/// Each collection's cost is amplified by its sampling probability (see
+/// [`PrivacyCost::subsampled`]) and then added to the total spent, by basic
+/// sequential composition. Collections that would overspend the budget are
+/// refused.
+///
+/// Because sampling rate and privacy cost are coupled, the budget can also
+/// answer the inverse question: how high a sampling probability can a
+/// collection use and still fit in what remains?
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{PrivacyBudget, PrivacyCost};
+///
+/// let mut budget = PrivacyBudget::new(1.0, 1e-6);
+///
+/// // Run an ε = 2 mechanism over a 10% sample of the data.
+/// let cost = budget.charge(PrivacyCost::new(2.0, 0.0), 0.1).unwrap();
+/// assert!(cost.epsilon < 0.5);
+///
+/// // How much can the next ε = 2 collection sample?
+/// let p = budget.max_sampling_probability(2.0);
+/// assert!(0.0 < p && p < 1.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrivacyBudget {
+    epsilon: f64,
+    delta: f64,
+    spent_epsilon: f64,
+    spent_delta: f64,
+    collections: u64,
+}
+
+/// An error returned when a collection would overspend a [`PrivacyBudget`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct BudgetExceeded {
+    /// The collection's amplified cost.
+    pub cost: PrivacyCost,
+    /// What remained of the budget.
+    pub remaining: PrivacyCost,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "privacy budget exceeded: collection costs (ε = {}, δ = {}), \
+             but only (ε = {}, δ = {}) remains",
+            self.cost.epsilon, self.cost.delta, self.remaining.epsilon, self.remaining.delta,
+        )
+    }
+}
+
+impl Error for BudgetExceeded {}
+
+impl PrivacyBudget {
+    /// Construct a new, unspent budget of `(epsilon, delta)`.
+    ///
+    /// # Panics
+    ///
+    /// Both `epsilon` and `delta` must be non-negative and this method will
+    /// panic if that is not the case.
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        assert!(epsilon >= 0.0, "`epsilon` must be non-negative");
+        assert!(delta >= 0.0, "`delta` must be non-negative");
+        PrivacyBudget {
+            epsilon,
+            delta,
+            spent_epsilon: 0.0,
+            spent_delta: 0.0,
+            collections: 0,
+        }
+    }
+
+    /// Charge the budget for a collection that runs a mechanism with the given
+    /// cost over a sample taken with the given probability.
+    ///
+    /// Returns the amplified cost that was charged, or an error, leaving the
+    /// budget unchanged, if it would have been overspent.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn charge(
+        &mut self,
+        mechanism: PrivacyCost,
+        probability: f64,
+    ) -> Result<PrivacyCost, BudgetExceeded> {
+        let cost = mechanism.subsampled(probability);
+        let remaining = self.remaining();
+        if cost.epsilon > remaining.epsilon || cost.delta > remaining.delta {
+            return Err(BudgetExceeded { cost, remaining });
+        }
+        self.spent_epsilon += cost.epsilon;
+        self.spent_delta += cost.delta;
+        self.collections += 1;
+        Ok(cost)
+    }
+
+    /// Get the largest sampling probability with which a collection running
+    /// an `epsilon`-differentially private mechanism fits in the remaining
+    /// `ε` budget.
+    pub fn max_sampling_probability(&self, epsilon: f64) -> f64 {
+        if epsilon <= 0.0 {
+            return 1.0;
+        }
+        // Invert `ln(1 + p(e^ε - 1)) <= remaining`.
+        (self.remaining().epsilon.exp_m1() / epsilon.exp_m1()).min(1.0)
+    }
+
+    /// Get the total spent so far.
+    #[inline]
+    pub fn spent(&self) -> PrivacyCost {
+        PrivacyCost::new(self.spent_epsilon, self.spent_delta)
+    }
+
+    /// Get what remains of the budget.
+    #[inline]
+    pub fn remaining(&self) -> PrivacyCost {
+        PrivacyCost::new(
+            (self.epsilon - self.spent_epsilon).max(0.0),
+            (self.delta - self.spent_delta).max(0.0),
+        )
+    }
+
+    /// Get the number of collections charged so far.
+    #[inline]
+    pub fn collections(&self) -> u64 {
+        self.collections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_to_overspend() {
+        let mut budget = PrivacyBudget::new(1.0, 0.0);
+        let mechanism = PrivacyCost::new(1.0, 0.0);
+
+        // The whole budget affords the mechanism on all of the data.
+        assert_eq!(budget.max_sampling_probability(1.0), 1.0);
+        let half = budget.charge(mechanism, 0.5).unwrap();
+        assert!((half.epsilon - (0.5 * 1_f64.exp_m1()).ln_1p()).abs() < 1e-12);
+
+        // Sampling at the maximum probability uses up the rest of it.
+        let p = budget.max_sampling_probability(1.0);
+        let rest = budget.charge(mechanism, p * (1.0 - 1e-9)).unwrap();
+        assert!((budget.spent().epsilon - 1.0).abs() < 1e-6);
+        assert!(rest.epsilon > 0.0);
+
+        let err = budget.charge(mechanism, 0.01).unwrap_err();
+        assert!(err.cost.epsilon > err.remaining.epsilon);
+        assert_eq!(budget.collections(), 2);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// Randomized response, for collecting boolean answers with local
+/// differential privacy.
+///
+/// Each respondent reports their true answer, but flipped with a configured
+/// probability `q`. Any individual report is deniable, while the proportion of
+/// true answers across many respondents can still be estimated without bias,
+/// with a [`RandomizedResponseEstimator`]. The mechanism is `ε`-differentially
+/// private for `ε = ln((1 - q) / q)`.
+///
+/// Flips are decided by a [`FastBernoulli`], so with small flip
+/// probabilities, most reports don't need a random number at all.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{RandomizedResponse, RandomizedResponseEstimator};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut mechanism = RandomizedResponse::new(0.25, &mut rng);
+/// let mut estimator = RandomizedResponseEstimator::new(mechanism.flip_probability());
+///
+/// // A third of respondents truly answer "yes".
+/// for respondent in 0..30_000 {
+///     let truth = respondent % 3 == 0;
+///     estimator.add(mechanism.respond(truth, &mut rng));
+/// }
+///
+/// let (low, high) = estimator.confidence_interval(5.0);
+/// assert!(low <= 1.0 / 3.0 && 1.0 / 3.0 <= high);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RandomizedResponse {
+    flip: FastBernoulli,
+}
+
+impl RandomizedResponse {
+    /// Construct a new `RandomizedResponse` mechanism that flips answers with
+    /// the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The flip probability must be within the range
+    /// `0.0 <= flip_probability < 0.5` and this method will panic if that is
+    /// not the case.
+    pub fn new<R>(flip_probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        check_flip_probability(flip_probability);
+        RandomizedResponse {
+            flip: FastBernoulli::new(flip_probability, rng),
+        }
+    }
+
+    /// Construct a new `RandomizedResponse` mechanism that is
+    /// `epsilon`-differentially private.
+    ///
+    /// # Panics
+    ///
+    /// Epsilon must be positive and this method will panic if that is not the
+    /// case.
+    pub fn with_epsilon<R>(epsilon: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(epsilon > 0.0, "`epsilon` must be positive");
+        RandomizedResponse::new(1.0 / (1.0 + epsilon.exp()), rng)
+    }
+
+    /// Get the randomized report for a respondent whose true answer is
+    /// `truth`.
+    #[inline]
+    pub fn respond<R>(&mut self, truth: bool, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        truth ^ self.flip.trial(rng)
+    }
+
+    /// Get the probability with which answers are flipped.
+    #[inline]
+    pub fn flip_probability(&self) -> f64 {
+        self.flip.probability()
+    }
+
+    /// Get the `ε` for which this mechanism is `ε`-differentially private.
+    ///
+    /// This is infinite when answers are never flipped.
+    #[inline]
+    pub fn epsilon(&self) -> f64 {
+        let q = self.flip_probability();
+        ((1.0 - q) / q).ln()
+    }
+}
+
+/// An unbiased estimator of the proportion of true answers, from reports made
+/// through [`RandomizedResponse`].
+///
+/// If the true proportion is `π` and answers are flipped with probability
+/// `q`, reports are `true` with probability `λ = π(1 - q) + (1 - π)q`, so
+/// `π = (λ - q) / (1 - 2q)`. Substituting the observed proportion of `true`
+/// reports for `λ` gives an unbiased estimate of `π`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RandomizedResponseEstimator {
+    flip_probability: f64,
+    yes: u64,
+    total: u64,
+}
+
+impl RandomizedResponseEstimator {
+    /// Construct a new, empty estimator for reports flipped with the given
+    /// probability.
+    ///
+    /// # Panics
+    ///
+    /// The flip probability must be within the range
+    /// `0.0 <= flip_probability < 0.5` and this method will panic if that is
+    /// not the case.
+    pub fn new(flip_probability: f64) -> Self {
+        check_flip_probability(flip_probability);
+        RandomizedResponseEstimator {
+            flip_probability,
+            yes: 0,
+            total: 0,
+        }
+    }
+
+    /// Add a report.
+    #[inline]
+    pub fn add(&mut self, report: bool) {
+        self.yes += u64::from(report);
+        self.total += 1;
+    }
+
+    /// Merge another estimator's reports into this one.
+    ///
+    /// # Panics
+    ///
+    /// Both estimators must have the same flip probability, and this method
+    /// will panic if that is not the case.
+    pub fn merge(&mut self, other: &RandomizedResponseEstimator) {
+        assert_eq!(
+            self.flip_probability, other.flip_probability,
+            "cannot merge estimators with different flip probabilities"
+        );
+        self.yes += other.yes;
+        self.total += other.total;
+    }
+
+    /// Get the number of reports added.
+    #[inline]
+    pub fn reports(&self) -> u64 {
+        self.total
+    }
+
+    /// Get the estimated proportion of respondents whose true answer is
+    /// `true`.
+    ///
+    /// The estimate is unbiased, and so may fall slightly outside `0.0..=1.0`.
+    /// It is NaN when there are no reports.
+    pub fn proportion(&self) -> f64 {
+        let q = self.flip_probability;
+        (self.observed() - q) / (1.0 - 2.0 * q)
+    }
+
+    /// Get the estimated number of respondents whose true answer is `true`.
+    #[inline]
+    pub fn count(&self) -> f64 {
+        self.proportion() * self.total as f64
+    }
+
+    /// Get the estimated standard error of the estimated proportion.
+    pub fn standard_error(&self) -> f64 {
+        let lambda = self.observed();
+        let q = self.flip_probability;
+        (lambda * (1.0 - lambda) / self.total as f64).sqrt() / (1.0 - 2.0 * q)
+    }
+
+    /// Get an approximate confidence interval for the proportion, spanning `z`
+    /// standard errors on either side of the estimate.
+    #[inline]
+    pub fn confidence_interval(&self, z: f64) -> (f64, f64) {
+        let proportion = self.proportion();
+        let margin = z * self.standard_error();
+        (proportion - margin, proportion + margin)
+    }
+
+    #[inline]
+    fn observed(&self) -> f64 {
+        self.yes as f64 / self.total as f64
+    }
+}
+
+fn check_flip_probability(flip_probability: f64) {
+    assert!(
+        (0.0..0.5).contains(&flip_probability),
+        "`flip_probability` must be in the range `0.0 <= flip_probability < 0.5`"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epsilon_round_trips() {
+        let mut rng = rand::thread_rng();
+        let mechanism = RandomizedResponse::with_epsilon(1.5, &mut rng);
+        assert!((mechanism.epsilon() - 1.5).abs() < 1e-12);
+
+        let mut truthful = RandomizedResponse::new(0.0, &mut rng);
+        assert!(truthful.respond(true, &mut rng));
+        assert_eq!(truthful.epsilon(), f64::INFINITY);
+    }
+}
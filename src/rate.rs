@@ -0,0 +1,184 @@
+use crate::time::Instant;
+use std::time::Duration;
+
+/// A rate of events: at most `count` per `period`.
+///
+/// Usually constructed with the [`RateExt`] methods, as in
+/// `100.per_second()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    count: u32,
+    period: Duration,
+}
+
+impl Rate {
+    /// Construct a new rate of `count` events per `period`.
+    ///
+    /// # Panics
+    ///
+    /// The period must be non-zero and this method will panic if that is not
+    /// the case.
+    pub fn new(count: u32, period: Duration) -> Self {
+        assert!(!period.is_zero(), "`period` must be non-zero");
+        Rate { count, period }
+    }
+
+    /// Get the number of events allowed per period.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Get the period.
+    #[inline]
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+/// Extension methods for constructing [`Rate`]s from counts.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::RateExt;
+///
+/// let rate = 100.per_second();
+/// assert_eq!(rate.count(), 100);
+/// ```
+pub trait RateExt {
+    /// This many events per second.
+    fn per_second(self) -> Rate;
+
+    /// This many events per minute.
+    fn per_minute(self) -> Rate;
+
+    /// This many events per `period`.
+    fn per(self, period: Duration) -> Rate;
+}
+
+impl RateExt for u32 {
+    #[inline]
+    fn per_second(self) -> Rate {
+        Rate::new(self, Duration::from_secs(1))
+    }
+
+    #[inline]
+    fn per_minute(self) -> Rate {
+        Rate::new(self, Duration::from_secs(60))
+    }
+
+    #[inline]
+    fn per(self, period: Duration) -> Rate {
+        Rate::new(self, period)
+    }
+}
+
+/// A token bucket enforcing a [`Rate`], with bursts of up to one period's
+/// worth.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens_per_nano: f64,
+    tokens: f64,
+    last: Option<Instant>,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(rate: Rate) -> Self {
+        let capacity = f64::from(rate.count);
+        TokenBucket {
+            capacity,
+            tokens_per_nano: capacity / rate.period.as_nanos() as f64,
+            tokens: capacity,
+            last: None,
+        }
+    }
+
+    /// Add the tokens accrued between the last refill and `now`.
+    pub(crate) fn refill(&mut self, now: Instant) {
+        if let Some(last) = self.last {
+            let elapsed = now.saturating_duration_since(last).as_nanos() as f64;
+            self.tokens = (self.tokens + elapsed * self.tokens_per_nano).min(self.capacity);
+        }
+        self.last = Some(match self.last {
+            Some(last) if last > now => last,
+            _ => now,
+        });
+    }
+
+    /// Is there a whole token available, as of the last refill?
+    #[inline]
+    pub(crate) fn has_token(&self) -> bool {
+        self.tokens >= 1.0
+    }
+
+    /// Spend a token, which must be available.
+    #[inline]
+    pub(crate) fn spend(&mut self) {
+        debug_assert!(self.has_token());
+        self.tokens -= 1.0;
+        if !self.has_token() {
+            trace_sampler!(
+                debug,
+                tokens_per_second = self.tokens_per_nano * 1e9,
+                "quota exhausted"
+            );
+        }
+    }
+
+    /// Refill, then spend a token if one is available.
+    pub(crate) fn take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.has_token() {
+            self.spend();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_constructors() {
+        assert_eq!(5.per_second(), Rate::new(5, Duration::from_secs(1)));
+        assert_eq!(5.per_minute().period(), Duration::from_secs(60));
+        let rate = 7.per(Duration::from_millis(10));
+        assert_eq!(
+            (rate.count(), rate.period()),
+            (7, Duration::from_millis(10))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "`period` must be non-zero")]
+    fn rejects_zero_period() {
+        3.per(Duration::ZERO);
+    }
+
+    #[test]
+    fn bucket_refills_at_the_rate_up_to_one_period() {
+        let mut bucket = TokenBucket::new(4.per_second());
+        let start = Instant::now();
+
+        // Starts full, with one period's worth of tokens.
+        assert_eq!((0..10).filter(|_| bucket.take(start)).count(), 4);
+
+        // A quarter of a second accrues one token.
+        let later = start + Duration::from_millis(250);
+        assert_eq!((0..10).filter(|_| bucket.take(later)).count(), 1);
+
+        // Going back in time doesn't accrue tokens, or lose the ones accrued.
+        assert!(!bucket.take(start));
+        let later = later + Duration::from_millis(500);
+        assert_eq!((0..10).filter(|_| bucket.take(later)).count(), 2);
+
+        // Idling accrues at most one period's worth.
+        let much_later = later + Duration::from_secs(3600);
+        assert_eq!((0..10).filter(|_| bucket.take(much_later)).count(), 4);
+    }
+}
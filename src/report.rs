@@ -0,0 +1,198 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Crash- and error-report throttling keyed by fingerprint, with per-key
+/// exponential backoff.
+///
+/// This implements the usual crash-reporting policy:
+///
+/// * The first occurrence of each fingerprint is always reported.
+///
+/// * After each report, the probability of reporting that fingerprint's later
+///   occurrences is multiplied by the backoff factor, down to a configured
+///   floor. Noisy errors quickly fade to a trickle, while new errors are
+///   always seen.
+///
+/// * Each report carries the number of occurrences that were suppressed since
+///   the previous report of the same fingerprint, so that totals can still be
+///   reconstructed.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::ReportThrottler;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Halve the reporting probability after every report, down to 0.1%.
+/// let mut throttler = ReportThrottler::new(0.5, 0.001);
+///
+/// # fn send_report(_: &str, _: u64) {}
+/// let fingerprint = "TypeError at app.js:42";
+/// if let Some(report) = throttler.trial(&fingerprint, &mut rng) {
+///     send_report(fingerprint, report.suppressed);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReportThrottler<K> {
+    backoff: f64,
+    min_probability: f64,
+    keys: HashMap<K, KeyState>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct KeyState {
+    bernoulli: FastBernoulli,
+    suppressed: u64,
+    reports: u64,
+}
+
+/// A decision to send a report, returned by [`ReportThrottler::trial`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct Report {
+    /// The number of occurrences of this fingerprint that were suppressed since
+    /// its previous report.
+    pub suppressed: u64,
+
+    /// The number of reports for this fingerprint so far, including this one.
+    pub reports: u64,
+
+    /// The probability with which this occurrence was reported.
+    pub probability: f64,
+}
+
+impl<K> ReportThrottler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Construct a new `ReportThrottler`.
+    ///
+    /// After each report of a fingerprint, its reporting probability is
+    /// multiplied by `backoff`, but never drops below `min_probability`.
+    ///
+    /// # Panics
+    ///
+    /// Both `backoff` and `min_probability` must be within the range
+    /// `0.0 <= x <= 1.0` and this method will panic if that is not the case.
+    pub fn new(backoff: f64, min_probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&backoff),
+            "`backoff` must be in the range `0.0 <= backoff <= 1.0`"
+        );
+        assert!(
+            (0.0..=1.0).contains(&min_probability),
+            "`min_probability` must be in the range `0.0 <= min_probability <= 1.0`"
+        );
+        ReportThrottler {
+            backoff,
+            min_probability,
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Perform a trial for an occurrence of the error with the given
+    /// fingerprint.
+    ///
+    /// Returns the report to send, or `None` if this occurrence should be
+    /// suppressed.
+    pub fn trial<R>(&mut self, fingerprint: &K, rng: &mut R) -> Option<Report>
+    where
+        R: Rng + ?Sized,
+    {
+        let next_probability = |p: f64| (p * self.backoff).max(self.min_probability);
+        let state = match self.keys.get_mut(fingerprint) {
+            Some(state) => state,
+            None => {
+                let first = Report {
+                    suppressed: 0,
+                    reports: 1,
+                    probability: 1.0,
+                };
+                let state = KeyState {
+                    bernoulli: FastBernoulli::new(next_probability(1.0), rng),
+                    suppressed: 0,
+                    reports: 1,
+                };
+                self.keys.insert(fingerprint.clone(), state);
+                return Some(first);
+            }
+        };
+
+        if !state.bernoulli.trial(rng) {
+            state.suppressed += 1;
+            return None;
+        }
+
+        let probability = state.bernoulli.probability();
+        state.reports += 1;
+        let report = Report {
+            suppressed: std::mem::take(&mut state.suppressed),
+            reports: state.reports,
+            probability: state.bernoulli.effective_probability(),
+        };
+
+        let next = next_probability(probability);
+        if next != probability {
+            state.bernoulli = FastBernoulli::new(next, rng);
+        }
+        Some(report)
+    }
+
+    /// Get the probability with which the next occurrence of `fingerprint`
+    /// would be reported.
+    pub fn probability(&self, fingerprint: &K) -> f64 {
+        self.keys
+            .get(fingerprint)
+            .map_or(1.0, |s| s.bernoulli.probability())
+    }
+
+    /// Get the number of occurrences of `fingerprint` suppressed since its last
+    /// report.
+    pub fn suppressed(&self, fingerprint: &K) -> u64 {
+        self.keys.get(fingerprint).map_or(0, |s| s.suppressed)
+    }
+
+    /// Forget everything about `fingerprint`, so that its next occurrence is
+    /// reported as if it were the first.
+    pub fn forget(&mut self, fingerprint: &K) {
+        self.keys.remove(fingerprint);
+    }
+
+    /// Get the number of fingerprints being tracked.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Are no fingerprints being tracked?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_occurrence_always_reported() {
+        let mut rng = rand::thread_rng();
+        let mut throttler = ReportThrottler::new(0.0, 0.0);
+
+        let report = throttler.trial(&"boom", &mut rng).unwrap();
+        assert_eq!(report.suppressed, 0);
+        assert_eq!(report.reports, 1);
+
+        // With no backoff floor, nothing after the first is reported.
+        for _ in 0..100 {
+            assert!(throttler.trial(&"boom", &mut rng).is_none());
+        }
+        assert_eq!(throttler.suppressed(&"boom"), 100);
+        assert_eq!(throttler.probability(&"boom"), 0.0);
+        assert!(throttler.trial(&"bang", &mut rng).is_some());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::time::Instant;
+use crate::FastBernoulli;
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Why an event was included in the sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Inclusion {
+    /// The event was chosen by an ordinary Bernoulli trial, and is a fair
+    /// sample at the configured probability.
+    Sampled,
+
+    /// The event was included regardless of probability, to satisfy some
+    /// guarantee. Estimators that assume Bernoulli sampling should exclude or
+    /// down-weight forced samples.
+    Forced,
+}
+
+/// Sampling with a minimum-representation guarantee: every category observed
+/// in a time window is sampled at least once in that window.
+///
+/// Events are sampled with the configured probability as usual. Additionally,
+/// the first event of each category in each window is forced into the sample
+/// if that category has not already been sampled in the window, so that
+/// rare categories are never completely invisible.
+///
+/// Windows are fixed-length and tumbling, and start at the time of the first
+/// trial.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{Inclusion, RepresentationSampler};
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sampler = RepresentationSampler::new(0.001, Duration::from_secs(60), &mut rng);
+///
+/// let now = Instant::now();
+/// match sampler.trial(&"rare-error-kind", now, &mut rng) {
+///     Some(Inclusion::Sampled) => { /* a fair sample */ }
+///     Some(Inclusion::Forced) => { /* record it, but flag it for estimators */ }
+///     None => { /* not sampled */ }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RepresentationSampler<K> {
+    bernoulli: FastBernoulli,
+    window: Duration,
+    start: Option<Instant>,
+    // The index of the most recent window in which each category was sampled.
+    last_sampled: HashMap<K, u64>,
+}
+
+impl<K> RepresentationSampler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Construct a new `RepresentationSampler` that samples events with the
+    /// given probability, and guarantees at least one sample per category per
+    /// `window`.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0`,
+    /// and the window must be non-zero. This method will panic if that is not
+    /// the case.
+    pub fn new<R>(probability: f64, window: Duration, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(!window.is_zero(), "`window` must be non-zero");
+        RepresentationSampler {
+            bernoulli: FastBernoulli::new(probability, rng),
+            window,
+            start: None,
+            last_sampled: HashMap::new(),
+        }
+    }
+
+    /// Perform a trial for an event in `category` that occurred at `now`.
+    ///
+    /// Returns how the event was included in the sample, or `None` if it was
+    /// not.
+    pub fn trial<R>(&mut self, category: &K, now: Instant, rng: &mut R) -> Option<Inclusion>
+    where
+        R: Rng + ?Sized,
+    {
+        let window = self.window_index(now);
+        let inclusion = if self.bernoulli.trial(rng) {
+            Inclusion::Sampled
+        } else if self.last_sampled.get(category) != Some(&window) {
+            Inclusion::Forced
+        } else {
+            return None;
+        };
+
+        match self.last_sampled.get_mut(category) {
+            Some(w) => *w = window,
+            None => {
+                self.last_sampled.insert(category.clone(), window);
+            }
+        }
+        Some(inclusion)
+    }
+
+    /// Forget every category that has not been sampled in the window
+    /// containing `now`.
+    ///
+    /// Categories are otherwise remembered forever; call this periodically
+    /// if the set of categories is unbounded.
+    pub fn evict_stale(&mut self, now: Instant) {
+        let window = self.window_index(now);
+        self.last_sampled.retain(|_, &mut w| w == window);
+    }
+
+    /// Get the probability with which events are sampled, not counting forced
+    /// samples.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Get the length of each window.
+    #[inline]
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    fn window_index(&mut self, now: Instant) -> u64 {
+        let start = *self.start.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(start);
+        (elapsed.as_nanos() / self.window.as_nanos()) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_forced_sample_per_category_per_window() {
+        let mut rng = rand::thread_rng();
+        let window = Duration::from_secs(1);
+        let mut sampler = RepresentationSampler::new(0.0, window, &mut rng);
+        let start = Instant::now();
+
+        assert_eq!(
+            sampler.trial(&'a', start, &mut rng),
+            Some(Inclusion::Forced)
+        );
+        assert_eq!(sampler.trial(&'a', start, &mut rng), None);
+        assert_eq!(
+            sampler.trial(&'b', start, &mut rng),
+            Some(Inclusion::Forced)
+        );
+        assert_eq!(
+            sampler.trial(&'a', start + window, &mut rng),
+            Some(Inclusion::Forced)
+        );
+        assert_eq!(sampler.trial(&'a', start + window, &mut rng), None);
+
+        sampler.evict_stale(start + window);
+        assert_eq!(sampler.last_sampled.len(), 1);
+    }
+}
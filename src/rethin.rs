@@ -0,0 +1,162 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// Downsample an existing sample from probability `p1` to a lower effective
+/// probability `p2`.
+///
+/// Items that were already sampled with probability `p1` are each kept with
+/// probability `p2 / p1`, so that a surviving item's overall inclusion
+/// probability is exactly `p2`. Surviving items come with their corrected
+/// combined weight, `1 / p2`, so that multi-stage pipelines stay unbiased.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::Rethinner;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // These records were sampled at 1%, but we only want to keep 0.1%.
+/// let mut rethinner = Rethinner::new(0.01, 0.001, &mut rng);
+///
+/// # let records = vec![(); 100];
+/// for record in records {
+///     if let Some(weight) = rethinner.trial(&mut rng) {
+///         // Each surviving record stands in for `weight == 1000.0` events.
+///         # let _ = (record, weight);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Rethinner {
+    bernoulli: FastBernoulli,
+    from: f64,
+    to: f64,
+}
+
+impl Rethinner {
+    /// Construct a new `Rethinner` that downsamples items sampled with
+    /// probability `from` to an effective probability of `to`.
+    ///
+    /// # Panics
+    ///
+    /// Both probabilities must be within the range `0.0 <= probability <= 1.0`,
+    /// `from` must be non-zero, and `to` must not be greater than `from`. This
+    /// method will panic if that is not the case.
+    pub fn new<R>(from: f64, to: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            0.0 < from && from <= 1.0,
+            "`from` must be in the range `0.0 < from <= 1.0`"
+        );
+        assert!(
+            (0.0..=from).contains(&to),
+            "`to` must be in the range `0.0 <= to <= from`"
+        );
+        Rethinner {
+            // Clamp in case floating-point division nudges the ratio above one.
+            bernoulli: FastBernoulli::new((to / from).min(1.0), rng),
+            from,
+            to,
+        }
+    }
+
+    /// Perform the secondary trial for one item of the existing sample.
+    ///
+    /// Returns the item's combined weight if it survives, or `None` if it
+    /// should be dropped.
+    #[inline]
+    pub fn trial<R>(&mut self, rng: &mut R) -> Option<f64>
+    where
+        R: Rng + ?Sized,
+    {
+        if self.bernoulli.trial(rng) {
+            Some(self.combined_weight())
+        } else {
+            None
+        }
+    }
+
+    /// Get the weight of each surviving item: the reciprocal of its overall
+    /// inclusion probability, using the secondary trial's
+    /// [effective][FastBernoulli::effective_probability] probability.
+    #[inline]
+    pub fn combined_weight(&self) -> f64 {
+        1.0 / (self.from * self.bernoulli.effective_probability())
+    }
+
+    /// Get the probability with which the existing sample was taken.
+    #[inline]
+    pub fn from_probability(&self) -> f64 {
+        self.from
+    }
+
+    /// Get the effective probability after downsampling.
+    #[inline]
+    pub fn to_probability(&self) -> f64 {
+        self.to
+    }
+
+    /// Get the probability with which each item of the existing sample is
+    /// kept.
+    #[inline]
+    pub fn retention_probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn survivors_have_the_combined_probability() {
+        let mut rng = rand::thread_rng();
+        let mut rethinner = Rethinner::new(0.5, 0.1, &mut rng);
+        assert!((rethinner.retention_probability() - 0.2).abs() < 1e-12);
+
+        let n = 100_000;
+        let mut survivors = 0;
+        for _ in 0..n {
+            if let Some(weight) = rethinner.trial(&mut rng) {
+                assert_eq!(weight, 10.0);
+                survivors += 1;
+            }
+        }
+
+        // The existing sample is rethinned at `0.1 / 0.5`.
+        let q = 0.2;
+        let expected = q * f64::from(n);
+        assert!((f64::from(survivors) - expected).abs() <= 5.0 * (expected * (1.0 - q)).sqrt());
+    }
+
+    #[test]
+    fn rethinning_to_the_same_probability_keeps_everything() {
+        let mut rng = rand::thread_rng();
+        let p = 0.3;
+        let mut rethinner = Rethinner::new(p, p, &mut rng);
+        assert_eq!(rethinner.retention_probability(), 1.0);
+        assert!((0..1000).all(|_| rethinner.trial(&mut rng).is_some()));
+
+        let mut rethinner = Rethinner::new(p, 0.0, &mut rng);
+        assert!((0..1000).all(|_| rethinner.trial(&mut rng).is_none()));
+    }
+
+    #[test]
+    fn tiny_rates_are_weighted_by_the_effective_probability() {
+        let mut rng = rand::thread_rng();
+        let rethinner = Rethinner::new(0.5, 1e-12, &mut rng);
+        let effective = rethinner.bernoulli.effective_probability();
+        assert!(effective > 100.0 * 2e-12);
+        assert_eq!(rethinner.combined_weight(), 1.0 / (0.5 * effective));
+        assert!(rethinner.combined_weight() < 1e12 / 100.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "`to` must be in the range `0.0 <= to <= from`")]
+    fn cannot_rethin_upwards() {
+        Rethinner::new(0.1, 0.2, &mut rand::thread_rng());
+    }
+}
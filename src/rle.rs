@@ -0,0 +1,136 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// A run of identical decisions in a run-length-encoded decision stream.
+///
+/// See [`FastBernoulli::fill_runs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecisionRun {
+    /// The decision made for every event in the run.
+    pub sampled: bool,
+    /// The number of events in the run.
+    pub len: u64,
+}
+
+impl FastBernoulli {
+    /// Make the decisions for the next `events` events, writing them into
+    /// `out` as runs of identical decisions: `false × 1234, true × 1, ...`.
+    ///
+    /// This advances `self` exactly as calling [`trial`][FastBernoulli::trial]
+    /// once per event would, but takes time proportional to the number of runs
+    /// rather than the number of events. The run-length-encoded stream is far
+    /// cheaper to log, compress, and replay than one boolean per event.
+    ///
+    /// Stops early if `out` fills up. Returns the number of runs written and
+    /// the number of events they cover; call again with the remaining events
+    /// to continue. Adjacent runs within one call never have the same
+    /// decision, but the last run of one call and the first of the next may.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::{DecisionRun, FastBernoulli};
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let mut bernoulli = FastBernoulli::new(0.001, &mut rng);
+    ///
+    /// let mut runs = [DecisionRun { sampled: false, len: 0 }; 64];
+    /// let (written, events) = bernoulli.fill_runs(1_000_000, &mut runs, &mut rng);
+    ///
+    /// let covered: u64 = runs[..written].iter().map(|run| run.len).sum();
+    /// assert_eq!(covered, events);
+    /// ```
+    pub fn fill_runs<R>(
+        &mut self,
+        events: u64,
+        out: &mut [DecisionRun],
+        rng: &mut R,
+    ) -> (usize, u64)
+    where
+        R: Rng + ?Sized,
+    {
+        let mut written = 0;
+        let mut consumed = 0;
+        while consumed < events && written < out.len() {
+            let remaining = events - consumed;
+            let len = if self.probability == 0.0 {
+                // Every decision is `false`, but the skip count still counts
+                // down and resets just as it would in `trial`.
+                let mut n = remaining;
+                while n > 0 {
+                    if self.skip_count == 0 {
+                        self.skip_count = u32::MAX;
+                        n -= 1;
+                    } else {
+                        let d = u64::from(self.skip_count).min(n);
+                        self.skip_count -= d as u32;
+                        n -= d;
+                    }
+                }
+                out[written] = DecisionRun {
+                    sampled: false,
+                    len: remaining,
+                };
+                remaining
+            } else if self.skip_count > 0 {
+                let len = u64::from(self.skip_count).min(remaining);
+                self.skip_count -= len as u32;
+                out[written] = DecisionRun {
+                    sampled: false,
+                    len,
+                };
+                len
+            } else {
+                let mut len = 0;
+                while len < remaining && self.skip_count == 0 {
+                    self.reset_skip_count(rng);
+                    len += 1;
+                }
+                out[written] = DecisionRun { sampled: true, len };
+                len
+            };
+            written += 1;
+            consumed += len;
+        }
+        (written, consumed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn runs_match_individual_trials() {
+        for &probability in &[0.0_f64, 0.01, 0.5, 0.9, 1.0] {
+            let mut rng = StdRng::seed_from_u64(probability.to_bits());
+            let mut by_runs = FastBernoulli::new(probability, &mut rng);
+            // A deliberate copy, with the same state but its own identity.
+            let mut by_trials = FastBernoulli::from_state(by_runs.state());
+            let mut trials_rng = rng.clone();
+
+            let mut decisions = Vec::new();
+            let mut runs = [DecisionRun {
+                sampled: false,
+                len: 0,
+            }; 7];
+            let mut remaining = 10_000;
+            while remaining > 0 {
+                let (written, events) = by_runs.fill_runs(remaining, &mut runs, &mut rng);
+                for run in &runs[..written] {
+                    decisions.extend((0..run.len).map(|_| run.sampled));
+                }
+                remaining -= events;
+            }
+
+            let expected: Vec<bool> = (0..10_000)
+                .map(|_| by_trials.trial(&mut trials_rng))
+                .collect();
+            assert_eq!(decisions, expected, "probability = {}", probability);
+            assert_eq!(by_runs.skip_count(), by_trials.skip_count());
+            assert_eq!(crate::lineage::duplicates_on_this_thread(), 0);
+        }
+    }
+}
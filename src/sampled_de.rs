@@ -0,0 +1,358 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use serde::de::{
+    DeserializeSeed, Deserializer, EnumAccess, IgnoredAny, MapAccess, SeqAccess, Visitor,
+};
+use std::fmt;
+
+/// A `serde` [`Deserializer`] adapter that only deserializes a sampled subset
+/// of the elements of the first sequence or map it encounters.
+///
+/// Each element of that sequence, or entry of that map, is sampled with the
+/// given [`FastBernoulli`], and the rest are skipped over as
+/// [`IgnoredAny`]s, without being deserialized into their target types. To
+/// whatever is being deserialized, the sequence or map simply looks shorter.
+/// This allows inspecting a multi-gigabyte JSON or CBOR dump by sampling it
+/// while parsing, without materializing it.
+///
+/// The sampled sequence or map is the outermost one: at the top level, or
+/// inside `Option`s and newtype structs. Sequences and maps nested inside its
+/// elements are deserialized in full.
+///
+/// Requires the `serde` feature.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{FastBernoulli, SampledDeserializer};
+/// use serde::de::value::{Error, SeqDeserializer};
+/// use serde::Deserialize;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut bernoulli = FastBernoulli::new(0.01, &mut rng);
+///
+/// // With `serde_json`, this would be a `&mut serde_json::Deserializer`
+/// // reading a huge file.
+/// let records = SeqDeserializer::<_, Error>::new(0..1_000_000_u32);
+///
+/// let sample =
+///     Vec::<u32>::deserialize(SampledDeserializer::new(records, &mut bernoulli, &mut rng))
+///         .unwrap();
+/// assert!(sample.len() < 1_000_000);
+/// ```
+pub struct SampledDeserializer<'a, D, R: ?Sized> {
+    deserializer: D,
+    sampling: Sampling<'a, R>,
+}
+
+impl<D, R: ?Sized> fmt::Debug for SampledDeserializer<'_, D, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SampledDeserializer")
+            .field("bernoulli", &self.sampling.bernoulli)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, D, R> SampledDeserializer<'a, D, R>
+where
+    R: Rng + ?Sized,
+{
+    /// Construct a new `SampledDeserializer` wrapping `deserializer`, that
+    /// samples elements with `bernoulli`.
+    #[inline]
+    pub fn new(deserializer: D, bernoulli: &'a mut FastBernoulli, rng: &'a mut R) -> Self {
+        SampledDeserializer {
+            deserializer,
+            sampling: Sampling { bernoulli, rng },
+        }
+    }
+}
+
+struct Sampling<'a, R: ?Sized> {
+    bernoulli: &'a mut FastBernoulli,
+    rng: &'a mut R,
+}
+
+impl<R> Sampling<'_, R>
+where
+    R: Rng + ?Sized,
+{
+    #[inline]
+    fn trial(&mut self) -> bool {
+        self.bernoulli.trial(self.rng)
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserializer.$method($($arg,)* SampledVisitor {
+                    visitor,
+                    sampling: self.sampling,
+                })
+            }
+        )*
+    };
+}
+
+impl<'de, D, R> Deserializer<'de> for SampledDeserializer<'_, D, R>
+where
+    D: Deserializer<'de>,
+    R: Rng + ?Sized,
+{
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        self.deserializer.is_human_readable()
+    }
+}
+
+/// Wraps the visitor passed to a `SampledDeserializer`, to intercept the
+/// sequence or map it is given.
+struct SampledVisitor<'a, V, R: ?Sized> {
+    visitor: V,
+    sampling: Sampling<'a, R>,
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            #[inline]
+            fn $method<E>(self, v: $ty) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                self.visitor.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V, R> Visitor<'de> for SampledVisitor<'_, V, R>
+where
+    V: Visitor<'de>,
+    R: Rng + ?Sized,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.visitor.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_str(&str);
+        visit_borrowed_str(&'de str);
+        visit_string(String);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+        visit_byte_buf(Vec<u8>);
+    }
+
+    #[inline]
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.visitor.visit_none()
+    }
+
+    #[inline]
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.visitor.visit_some(SampledDeserializer {
+            deserializer,
+            sampling: self.sampling,
+        })
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.visitor.visit_newtype_struct(SampledDeserializer {
+            deserializer,
+            sampling: self.sampling,
+        })
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.visitor.visit_seq(SampledAccess {
+            access: seq,
+            sampling: self.sampling,
+        })
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.visitor.visit_map(SampledAccess {
+            access: map,
+            sampling: self.sampling,
+        })
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        self.visitor.visit_enum(data)
+    }
+}
+
+/// Wraps the sampled sequence or map, skipping unsampled elements.
+struct SampledAccess<'a, A, R: ?Sized> {
+    access: A,
+    sampling: Sampling<'a, R>,
+}
+
+impl<'de, A, R> SeqAccess<'de> for SampledAccess<'_, A, R>
+where
+    A: SeqAccess<'de>,
+    R: Rng + ?Sized,
+{
+    type Error = A::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        while !self.sampling.trial() {
+            if self.access.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(None);
+            }
+        }
+        self.access.next_element_seed(seed)
+    }
+}
+
+impl<'de, A, R> MapAccess<'de> for SampledAccess<'_, A, R>
+where
+    A: MapAccess<'de>,
+    R: Rng + ?Sized,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        while !self.sampling.trial() {
+            if self
+                .access
+                .next_entry::<IgnoredAny, IgnoredAny>()?
+                .is_none()
+            {
+                return Ok(None);
+            }
+        }
+        self.access.next_key_seed(seed)
+    }
+
+    #[inline]
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.access.next_value_seed(seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn samples_outermost_sequence_or_map() {
+        let mut rng = rand::thread_rng();
+
+        let mut all = FastBernoulli::new(1.0, &mut rng);
+        let seq = SeqDeserializer::<_, Error>::new(0..10_u32);
+        let v = Vec::<u32>::deserialize(SampledDeserializer::new(seq, &mut all, &mut rng));
+        assert_eq!(v.unwrap(), (0..10).collect::<Vec<_>>());
+
+        let mut none = FastBernoulli::new(0.0, &mut rng);
+        let map = MapDeserializer::<_, Error>::new((0..10_u32).map(|i| (i, i)));
+        let m =
+            BTreeMap::<u32, u32>::deserialize(SampledDeserializer::new(map, &mut none, &mut rng));
+        assert!(m.unwrap().is_empty());
+
+        let n = 10_000;
+        let mut half = FastBernoulli::new(0.5, &mut rng);
+        let map = MapDeserializer::<_, Error>::new((0..n).map(|i| (i, i)));
+        let m =
+            BTreeMap::<u32, u32>::deserialize(SampledDeserializer::new(map, &mut half, &mut rng))
+                .unwrap();
+        assert!(m.iter().all(|(k, v)| k == v));
+        let expected = 0.5 * f64::from(n);
+        assert!((m.len() as f64 - expected).abs() <= 5.0 * (expected * 0.5).sqrt());
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{FastBernoulli, HorvitzThompson};
+use rand::Rng;
+
+/// A vector that keeps only a Bernoulli-sampled subset of the items pushed
+/// into it, while counting everything that was offered.
+///
+/// This packages the common "keep a thin slice of everything" pattern: push
+/// every item, and the vector decides which ones to keep. Each kept item
+/// remembers its inclusion probability, so that the vector can produce
+/// unbiased estimates about the full stream of offered items.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::SampledVec;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut requests = SampledVec::new(0.01, &mut rng);
+///
+/// for status in (0..100_000).map(|i| if i % 10 == 0 { 500 } else { 200 }) {
+///     requests.push(status, &mut rng);
+/// }
+///
+/// assert_eq!(requests.offered(), 100_000);
+/// assert!(requests.len() < 2_000);
+///
+/// // Roughly 10,000 of the offered requests were errors.
+/// let errors = requests.estimate_count(|&status| status == 500);
+/// # let _ = errors;
+/// ```
+#[derive(Debug, Clone)]
+pub struct SampledVec<T> {
+    bernoulli: FastBernoulli,
+    items: Vec<T>,
+    probabilities: Vec<f64>,
+    offered: u64,
+    offered_size: u64,
+}
+
+impl<T> SampledVec<T> {
+    /// Construct a new, empty `SampledVec` that keeps items with the given
+    /// probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        SampledVec {
+            bernoulli: FastBernoulli::new(probability, rng),
+            items: Vec::new(),
+            probabilities: Vec::new(),
+            offered: 0,
+            offered_size: 0,
+        }
+    }
+
+    /// Offer an item, keeping it if a Bernoulli trial says to.
+    ///
+    /// Returns whether the item was kept.
+    pub fn push<R>(&mut self, item: T, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.offered += 1;
+        self.offered_size += 1;
+        if !self.bernoulli.trial(rng) {
+            return false;
+        }
+        self.keep(item, self.bernoulli.effective_probability());
+        true
+    }
+
+    /// Offer an item of size `n`, keeping it if `multi_trial(n)` says to.
+    ///
+    /// Larger items are proportionally more likely to be kept; see
+    /// [`FastBernoulli::multi_trial`]. Returns whether the item was kept.
+    pub fn push_sized<R>(&mut self, item: T, n: u32, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.offered += 1;
+        self.offered_size += u64::from(n);
+        if !self.bernoulli.multi_trial(n, rng) {
+            return false;
+        }
+        let probability = self
+            .bernoulli
+            .multi_trial_weighted_probability(f64::from(n));
+        self.keep(item, probability);
+        true
+    }
+
+    fn keep(&mut self, item: T, probability: f64) {
+        self.items.push(item);
+        self.probabilities.push(probability);
+    }
+
+    /// Get the number of items offered, whether or not they were kept.
+    #[inline]
+    pub fn offered(&self) -> u64 {
+        self.offered
+    }
+
+    /// Get the total size of all items offered, whether or not they were kept.
+    ///
+    /// Items offered with [`push`][SampledVec::push] have size one.
+    #[inline]
+    pub fn offered_size(&self) -> u64 {
+        self.offered_size
+    }
+
+    /// Get the number of items kept.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Were no items kept?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Get the kept items.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Iterate over the kept items, along with each item's weight: the number
+    /// of offered items it stands in for.
+    pub fn iter_weighted(&self) -> impl Iterator<Item = (&T, f64)> + '_ {
+        self.items
+            .iter()
+            .zip(&self.probabilities)
+            .map(|(item, p)| (item, 1.0 / p))
+    }
+
+    /// Estimate how many offered items match the given predicate.
+    pub fn estimate_count<F>(&self, mut predicate: F) -> HorvitzThompson
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.estimate_sum(|item| if predicate(item) { 1.0 } else { 0.0 })
+    }
+
+    /// Estimate the sum of `value(item)` over all offered items.
+    pub fn estimate_sum<F>(&self, mut value: F) -> HorvitzThompson
+    where
+        F: FnMut(&T) -> f64,
+    {
+        let mut estimate = HorvitzThompson::new();
+        for (item, &p) in self.items.iter().zip(&self.probabilities) {
+            estimate.add(p, value(item));
+        }
+        estimate
+    }
+
+    /// Remove every kept item and reset the offered counts.
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.probabilities.clear();
+        self.offered = 0;
+        self.offered_size = 0;
+    }
+
+    /// Get the probability with which items are kept.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Unwrap this `SampledVec`, returning the kept items.
+    #[inline]
+    pub fn into_vec(self) -> Vec<T> {
+        self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sized_estimates_are_unbiased() {
+        let mut rng = rand::thread_rng();
+        let mut allocations = SampledVec::new(0.001, &mut rng);
+
+        let mut true_total = 0.0;
+        for i in 0..100_000_u32 {
+            let size = 1 + i % 4096;
+            true_total += f64::from(size);
+            allocations.push_sized(size, size, &mut rng);
+        }
+
+        assert_eq!(allocations.offered(), 100_000);
+        let estimate = allocations.estimate_sum(|&size| f64::from(size));
+        let (low, high) = estimate.confidence_interval(4.0);
+        assert!(
+            low <= true_total && true_total <= high,
+            "estimated {} bytes (between {} and {}), actually {}",
+            estimate.total(),
+            low,
+            high,
+            true_total,
+        );
+    }
+
+    #[test]
+    fn rare_items_are_weighted_by_the_effective_probability() {
+        let mut rng = rand::thread_rng();
+        let mut items = SampledVec::new(1e-12, &mut rng);
+        let effective = items.bernoulli.effective_probability();
+        assert!(1.0 / effective < 1e10);
+
+        // Start from a skip count of zero, so that the next item is kept.
+        let due = crate::FastBernoulliState::new(1e-12, 0);
+        items.bernoulli = FastBernoulli::from_state(due);
+        assert!(items.push("one", &mut rng));
+        items.bernoulli = FastBernoulli::from_state(due);
+        assert!(items.push_sized("two", 2, &mut rng));
+
+        let weights: Vec<f64> = items.iter_weighted().map(|(_, weight)| weight).collect();
+        assert_eq!(weights[0], 1.0 / effective);
+        assert!((weights[1] / (0.5 / effective) - 1.0).abs() < 1e-6);
+    }
+}
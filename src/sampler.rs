@@ -0,0 +1,160 @@
+use crate::time::Instant;
+use crate::{
+    FastBernoulli, IntegerBernoulli, LoadCurve, LoadShedder, MemoizedSampler, ReportThrottler,
+    StickySampler,
+};
+use rand::RngCore;
+use std::hash::Hash;
+
+/// A sampling policy: something that decides, event by event, whether to
+/// sample.
+///
+/// Every sampler in this crate answers the same question, but they differ in
+/// what they need to know about each event: some ignore everything but the
+/// RNG, some key their decisions on the event, and some depend on the time.
+/// This trait gives them a common shape, so that they can be composed into a
+/// [`Pipeline`][crate::Pipeline] or used interchangeably as trait objects.
+///
+/// Samplers that ignore keys implement `Sampler<K>` for every `K`, and
+/// samplers that ignore time ignore `now`.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{FastBernoulli, Sampler, StickySampler};
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut samplers: Vec<Box<dyn Sampler<&str>>> = vec![
+///     Box::new(FastBernoulli::new(0.01, &mut rng)),
+///     Box::new(StickySampler::new(0.01, Duration::from_secs(60), &mut rng)),
+/// ];
+///
+/// let now = Instant::now();
+/// for sampler in &mut samplers {
+///     sampler.sample(&"session", now, &mut rng);
+/// }
+/// ```
+pub trait Sampler<K: ?Sized = ()> {
+    /// Decide whether to sample the event with the given key, which occurred
+    /// at `now`.
+    fn sample(&mut self, key: &K, now: Instant, rng: &mut dyn RngCore) -> bool;
+}
+
+impl<K, S> Sampler<K> for Box<S>
+where
+    K: ?Sized,
+    S: Sampler<K> + ?Sized,
+{
+    #[inline]
+    fn sample(&mut self, key: &K, now: Instant, rng: &mut dyn RngCore) -> bool {
+        (**self).sample(key, now, rng)
+    }
+}
+
+impl<K: ?Sized> Sampler<K> for FastBernoulli {
+    #[inline]
+    fn sample(&mut self, _key: &K, _now: Instant, rng: &mut dyn RngCore) -> bool {
+        self.trial(rng)
+    }
+}
+
+impl<K: ?Sized> Sampler<K> for IntegerBernoulli {
+    #[inline]
+    fn sample(&mut self, _key: &K, _now: Instant, rng: &mut dyn RngCore) -> bool {
+        self.trial(rng)
+    }
+}
+
+impl<K> Sampler<K> for StickySampler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    #[inline]
+    fn sample(&mut self, key: &K, now: Instant, rng: &mut dyn RngCore) -> bool {
+        self.trial(key, now, rng)
+    }
+}
+
+impl<K> Sampler<K> for MemoizedSampler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    #[inline]
+    fn sample(&mut self, key: &K, now: Instant, rng: &mut dyn RngCore) -> bool {
+        self.trial(key, now, rng)
+    }
+}
+
+impl<K> Sampler<K> for ReportThrottler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    #[inline]
+    fn sample(&mut self, key: &K, _now: Instant, rng: &mut dyn RngCore) -> bool {
+        self.trial(key, rng).is_some()
+    }
+}
+
+impl<K, L, C> Sampler<K> for LoadShedder<L, C>
+where
+    K: ?Sized,
+    L: FnMut() -> f64,
+    C: LoadCurve,
+{
+    #[inline]
+    fn sample(&mut self, _key: &K, now: Instant, rng: &mut dyn RngCore) -> bool {
+        self.trial(now, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn samplers_are_interchangeable() {
+        let mut rng = rand::thread_rng();
+        let now = Instant::now();
+        let ttl = Duration::from_secs(60);
+
+        let mut always: Vec<Box<dyn Sampler<&str>>> = vec![
+            Box::new(FastBernoulli::new(1.0, &mut rng)),
+            Box::new(IntegerBernoulli::from_ratio(1, 1, &mut rng)),
+            Box::new(StickySampler::new(1.0, ttl, &mut rng)),
+            Box::new(MemoizedSampler::new(1.0, ttl, &mut rng)),
+        ];
+        let mut never: Vec<Box<dyn Sampler<&str>>> = vec![
+            Box::new(FastBernoulli::new(0.0, &mut rng)),
+            Box::new(IntegerBernoulli::from_ratio(0, 1, &mut rng)),
+            Box::new(StickySampler::new(0.0, ttl, &mut rng)),
+            Box::new(MemoizedSampler::new(0.0, ttl, &mut rng)),
+        ];
+        for _ in 0..10 {
+            for sampler in &mut always {
+                assert!(sampler.sample(&"key", now, &mut rng));
+            }
+            for sampler in &mut never {
+                assert!(!sampler.sample(&"key", now, &mut rng));
+            }
+        }
+    }
+
+    #[test]
+    fn keyed_samplers_decide_per_key() {
+        let mut rng = rand::thread_rng();
+        let now = Instant::now();
+        let mut sampler: Box<dyn Sampler<u32>> =
+            Box::new(MemoizedSampler::new(0.5, Duration::from_secs(60), &mut rng));
+
+        // Every key's first decision is remembered.
+        let first: Vec<bool> = (0..100)
+            .map(|key| sampler.sample(&key, now, &mut rng))
+            .collect();
+        for (key, &decision) in (0..100).zip(&first) {
+            assert_eq!(sampler.sample(&key, now, &mut rng), decision);
+        }
+        assert!(first.iter().any(|&d| d) && first.iter().any(|&d| !d));
+    }
+}
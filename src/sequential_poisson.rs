@@ -0,0 +1,246 @@
+use crate::HorvitzThompson;
+use rand::Rng;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Streaming sampling with probability proportional to size (PPS), keeping a
+/// fixed-size sample.
+///
+/// This is order sampling with sequential Poisson ranks: each offered item of
+/// size `x` gets the rank `u / x`, for a fresh uniform `u`, and the sampler
+/// keeps the `sample_size` items with the smallest ranks. Big items get small
+/// ranks and are likely to be kept, so the sample approximates Poisson
+/// sampling with probabilities proportional to size, while always having
+/// exactly `sample_size` items (once that many have been offered), and without
+/// knowing the total size in advance.
+///
+/// The smallest rank not kept acts as a threshold `t`: conditional on it,
+/// each kept item of size `x` was included with probability
+/// `min(1.0, x * t)`. Those probabilities feed the same Horvitz-Thompson
+/// estimators as the crate's other samplers, giving unbiased estimates of
+/// totals.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::SequentialPoissonSampler;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Pick ~100 accounts with probability proportional to their revenue.
+/// let mut sampler = SequentialPoissonSampler::new(100);
+/// for account in 0..10_000_u32 {
+///     let revenue = f64::from(account % 1000 + 1);
+///     sampler.offer((account, revenue), revenue, &mut rng);
+/// }
+///
+/// assert_eq!(sampler.len(), 100);
+///
+/// // Estimate the total revenue from the sample.
+/// let (low, high) = sampler.estimate_sum(|&(_, revenue)| revenue).confidence_interval(5.0);
+/// let true_total = 10.0 * (1..=1000).sum::<u32>() as f64;
+/// assert!(low <= true_total && true_total <= high);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SequentialPoissonSampler<T> {
+    sample_size: usize,
+    // The `sample_size + 1` smallest-ranked items, as a max-heap on rank, so
+    // that the top is the threshold.
+    heap: BinaryHeap<Ranked<T>>,
+    offered: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Ranked<T> {
+    rank: f64,
+    size: f64,
+    item: T,
+}
+
+impl<T> PartialEq for Ranked<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Ranked<T> {}
+
+impl<T> PartialOrd for Ranked<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Ranked<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank.total_cmp(&other.rank)
+    }
+}
+
+impl<T> SequentialPoissonSampler<T> {
+    /// Construct a new `SequentialPoissonSampler` that keeps `sample_size`
+    /// items.
+    ///
+    /// # Panics
+    ///
+    /// The sample size must be non-zero and this method will panic if that is
+    /// not the case.
+    pub fn new(sample_size: usize) -> Self {
+        assert!(sample_size > 0, "`sample_size` must be non-zero");
+        SequentialPoissonSampler {
+            sample_size,
+            heap: BinaryHeap::with_capacity(sample_size + 1),
+            offered: 0,
+        }
+    }
+
+    /// Offer an item of the given size.
+    ///
+    /// Items of size zero are counted as offered, but never kept.
+    ///
+    /// # Panics
+    ///
+    /// The size must be non-negative and finite, and this method will panic if
+    /// that is not the case.
+    pub fn offer<R>(&mut self, item: T, size: f64, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            size >= 0.0 && size.is_finite(),
+            "`size` must be non-negative and finite"
+        );
+        self.offered += 1;
+        if size == 0.0 {
+            return;
+        }
+
+        let rank = rng.gen::<f64>() / size;
+        if self.heap.len() <= self.sample_size {
+            self.heap.push(Ranked { rank, size, item });
+        } else if let Some(mut top) = self.heap.peek_mut() {
+            if rank < top.rank {
+                *top = Ranked { rank, size, item };
+            }
+        }
+    }
+
+    /// Get the number of items offered so far.
+    #[inline]
+    pub fn offered(&self) -> u64 {
+        self.offered
+    }
+
+    /// Get the number of items in the sample.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.heap.len().min(self.sample_size)
+    }
+
+    /// Is the sample empty?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Get the rank threshold, if more than `sample_size` items of non-zero
+    /// size have been offered.
+    ///
+    /// Until then, every item is kept with probability one.
+    #[inline]
+    pub fn threshold(&self) -> Option<f64> {
+        if self.heap.len() > self.sample_size {
+            self.heap.peek().map(|top| top.rank)
+        } else {
+            None
+        }
+    }
+
+    /// Iterate over the sampled items, along with each item's inclusion
+    /// probability.
+    pub fn iter(&self) -> impl Iterator<Item = (&T, f64)> + '_ {
+        let threshold = self.threshold();
+        self.heap
+            .iter()
+            .filter(move |entry| Some(entry.rank) != threshold)
+            .map(move |entry| (&entry.item, inclusion_probability(entry.size, threshold)))
+    }
+
+    /// Iterate over the sampled items, along with each item's weight: the
+    /// reciprocal of its inclusion probability.
+    pub fn iter_weighted(&self) -> impl Iterator<Item = (&T, f64)> + '_ {
+        self.iter().map(|(item, p)| (item, 1.0 / p))
+    }
+
+    /// Estimate how many offered items match the given predicate.
+    pub fn estimate_count<F>(&self, mut predicate: F) -> HorvitzThompson
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.estimate_sum(|item| if predicate(item) { 1.0 } else { 0.0 })
+    }
+
+    /// Estimate the sum of `value(item)` over all offered items.
+    pub fn estimate_sum<F>(&self, mut value: F) -> HorvitzThompson
+    where
+        F: FnMut(&T) -> f64,
+    {
+        let mut estimate = HorvitzThompson::new();
+        for (item, p) in self.iter() {
+            estimate.add(p, value(item));
+        }
+        estimate
+    }
+
+    /// Unwrap this sampler, returning the sampled items along with each item's
+    /// inclusion probability.
+    pub fn into_vec(self) -> Vec<(T, f64)> {
+        let threshold = self.threshold();
+        let mut entries = self.heap.into_sorted_vec();
+        if threshold.is_some() {
+            entries.pop();
+        }
+        entries
+            .into_iter()
+            .map(|entry| {
+                let p = inclusion_probability(entry.size, threshold);
+                (entry.item, p)
+            })
+            .collect()
+    }
+}
+
+#[inline]
+fn inclusion_probability(size: f64, threshold: Option<f64>) -> f64 {
+    threshold.map_or(1.0, |t| (size * t).min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_big_items_and_estimates_totals() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = SequentialPoissonSampler::new(50);
+
+        // One huge item that must always be kept, among many small ones.
+        sampler.offer(u32::MAX, 1e12, &mut rng);
+        for i in 0..10_000 {
+            sampler.offer(i, 1.0, &mut rng);
+        }
+
+        assert_eq!(sampler.offered(), 10_001);
+        let sample = sampler.clone().into_vec();
+        assert_eq!(sample.len(), 50);
+        assert!(sample.iter().any(|&(item, p)| item == u32::MAX && p == 1.0));
+
+        let small = sampler.estimate_count(|&item| item != u32::MAX);
+        let (low, high) = small.confidence_interval(5.0);
+        assert!(
+            low <= 10_000.0 && 10_000.0 <= high,
+            "estimated {} small items",
+            small.total()
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use crate::FastBernoulli;
+use rand::Rng;
+#[cfg(feature = "tracing")]
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Severity-aware sampling: severe events are always kept, and everything
+/// else is sampled at per-severity rates.
+///
+/// Events whose severity is at or above the configured threshold bypass
+/// sampling entirely. Events below it are sampled with the probability
+/// configured for their severity, or with the default probability if their
+/// severity has none. Each severity with its own probability also has its own
+/// skip count, so a flood of debug messages doesn't affect how often info
+/// messages are sampled. Severities without one share the default
+/// probability's skip count.
+///
+/// Severities can be any ordered type, where greater means more severe. Note
+/// that the `log` and `tracing` crates order their levels the other way around
+/// (`ERROR` is the least), so wrap those levels in [`std::cmp::Reverse`]. With
+/// the `tracing` feature enabled, [`for_tracing`][SeveritySampler::for_tracing]
+/// does this for `tracing` levels, and
+/// [`trial_metadata`][SeveritySampler::trial_metadata] samples events and spans
+/// by their metadata, for use in a `tracing` subscriber or layer.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::SeveritySampler;
+///
+/// #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// enum Severity { Debug, Info, Warn, Error }
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Always keep warnings and errors, keep 1% of debug messages and 10% of
+/// // everything else.
+/// let mut sampler = SeveritySampler::new(Severity::Warn, 0.1, &mut rng)
+///     .with_probability(Severity::Debug, 0.01, &mut rng);
+///
+/// assert!(sampler.trial(Severity::Error, &mut rng));
+/// assert_eq!(sampler.probability(Severity::Info), 0.1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SeveritySampler<S> {
+    threshold: S,
+    default: FastBernoulli,
+    per_severity: BTreeMap<S, FastBernoulli>,
+}
+
+impl<S> SeveritySampler<S>
+where
+    S: Ord,
+{
+    /// Construct a new `SeveritySampler` that always samples events at or above
+    /// `threshold`, and samples other events with `default_probability`.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(threshold: S, default_probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        SeveritySampler {
+            threshold,
+            default: FastBernoulli::new(default_probability, rng),
+            per_severity: BTreeMap::new(),
+        }
+    }
+
+    /// Sample events of the given severity with the given probability, instead
+    /// of the default probability.
+    ///
+    /// Has no effect on severities at or above the threshold, which are always
+    /// sampled.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn with_probability<R>(mut self, severity: S, probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        self.set_probability(severity, probability, rng);
+        self
+    }
+
+    /// Sample events of the given severity with the given probability, instead
+    /// of the default probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn set_probability<R>(&mut self, severity: S, probability: f64, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        trace_sampler!(
+            info,
+            from = self
+                .per_severity
+                .get(&severity)
+                .unwrap_or(&self.default)
+                .probability(),
+            to = probability,
+            "severity probability changed"
+        );
+        self.per_severity
+            .insert(severity, FastBernoulli::new(probability, rng));
+    }
+
+    /// Perform a trial for an event of the given severity.
+    pub fn trial<R>(&mut self, severity: S, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        if severity >= self.threshold {
+            return true;
+        }
+        self.per_severity
+            .get_mut(&severity)
+            .unwrap_or(&mut self.default)
+            .trial(rng)
+    }
+
+    /// Get the probability with which events of the given severity are
+    /// sampled.
+    pub fn probability(&self, severity: S) -> f64 {
+        if severity >= self.threshold {
+            return 1.0;
+        }
+        self.per_severity
+            .get(&severity)
+            .unwrap_or(&self.default)
+            .probability()
+    }
+
+    /// Get the severity at and above which events are always sampled.
+    #[inline]
+    pub fn threshold(&self) -> &S {
+        &self.threshold
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl SeveritySampler<Reverse<tracing::Level>> {
+    /// Construct a new `SeveritySampler` for `tracing` levels, that always
+    /// samples events and spans at `threshold` or more severe, and samples
+    /// others with `default_probability`.
+    ///
+    /// Requires the `tracing` feature.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::SeveritySampler;
+    /// use std::cmp::Reverse;
+    /// use tracing::Level;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let mut sampler = SeveritySampler::for_tracing(Level::WARN, 0.1, &mut rng)
+    ///     .with_probability(Reverse(Level::TRACE), 0.001, &mut rng);
+    ///
+    /// assert!(sampler.trial(Reverse(Level::ERROR), &mut rng));
+    /// assert_eq!(sampler.probability(Reverse(Level::DEBUG)), 0.1);
+    /// ```
+    pub fn for_tracing<R>(threshold: tracing::Level, default_probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        SeveritySampler::new(Reverse(threshold), default_probability, rng)
+    }
+
+    /// Perform a trial for a `tracing` event or span, by its level.
+    ///
+    /// Call this from a subscriber or layer, such as from a
+    /// `tracing_subscriber::Layer`'s `on_event`, to decide whether to record
+    /// an event.
+    ///
+    /// Requires the `tracing` feature.
+    #[inline]
+    pub fn trial_metadata<R>(&mut self, metadata: &tracing::Metadata<'_>, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.trial(Reverse(*metadata.level()), rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    enum Severity {
+        Debug,
+        Info,
+        Warn,
+        Error,
+    }
+
+    fn count_sampled(sampler: &mut SeveritySampler<Severity>, severity: Severity, n: u32) -> u32 {
+        let mut rng = rand::thread_rng();
+        (0..n).filter(|_| sampler.trial(severity, &mut rng)).count() as u32
+    }
+
+    #[test]
+    fn each_severity_is_sampled_at_its_own_rate() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = SeveritySampler::new(Severity::Warn, 0.2, &mut rng).with_probability(
+            Severity::Debug,
+            0.05,
+            &mut rng,
+        );
+        assert_eq!(sampler.threshold(), &Severity::Warn);
+
+        let n = 20_000;
+        for (severity, p) in [
+            (Severity::Debug, 0.05),
+            (Severity::Info, 0.2),
+            (Severity::Warn, 1.0),
+            (Severity::Error, 1.0),
+        ] {
+            assert_eq!(sampler.probability(severity), p);
+            let sampled = count_sampled(&mut sampler, severity, n);
+            let expected = p * f64::from(n);
+            assert!(
+                (f64::from(sampled) - expected).abs() <= 5.0 * (expected * (1.0 - p)).sqrt(),
+                "sampled {sampled} {severity:?} events, expected ~{expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn severities_above_the_threshold_ignore_their_probabilities() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = SeveritySampler::new(Severity::Error, 0.0, &mut rng);
+        sampler.set_probability(Severity::Error, 0.0, &mut rng);
+        sampler.set_probability(Severity::Info, 1.0, &mut rng);
+
+        assert_eq!(count_sampled(&mut sampler, Severity::Error, 100), 100);
+        assert_eq!(count_sampled(&mut sampler, Severity::Info, 100), 100);
+        assert_eq!(count_sampled(&mut sampler, Severity::Warn, 100), 0);
+        assert_eq!(count_sampled(&mut sampler, Severity::Debug, 100), 0);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn samples_tracing_events_by_level() {
+        use std::sync::Mutex;
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::subscriber::Subscriber;
+        use tracing::{Event, Level};
+
+        /// Records which events its sampler keeps.
+        struct Sampling(Mutex<(SeveritySampler<Reverse<Level>>, Vec<Level>)>);
+
+        impl Subscriber for Sampling {
+            fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let (sampler, kept) = &mut *self.0.lock().unwrap();
+                if sampler.trial_metadata(event.metadata(), &mut rand::thread_rng()) {
+                    kept.push(*event.metadata().level());
+                }
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let mut rng = rand::thread_rng();
+        let sampler = SeveritySampler::for_tracing(Level::WARN, 0.0, &mut rng).with_probability(
+            Reverse(Level::INFO),
+            1.0,
+            &mut rng,
+        );
+        let subscriber = std::sync::Arc::new(Sampling(Mutex::new((sampler, Vec::new()))));
+
+        tracing::subscriber::with_default(subscriber.clone(), || {
+            tracing::error!("kept, as severe");
+            tracing::warn!("kept, at the threshold");
+            tracing::info!("kept, at 100%");
+            tracing::debug!("dropped, at the default 0%");
+        });
+        let kept = &subscriber.0.lock().unwrap().1;
+        assert_eq!(kept, &[Level::ERROR, Level::WARN, Level::INFO]);
+    }
+}
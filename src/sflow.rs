@@ -0,0 +1,180 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// 1-in-N packet sampling with the bookkeeping that sFlow and NetFlow
+/// exporters report.
+///
+/// Packet sampling specifications express the sampling rate as an integer `N`,
+/// sampling each packet with probability `1 / N`, and expect each exported
+/// sample to carry counters from which collectors renormalize. A
+/// `PacketSampler` maintains those counters, using the sFlow version 5 field
+/// definitions:
+///
+/// * `sequence_number`: incremented with each sample generated.
+/// * `sampling_rate`: the `N` in 1-in-N.
+/// * `sample_pool`: the total number of packets that could have been sampled,
+///   whether or not they were.
+/// * `drops`: the number of samples that were taken but discarded for lack of
+///   resources, reported with [`record_drops`][PacketSampler::record_drops].
+///
+/// Like the specifications, the counters are 32 bits wide, and wrap.
+///
+/// The packets to sample are chosen with random geometric skip counts, which is
+/// what sFlow calls for, and is NetFlow's "random" sampling algorithm,
+/// [`NETFLOW_RANDOM_SAMPLING`][PacketSampler::NETFLOW_RANDOM_SAMPLING].
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::PacketSampler;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sampler = PacketSampler::one_in(512, &mut rng);
+///
+/// for packet in 0..100_000_u32 {
+///     if let Some(header) = sampler.sample(&mut rng) {
+///         // Export the packet's header along with these fields...
+///         assert_eq!(header.sampling_rate, 512);
+///         assert_eq!(header.sample_pool, packet + 1);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PacketSampler {
+    bernoulli: FastBernoulli,
+    sampling_rate: u32,
+    sequence_number: u32,
+    sample_pool: u32,
+    drops: u32,
+}
+
+/// The counters an sFlow flow sample carries, returned by
+/// [`PacketSampler::sample`] for sampled packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct FlowSampleHeader {
+    /// The sample's sequence number, starting at `1`.
+    pub sequence_number: u32,
+
+    /// The sampling rate: one in this many packets is sampled.
+    pub sampling_rate: u32,
+
+    /// The total number of packets that could have been sampled, including
+    /// this one.
+    pub sample_pool: u32,
+
+    /// The number of samples dropped for lack of resources.
+    pub drops: u32,
+}
+
+impl FlowSampleHeader {
+    /// Get the number of packets this sample stands in for, which is the
+    /// sampling rate.
+    #[inline]
+    pub fn weight(&self) -> f64 {
+        f64::from(self.sampling_rate)
+    }
+}
+
+impl PacketSampler {
+    /// The NetFlow version 9 and IPFIX `samplingAlgorithm` value for random
+    /// sampling.
+    pub const NETFLOW_RANDOM_SAMPLING: u8 = 0x02;
+
+    /// Construct a new `PacketSampler` that samples one in `n` packets.
+    ///
+    /// # Panics
+    ///
+    /// `n` must be non-zero and this method will panic if that is not the
+    /// case.
+    pub fn one_in<R>(n: u32, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(n > 0, "`n` must be non-zero");
+        PacketSampler {
+            bernoulli: FastBernoulli::new(1.0 / f64::from(n), rng),
+            sampling_rate: n,
+            sequence_number: 0,
+            sample_pool: 0,
+            drops: 0,
+        }
+    }
+
+    /// Perform a trial for a packet.
+    ///
+    /// Returns the flow sample's counters if the packet should be sampled, or
+    /// `None` if it should not.
+    pub fn sample<R>(&mut self, rng: &mut R) -> Option<FlowSampleHeader>
+    where
+        R: Rng + ?Sized,
+    {
+        self.sample_pool = self.sample_pool.wrapping_add(1);
+        if !self.bernoulli.trial(rng) {
+            return None;
+        }
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+        Some(FlowSampleHeader {
+            sequence_number: self.sequence_number,
+            sampling_rate: self.sampling_rate,
+            sample_pool: self.sample_pool,
+            drops: self.drops,
+        })
+    }
+
+    /// Record that `n` samples were dropped for lack of resources, such as a
+    /// full export queue.
+    #[inline]
+    pub fn record_drops(&mut self, n: u32) {
+        self.drops = self.drops.wrapping_add(n);
+    }
+
+    /// Get the sampling rate: one in this many packets is sampled.
+    #[inline]
+    pub fn sampling_rate(&self) -> u32 {
+        self.sampling_rate
+    }
+
+    /// Get the total number of packets that could have been sampled so far.
+    #[inline]
+    pub fn sample_pool(&self) -> u32 {
+        self.sample_pool
+    }
+
+    /// Get the number of samples generated so far.
+    #[inline]
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    /// Get the number of samples dropped so far.
+    #[inline]
+    pub fn drops(&self) -> u32 {
+        self.drops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maintains_sflow_counters() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = PacketSampler::one_in(1, &mut rng);
+
+        let first = sampler.sample(&mut rng).unwrap();
+        sampler.record_drops(1);
+        let second = sampler.sample(&mut rng).unwrap();
+
+        assert_eq!(
+            (first.sequence_number, first.sample_pool, first.drops),
+            (1, 1, 0)
+        );
+        assert_eq!(
+            (second.sequence_number, second.sample_pool, second.drops),
+            (2, 2, 1)
+        );
+        assert_eq!(second.weight(), 1.0);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::FastBernoulli;
+use rand::Rng;
+use std::fmt;
+
+/// A callback wrapper that forwards only sampled items, and counts the rest.
+///
+/// This turns adding sampling to an existing callback-based pipeline into a
+/// one-liner: wrap the callback, and send every item through the wrapper
+/// instead.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::SamplingSink;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut recorded = Vec::new();
+///
+/// let mut sink = SamplingSink::new(0.1, |event: u32| recorded.push(event), &mut rng);
+/// for event in 0..1000 {
+///     sink.send(event, &mut rng);
+/// }
+///
+/// assert_eq!(sink.forwarded() + sink.dropped(), 1000);
+/// let forwarded = sink.forwarded();
+/// drop(sink);
+/// assert_eq!(recorded.len() as u64, forwarded);
+/// ```
+pub struct SamplingSink<F> {
+    bernoulli: FastBernoulli,
+    sink: F,
+    forwarded: u64,
+    dropped: u64,
+}
+
+impl<F> fmt::Debug for SamplingSink<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SamplingSink")
+            .field("bernoulli", &self.bernoulli)
+            .field("forwarded", &self.forwarded)
+            .field("dropped", &self.dropped)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> SamplingSink<F> {
+    /// Construct a new `SamplingSink` that forwards items to `sink` with the
+    /// given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, sink: F, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        SamplingSink {
+            bernoulli: FastBernoulli::new(probability, rng),
+            sink,
+            forwarded: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Perform a trial for `item`, forwarding it to the wrapped sink if it is
+    /// sampled.
+    ///
+    /// Returns whether the item was forwarded.
+    pub fn send<T, R>(&mut self, item: T, rng: &mut R) -> bool
+    where
+        F: FnMut(T),
+        R: Rng + ?Sized,
+    {
+        let sampled = self.bernoulli.trial(rng);
+        self.forward(item, sampled)
+    }
+
+    /// Perform `n` trials at once for an item of size `n`, forwarding it to the
+    /// wrapped sink if it is sampled.
+    ///
+    /// See [`FastBernoulli::multi_trial`] for details. Returns whether the item
+    /// was forwarded.
+    pub fn send_sized<T, R>(&mut self, item: T, n: u32, rng: &mut R) -> bool
+    where
+        F: FnMut(T),
+        R: Rng + ?Sized,
+    {
+        let sampled = self.bernoulli.multi_trial(n, rng);
+        self.forward(item, sampled)
+    }
+
+    #[inline]
+    fn forward<T>(&mut self, item: T, sampled: bool) -> bool
+    where
+        F: FnMut(T),
+    {
+        if sampled {
+            self.forwarded += 1;
+            (self.sink)(item);
+        } else {
+            self.dropped += 1;
+        }
+        sampled
+    }
+
+    /// Get the number of items forwarded to the wrapped sink.
+    #[inline]
+    pub fn forwarded(&self) -> u64 {
+        self.forwarded
+    }
+
+    /// Get the number of items that were not sampled, and not forwarded.
+    #[inline]
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Get the probability with which items are forwarded.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Get a shared reference to the wrapped sink.
+    #[inline]
+    pub fn get_ref(&self) -> &F {
+        &self.sink
+    }
+
+    /// Get an exclusive reference to the wrapped sink.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.sink
+    }
+
+    /// Unwrap this `SamplingSink`, returning the wrapped sink.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.sink
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forwards_only_sampled_items() {
+        let mut rng = rand::thread_rng();
+
+        let mut seen = Vec::new();
+        let mut sink = SamplingSink::new(1.0, |item| seen.push(item), &mut rng);
+        assert!(sink.send(1, &mut rng));
+        assert!(sink.send_sized(2, 10, &mut rng));
+        (sink.get_mut())(3);
+        assert_eq!((sink.forwarded(), sink.dropped()), (2, 0));
+        assert_eq!(seen, [1, 2, 3]);
+
+        let mut sink = SamplingSink::new(
+            0.0,
+            |_: u32| panic!("dropped items are forwarded"),
+            &mut rng,
+        );
+        for item in 0..100 {
+            assert!(!sink.send(item, &mut rng));
+        }
+        assert!(!sink.send_sized(100, 0, &mut rng));
+        assert_eq!((sink.forwarded(), sink.dropped()), (0, 101));
+    }
+
+    #[test]
+    fn forwards_at_the_configured_rate() {
+        let mut rng = rand::thread_rng();
+        let p = 0.1;
+        let mut forwarded = 0_u64;
+        let mut sink = SamplingSink::new(p, |_: u32| forwarded += 1, &mut rng);
+
+        let n = 100_000;
+        for item in 0..n {
+            sink.send(item, &mut rng);
+        }
+        let (counted, dropped) = (sink.forwarded(), sink.dropped());
+        assert_eq!(counted + dropped, u64::from(n));
+        assert_eq!(forwarded, counted);
+
+        let expected = p * f64::from(n);
+        assert!((forwarded as f64 - expected).abs() <= 5.0 * (expected * (1.0 - p)).sqrt());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::hash::hash_with_salt;
+use std::hash::Hash;
+
+/// A count-min sketch: approximate per-key event counts in fixed memory.
+///
+/// Estimates never undercount. They overcount by at most `epsilon * total`
+/// with probability at least `1 - delta`, where `total` is the sum of all
+/// counts added to the sketch since it was last cleared; see
+/// [`with_error_bounds`][CountMinSketch::with_error_bounds].
+///
+/// Counters saturate at `u32::MAX`. Long-running users that care about recent
+/// rather than all-time frequency should periodically [`halve`] the sketch.
+///
+/// [`halve`]: CountMinSketch::halve
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::CountMinSketch;
+///
+/// let mut sketch = CountMinSketch::new(1024, 4);
+/// for _ in 0..10 {
+///     sketch.increment(&"GET /index.html");
+/// }
+/// sketch.increment(&"GET /favicon.ico");
+///
+/// assert!(sketch.estimate(&"GET /index.html") >= 10);
+/// assert!(sketch.estimate(&"GET /favicon.ico") >= 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    counters: Vec<u32>,
+}
+
+impl CountMinSketch {
+    /// Construct a new, empty `CountMinSketch` with `depth` rows of `width`
+    /// counters each.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `depth` is zero.
+    pub fn new(width: usize, depth: usize) -> Self {
+        assert!(width > 0, "`width` must be greater than zero");
+        assert!(depth > 0, "`depth` must be greater than zero");
+        CountMinSketch {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+        }
+    }
+
+    /// Construct a new, empty `CountMinSketch` sized so that estimates exceed
+    /// the true count by at most `epsilon` times the total count, with
+    /// probability at least `1 - delta`.
+    ///
+    /// # Panics
+    ///
+    /// Both `epsilon` and `delta` must be within the range `0.0 < x < 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn with_error_bounds(epsilon: f64, delta: f64) -> Self {
+        assert!(
+            0.0 < epsilon && epsilon < 1.0,
+            "`epsilon` must be in the range `0.0 < epsilon < 1.0`"
+        );
+        assert!(
+            0.0 < delta && delta < 1.0,
+            "`delta` must be in the range `0.0 < delta < 1.0`"
+        );
+        let width = (std::f64::consts::E / epsilon).ceil() as usize;
+        let depth = (1.0 / delta).ln().ceil().max(1.0) as usize;
+        CountMinSketch::new(width, depth)
+    }
+
+    /// Add one to `key`'s count, returning its new estimated count.
+    #[inline]
+    pub fn increment<K>(&mut self, key: &K) -> u32
+    where
+        K: Hash + ?Sized,
+    {
+        self.add(key, 1)
+    }
+
+    /// Add `n` to `key`'s count, returning its new estimated count.
+    pub fn add<K>(&mut self, key: &K, n: u32) -> u32
+    where
+        K: Hash + ?Sized,
+    {
+        let mut estimate = u32::MAX;
+        for row in 0..self.depth {
+            let i = self.index(row, key);
+            let counter = &mut self.counters[i];
+            *counter = counter.saturating_add(n);
+            estimate = estimate.min(*counter);
+        }
+        estimate
+    }
+
+    /// Get `key`'s estimated count.
+    pub fn estimate<K>(&self, key: &K) -> u32
+    where
+        K: Hash + ?Sized,
+    {
+        (0..self.depth)
+            .map(|row| self.counters[self.index(row, key)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Halve every counter, aging out old observations so that estimates
+    /// favor recent frequency.
+    pub fn halve(&mut self) {
+        for counter in &mut self.counters {
+            *counter >>= 1;
+        }
+    }
+
+    /// Reset every counter to zero.
+    pub fn clear(&mut self) {
+        self.counters.iter_mut().for_each(|c| *c = 0);
+    }
+
+    /// Get the number of counters in each row.
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the number of rows.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    #[inline]
+    fn index<K>(&self, row: usize, key: &K) -> usize
+    where
+        K: Hash + ?Sized,
+    {
+        let h = hash_with_salt(row as u64, key);
+        row * self.width + (h % self.width as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_undercounts() {
+        let mut sketch = CountMinSketch::new(64, 3);
+        for key in 0..1000_u32 {
+            for _ in 0..(key % 7) {
+                sketch.increment(&key);
+            }
+        }
+        for key in 0..1000_u32 {
+            assert!(sketch.estimate(&key) >= key % 7);
+        }
+
+        sketch.clear();
+        assert_eq!(sketch.estimate(&3_u32), 0);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{FastBernoulli, SampleDecision};
+use rand::Rng;
+use std::time::Duration;
+
+/// A sampler for spans that decides when each span closes, keeping slow spans
+/// far more often than fast ones.
+///
+/// Each microsecond of a span's duration is a trial with the configured
+/// probability, and the span is kept if any of them is sampled, as in
+/// [`trial_elapsed`][FastBernoulli::trial_elapsed]. A span lasting `t`
+/// microseconds is kept with probability `1 - (1 - p)^t`: nearly
+/// `p * t` for short spans, so the base rate stays low, and approaching
+/// certainty for slow outliers.
+///
+/// This is a building block, not a tracing layer: this crate doesn't depend
+/// on `tracing-subscriber`, and doesn't hook into span lifecycles itself. A
+/// layer built on it would record each span's start time when it is opened,
+/// and ask the sampler in its own `on_close` hook whether to export it. The
+/// returned [`SampleDecision`] records the span's own inclusion probability,
+/// so that kept spans can be reweighted into unbiased counts.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::SpanLatencySampler;
+/// use std::time::Instant;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Keep about one in a thousand 1ms spans, and about 63% of 1s spans.
+/// let mut sampler = SpanLatencySampler::per_microsecond(1e-6, &mut rng);
+///
+/// let start = Instant::now();
+/// // Run the span...
+/// if let Some(decision) = sampler.on_close(start.elapsed(), &mut rng) {
+///     // Export the span, with `decision.weight`...
+///     # let _ = decision;
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct SpanLatencySampler {
+    bernoulli: FastBernoulli,
+}
+
+impl SpanLatencySampler {
+    /// Construct a new `SpanLatencySampler` that samples each microsecond of
+    /// span duration with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn per_microsecond<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        SpanLatencySampler {
+            bernoulli: FastBernoulli::new(probability, rng),
+        }
+    }
+
+    /// Decide whether to keep a span that ran for `elapsed`, now that it has
+    /// closed.
+    ///
+    /// Returns the decision, recording the span's inclusion probability, if
+    /// the span should be kept, or `None` if it should not.
+    pub fn on_close<R>(&mut self, elapsed: Duration, rng: &mut R) -> Option<SampleDecision>
+    where
+        R: Rng + ?Sized,
+    {
+        if !self
+            .bernoulli
+            .trial_elapsed(elapsed, Duration::from_micros(1), rng)
+        {
+            return None;
+        }
+        trace_sampler!(trace, ?elapsed, "kept span");
+        Some(SampleDecision::new(self.probability(elapsed)))
+    }
+
+    /// Get the probability with which a span that ran for `elapsed` is kept.
+    pub fn probability(&self, elapsed: Duration) -> f64 {
+        self.bernoulli
+            .multi_trial_weighted_probability(microseconds(elapsed))
+    }
+
+    /// Get the probability with which each microsecond is sampled.
+    #[inline]
+    pub fn probability_per_microsecond(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+}
+
+fn microseconds(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64() * 1e6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_spans_are_kept_more_often() {
+        let mut rng = rand::thread_rng();
+        let p = 1e-4;
+        let mut sampler = SpanLatencySampler::per_microsecond(p, &mut rng);
+
+        let n = 20_000;
+        for elapsed in [Duration::from_micros(10), Duration::from_millis(5)] {
+            let q = sampler.probability(elapsed);
+            let mut sampled = 0;
+            for _ in 0..n {
+                if let Some(decision) = sampler.on_close(elapsed, &mut rng) {
+                    assert_eq!(decision.probability, q);
+                    sampled += 1;
+                }
+            }
+            let expected = q * f64::from(n);
+            assert!(
+                (f64::from(sampled) - expected).abs() <= 5.0 * (expected * (1.0 - q)).sqrt(),
+                "kept {sampled} spans of {elapsed:?}, expected ~{expected}"
+            );
+        }
+
+        assert!((sampler.probability(Duration::from_micros(10)) - 1e-3).abs() < 1e-6);
+        assert!(sampler.probability(Duration::from_millis(5)) > 0.39);
+        assert_eq!(sampler.on_close(Duration::ZERO, &mut rng), None);
+    }
+}
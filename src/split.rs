@@ -0,0 +1,111 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+impl FastBernoulli {
+    /// Draw a fresh skip count, decorrelating this instance from any copies of
+    /// it, and give it a new instance identity.
+    ///
+    /// A copy of a `FastBernoulli` has the same skip count as the original, so
+    /// the two sample the same events until their next skip counts are drawn.
+    /// When cloning a sampler on purpose, for example to give each worker its
+    /// own, reseed each clone, or use [`split`][FastBernoulli::split], which
+    /// does both at once.
+    ///
+    /// Since each trial is independent, this doesn't affect the distribution
+    /// of sampled events.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let template = FastBernoulli::new(0.01, &mut rng);
+    ///
+    /// let workers: Vec<FastBernoulli> = (0..4)
+    ///     .map(|_| {
+    ///         let mut bernoulli = template;
+    ///         bernoulli.reseed(&mut rng);
+    ///         bernoulli
+    ///     })
+    ///     .collect();
+    /// # let _ = workers;
+    /// ```
+    pub fn reseed<R>(&mut self, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        self.lineage = crate::lineage::Lineage::new();
+        self.reset_skip_count(rng);
+    }
+
+    /// Split off a new instance with the same probability, but its own skip
+    /// count and instance identity, so that the two never share correlated
+    /// futures.
+    ///
+    /// This is a copy of `self` followed by [`reseed`][FastBernoulli::reseed]
+    /// on the copy; `self` is left unchanged.
+    ///
+    /// For results that don't depend on how work is divided between workers,
+    /// split with, and then drive each split by, a
+    /// [`PhiloxRng`][crate::PhiloxRng] stream per unit of work.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let bernoulli = FastBernoulli::new(0.01, &mut rng);
+    ///
+    /// let per_worker = bernoulli.split(&mut rng);
+    /// assert_eq!(per_worker.probability(), bernoulli.probability());
+    /// ```
+    pub fn split<R>(&self, rng: &mut R) -> FastBernoulli
+    where
+        R: Rng + ?Sized,
+    {
+        let mut split = *self;
+        split.reseed(rng);
+        split
+    }
+
+    /// Get this instance's identity, shared by all of its copies, and replaced
+    /// by [`reseed`][FastBernoulli::reseed].
+    ///
+    /// Identities are tracked in every build, though only debug builds use
+    /// them to detect accidental copies; see [Copies][FastBernoulli#copies].
+    /// They are 24 bits wide, and wrap around after `2^24` instances have been
+    /// constructed.
+    #[inline]
+    pub fn instance_id(&self) -> u32 {
+        self.lineage.nonce()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_instances_are_decorrelated() {
+        let mut rng = rand::thread_rng();
+        let bernoulli = FastBernoulli::new(0.001, &mut rng);
+
+        // With 1000 splits, all sharing the original's skip count would be
+        // astronomically unlikely.
+        let splits: Vec<FastBernoulli> = (0..1000).map(|_| bernoulli.split(&mut rng)).collect();
+        assert!(splits
+            .iter()
+            .any(|split| split.skip_count() != bernoulli.skip_count()));
+
+        let mut copy = bernoulli;
+        assert_eq!(copy.instance_id(), bernoulli.instance_id());
+        copy.reseed(&mut rng);
+        assert_ne!(copy.instance_id(), bernoulli.instance_id());
+        assert_ne!(
+            bernoulli.split(&mut rng).instance_id(),
+            bernoulli.instance_id()
+        );
+    }
+}
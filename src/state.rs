@@ -0,0 +1,157 @@
+use crate::FastBernoulli;
+
+/// A snapshot of a [`FastBernoulli`]'s state, for saving and restoring it
+/// across process or instance restarts.
+///
+/// A `FastBernoulli` is fully described by its probability and its current
+/// skip count. Restoring a saved state resumes the same sequence of decisions
+/// where it left off, instead of drawing a fresh skip count, so that a sampler
+/// recreated for every short-lived instance behaves like one long-lived
+/// sampler.
+///
+/// With the `serde` feature enabled, `FastBernoulliState` implements
+/// `Serialize` and `Deserialize`.
+///
+/// `FastBernoulliState` is `#[repr(C)]`, and with the `abi_stable` feature
+/// enabled, it implements `abi_stable::StableAbi`, so that it can cross a
+/// `cdylib` boundary between separately compiled Rust binaries, such as into
+/// or out of a dynamically loaded sampling-policy plugin.
+///
+/// With the `rkyv` feature enabled, `FastBernoulliState` implements `rkyv`'s
+/// `Archive`, `Serialize`, and `Deserialize`, so that large, memory-mapped
+/// collections of saved states can be read in place, and restored one at a
+/// time with [`FastBernoulli::from_archived`].
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::FastBernoulli;
+///
+/// let mut rng = rand::thread_rng();
+/// let bernoulli = FastBernoulli::new(0.01, &mut rng);
+///
+/// let state = bernoulli.state();
+/// let restored = FastBernoulli::from_state(state);
+/// assert_eq!(restored.skip_count(), bernoulli.skip_count());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "abi_stable", derive(abi_stable::StableAbi))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[repr(C)]
+#[non_exhaustive]
+pub struct FastBernoulliState {
+    /// The probability with which events are sampled.
+    pub probability: f64,
+
+    /// The number of events to skip before the next sample.
+    pub skip_count: u32,
+}
+
+impl FastBernoulliState {
+    /// Construct a new `FastBernoulliState` from its parts.
+    #[inline]
+    pub fn new(probability: f64, skip_count: u32) -> Self {
+        FastBernoulliState {
+            probability,
+            skip_count,
+        }
+    }
+}
+
+impl FastBernoulli {
+    /// Get a snapshot of this instance's state, which can be restored later
+    /// with [`FastBernoulli::from_state`].
+    #[inline]
+    pub fn state(&self) -> FastBernoulliState {
+        FastBernoulliState::new(self.probability, self.skip_count)
+    }
+
+    /// Restore a `FastBernoulli` instance from a snapshot of its state.
+    ///
+    /// Skip counts that are impossible for the probability (anything but
+    /// `u32::MAX` when the probability is `0.0`, or anything but `0` when it is
+    /// `1.0`) are corrected.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn from_state(state: FastBernoulliState) -> Self {
+        let probability = state.probability;
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        let skip_count = if probability == 0.0 {
+            u32::MAX
+        } else if probability == 1.0 {
+            0
+        } else {
+            state.skip_count
+        };
+        FastBernoulli {
+            probability,
+            denominator: 0,
+            skip_count,
+            lineage: crate::lineage::Lineage::new(),
+        }
+    }
+
+    /// Restore a `FastBernoulli` instance from an archived snapshot of its
+    /// state, without deserializing it first.
+    ///
+    /// Requires the `rkyv` feature.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    #[cfg(feature = "rkyv")]
+    pub fn from_archived(state: &ArchivedFastBernoulliState) -> Self {
+        FastBernoulli::from_state(FastBernoulliState::new(
+            state.probability.to_native(),
+            state.skip_count.to_native(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restored_instance_continues_the_same_sequence() {
+        let mut rng = rand::thread_rng();
+        let mut bernoulli = FastBernoulli::new(0.1, &mut rng);
+        bernoulli.trial(&mut rng);
+
+        let mut restored = FastBernoulli::from_state(bernoulli.state());
+        for _ in 0..bernoulli.skip_count() {
+            assert!(!restored.trial(&mut rng));
+        }
+        assert!(restored.trial(&mut rng));
+
+        let never = FastBernoulli::from_state(FastBernoulliState::new(0.0, 3));
+        assert_eq!(never.skip_count(), u32::MAX);
+    }
+
+    #[test]
+    #[cfg(feature = "rkyv")]
+    fn archived_states_are_restored_in_place() {
+        let states: Vec<FastBernoulliState> =
+            (0..100).map(|i| FastBernoulliState::new(0.01, i)).collect();
+        let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&states).unwrap();
+
+        let archived =
+            rkyv::access::<rkyv::Archived<Vec<FastBernoulliState>>, rkyv::rancor::Error>(&bytes)
+                .unwrap();
+        for (i, state) in archived.iter().enumerate() {
+            assert_eq!(FastBernoulli::from_archived(state).skip_count(), i as u32);
+        }
+    }
+}
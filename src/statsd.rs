@@ -0,0 +1,169 @@
+use crate::FastBernoulli;
+use cadence::prelude::*;
+use cadence::{MetricResult, StatsdClient};
+use rand::Rng;
+use std::borrow::Borrow;
+use std::fmt;
+use std::time::Duration;
+
+/// A [`cadence`] StatsD client that only emits a sampled fraction of metrics,
+/// each tagged with the `|@rate` suffix for its sampling probability.
+///
+/// StatsD servers scale sampled counters and timers back up by the `@rate`
+/// they were sent with. Most StatsD clients decide whether to send each metric
+/// with a fresh `rand::random::<f64>() < rate`; this makes the same decision
+/// with a [`FastBernoulli`] trial instead, which is much cheaper at low rates.
+///
+/// The client can be owned, or shared through an `Arc` or a reference.
+///
+/// Requires the `cadence` feature.
+///
+/// # Example
+///
+/// ```
+/// use cadence::{NopMetricSink, StatsdClient};
+/// use fast_bernoulli::SampledStatsd;
+/// use std::time::Duration;
+///
+/// let mut rng = rand::thread_rng();
+/// let client = StatsdClient::from_sink("my.app", NopMetricSink);
+///
+/// // Send 10% of metrics, as `my.app.requests:1|c|@0.1` and the like.
+/// let mut statsd = SampledStatsd::new(client, 0.1, &mut rng);
+///
+/// statsd.count("requests", 1, &mut rng).unwrap();
+/// statsd.time("latency", Duration::from_millis(12), &mut rng).unwrap();
+/// ```
+pub struct SampledStatsd<C> {
+    client: C,
+    bernoulli: FastBernoulli,
+}
+
+impl<C> fmt::Debug for SampledStatsd<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SampledStatsd")
+            .field("bernoulli", &self.bernoulli)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C> SampledStatsd<C>
+where
+    C: Borrow<StatsdClient>,
+{
+    /// Construct a new `SampledStatsd` that emits metrics through `client`
+    /// with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 < probability <= 1.0` and
+    /// this method will panic if that is not the case. A rate of zero can't be
+    /// scaled back up.
+    pub fn new<R>(client: C, probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            probability > 0.0 && probability <= 1.0,
+            "`probability` must be in the range `0.0 < probability <= 1.0`"
+        );
+        SampledStatsd {
+            client,
+            bernoulli: FastBernoulli::new(probability, rng),
+        }
+    }
+
+    /// Increment a counter by `count`, if sampled.
+    ///
+    /// Returns whether the metric was sampled and sent.
+    pub fn count<R>(&mut self, key: &str, count: i64, rng: &mut R) -> MetricResult<bool>
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.bernoulli.trial(rng) {
+            return Ok(false);
+        }
+        let rate = self.bernoulli.probability();
+        self.client
+            .borrow()
+            .count_with_tags(key, count)
+            .with_sampling_rate(rate)
+            .try_send()?;
+        Ok(true)
+    }
+
+    /// Record a timer, if sampled.
+    ///
+    /// Returns whether the metric was sampled and sent.
+    pub fn time<R>(&mut self, key: &str, time: Duration, rng: &mut R) -> MetricResult<bool>
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.bernoulli.trial(rng) {
+            return Ok(false);
+        }
+        let rate = self.bernoulli.probability();
+        self.client
+            .borrow()
+            .time_with_tags(key, time)
+            .with_sampling_rate(rate)
+            .try_send()?;
+        Ok(true)
+    }
+
+    /// Record a histogram value, if sampled.
+    ///
+    /// Returns whether the metric was sampled and sent.
+    pub fn histogram<R>(&mut self, key: &str, value: u64, rng: &mut R) -> MetricResult<bool>
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.bernoulli.trial(rng) {
+            return Ok(false);
+        }
+        let rate = self.bernoulli.probability();
+        self.client
+            .borrow()
+            .histogram_with_tags(key, value)
+            .with_sampling_rate(rate)
+            .try_send()?;
+        Ok(true)
+    }
+
+    /// Get the probability with which metrics are sent.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Get the underlying client.
+    #[inline]
+    pub fn client(&self) -> &StatsdClient {
+        self.client.borrow()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cadence::SpyMetricSink;
+
+    #[test]
+    fn sampled_metrics_carry_their_rate() {
+        let mut rng = rand::thread_rng();
+        let (received, sink) = SpyMetricSink::new();
+        let client = StatsdClient::from_sink("test", sink);
+        let mut statsd = SampledStatsd::new(&client, 0.5, &mut rng);
+
+        let n = 10_000;
+        let sent = (0..n)
+            .filter(|_| statsd.count("hits", 1, &mut rng).unwrap())
+            .count();
+        let metrics: Vec<Vec<u8>> = received.try_iter().collect();
+        assert_eq!(metrics.len(), sent);
+        assert!(metrics.iter().all(|m| m == b"test.hits:1|c|@0.5"));
+
+        let expected = 0.5 * f64::from(n);
+        assert!((sent as f64 - expected).abs() <= 5.0 * (expected * 0.5).sqrt());
+    }
+}
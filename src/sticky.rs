@@ -0,0 +1,151 @@
+use crate::time::Instant;
+use crate::FastBernoulli;
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Sticky, per-key sampling: once a key is sampled, all of its events are
+/// sampled until a time-to-live expires.
+///
+/// This is useful when events belong to larger units, such as sessions or
+/// users, and a sampled unit is only useful if all of its events are
+/// recorded. Keys that are not currently sticky fall back to ordinary
+/// Bernoulli trials with the configured probability. When a sticky entry
+/// expires, the key goes back to fresh trials as well.
+///
+/// Only positive decisions are remembered, so memory use is proportional to
+/// the number of keys sampled within one time-to-live. Expired entries are
+/// dropped when their key is next seen, or in bulk with
+/// [`evict_expired`][StickySampler::evict_expired].
+///
+/// Like the RNG, the current time is passed in by the caller.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::StickySampler;
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Sample 1% of sessions, and keep each sampled session for ten minutes.
+/// let mut sampler = StickySampler::new(0.01, Duration::from_secs(600), &mut rng);
+///
+/// let session_id = 1234_u64;
+/// let now = Instant::now();
+/// if sampler.trial(&session_id, now, &mut rng) {
+///     // Every event for this session in the next ten minutes is sampled too.
+///     assert!(sampler.trial(&session_id, now + Duration::from_secs(60), &mut rng));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StickySampler<K> {
+    bernoulli: FastBernoulli,
+    ttl: Duration,
+    expirations: HashMap<K, Instant>,
+}
+
+impl<K> StickySampler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Construct a new `StickySampler` that samples unknown keys with the given
+    /// probability, and keeps sampling a sampled key for `ttl` afterwards.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, ttl: Duration, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        StickySampler {
+            bernoulli: FastBernoulli::new(probability, rng),
+            ttl,
+            expirations: HashMap::new(),
+        }
+    }
+
+    /// Perform a trial for an event belonging to `key` that occurred at `now`.
+    ///
+    /// Returns `true` if the key is currently sticky, or if it is not and a
+    /// fresh Bernoulli trial returns `true`. In the latter case, the key
+    /// becomes sticky until `now + ttl`.
+    pub fn trial<R>(&mut self, key: &K, now: Instant, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        if let Some(&expiration) = self.expirations.get(key) {
+            if now < expiration {
+                return true;
+            }
+            self.expirations.remove(key);
+        }
+
+        if self.bernoulli.trial(rng) {
+            self.expirations.insert(key.clone(), now + self.ttl);
+            return true;
+        }
+
+        false
+    }
+
+    /// Is the given key currently sticky?
+    pub fn is_sticky(&self, key: &K, now: Instant) -> bool {
+        self.expirations.get(key).is_some_and(|&e| now < e)
+    }
+
+    /// Drop every sticky entry that has expired by `now`.
+    pub fn evict_expired(&mut self, now: Instant) {
+        self.expirations.retain(|_, &mut e| now < e);
+    }
+
+    /// Get the number of keys with sticky entries, including any expired
+    /// entries that have not yet been evicted.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.expirations.len()
+    }
+
+    /// Are there no sticky entries?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.expirations.is_empty()
+    }
+
+    /// Get the probability with which unknown keys are sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.bernoulli.probability()
+    }
+
+    /// Get the time-to-live of sticky entries.
+    #[inline]
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sticky_until_expiration() {
+        let mut rng = rand::thread_rng();
+        let ttl = Duration::from_secs(10);
+        let mut sampler = StickySampler::new(1.0, ttl, &mut rng);
+        let start = Instant::now();
+
+        assert!(sampler.trial(&"session", start, &mut rng));
+
+        // Drop the probability to zero: only stickiness can sample now.
+        sampler.bernoulli = FastBernoulli::new(0.0, &mut rng);
+        assert!(sampler.trial(&"session", start + Duration::from_secs(9), &mut rng));
+        assert!(!sampler.trial(&"other", start + Duration::from_secs(9), &mut rng));
+        assert!(!sampler.trial(&"session", start + ttl, &mut rng));
+        assert!(sampler.is_empty());
+    }
+}
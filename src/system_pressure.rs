@@ -0,0 +1,445 @@
+use crate::time::Instant;
+use crate::FastBernoulli;
+use rand::Rng;
+use std::fmt;
+use std::time::Duration;
+use sysinfo::System;
+
+/// Host pressure signals, CPU load, memory use, and cgroup CPU throttling, read
+/// with `sysinfo`.
+///
+/// Each [`read`][SystemPressure::read] refreshes the signals and reports them
+/// as fractions between `0.0` and `1.0`:
+///
+/// * CPU load is the global CPU usage since the previous read. The first read
+///   has nothing to compare against, and reports no load.
+/// * Memory use is the fraction of memory not available to new allocations,
+///   within the process's cgroup limit when it has one.
+/// * Throttling is the fraction of cgroup v2 scheduling periods since the
+///   previous read in which the cgroup was throttled by its CPU quota. It is
+///   always zero outside of a CPU-limited cgroup.
+///
+/// A [`SystemPressureSampler`] reads these for you, but `SystemPressure` can
+/// also drive a [`LoadShedder`][crate::LoadShedder], through
+/// [`PressureReading::pressure`].
+///
+/// Requires the `sysinfo` feature.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::SystemPressure;
+///
+/// let mut pressure = SystemPressure::new();
+/// let reading = pressure.read();
+/// assert!((0.0..=1.0).contains(&reading.pressure()));
+/// ```
+pub struct SystemPressure {
+    system: System,
+    throttling: Option<CpuStat>,
+}
+
+/// A reading of host pressure, taken by [`SystemPressure::read`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[non_exhaustive]
+pub struct PressureReading {
+    /// The fraction of CPU time in use, from `0.0` to `1.0`.
+    pub cpu: f64,
+    /// The fraction of memory in use, from `0.0` to `1.0`.
+    pub memory: f64,
+    /// The fraction of scheduling periods in which the cgroup was throttled,
+    /// from `0.0` to `1.0`.
+    pub throttling: f64,
+}
+
+/// Cumulative scheduling counts from a cgroup's `cpu.stat`.
+#[derive(Debug, Clone, Copy)]
+struct CpuStat {
+    periods: u64,
+    throttled: u64,
+}
+
+impl fmt::Debug for SystemPressure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SystemPressure")
+            .field("throttling", &self.throttling)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for SystemPressure {
+    fn default() -> Self {
+        SystemPressure::new()
+    }
+}
+
+impl SystemPressure {
+    /// Construct a new `SystemPressure`, taking the baseline that the first
+    /// read's CPU load and throttling are measured from.
+    pub fn new() -> Self {
+        let mut system = System::new();
+        system.refresh_cpu_usage();
+        SystemPressure {
+            system,
+            throttling: CpuStat::read(),
+        }
+    }
+
+    /// Refresh and read the host's pressure signals.
+    pub fn read(&mut self) -> PressureReading {
+        self.system.refresh_cpu_usage();
+        self.system.refresh_memory();
+
+        let cpu = f64::from(self.system.global_cpu_usage()) / 100.0;
+
+        let (total, available) = match self.system.cgroup_limits() {
+            // The cgroup's own free memory excludes reclaimable page cache, so
+            // an idle cgroup would read as nearly full. Its headroom above the
+            // resident set is bounded by what the host still has available.
+            Some(limits) if limits.total_memory > 0 => (
+                limits.total_memory,
+                limits
+                    .total_memory
+                    .saturating_sub(limits.rss)
+                    .min(self.system.available_memory()),
+            ),
+            _ => (self.system.total_memory(), self.system.available_memory()),
+        };
+        let memory = if total == 0 {
+            0.0
+        } else {
+            1.0 - available as f64 / total as f64
+        };
+
+        let current = CpuStat::read();
+        let throttling = match (self.throttling, current) {
+            (Some(previous), Some(current)) if current.periods > previous.periods => {
+                current.throttled.saturating_sub(previous.throttled) as f64
+                    / (current.periods - previous.periods) as f64
+            }
+            _ => 0.0,
+        };
+        self.throttling = current;
+
+        PressureReading::new(cpu, memory, throttling)
+    }
+}
+
+impl CpuStat {
+    /// Read the current cgroup's CPU statistics, if it is a cgroup v2 with a
+    /// CPU controller.
+    fn read() -> Option<CpuStat> {
+        let stat = std::fs::read_to_string("/sys/fs/cgroup/cpu.stat").ok()?;
+        let field = |name: &str| {
+            stat.lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+                .and_then(|value| value.trim().parse().ok())
+        };
+        Some(CpuStat {
+            periods: field("nr_periods")?,
+            throttled: field("nr_throttled")?,
+        })
+    }
+}
+
+impl PressureReading {
+    /// Construct a new `PressureReading` from its parts, each clamped into
+    /// `0.0..=1.0`.
+    pub fn new(cpu: f64, memory: f64, throttling: f64) -> Self {
+        let clamp = |x: f64| if x.is_nan() { 0.0 } else { x.clamp(0.0, 1.0) };
+        PressureReading {
+            cpu: clamp(cpu),
+            memory: clamp(memory),
+            throttling: clamp(throttling),
+        }
+    }
+
+    /// Get the overall pressure: the most pressing of the signals.
+    #[inline]
+    pub fn pressure(&self) -> f64 {
+        self.cpu.max(self.memory).max(self.throttling)
+    }
+}
+
+/// A sampler that samples less while its host is under pressure.
+///
+/// At most once per interval, a trial reads the host's [`SystemPressure`],
+/// and the sampler switches between a relaxed and a pressured probability
+/// with hysteresis: it becomes pressured once the overall
+/// [`pressure`][PressureReading::pressure] reaches the enter threshold, and
+/// is relieved only once the pressure drops below the lower exit threshold.
+/// Pressure hovering around a single threshold doesn't make the sampler
+/// flap between probabilities.
+///
+/// By default the thresholds are `0.9` to enter and `0.7` to exit, and the
+/// interval is one second. Every event is counted in [`SystemPressureStats`],
+/// separately for relaxed and pressured periods, and sampled events come with
+/// the weight in effect when they were sampled.
+///
+/// Requires the `sysinfo` feature.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::SystemPressureSampler;
+/// use std::time::{Duration, Instant};
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Sample 1% of events, or 0.1% while the host is overloaded.
+/// let mut sampler = SystemPressureSampler::new(0.01, 0.001, &mut rng)
+///     .with_thresholds(0.85, 0.6)
+///     .with_interval(Duration::from_secs(5));
+///
+/// if let Some(_weight) = sampler.trial(Instant::now(), &mut rng) {
+///     // Record the sample, with its weight...
+/// }
+/// ```
+pub struct SystemPressureSampler {
+    pressure: SystemPressure,
+    interval: Duration,
+    next_evaluation: Option<Instant>,
+    relaxed: FastBernoulli,
+    pressured: FastBernoulli,
+    enter: f64,
+    exit: f64,
+    currently_pressured: bool,
+    last_reading: PressureReading,
+    stats: SystemPressureStats,
+}
+
+/// Statistics about a [`SystemPressureSampler`]'s relaxed and pressured
+/// periods.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[non_exhaustive]
+pub struct SystemPressureStats {
+    /// Events seen while the host was not under pressure.
+    pub events: u64,
+    /// Events sampled while the host was not under pressure.
+    pub samples: u64,
+    /// Events seen while the host was under pressure.
+    pub pressured_events: u64,
+    /// Events sampled while the host was under pressure.
+    pub pressured_samples: u64,
+    /// The number of distinct periods of pressure that have begun.
+    pub pressure_periods: u64,
+}
+
+impl fmt::Debug for SystemPressureSampler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SystemPressureSampler")
+            .field("interval", &self.interval)
+            .field("relaxed", &self.relaxed)
+            .field("pressured", &self.pressured)
+            .field("enter", &self.enter)
+            .field("exit", &self.exit)
+            .field("currently_pressured", &self.currently_pressured)
+            .field("last_reading", &self.last_reading)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SystemPressureSampler {
+    /// Construct a new `SystemPressureSampler` that samples with
+    /// `probability` normally and with `pressured_probability` while the host
+    /// is under pressure.
+    ///
+    /// # Panics
+    ///
+    /// Both probabilities must be within the range `0.0 <= probability <= 1.0`
+    /// and this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, pressured_probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        SystemPressureSampler {
+            pressure: SystemPressure::new(),
+            interval: Duration::from_secs(1),
+            next_evaluation: None,
+            relaxed: FastBernoulli::new(probability, rng),
+            pressured: FastBernoulli::new(pressured_probability, rng),
+            enter: 0.9,
+            exit: 0.7,
+            currently_pressured: false,
+            last_reading: PressureReading::default(),
+            stats: SystemPressureStats::default(),
+        }
+    }
+
+    /// Become pressured once the pressure reaches `enter`, and be relieved
+    /// once it drops below `exit`.
+    ///
+    /// # Panics
+    ///
+    /// The thresholds must satisfy `0.0 <= exit <= enter <= 1.0` and this
+    /// method will panic if that is not the case.
+    pub fn with_thresholds(mut self, enter: f64, exit: f64) -> Self {
+        assert!(
+            0.0 <= exit && exit <= enter && enter <= 1.0,
+            "thresholds must satisfy `0.0 <= exit <= enter <= 1.0`"
+        );
+        self.enter = enter;
+        self.exit = exit;
+        self
+    }
+
+    /// Read the host's pressure at most once per `interval`.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Perform a trial for an event that occurred at `now`, first reading the
+    /// host's pressure if the interval has elapsed.
+    ///
+    /// Returns the sampled event's weight, which is the reciprocal of the
+    /// probability it was sampled with, or `None` if it was not sampled.
+    pub fn trial<R>(&mut self, now: Instant, rng: &mut R) -> Option<f64>
+    where
+        R: Rng + ?Sized,
+    {
+        match self.next_evaluation {
+            Some(next) if now < next => {}
+            _ => {
+                self.next_evaluation = Some(now + self.interval);
+                let reading = self.pressure.read();
+                self.observe(reading);
+            }
+        }
+
+        let (bernoulli, events, samples) = if self.currently_pressured {
+            (
+                &mut self.pressured,
+                &mut self.stats.pressured_events,
+                &mut self.stats.pressured_samples,
+            )
+        } else {
+            (
+                &mut self.relaxed,
+                &mut self.stats.events,
+                &mut self.stats.samples,
+            )
+        };
+
+        *events += 1;
+        if bernoulli.trial(rng) {
+            *samples += 1;
+            Some(1.0 / bernoulli.effective_probability())
+        } else {
+            None
+        }
+    }
+
+    /// Update the pressure state from `reading`, as if it had just been read
+    /// from the host.
+    pub fn observe(&mut self, reading: PressureReading) {
+        self.last_reading = reading;
+        let pressure = reading.pressure();
+        let pressured = if self.currently_pressured {
+            pressure >= self.exit
+        } else {
+            pressure >= self.enter
+        };
+        if pressured != self.currently_pressured {
+            if pressured {
+                self.stats.pressure_periods += 1;
+            }
+            trace_sampler!(
+                info,
+                pressure,
+                cpu = reading.cpu,
+                memory = reading.memory,
+                throttling = reading.throttling,
+                pressured,
+                "host pressure changed"
+            );
+        }
+        self.currently_pressured = pressured;
+    }
+
+    /// Was the host under pressure as of the last reading?
+    #[inline]
+    pub fn is_pressured(&self) -> bool {
+        self.currently_pressured
+    }
+
+    /// Get the most recent pressure reading.
+    #[inline]
+    pub fn last_reading(&self) -> PressureReading {
+        self.last_reading
+    }
+
+    /// Get the probability that the next trial will use, assuming the
+    /// pressure state doesn't change.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        if self.currently_pressured {
+            self.pressured.probability()
+        } else {
+            self.relaxed.probability()
+        }
+    }
+
+    /// Get the statistics for the events seen so far.
+    #[inline]
+    pub fn stats(&self) -> SystemPressureStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switches_with_hysteresis() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = SystemPressureSampler::new(1.0, 0.0, &mut rng)
+            .with_thresholds(0.9, 0.7)
+            .with_interval(Duration::from_secs(3600));
+        // Don't let a trial read the host: drive the state through `observe`.
+        let now = Instant::now();
+        sampler.next_evaluation = Some(now + sampler.interval);
+
+        for (cpu, pressured) in [
+            (0.85, false),
+            (0.95, true),
+            (0.8, true),
+            (0.9, true),
+            (0.6, false),
+            (0.8, false),
+        ] {
+            sampler.observe(PressureReading::new(cpu, 0.0, 0.0));
+            assert_eq!(sampler.is_pressured(), pressured, "at {cpu}");
+            assert_eq!(sampler.trial(now, &mut rng).is_some(), !pressured);
+        }
+
+        // Any one signal is enough.
+        sampler.observe(PressureReading::new(0.0, 0.0, 1.0));
+        assert!(sampler.is_pressured());
+
+        let stats = sampler.stats();
+        assert_eq!(stats.events, 3);
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.pressured_events, 3);
+        assert_eq!(stats.pressured_samples, 0);
+        assert_eq!(stats.pressure_periods, 2);
+    }
+
+    #[test]
+    fn readings_are_fractions() {
+        let mut pressure = SystemPressure::new();
+        for _ in 0..2 {
+            let reading = pressure.read();
+            for signal in [reading.cpu, reading.memory, reading.throttling] {
+                assert!((0.0..=1.0).contains(&signal), "{reading:?}");
+            }
+        }
+        assert_eq!(PressureReading::new(2.0, f64::NAN, -1.0).pressure(), 1.0);
+    }
+}
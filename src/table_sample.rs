@@ -0,0 +1,145 @@
+use crate::FastBernoulli;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Row sampling with the semantics of SQL's `TABLESAMPLE BERNOULLI`.
+///
+/// `TABLESAMPLE BERNOULLI (percent)` includes each row independently with
+/// probability `percent / 100`, and `REPEATABLE (seed)` makes the sample
+/// deterministic: the same seed over the same rows, in the same order, selects
+/// the same rows. `TableSample` does the same for any iterator of rows, so
+/// that samples taken in Rust are comparable to samples taken in the database.
+///
+/// Rather than deciding row by row, the sample skips ahead over unselected
+/// rows with [`Iterator::nth`], which is `O(1)` for slices, vectors, and
+/// ranges.
+///
+/// Repeatable samples use [`StdRng`], whose algorithm may change between
+/// major versions of `rand`, so they are only reproducible with the same
+/// version.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::TableSample;
+///
+/// let rows: Vec<u32> = (0..100_000).collect();
+///
+/// // SELECT * FROM rows TABLESAMPLE BERNOULLI (1) REPEATABLE (42)
+/// let sample = TableSample::bernoulli(1.0).repeatable(42);
+/// let first: Vec<&u32> = sample.rows(&rows).collect();
+/// let second: Vec<&u32> = sample.rows(&rows).collect();
+/// assert_eq!(first, second);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TableSample {
+    percent: f64,
+    seed: Option<u64>,
+}
+
+/// An iterator over the sampled rows of a [`TableSample`], returned by
+/// [`TableSample::rows`].
+#[derive(Debug, Clone)]
+pub struct TableSampleRows<I> {
+    rows: I,
+    bernoulli: FastBernoulli,
+    rng: StdRng,
+}
+
+impl TableSample {
+    /// Construct a new `TableSample` that includes each row with the given
+    /// percentage.
+    ///
+    /// # Panics
+    ///
+    /// The percentage must be within the range `0.0 <= percent <= 100.0` and
+    /// this method will panic if that is not the case.
+    pub fn bernoulli(percent: f64) -> Self {
+        assert!(
+            (0.0..=100.0).contains(&percent),
+            "`percent` must be in the range `0.0 <= percent <= 100.0`"
+        );
+        TableSample {
+            percent,
+            seed: None,
+        }
+    }
+
+    /// Make the sample repeatable, seeding it with `seed`.
+    #[inline]
+    pub fn repeatable(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sample the given rows.
+    pub fn rows<I>(&self, rows: I) -> TableSampleRows<I::IntoIter>
+    where
+        I: IntoIterator,
+    {
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_rng(crate::default_rng()).expect("the default RNG never fails"),
+        };
+        TableSampleRows {
+            rows: rows.into_iter(),
+            bernoulli: FastBernoulli::new(self.probability(), &mut rng),
+            rng,
+        }
+    }
+
+    /// Get the percentage of rows to include.
+    #[inline]
+    pub fn percent(&self) -> f64 {
+        self.percent
+    }
+
+    /// Get the probability with which each row is included.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        // Clamp away any rounding above `1.0`.
+        (self.percent / 100.0).min(1.0)
+    }
+
+    /// Get the seed, if the sample is repeatable.
+    #[inline]
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
+impl<I> Iterator for TableSampleRows<I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.bernoulli.probability() == 0.0 {
+            return None;
+        }
+        let skip = self.bernoulli.skip_count() as usize;
+        let row = self.rows.nth(skip)?;
+        self.bernoulli.reset_skip_count(&mut self.rng);
+        Some(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_the_configured_percentage() {
+        let rows = 0..1_000_000_u32;
+        let sample = TableSample::bernoulli(5.0);
+        let sampled = sample.rows(rows.clone()).count() as f64;
+
+        let expected = 50_000.0;
+        let tolerance = 5.0 * (expected * 0.95_f64).sqrt();
+        assert!((sampled - expected).abs() <= tolerance);
+
+        assert_eq!(TableSample::bernoulli(0.0).rows(rows.clone()).count(), 0);
+        assert_eq!(TableSample::bernoulli(100.0).rows(rows).count(), 1_000_000);
+    }
+}
@@ -0,0 +1,322 @@
+use crate::rate::TokenBucket;
+use crate::time::Instant;
+use crate::{FastBernoulli, Rate};
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A tenant's sampling policy in a [`TenantSampler`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TenantPolicy {
+    probability: f64,
+    quota: Option<Rate>,
+}
+
+impl TenantPolicy {
+    /// Construct a new policy that samples the tenant's events with the given
+    /// probability, with no quota.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new(probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        TenantPolicy {
+            probability,
+            quota: None,
+        }
+    }
+
+    /// Limit the tenant to at most `quota` samples.
+    #[inline]
+    pub fn with_quota(mut self, quota: Rate) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Get the probability with which the tenant's events are sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// Get the tenant's quota, if any.
+    #[inline]
+    pub fn quota(&self) -> Option<Rate> {
+        self.quota
+    }
+}
+
+/// Per-tenant counts from a [`TenantSampler`], for reweighting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[non_exhaustive]
+pub struct TenantStats {
+    /// Events offered for this tenant.
+    pub events: u64,
+    /// Events sampled for this tenant.
+    pub samples: u64,
+    /// Events that won their Bernoulli trial, but were dropped by the tenant's
+    /// quota or the global cap.
+    pub capped: u64,
+}
+
+/// Multi-tenant sampling, with per-tenant probabilities and quotas and a
+/// global cap.
+///
+/// Each event belongs to a tenant. It is sampled if it wins a Bernoulli trial
+/// at its tenant's probability, and its tenant is within its quota, and the
+/// sampler as a whole is within its global cap. Tenants without a policy of
+/// their own use the default policy, but still get their own quota.
+///
+/// Policies can be changed for individual tenants at any time with
+/// [`set_policy`][TenantSampler::set_policy], without disturbing other
+/// tenants.
+///
+/// Since quotas and the cap drop events that won their trials, the sample is
+/// no longer a Bernoulli sample of the tenant's events whenever they kick in.
+/// [`TenantStats`] counts the events offered and sampled per tenant, so that
+/// `events / samples` can be used as the weight of each sampled event.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{RateExt, TenantPolicy, TenantSampler};
+/// use std::time::Instant;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Sample 1% of each tenant's events, at most 100 per tenant per second, and
+/// // at most 1,000 per second overall.
+/// let mut sampler = TenantSampler::new(TenantPolicy::new(0.01).with_quota(100.per_second()))
+///     .with_global_cap(1_000.per_second());
+///
+/// // A noisy tenant gets a lower probability.
+/// sampler.set_policy("acme", TenantPolicy::new(0.001), &mut rng);
+///
+/// let now = Instant::now();
+/// if sampler.trial(&"acme", now, &mut rng) {
+///     // Record the sample...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TenantSampler<T> {
+    default_policy: TenantPolicy,
+    global_cap: Option<TokenBucket>,
+    tenants: HashMap<T, TenantState>,
+}
+
+#[derive(Debug, Clone)]
+struct TenantState {
+    policy: TenantPolicy,
+    explicit: bool,
+    bernoulli: FastBernoulli,
+    quota: Option<TokenBucket>,
+    stats: TenantStats,
+}
+
+impl TenantState {
+    fn new<R>(policy: TenantPolicy, explicit: bool, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        TenantState {
+            policy,
+            explicit,
+            bernoulli: FastBernoulli::new(policy.probability, rng),
+            quota: policy.quota.map(TokenBucket::new),
+            stats: TenantStats::default(),
+        }
+    }
+
+    fn update<R>(&mut self, policy: TenantPolicy, explicit: bool, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        let stats = self.stats;
+        *self = TenantState::new(policy, explicit, rng);
+        self.stats = stats;
+    }
+}
+
+impl<T> TenantSampler<T>
+where
+    T: Hash + Eq + Clone,
+{
+    /// Construct a new `TenantSampler` that applies `default_policy` to tenants
+    /// without a policy of their own, and has no global cap.
+    pub fn new(default_policy: TenantPolicy) -> Self {
+        TenantSampler {
+            default_policy,
+            global_cap: None,
+            tenants: HashMap::new(),
+        }
+    }
+
+    /// Limit the total number of samples across all tenants to at most `cap`.
+    pub fn with_global_cap(mut self, cap: Rate) -> Self {
+        self.global_cap = Some(TokenBucket::new(cap));
+        self
+    }
+
+    /// Set the policy for `tenant`, replacing its previous one.
+    ///
+    /// The tenant's quota starts afresh, and its counts are kept.
+    pub fn set_policy<R>(&mut self, tenant: T, policy: TenantPolicy, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        trace_sampler!(
+            info,
+            from = self.policy(&tenant).probability(),
+            to = policy.probability(),
+            "tenant probability changed"
+        );
+        match self.tenants.get_mut(&tenant) {
+            Some(state) => state.update(policy, true, rng),
+            None => {
+                self.tenants
+                    .insert(tenant, TenantState::new(policy, true, rng));
+            }
+        }
+    }
+
+    /// Remove `tenant`'s own policy, returning it to the default policy.
+    pub fn clear_policy<R>(&mut self, tenant: &T, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        let default_policy = self.default_policy;
+        if let Some(state) = self.tenants.get_mut(tenant) {
+            if state.explicit {
+                state.update(default_policy, false, rng);
+            }
+        }
+    }
+
+    /// Set the default policy, for tenants without a policy of their own.
+    pub fn set_default_policy<R>(&mut self, policy: TenantPolicy, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        trace_sampler!(
+            info,
+            from = self.default_policy.probability(),
+            to = policy.probability(),
+            "default tenant probability changed"
+        );
+        self.default_policy = policy;
+        for state in self.tenants.values_mut() {
+            if !state.explicit {
+                state.update(policy, false, rng);
+            }
+        }
+    }
+
+    /// Perform a trial for an event belonging to `tenant`, which occurred at
+    /// `now`.
+    pub fn trial<R>(&mut self, tenant: &T, now: Instant, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let default_policy = self.default_policy;
+        let state = match self.tenants.get_mut(tenant) {
+            Some(state) => state,
+            None => self
+                .tenants
+                .entry(tenant.clone())
+                .or_insert_with(|| TenantState::new(default_policy, false, rng)),
+        };
+
+        state.stats.events += 1;
+        if !state.bernoulli.trial(rng) {
+            return false;
+        }
+
+        // Only spend tokens once both the tenant's quota and the global cap
+        // can afford it, so that one doesn't waste the other's budget.
+        if let Some(quota) = &mut state.quota {
+            quota.refill(now);
+        }
+        if let Some(cap) = &mut self.global_cap {
+            cap.refill(now);
+        }
+        let within_quota = state.quota.as_ref().is_none_or(TokenBucket::has_token);
+        let within_cap = self.global_cap.as_ref().is_none_or(TokenBucket::has_token);
+        if !(within_quota && within_cap) {
+            state.stats.capped += 1;
+            return false;
+        }
+        if let Some(quota) = &mut state.quota {
+            quota.spend();
+        }
+        if let Some(cap) = &mut self.global_cap {
+            cap.spend();
+        }
+
+        state.stats.samples += 1;
+        true
+    }
+
+    /// Get the policy in effect for `tenant`.
+    pub fn policy(&self, tenant: &T) -> TenantPolicy {
+        self.tenants
+            .get(tenant)
+            .map_or(self.default_policy, |state| state.policy)
+    }
+
+    /// Get the counts for `tenant`, if any of its events have been offered.
+    pub fn stats(&self, tenant: &T) -> Option<TenantStats> {
+        self.tenants.get(tenant).map(|state| state.stats)
+    }
+
+    /// Get the number of tenants being tracked.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tenants.len()
+    }
+
+    /// Are no tenants being tracked?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RateExt;
+
+    #[test]
+    fn quotas_and_global_cap() {
+        let mut rng = rand::thread_rng();
+        let mut sampler = TenantSampler::new(TenantPolicy::new(1.0).with_quota(10.per_second()))
+            .with_global_cap(15.per_second());
+        sampler.set_policy("quiet", TenantPolicy::new(0.0), &mut rng);
+
+        let now = Instant::now();
+        let sampled = |sampler: &mut TenantSampler<&str>, tenant, rng: &mut _| {
+            (0..100)
+                .filter(|_| sampler.trial(&tenant, now, rng))
+                .count()
+        };
+        assert_eq!(sampled(&mut sampler, "quiet", &mut rng), 0);
+        assert_eq!(sampled(&mut sampler, "a", &mut rng), 10);
+        assert_eq!(sampled(&mut sampler, "b", &mut rng), 5);
+
+        let stats = sampler.stats(&"a").unwrap();
+        assert_eq!((stats.events, stats.samples, stats.capped), (100, 10, 90));
+        assert_eq!(sampler.stats(&"quiet").unwrap().capped, 0);
+
+        sampler.clear_policy(&"quiet", &mut rng);
+        assert_eq!(sampler.policy(&"quiet").probability(), 1.0);
+    }
+}
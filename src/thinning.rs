@@ -0,0 +1,100 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// Bernoulli trials at a probability that may change from one trial to the
+/// next, as cheap as a plain [`FastBernoulli`]'s.
+///
+/// Candidates are drawn with skip counts at an upper bound of the probability,
+/// and each candidate is kept with the ratio of the probability at that trial
+/// to the bound. The bound is redrawn at the current probability whenever the
+/// probability rises above it, or falls below half of it, so that at least
+/// half of all candidates are kept. Geometric skip counts are memoryless, so
+/// discarding the in-flight skip count when the bound is redrawn is exact.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ThinnedBernoulli {
+    candidates: FastBernoulli,
+}
+
+impl ThinnedBernoulli {
+    pub(crate) fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        ThinnedBernoulli {
+            candidates: FastBernoulli::new(probability, rng),
+        }
+    }
+
+    /// Redraw the bound at `probability`, whether or not it is loose.
+    pub(crate) fn rebound<R>(&mut self, probability: f64, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        self.candidates = FastBernoulli::new(probability, rng);
+    }
+
+    /// Perform a trial at `probability`, first redrawing the bound if it no
+    /// longer bounds the probability, or is loose.
+    pub(crate) fn trial<R>(&mut self, probability: f64, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let bound = self.bound();
+        if probability > bound || probability < bound / 2.0 {
+            self.rebound(probability, rng);
+        }
+
+        if !self.candidates.trial(rng) {
+            return false;
+        }
+        let bound = self.bound();
+        probability >= bound || rng.gen::<f64>() * bound < probability
+    }
+
+    /// Get the probability with which candidates are drawn.
+    #[inline]
+    pub(crate) fn bound(&self) -> f64 {
+        self.candidates.probability()
+    }
+
+    /// Get the rate at which candidates are actually drawn; see
+    /// [`FastBernoulli::effective_probability`].
+    #[inline]
+    pub(crate) fn effective_bound(&self) -> f64 {
+        self.candidates.effective_probability()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_at_the_changing_probability() {
+        let mut rng = rand::thread_rng();
+        let mut thinned = ThinnedBernoulli::new(0.5, &mut rng);
+
+        let mut expected = 0.0;
+        let mut variance = 0.0;
+        let mut sampled = 0;
+        for i in 0..100_000 {
+            // Falls from 50% to 0.5% and back, tightening and loosening the
+            // bound along the way.
+            let p = 0.5 * 0.01_f64.powf(1.0 - (f64::from(i) / 50_000.0 - 1.0).abs());
+            expected += p;
+            variance += p * (1.0 - p);
+            if thinned.trial(p, &mut rng) {
+                sampled += 1;
+            }
+            assert!(p <= thinned.bound() && thinned.bound() <= 2.0 * p);
+        }
+
+        let tolerance = 5.0 * variance.sqrt();
+        assert!(
+            (f64::from(sampled) - expected).abs() <= tolerance,
+            "expected ~{} samples, found {}",
+            expected,
+            sampled,
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::FastBernoulli;
+use rand::Rng;
+
+/// Simultaneous sampling at several nested rates, such as 10%, 1%, and 0.1%.
+///
+/// Each trial reports how many tiers the event falls into. Tier `k` is
+/// sampled with probability `probabilities[k]`, and because the tiers are
+/// nested, every event in tier `k` is also in every tier before it: the
+/// tier-`k` sample is always a subset of the tier-`(k - 1)` sample.
+///
+/// Conceptually, every event draws a single uniform number `x` from `[0, 1)`
+/// and is in tier `k` when `x < probabilities[k]`. In practice, only the
+/// outermost tier performs Bernoulli trials, using skip counts as
+/// [`FastBernoulli`] does; an event that survives it draws one uniform number
+/// to pick its depth within the remaining tiers. This is equivalent, and
+/// keeps the cost for unsampled events as low as a plain `FastBernoulli`.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::TieredSampler;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sampler = TieredSampler::new(&[0.1, 0.01, 0.001], &mut rng);
+///
+/// # let mut datasets: [Vec<u32>; 3] = Default::default();
+/// # let event = 42;
+/// let depth = sampler.trial(&mut rng);
+/// for dataset in &mut datasets[..depth] {
+///     dataset.push(event);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TieredSampler {
+    outer: FastBernoulli,
+    probabilities: Vec<f64>,
+    // `probabilities[k] / probabilities[0]` for each inner tier `k >= 1`: the
+    // probability of reaching tier `k` given that the event is in tier `0`.
+    conditional: Vec<f64>,
+}
+
+impl TieredSampler {
+    /// Construct a new `TieredSampler` with the given per-tier probabilities.
+    ///
+    /// # Panics
+    ///
+    /// There must be at least one tier, every probability must be within the
+    /// range `0.0 <= probability <= 1.0`, and the probabilities must be
+    /// non-increasing. This method will panic if that is not the case.
+    pub fn new<R>(probabilities: &[f64], rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(!probabilities.is_empty(), "there must be at least one tier");
+        for p in probabilities {
+            assert!(
+                (0.0..=1.0).contains(p),
+                "every probability must be in the range `0.0 <= probability <= 1.0`"
+            );
+        }
+        assert!(
+            probabilities.windows(2).all(|w| w[1] <= w[0]),
+            "tier probabilities must be non-increasing"
+        );
+
+        let outer = probabilities[0];
+        let conditional = probabilities[1..]
+            .iter()
+            .map(|&p| if outer == 0.0 { 0.0 } else { p / outer })
+            .collect();
+
+        TieredSampler {
+            outer: FastBernoulli::new(outer, rng),
+            probabilities: probabilities.to_vec(),
+            conditional,
+        }
+    }
+
+    /// Perform a trial, returning the number of tiers the event falls into.
+    ///
+    /// A return value of `depth` means the event is in tiers `0..depth`; zero
+    /// means the event is not sampled at all.
+    pub fn trial<R>(&mut self, rng: &mut R) -> usize
+    where
+        R: Rng + ?Sized,
+    {
+        if !self.outer.trial(rng) {
+            return 0;
+        }
+        if self.conditional.is_empty() {
+            return 1;
+        }
+
+        let x: f64 = rng.gen_range(0.0..1.0);
+        // Thresholds are non-increasing, so the tiers containing `x` form a
+        // prefix.
+        1 + self.conditional.iter().take_while(|&&c| x < c).count()
+    }
+
+    /// Get the number of tiers.
+    #[inline]
+    pub fn num_tiers(&self) -> usize {
+        self.probabilities.len()
+    }
+
+    /// Get the probability with which events are sampled into the given tier,
+    /// or `None` if there is no such tier.
+    #[inline]
+    pub fn probability(&self, tier: usize) -> Option<f64> {
+        self.probabilities.get(tier).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_tier_sizes() {
+        let mut rng = rand::thread_rng();
+        let probabilities = [0.5, 0.1, 0.02];
+        let mut sampler = TieredSampler::new(&probabilities, &mut rng);
+
+        let events = 100_000;
+        let mut counts = [0; 3];
+        for _ in 0..events {
+            for count in &mut counts[..sampler.trial(&mut rng)] {
+                *count += 1;
+            }
+        }
+
+        for (count, p) in counts.iter().zip(probabilities) {
+            let expected = p * events as f64;
+            assert!(
+                (*count as f64 - expected).abs() < expected * 0.15,
+                "expected ~{} samples, found {}",
+                expected,
+                count,
+            );
+        }
+    }
+}
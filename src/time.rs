@@ -0,0 +1,12 @@
+//! The clock types that time-based samplers take and return.
+//!
+//! These are the standard library's, except with the `web-time` feature
+//! enabled, when they are `web_time`'s. Those are the standard library's too
+//! on every target but `wasm32-unknown-unknown`, where the standard library's
+//! panic when asked for the current time, and `web_time`'s read
+//! `performance.now()` and `Date.now()` instead.
+
+#[cfg(not(feature = "web-time"))]
+pub(crate) use std::time::{Instant, SystemTime};
+#[cfg(feature = "web-time")]
+pub(crate) use web_time::{Instant, SystemTime};
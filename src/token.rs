@@ -0,0 +1,54 @@
+use crate::hash::hash_with_salt;
+use std::hash::Hash;
+
+/// Derive a stable 64-bit token identifying the decision to sample `key` with
+/// the given probability during the given policy epoch.
+///
+/// Services that observe the same entity under the same policy derive the
+/// same token, on any machine and in any process, so the token can be attached
+/// to samples to deduplicate them downstream. Because the probability and
+/// epoch are mixed in, tokens from services running different policies don't
+/// match, which makes mismatched configurations visible instead of silently
+/// double-counting or dropping samples.
+///
+/// The epoch is an arbitrary policy version chosen by the caller, e.g. a
+/// configuration revision or a day number.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::decision_token;
+///
+/// let a = decision_token(&"trace-1234", 0.01, 7);
+/// let b = decision_token(&"trace-1234", 0.01, 7);
+/// assert_eq!(a, b);
+///
+/// // A different policy gives a different token.
+/// assert_ne!(a, decision_token(&"trace-1234", 0.02, 7));
+/// assert_ne!(a, decision_token(&"trace-1234", 0.01, 8));
+/// ```
+pub fn decision_token<K>(key: &K, probability: f64, epoch: u64) -> u64
+where
+    K: Hash + ?Sized,
+{
+    // Adding zero turns `-0.0` into `0.0`, so that equal probabilities always
+    // have equal bits.
+    let probability = (probability + 0.0).to_bits();
+    hash_with_salt(epoch, &(probability, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_across_runs() {
+        // This value must never change: tokens are compared across services
+        // and releases.
+        assert_eq!(decision_token(&42_u64, 0.5, 1), 0xb1dc_a578_c600_75cf_u64);
+        assert_eq!(
+            decision_token(&1_u32, -0.0, 0),
+            decision_token(&1_u32, 0.0, 0)
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use crate::estimate::multi_trial_probability;
+use crate::WideBernoulli;
+use rand::Rng;
+
+/// [`multi_trial`][crate::FastBernoulli::multi_trial] over sizes in natural units,
+/// such as bytes, with the sampling probability configured per larger unit,
+/// such as KiB or 4KiB pages.
+///
+/// Dividing sizes by the unit before calling `multi_trial` is biased: a
+/// 100-byte allocation rounds down to zero KiB and is never sampled, or up to
+/// one KiB and is sampled ten times too often. Instead, this converts the
+/// per-unit probability to the equivalent per-byte probability, so that a size
+/// of `s` is sampled with probability exactly `1 - (1 - p)^(s / unit)`, for
+/// fractional units too.
+///
+/// Sizes are `u64`, so that they can't overflow, and trials use a
+/// [`WideBernoulli`], so that the tiny per-byte probabilities that large units
+/// and low rates call for aren't skewed by
+/// [`FastBernoulli`][crate::FastBernoulli]'s `u32::MAX` clamp on skip counts.
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::UnitScaledBernoulli;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Sample each 4KiB page of allocated memory with probability 1%.
+/// let mut sampler = UnitScaledBernoulli::new(0.01, 4096, &mut rng);
+///
+/// // Allocation sizes are in bytes.
+/// if sampler.multi_trial(100, &mut rng) {
+///     // Record a sample of this allocation, with weight...
+///     let weight = 1.0 / sampler.probability_for(100);
+///     # let _ = weight;
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct UnitScaledBernoulli {
+    bernoulli: WideBernoulli,
+    probability: f64,
+    unit: u64,
+}
+
+impl UnitScaledBernoulli {
+    /// Construct a new `UnitScaledBernoulli` that samples each `unit` of size
+    /// with the given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0`,
+    /// and the unit must be non-zero, and this method will panic if that is not
+    /// the case.
+    pub fn new<R>(probability: f64, unit: u64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        assert!(unit > 0, "`unit` must be non-zero");
+        // Solve `1 - (1 - q)^unit = p` for the per-byte probability `q`.
+        let per_byte = if probability == 1.0 {
+            1.0
+        } else {
+            -((-probability).ln_1p() / unit as f64).exp_m1()
+        };
+        UnitScaledBernoulli {
+            bernoulli: WideBernoulli::new(per_byte.clamp(0.0, 1.0), rng),
+            probability,
+            unit,
+        }
+    }
+
+    /// Perform a trial for an event of the given size, in natural units.
+    ///
+    /// Returns `true` with probability [`probability_for(size)`].
+    ///
+    /// [`probability_for(size)`]: UnitScaledBernoulli::probability_for
+    pub fn multi_trial<R>(&mut self, size: u64, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.bernoulli.multi_trial(size, rng)
+    }
+
+    /// Get the probability with which an event of the given size, in natural
+    /// units, is sampled.
+    pub fn probability_for(&self, size: u64) -> f64 {
+        multi_trial_probability(self.probability, size as f64 / self.unit as f64)
+    }
+
+    /// Get the probability with which each unit of size is sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// Get the unit, in natural units.
+    #[inline]
+    pub fn unit(&self) -> u64 {
+        self.unit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fractional_units_are_sampled_proportionally() {
+        let mut rng = rand::thread_rng();
+        let sampler = UnitScaledBernoulli::new(0.5, 1024, &mut rng);
+        assert!((sampler.probability_for(1024) - 0.5).abs() < 1e-12);
+        assert!((sampler.probability_for(2048) - 0.75).abs() < 1e-12);
+
+        // A quarter-unit event is sampled with probability `1 - 0.5^0.25`.
+        let p = sampler.probability_for(256);
+        let trials = 10_000;
+        let sampled = (0..trials)
+            .filter(|_| UnitScaledBernoulli::new(0.5, 1024, &mut rng).multi_trial(256, &mut rng))
+            .count() as f64;
+        let expected = p * f64::from(trials);
+        assert!((sampled - expected).abs() <= 5.0 * (expected * (1.0 - p)).sqrt());
+
+        // Sizes beyond `u32::MAX` don't overflow.
+        let mut always = UnitScaledBernoulli::new(1.0, 1, &mut rng);
+        assert!(always.multi_trial(u64::MAX, &mut rng));
+        let mut never = UnitScaledBernoulli::new(0.0, 1, &mut rng);
+        assert!(!never.multi_trial(u64::MAX, &mut rng));
+    }
+
+    #[test]
+    fn tiny_per_byte_probabilities_are_not_clamped() {
+        let mut rng = rand::thread_rng();
+
+        // 1e-6 per 4KiB page is about 2.4e-10 per byte, at which a `u32` skip
+        // count would be clamped over a third of the time.
+        let p = 1e-6;
+        let unit = 4096;
+        let mut sampler = UnitScaledBernoulli::new(p, unit, &mut rng);
+
+        // Offer 64GiB at a time, which is sampled with probability
+        // `1 - (1 - p)^(2^24)`, about 0.9999999.
+        let size = 1 << 36;
+        let q = sampler.probability_for(size);
+        assert!(q > 0.99999);
+        let n = 1_000;
+        let sampled = (0..n)
+            .filter(|_| sampler.multi_trial(size, &mut rng))
+            .count();
+        assert!(sampled >= n - 1, "only {sampled} of {n} sampled");
+
+        // Skip counts, in bytes, must average `1 / q - 1` for the per-byte
+        // probability `q`, over four billion, rather than be capped at `2^32`.
+        let q = -((-p).ln_1p() / unit as f64).exp_m1();
+        let draws = 10_000;
+        let total: f64 = (0..draws)
+            .map(|_| {
+                sampler.bernoulli.reset_skip_count(&mut rng);
+                sampler.bernoulli.skip_count() as f64
+            })
+            .sum();
+        let mean = total / f64::from(draws);
+        let expected = 1.0 / q - 1.0;
+        // Geometric skip counts have a standard deviation of about their mean.
+        let tolerance = 5.0 * expected / f64::from(draws).sqrt();
+        assert!(
+            (mean - expected).abs() <= tolerance,
+            "mean skip count of {mean} bytes, expected ~{expected}"
+        );
+    }
+}
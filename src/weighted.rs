@@ -0,0 +1,192 @@
+use crate::estimate::multi_trial_probability;
+use crate::FastBernoulli;
+use rand::Rng;
+use std::time::Duration;
+
+impl FastBernoulli {
+    /// Perform a trial for an event with a fractional size, such as a number
+    /// of CPU-seconds, of `weight` units.
+    ///
+    /// Like [`multi_trial`][FastBernoulli::multi_trial], this samples the
+    /// event if any of its units would be sampled, with probability
+    /// `1 - (1 - p)^weight`, but the weight doesn't have to be an integer.
+    /// Events smaller than one unit are less likely to be sampled than a
+    /// single unit is, and events of weight zero are never sampled.
+    ///
+    /// The whole units are decided from the skip count, like `multi_trial`.
+    /// A fractional remainder needs one random draw of its own, so whole
+    /// weights are cheaper.
+    ///
+    /// # Panics
+    ///
+    /// The weight must be non-negative and this method will panic if that is
+    /// not the case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    ///
+    /// let mut rng = rand::thread_rng();
+    ///
+    /// // Sample about one in every hundred CPU-seconds.
+    /// let mut bernoulli = FastBernoulli::new(0.01, &mut rng);
+    ///
+    /// let cpu_seconds = 0.35;
+    /// if bernoulli.multi_trial_weighted(cpu_seconds, &mut rng) {
+    ///     // Record a sample of this task...
+    /// }
+    /// ```
+    pub fn multi_trial_weighted<R>(&mut self, weight: f64, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(weight >= 0.0, "`weight` must be non-negative");
+
+        if weight > f64::from(u32::MAX) {
+            // Too many whole units for the skip count; since trials are
+            // memoryless, decide directly and start afresh.
+            let sampled = rng.gen::<f64>() < multi_trial_probability(self.probability, weight);
+            self.reset_skip_count(rng);
+            return sampled && self.probability != 0.0;
+        }
+
+        let whole = weight.floor();
+        let fraction = weight - whole;
+        if whole > 0.0 && self.multi_trial(whole as u32, rng) {
+            return true;
+        }
+        // The fractional remainder is independent of the whole units.
+        fraction > 0.0 && rng.gen::<f64>() < multi_trial_probability(self.probability, fraction)
+    }
+
+    /// Perform a trial for an operation that took `elapsed`, where each
+    /// `quantum` of time is sampled with this instance's probability.
+    ///
+    /// This is [`multi_trial_weighted`][FastBernoulli::multi_trial_weighted]
+    /// with a weight of `elapsed / quantum`, so an operation is sampled with
+    /// probability `1 - (1 - p)^(elapsed / quantum)`, and long-running
+    /// operations are proportionally more likely to be sampled than short
+    /// ones.
+    ///
+    /// # Panics
+    ///
+    /// The quantum must be non-zero and this method will panic if that is not
+    /// the case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use fast_bernoulli::FastBernoulli;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut rng = rand::thread_rng();
+    ///
+    /// // Sample about one in every thousand milliseconds of request handling.
+    /// let mut bernoulli = FastBernoulli::new(0.001, &mut rng);
+    /// let per_millisecond = Duration::from_millis(1);
+    ///
+    /// let start = Instant::now();
+    /// // Handle the request...
+    /// if bernoulli.trial_elapsed(start.elapsed(), per_millisecond, &mut rng) {
+    ///     // Record a sample of this request...
+    /// }
+    /// ```
+    pub fn trial_elapsed<R>(&mut self, elapsed: Duration, quantum: Duration, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(!quantum.is_zero(), "`quantum` must be non-zero");
+        self.multi_trial_weighted(elapsed.as_secs_f64() / quantum.as_secs_f64(), rng)
+    }
+
+    /// The probability with which `multi_trial_weighted(weight)` samples an
+    /// event, accounting for clamped skip counts; see
+    /// [`effective_probability`][FastBernoulli::effective_probability].
+    ///
+    /// Whole units are decided by the skip count, at the effective
+    /// probability, and the rest directly, at the configured one.
+    pub(crate) fn multi_trial_weighted_probability(&self, weight: f64) -> f64 {
+        if weight > f64::from(u32::MAX) || self.probability == 1.0 {
+            return multi_trial_probability(self.probability, weight);
+        }
+        let whole = weight.floor();
+        let missed = whole * (-self.effective_probability()).ln_1p()
+            + (weight - whole) * (-self.probability).ln_1p();
+        -missed.exp_m1()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fractional_weights_have_exact_probabilities() {
+        let mut rng = rand::thread_rng();
+        let p = 0.2;
+        let mut bernoulli = FastBernoulli::new(p, &mut rng);
+
+        let n = 20_000;
+        for weight in [0.5, 2.25] {
+            let sampled = (0..n)
+                .filter(|_| bernoulli.multi_trial_weighted(weight, &mut rng))
+                .count() as f64;
+            let q = 1.0 - (1.0 - p).powf(weight);
+            let expected = q * f64::from(n);
+            assert!((sampled - expected).abs() <= 5.0 * (expected * (1.0 - q)).sqrt());
+        }
+
+        assert!(!bernoulli.multi_trial_weighted(0.0, &mut rng));
+        assert!(bernoulli.multi_trial_weighted(1e12, &mut rng));
+
+        let mut all = FastBernoulli::new(1.0, &mut rng);
+        assert!(all.multi_trial_weighted(0.01, &mut rng));
+        let mut none = FastBernoulli::new(0.0, &mut rng);
+        assert!(!none.multi_trial_weighted(1e12, &mut rng));
+    }
+
+    #[test]
+    fn weighted_probabilities_account_for_clamping() {
+        let mut rng = rand::thread_rng();
+        let bernoulli = FastBernoulli::new(0.2, &mut rng);
+        for weight in [0.0, 0.5, 1.0, 2.25] {
+            let expected = multi_trial_probability(0.2, weight);
+            assert!((bernoulli.multi_trial_weighted_probability(weight) - expected).abs() < 1e-12);
+        }
+
+        // Whole units are sampled at the effective probability, which is far
+        // from the configured one here, and the fraction at the configured one.
+        let rare = FastBernoulli::new(1e-12, &mut rng);
+        let effective = rare.effective_probability();
+        assert!(effective > 100.0 * 1e-12);
+        let probability = rare.multi_trial_weighted_probability(2.5);
+        assert!((probability / (2.0 * effective + 0.5e-12) - 1.0).abs() < 1e-6);
+
+        // Weights too large for the skip count are decided directly.
+        let huge = 1e13;
+        assert_eq!(
+            rare.multi_trial_weighted_probability(huge),
+            multi_trial_probability(1e-12, huge)
+        );
+    }
+
+    #[test]
+    fn elapsed_time_is_weighted_by_quantum() {
+        let mut rng = rand::thread_rng();
+        let p = 0.01;
+        let mut bernoulli = FastBernoulli::new(p, &mut rng);
+
+        let n = 20_000;
+        let quantum = Duration::from_millis(1);
+        let elapsed = Duration::from_micros(25_500);
+        let sampled = (0..n)
+            .filter(|_| bernoulli.trial_elapsed(elapsed, quantum, &mut rng))
+            .count() as f64;
+        let q = 1.0 - (1.0 - p).powf(25.5);
+        let expected = q * f64::from(n);
+        assert!((sampled - expected).abs() <= 5.0 * (expected * (1.0 - q)).sqrt());
+
+        assert!(!bernoulli.trial_elapsed(Duration::ZERO, quantum, &mut rng));
+    }
+}
@@ -0,0 +1,175 @@
+use rand::Rng;
+
+/// A [`FastBernoulli`][crate::FastBernoulli] with 64-bit skip counts, for
+/// probabilities too small for 32-bit ones.
+///
+/// `FastBernoulli`'s skip counts are `u32`s, clamped to `u32::MAX`, which skews
+/// sampling at probabilities below about one in a billion; see
+/// [`effective_probability`][crate::FastBernoulli::effective_probability].
+/// `WideBernoulli`'s skip counts are `u64`s, which are only clamped at
+/// probabilities below about `1e-18`. Trials are as cheap, and instances are
+/// the same size.
+///
+/// Also built by [`SamplerBuilder::wide`][crate::SamplerBuilder::wide].
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::WideBernoulli;
+///
+/// let mut rng = rand::thread_rng();
+///
+/// // Sample one in a hundred billion events, without clamping.
+/// let mut bernoulli = WideBernoulli::new(1e-11, &mut rng);
+/// if bernoulli.trial(&mut rng) {
+///     // Record the sample...
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct WideBernoulli {
+    probability: f64,
+    // As in `FastBernoulli`, the `n` of a one-in-`n` probability, or zero.
+    denominator: u64,
+    skip_count: u64,
+}
+
+impl WideBernoulli {
+    /// Construct a new `WideBernoulli` instance that samples events with the
+    /// given probability.
+    ///
+    /// # Panics
+    ///
+    /// The probability must be within the range `0.0 <= probability <= 1.0` and
+    /// this method will panic if that is not the case.
+    pub fn new<R>(probability: f64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "`probability` must be in the range `0.0 <= probability <= 1.0`"
+        );
+        Self::with_denominator(probability, 0, rng)
+    }
+
+    /// Construct an instance whose skip counts are drawn from `denominator`,
+    /// if it is non-zero, and otherwise from `probability`.
+    pub(crate) fn with_denominator<R>(probability: f64, denominator: u64, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let mut bernoulli = WideBernoulli {
+            probability,
+            denominator,
+            skip_count: 0,
+        };
+        bernoulli.reset_skip_count(rng);
+        bernoulli
+    }
+
+    pub(crate) fn reset_skip_count<R>(&mut self, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        self.skip_count = if self.probability == 0.0 {
+            u64::MAX
+        } else if self.probability == 1.0 {
+            0
+        } else if self.denominator != 0 {
+            crate::integer::one_in_skip_count(self.denominator, rng.next_u64())
+        } else {
+            let x: f64 = rng.gen();
+            // Float-to-integer casts saturate, so this clamps to `u64::MAX`.
+            (x.ln() / (-self.probability).ln_1p()).floor() as u64
+        };
+        trace_sampler!(
+            trace,
+            probability = self.probability,
+            skip_count = self.skip_count,
+            "reset skip count"
+        );
+    }
+
+    /// Perform a Bernoulli trial, as with
+    /// [`FastBernoulli::trial`][crate::FastBernoulli::trial].
+    #[inline]
+    pub fn trial<R>(&mut self, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        self.multi_trial(1, rng)
+    }
+
+    /// Perform `n` Bernoulli trials at once, as with
+    /// [`FastBernoulli::multi_trial`][crate::FastBernoulli::multi_trial].
+    #[inline]
+    pub fn multi_trial<R>(&mut self, n: u64, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        if n <= self.skip_count {
+            self.skip_count -= n;
+            return false;
+        }
+
+        self.reset_skip_count(rng);
+        self.probability != 0.0
+    }
+
+    /// Get the probability with which events are sampled.
+    #[inline]
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// How many events will be skipped until the next event is sampled?
+    #[inline]
+    pub fn skip_count(&self) -> u64 {
+        self.skip_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_counts_are_not_clamped_to_u32() {
+        let mut rng = rand::thread_rng();
+
+        // At 1e-10, a `u32` skip count would be clamped about 35% of the time,
+        // pulling the mean skip count down to about 6.5 billion.
+        let p = 1e-10;
+        let mut bernoulli = WideBernoulli::new(p, &mut rng);
+        let draws = 10_000;
+        let total: f64 = (0..draws)
+            .map(|_| {
+                bernoulli.reset_skip_count(&mut rng);
+                bernoulli.skip_count() as f64
+            })
+            .sum();
+        let mean = total / f64::from(draws);
+        let expected = (1.0 - p) / p;
+        // Geometric skip counts have a standard deviation of about their mean.
+        let tolerance = 5.0 * expected / f64::from(draws).sqrt();
+        assert!(
+            (mean - expected).abs() <= tolerance,
+            "mean skip count of {mean}, expected ~{expected}"
+        );
+    }
+
+    #[test]
+    fn trials_match_fast_bernoulli() {
+        let mut rng = rand::thread_rng();
+        assert!(!WideBernoulli::new(0.0, &mut rng).multi_trial(u64::MAX, &mut rng));
+        assert!(WideBernoulli::new(1.0, &mut rng).trial(&mut rng));
+
+        let mut bernoulli = WideBernoulli::new(0.5, &mut rng);
+        while bernoulli.skip_count() == 0 {
+            bernoulli = WideBernoulli::new(0.5, &mut rng);
+        }
+        let n = bernoulli.skip_count();
+        assert!(!bernoulli.multi_trial(n, &mut rng));
+        assert!(bernoulli.trial(&mut rng));
+    }
+}
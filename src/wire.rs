@@ -0,0 +1,348 @@
+use crate::{Pipeline, Rate, StickySampler};
+use rand::Rng;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// A sampler configuration with a compact, versioned binary encoding, for
+/// pushing identical configurations to a fleet of agents.
+///
+/// The encoding is simple enough to reimplement in any language. All integers
+/// and floats are little-endian:
+///
+/// | Bytes | Field |
+/// |-------|-------|
+/// | 2     | Magic: `b"FB"` |
+/// | 1     | Version: `1` |
+/// | 1     | Flags: bit 0 is set if a quota follows, bit 1 if a seed follows |
+/// | 8     | Probability, as an `f64` |
+/// | 1     | Mode tag, followed by the mode's fields (see [`SamplingMode`]) |
+/// | 12    | Quota, if present: a `u32` count per a `u64` period in nanoseconds |
+/// | 8     | Seed, if present: a `u64` |
+///
+/// # Example
+///
+/// ```
+/// use fast_bernoulli::{RateExt, SamplerConfig, SamplingMode};
+///
+/// let config = SamplerConfig {
+///     probability: 0.01,
+///     mode: SamplingMode::PerKeyBackoff {
+///         backoff: 0.5,
+///         min_probability: 0.001,
+///     },
+///     quota: Some(100.per_second()),
+///     seed: Some(42),
+/// };
+///
+/// let bytes = config.encode();
+/// assert_eq!(SamplerConfig::decode(&bytes).unwrap(), config);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerConfig {
+    /// The probability with which events are sampled.
+    pub probability: f64,
+
+    /// How keys affect sampling decisions.
+    pub mode: SamplingMode,
+
+    /// The maximum rate of samples, if any.
+    pub quota: Option<Rate>,
+
+    /// The seed for the agent's RNG, if decisions should be reproducible.
+    pub seed: Option<u64>,
+}
+
+/// How a [`SamplerConfig`]'s keys affect sampling decisions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum SamplingMode {
+    /// Every event is sampled independently, regardless of its key.
+    ///
+    /// Mode tag `0`, with no fields.
+    Uniform,
+
+    /// Each key's sampling probability backs off after each sample, as with a
+    /// [`ReportThrottler`][crate::ReportThrottler].
+    ///
+    /// Mode tag `1`, followed by `backoff` and `min_probability` as `f64`s.
+    PerKeyBackoff {
+        /// The factor by which a key's probability is multiplied after each
+        /// sample.
+        backoff: f64,
+        /// The floor for each key's probability.
+        min_probability: f64,
+    },
+
+    /// Once a key is sampled, it stays sampled for a time-to-live, as with a
+    /// [`StickySampler`].
+    ///
+    /// Mode tag `2`, followed by the time-to-live in nanoseconds as a `u64`.
+    Sticky {
+        /// How long a sampled key stays sampled.
+        ttl: Duration,
+    },
+}
+
+/// An error decoding a [`SamplerConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// The input does not start with the magic bytes.
+    BadMagic,
+    /// The input was encoded with an unsupported version of the format.
+    UnsupportedVersion(u8),
+    /// The input ended before the configuration did.
+    Truncated,
+    /// The input continues after the end of the configuration.
+    TrailingBytes,
+    /// The flags byte has unknown bits set.
+    UnknownFlags(u8),
+    /// The mode tag is unknown.
+    UnknownMode(u8),
+    /// A probability, backoff factor, or quota period is out of range.
+    OutOfRange,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::BadMagic => write!(f, "not an encoded sampler configuration"),
+            DecodeError::UnsupportedVersion(v) => {
+                write!(f, "unsupported sampler configuration version {v}")
+            }
+            DecodeError::Truncated => write!(f, "truncated sampler configuration"),
+            DecodeError::TrailingBytes => {
+                write!(f, "trailing bytes after sampler configuration")
+            }
+            DecodeError::UnknownFlags(flags) => {
+                write!(f, "unknown sampler configuration flags {flags:#04x}")
+            }
+            DecodeError::UnknownMode(tag) => write!(f, "unknown sampling mode {tag}"),
+            DecodeError::OutOfRange => write!(f, "sampler configuration value out of range"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+const MAGIC: [u8; 2] = *b"FB";
+const VERSION: u8 = 1;
+const HAS_QUOTA: u8 = 1 << 0;
+const HAS_SEED: u8 = 1 << 1;
+
+impl SamplerConfig {
+    /// Encode this configuration.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(48);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        let mut flags = 0;
+        if self.quota.is_some() {
+            flags |= HAS_QUOTA;
+        }
+        if self.seed.is_some() {
+            flags |= HAS_SEED;
+        }
+        bytes.push(flags);
+        bytes.extend_from_slice(&self.probability.to_le_bytes());
+
+        match self.mode {
+            SamplingMode::Uniform => bytes.push(0),
+            SamplingMode::PerKeyBackoff {
+                backoff,
+                min_probability,
+            } => {
+                bytes.push(1);
+                bytes.extend_from_slice(&backoff.to_le_bytes());
+                bytes.extend_from_slice(&min_probability.to_le_bytes());
+            }
+            SamplingMode::Sticky { ttl } => {
+                bytes.push(2);
+                bytes.extend_from_slice(&duration_to_nanos(ttl).to_le_bytes());
+            }
+        }
+
+        if let Some(quota) = self.quota {
+            bytes.extend_from_slice(&quota.count().to_le_bytes());
+            bytes.extend_from_slice(&duration_to_nanos(quota.period()).to_le_bytes());
+        }
+        if let Some(seed) = self.seed {
+            bytes.extend_from_slice(&seed.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decode a configuration produced by [`encode`][SamplerConfig::encode].
+    ///
+    /// Decoded configurations are validated: probabilities and backoff factors
+    /// are within `0.0..=1.0`, and quota periods are non-zero.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader { bytes };
+        if reader.take::<2>()? != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        let [version] = reader.take::<1>()?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let [flags] = reader.take::<1>()?;
+        if flags & !(HAS_QUOTA | HAS_SEED) != 0 {
+            return Err(DecodeError::UnknownFlags(flags));
+        }
+        let probability = reader.probability()?;
+
+        let mode = match reader.take::<1>()? {
+            [0] => SamplingMode::Uniform,
+            [1] => SamplingMode::PerKeyBackoff {
+                backoff: reader.probability()?,
+                min_probability: reader.probability()?,
+            },
+            [2] => SamplingMode::Sticky {
+                ttl: Duration::from_nanos(reader.u64()?),
+            },
+            [tag] => return Err(DecodeError::UnknownMode(tag)),
+        };
+
+        let quota = if flags & HAS_QUOTA != 0 {
+            let count = u32::from_le_bytes(reader.take()?);
+            let period = Duration::from_nanos(reader.u64()?);
+            if period.is_zero() {
+                return Err(DecodeError::OutOfRange);
+            }
+            Some(Rate::new(count, period))
+        } else {
+            None
+        };
+        let seed = if flags & HAS_SEED != 0 {
+            Some(reader.u64()?)
+        } else {
+            None
+        };
+
+        if !reader.bytes.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        Ok(SamplerConfig {
+            probability,
+            mode,
+            quota,
+            seed,
+        })
+    }
+
+    /// Build a [`Pipeline`] implementing this configuration.
+    ///
+    /// The seed is not used: seed `rng` with it to make decisions
+    /// reproducible.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration is invalid, which is never the case for
+    /// decoded configurations.
+    pub fn pipeline<K, R>(&self, rng: &mut R) -> Pipeline<K>
+    where
+        K: Hash + Eq + Clone + 'static,
+        R: Rng + ?Sized,
+    {
+        let builder = Pipeline::builder();
+        let builder = match self.mode {
+            SamplingMode::Uniform => builder.probability(self.probability),
+            SamplingMode::PerKeyBackoff {
+                backoff,
+                min_probability,
+            } => builder
+                .probability(self.probability)
+                .per_key_backoff_with(backoff, min_probability),
+            SamplingMode::Sticky { ttl } => {
+                builder.stage(StickySampler::new(self.probability, ttl, rng))
+            }
+        };
+        let builder = match self.quota {
+            Some(quota) => builder.rate_limit(quota),
+            None => builder,
+        };
+        builder.build(rng)
+    }
+}
+
+fn duration_to_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        if self.bytes.len() < N {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn probability(&mut self) -> Result<f64, DecodeError> {
+        let p = f64::from_le_bytes(self.take()?);
+        if (0.0..=1.0).contains(&p) {
+            Ok(p)
+        } else {
+            Err(DecodeError::OutOfRange)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_rejects_malformed_input() {
+        let config = SamplerConfig {
+            probability: 0.25,
+            mode: SamplingMode::Sticky {
+                ttl: Duration::from_secs(600),
+            },
+            quota: None,
+            seed: Some(7),
+        };
+        let bytes = config.encode();
+        assert_eq!(bytes.len(), 2 + 1 + 1 + 8 + 1 + 8 + 8);
+        assert_eq!(SamplerConfig::decode(&bytes), Ok(config));
+
+        for len in 0..bytes.len() {
+            assert_eq!(
+                SamplerConfig::decode(&bytes[..len]),
+                Err(DecodeError::Truncated)
+            );
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            SamplerConfig::decode(&trailing),
+            Err(DecodeError::TrailingBytes)
+        );
+
+        let mut future = bytes.clone();
+        future[2] = 2;
+        assert_eq!(
+            SamplerConfig::decode(&future),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+
+        let mut bad_probability = bytes;
+        bad_probability[4..12].copy_from_slice(&1.5_f64.to_le_bytes());
+        assert_eq!(
+            SamplerConfig::decode(&bad_probability),
+            Err(DecodeError::OutOfRange)
+        );
+    }
+}
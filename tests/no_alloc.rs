@@ -0,0 +1,76 @@
+//! Check that `trial` and `multi_trial` never allocate or panic, so that they
+//! are safe to call from inside a global allocator.
+
+use fast_bernoulli::FastBernoulli;
+use rand::rngs::mock::StepRng;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts the current thread's allocations, so that the test harness's own
+/// threads don't interfere.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+const PROBABILITIES: &[f64] = &[
+    0.0,
+    f64::MIN_POSITIVE,
+    1e-300,
+    1e-12,
+    0.001,
+    0.5,
+    1.0 - f64::EPSILON,
+    1.0,
+];
+
+const TRIALS: &[u32] = &[0, 1, 2, 1000, u32::MAX - 1, u32::MAX];
+
+/// Run every edge-case trial with `rng`, asserting that none of them allocate.
+fn check_with(rng: &mut dyn RngCore) {
+    for &probability in PROBABILITIES {
+        let mut bernoulli = FastBernoulli::new(probability, rng);
+
+        let before = allocations();
+        for _ in 0..1000 {
+            bernoulli.trial(rng);
+        }
+        for &n in TRIALS {
+            bernoulli.multi_trial(n, rng);
+        }
+        assert_eq!(
+            allocations(),
+            before,
+            "trials at probability {probability} allocated",
+        );
+    }
+}
+
+#[test]
+fn trials_never_allocate_or_panic() {
+    check_with(&mut StdRng::seed_from_u64(0));
+    // The extremes of the uniform draw: all zero bits and all one bits.
+    check_with(&mut StepRng::new(0, 0));
+    check_with(&mut StepRng::new(u64::MAX, 0));
+}